@@ -1,12 +1,200 @@
-use clap::{App, Arg};
-use std::fs::read_to_string;
-use yuml_rs::{parse_yuml, write_svg_from_dot};
+mod markdown;
+
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::{
+    fs::{read, write, File},
+    io::{ErrorKind, Write},
+    path::Path,
+};
+use yuml_rs::{
+    analysis, decode_yuml, example, examples, format_yuml, parse_yuml_with_includes, render_svg_data_uri,
+    render_svg_from_dot_to, ChartType, Directions, FileResolver, OutputFormat, RenderOptions,
+};
+
+/// Formats which can be written; `dot` is the graphviz description of the diagram, as passed to graphviz, and `cmapx`
+/// the HTML image map of the links of the `png` image
+const FORMATS: &[&str] = &["svg", "png", "cmapx", "dot"];
+
+/// Directives which can be set on the command line
+const OVERRIDES: &[&str] = &["type", "direction"];
+
+/// Replace the directives of the header with the values given on the command line, so snippets without a header can
+/// be rendered
+fn with_overrides(yuml: &str, overrides: &[(&str, &str)]) -> String {
+    let is_overridden = |line: &str| {
+        overrides
+            .iter()
+            .any(|(key, _)| line.trim_start().starts_with(&format!("// {{{}:", key)))
+    };
+
+    let mut lines = yuml.lines().peekable();
+    let mut header: Vec<String> = overrides
+        .iter()
+        .map(|(key, value)| format!("// {{{}:{}}}", key, value))
+        .collect();
+    while let Some(line) = lines.next_if(|line| line.trim_start().starts_with("// {")) {
+        if !is_overridden(line) {
+            header.push(line.to_string());
+        }
+    }
+
+    header
+        .into_iter()
+        .chain(lines.map(str::to_string))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Validate a chart type which has examples, as only those can be scaffolded
+fn supported_type(value: String) -> Result<(), String> {
+    match ChartType::try_from(value.as_str()) {
+        Ok(chart_type) if example(chart_type).is_some() => Ok(()),
+        Ok(chart_type) => Err(format!("{} diagrams are not supported yet", chart_type)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// The directives and a first statement of a new diagram of the `chart_type`, for the `new` subcommand
+fn scaffold(chart_type: ChartType) -> Option<String> {
+    let (direction, statement) = match chart_type {
+        ChartType::Activity => (Some("topDown"), "(start)->(First Step)->(end)"),
+        ChartType::Class => (Some("topDown"), "[Customer]->[Order]"),
+        ChartType::Timeline => (
+            Some("leftToRight"),
+            "[Phase 1]2024-01..2024-03->[Phase 2]2024-04..2024-06",
+        ),
+        ChartType::Er => (Some("topDown"), "[customer|id PK]1-places-*[order|id PK]"),
+        // mind maps are laid out radially
+        ChartType::Mindmap => (None, "Topic\n  Idea"),
+        _ => return None,
+    };
+
+    let mut yuml = format!("// {{type:{}}}\n", chart_type);
+    if let Some(direction) = direction {
+        yuml.push_str(&format!("// {{direction:{}}}\n", direction));
+    }
+    Some(format!("{}\n{}\n", yuml, statement))
+}
+
+/// Print the sample document of the chart type of the `examples` subcommand, or those of all supported chart types
+fn print_examples(matches: &ArgMatches) {
+    let chart_type = matches
+        .value_of("type")
+        .and_then(|value| ChartType::try_from(value).ok());
+    let yuml: Vec<&str> = examples()
+        .filter(|(example_type, _)| chart_type.map_or(true, |chart_type| chart_type == *example_type))
+        .map(|(_, yuml)| yuml)
+        .collect();
+    print!("{}", yuml.join("\n"));
+}
+
+/// Write a new diagram for the `new` subcommand, without overwriting an existing file
+fn create_diagram(matches: &ArgMatches) -> Result<(), String> {
+    let file = matches.value_of("file").expect("a file is mandatory");
+    let yuml = matches
+        .value_of("type")
+        .and_then(|value| ChartType::try_from(value).ok())
+        .and_then(scaffold)
+        .expect("the type is validated");
+
+    let mut output = File::options()
+        .write(true)
+        .create_new(true)
+        .open(file)
+        .map_err(|err| match err.kind() {
+            ErrorKind::AlreadyExists => format!("{} already exists", file),
+            _ => format!("can not create {}: {}", file, err),
+        })?;
+    output
+        .write_all(yuml.as_bytes())
+        .map_err(|err| format!("can not write {}: {}", file, err))
+}
+
+/// Keep the line endings of the `original` file in the `rewritten` text, so rewriting a file with Windows line endings
+/// does not change every line
+fn with_line_endings(original: &str, rewritten: String) -> String {
+    match original.contains("\r\n") {
+        true => rewritten.replace("\r\n", "\n").replace('\n', "\r\n"),
+        false => rewritten,
+    }
+}
+
+/// The file to write the `format` to: the output file when it is the only format, otherwise the output or input file
+/// with the extension of the format
+fn output_path(input: &str, output: Option<&str>, format: &str, single: bool) -> String {
+    match output {
+        Some(output) if single => output.to_string(),
+        output => Path::new(output.unwrap_or(input))
+            .with_extension(format)
+            .to_string_lossy()
+            .to_string(),
+    }
+}
+
+fn write_format(dot: &str, format: &str, path: &str) -> Result<(), String> {
+    match OutputFormat::try_from(format) {
+        Ok(image_format) => {
+            let file = File::create(path).map_err(|err| err.to_string())?;
+            render_svg_from_dot_to(dot, file, &RenderOptions::default().format(image_format))
+                .map_err(|err| err.to_string())
+        }
+        Err(_) => write(path, dot).map_err(|err| err.to_string()),
+    }
+}
+
+/// Render the `n`th diagram of a Markdown file as SVG, returning the target of its image link: a data URI when
+/// `inline`, otherwise the name of the image written next to the Markdown file, like `guide-1.svg`
+fn render_block(markdown_file: &str, n: usize, yuml: &str, inline: bool) -> Result<String, String> {
+    let dot = parse_yuml_with_includes(yuml, &FileResolver::for_file(markdown_file))
+        .map_err(|err| format!("diagram {}: {}", n, err))?
+        .to_string();
+
+    if inline {
+        return render_svg_data_uri(&dot).map_err(|err| err.to_string());
+    }
+
+    let path = Path::new(markdown_file);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let image = format!("{}-{}.svg", stem, n);
+    write_format(&dot, "svg", &path.with_file_name(&image).to_string_lossy())?;
+    Ok(image)
+}
 
 fn main() {
     let matches = App::new("yUML diagram utility")
         .version("0.1")
         .author("Marcel Ibes <mibes@avaya.com>")
         .about("Renders SVG and PNG images based on yUML input")
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("examples")
+                .about("Prints sample documents of the supported chart types, ready to be edited")
+                .arg(
+                    Arg::with_name("type")
+                        .value_name("TYPE")
+                        .help("Prints the sample of this chart type only")
+                        .validator(supported_type),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("new")
+                .about("Creates a yUML file with the directives of a chart type")
+                .arg(
+                    Arg::with_name("type")
+                        .long("type")
+                        .value_name("TYPE")
+                        .help("Sets the type of the diagram")
+                        .required(true)
+                        .validator(supported_type)
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .value_name("FILE")
+                        .help("Sets the file to create")
+                        .required(true),
+                ),
+        )
         .arg(
             Arg::with_name("input")
                 .short("i")
@@ -21,17 +209,117 @@ fn main() {
                 .short("o")
                 .long("output")
                 .value_name("FILE")
-                .help("Sets the input SVG file")
-                .required(true)
+                .help("Sets the output file; with several formats, its extension is replaced by the format")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("format")
+                .short("f")
+                .long("format")
+                .value_name("FORMAT")
+                .help("Sets the output format, may be repeated to write several formats from one parse [default: svg]")
+                .possible_values(FORMATS)
+                .multiple(true)
+                .number_of_values(1)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("type")
+                .long("type")
+                .value_name("TYPE")
+                .help("Sets the type of the diagram, overriding the 'type' directive")
+                .validator(|value| {
+                    ChartType::try_from(value.as_str())
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("direction")
+                .long("direction")
+                .value_name("DIRECTION")
+                .help("Sets the direction of the diagram, overriding the 'direction' directive")
+                .validator(|value| {
+                    Directions::try_from(value.as_str())
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
+                })
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("markdown")
+                .long("markdown")
+                .help("Renders the ```yuml blocks of the input Markdown file and links the images after each block")
+                .conflicts_with_all(&["output", "format", "fmt"]),
+        )
+        .arg(
+            Arg::with_name("inline")
+                .long("inline")
+                .requires("markdown")
+                .help("Embeds the images of the Markdown file as data URIs, instead of writing them next to it"),
+        )
+        .arg(
+            Arg::with_name("fmt")
+                .long("fmt")
+                .help("Rewrites the input file in the canonical yUML format, instead of rendering it"),
+        )
+        .arg(
+            Arg::with_name("stats")
+                .long("stats")
+                .conflicts_with_all(&["output", "format", "markdown", "fmt"])
+                .help("Prints the node and edge counts, fan-out, cycles and depth of the diagram, instead of rendering it"),
+        )
         .get_matches();
 
+    match matches.subcommand() {
+        ("examples", Some(examples)) => return print_examples(examples),
+        ("new", Some(new)) => {
+            if let Err(err) = create_diagram(new) {
+                println!("{}", err);
+            }
+            return;
+        }
+        _ => {}
+    }
+
     let input_file = matches.value_of("input").expect("an input file is mandatory");
-    let output_file = matches.value_of("output").expect("an output file is mandatory");
-    let yuml = read_to_string(input_file).expect("can not read input file");
+    let bytes = read(input_file).expect("can not read input file");
+    let yuml = match decode_yuml(&bytes) {
+        Ok(yuml) => yuml,
+        Err(err) => {
+            println!("{}", err);
+            return;
+        }
+    };
+
+    if matches.is_present("fmt") {
+        match format_yuml(&yuml) {
+            Ok(formatted) => write(input_file, with_line_endings(&yuml, formatted)).expect("can not write input file"),
+            Err(err) => println!("{}", err),
+        }
+        return;
+    }
+
+    let overrides: Vec<(&str, &str)> = OVERRIDES
+        .iter()
+        .filter_map(|key| matches.value_of(key).map(|value| (*key, value)))
+        .collect();
 
-    let dot = match parse_yuml(&yuml) {
+    if matches.is_present("markdown") {
+        let inline = matches.is_present("inline");
+        let rendered = markdown::link_diagrams(&yuml, |n, source| {
+            render_block(input_file, n, &with_overrides(source, &overrides), inline)
+        });
+        match rendered {
+            Ok(rendered) => write(input_file, with_line_endings(&yuml, rendered)).expect("can not write input file"),
+            Err(err) => println!("{}", err),
+        }
+        return;
+    }
+
+    let yuml = with_overrides(&yuml, &overrides);
+    let dot = match parse_yuml_with_includes(&yuml, &FileResolver::for_file(input_file)) {
         Ok(dot) => dot,
         Err(err) => {
             println!("{}", err);
@@ -39,5 +327,81 @@ fn main() {
         }
     };
 
-    write_svg_from_dot(&dot.to_string(), output_file).expect("can not write output file");
+    if matches.is_present("stats") {
+        match analysis::stats(&dot) {
+            Ok(stats) => print!("{}", stats),
+            Err(err) => println!("{}", err),
+        }
+        return;
+    }
+
+    let requested: Vec<&str> = matches
+        .values_of("format")
+        .map(Iterator::collect)
+        .unwrap_or_else(|| vec!["svg"]);
+    let formats: Vec<&str> = FORMATS
+        .iter()
+        .copied()
+        .filter(|format| requested.contains(format))
+        .collect();
+    let dot = dot.to_string();
+    for format in &formats {
+        let path = output_path(input_file, matches.value_of("output"), format, formats.len() == 1);
+        if let Err(err) = write_format(&dot, format, &path) {
+            println!("can not write {}: {}", path, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_overrides() {
+        let yuml = "// {type:class}\n// {generate:true}\n(start)->(end)";
+        assert_eq!(
+            with_overrides(yuml, &[("type", "activity"), ("direction", "leftToRight")]),
+            "// {type:activity}\n// {direction:leftToRight}\n// {generate:true}\n(start)->(end)"
+        );
+        assert_eq!(
+            with_overrides("(start)->(end)", &[("type", "activity")]),
+            "// {type:activity}\n(start)->(end)"
+        );
+        assert_eq!(with_overrides(yuml, &[]), yuml);
+    }
+
+    #[test]
+    fn test_output_path() {
+        assert_eq!(output_path("docs/flow.yuml", None, "svg", true), "docs/flow.svg");
+        assert_eq!(output_path("docs/flow.yuml", Some("out.svg"), "svg", true), "out.svg");
+        assert_eq!(
+            output_path("docs/flow.yuml", Some("out/flow.svg"), "png", false),
+            "out/flow.png"
+        );
+        assert_eq!(output_path("flow", None, "dot", false), "flow.dot");
+    }
+
+    #[test]
+    fn test_scaffold() {
+        assert_eq!(
+            scaffold(ChartType::Class),
+            Some("// {type:class}\n// {direction:topDown}\n\n[Customer]->[Order]\n".to_string())
+        );
+        assert_eq!(scaffold(ChartType::Sequence), None);
+
+        for (chart_type, _) in examples() {
+            let yuml = scaffold(chart_type).expect("no scaffold for a supported chart type");
+            assert!(yuml_rs::parse_yuml(&yuml).is_ok(), "invalid {} scaffold", chart_type);
+        }
+    }
+
+    #[test]
+    fn test_with_line_endings() {
+        assert_eq!(
+            with_line_endings("a\r\nb\r\n", "a\nb\r\nc\n".to_string()),
+            "a\r\nb\r\nc\r\n"
+        );
+        assert_eq!(with_line_endings("a\nb\n", "a\nb\n".to_string()), "a\nb\n");
+    }
 }
@@ -0,0 +1,97 @@
+/*
+Diagrams embedded in Markdown
+Block           ```yuml ... ```, rendered to an image linked right after the block
+Link            ![yuml diagram 1](guide-1.svg), replaced when the document is rendered again
+*/
+
+const FENCE: &str = "```";
+const LINK_PREFIX: &str = "![yuml diagram ";
+
+/// The source of a ```` ```yuml ```` block, with the index of its closing fence
+struct Block {
+    end: usize,
+    source: String,
+}
+
+fn find_blocks(lines: &[&str]) -> Vec<Block> {
+    let mut blocks = vec![];
+    let mut open: Option<(usize, Vec<&str>)> = None;
+    for (idx, line) in lines.iter().enumerate() {
+        match open.as_mut() {
+            None if line.trim() == "```yuml" => open = Some((idx, vec![])),
+            None => {}
+            Some(_) if line.trim() == FENCE => {
+                let (_, source) = open.take().unwrap_or_default();
+                blocks.push(Block {
+                    end: idx,
+                    source: source.join("\n"),
+                });
+            }
+            Some((_, source)) => source.push(line),
+        }
+    }
+    blocks
+}
+
+/// Link the image of each yUML block right after the block, replacing the link of an earlier run. `render` is called
+/// with the 1-based number and the source of each block, and returns the target of its image link.
+pub fn link_diagrams<F>(markdown: &str, mut render: F) -> Result<String, String>
+where
+    F: FnMut(usize, &str) -> Result<String, String>,
+{
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut links = vec![];
+    for (idx, block) in find_blocks(&lines).iter().enumerate() {
+        let target = render(idx + 1, &block.source)?;
+        links.push((block.end, format!("{}{}]({})", LINK_PREFIX, idx + 1, target)));
+    }
+
+    let mut output: Vec<&str> = vec![];
+    let mut links = links.iter().peekable();
+    let mut skip_link = false;
+    for (idx, line) in lines.iter().enumerate() {
+        if skip_link && line.starts_with(LINK_PREFIX) {
+            skip_link = false;
+            continue;
+        }
+        skip_link = false;
+        output.push(line);
+
+        if let Some((_, link)) = links.next_if(|(end, _)| *end == idx) {
+            output.push(link);
+            skip_link = true;
+        }
+    }
+
+    let mut rewritten = output.join("\n");
+    if markdown.ends_with('\n') {
+        rewritten.push('\n');
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_diagrams() {
+        let markdown = "# Guide\n```yuml\n// {type:activity}\n(start)->(end)\n```\ntext\n```rust\nfn main() {}\n```\n```yuml\n[A]->[B]\n```\n";
+        let mut sources = vec![];
+        let rewritten = link_diagrams(markdown, |n, source| {
+            sources.push(source.to_string());
+            Ok(format!("guide-{}.svg", n))
+        })
+        .unwrap();
+
+        assert_eq!(sources, vec!["// {type:activity}\n(start)->(end)", "[A]->[B]"]);
+        assert_eq!(
+            rewritten,
+            "# Guide\n```yuml\n// {type:activity}\n(start)->(end)\n```\n![yuml diagram 1](guide-1.svg)\ntext\n```rust\nfn main() {}\n```\n```yuml\n[A]->[B]\n```\n![yuml diagram 2](guide-2.svg)\n"
+        );
+
+        // the links of an earlier run are replaced
+        let again = link_diagrams(&rewritten, |n, _| Ok(format!("guide-{}.svg", n))).unwrap();
+        assert_eq!(again, rewritten);
+    }
+}
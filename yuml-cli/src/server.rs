@@ -0,0 +1,299 @@
+//! Self-hosted rendering service, similar to yuml.me.
+//!
+//! `POST /render?format=svg|png` renders the yUML text in the request body,
+//! `GET /render/<base64>?format=svg|png` renders the (URL-safe) base64 encoded yUML, for embedding in markdown.
+
+use base64::{
+    engine::general_purpose::{STANDARD, URL_SAFE, URL_SAFE_NO_PAD},
+    Engine,
+};
+use clap::{App, Arg};
+use std::{convert::TryFrom, io::Read, sync::Arc, thread, time::Duration};
+use tiny_http::{Header, Method, Request, Server};
+use yuml_rs::{parse_yuml_with_options, render_svg_from_dot_to, OutputFormat, ParseOptions, RenderOptions, YumlError};
+
+/// Rendered images are derived from the URL only, so `GET` responses never change
+const CACHE_IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const CACHE_NONE: &str = "no-store";
+const MAX_BODY_SIZE: u64 = 1024 * 1024;
+const MAX_IMAGE_SIZE: usize = 32 * 1024 * 1024;
+
+/// Limits on the diagrams, so a generated diagram can not make graphviz take all memory of the server
+fn limits() -> ParseOptions {
+    ParseOptions::default()
+        .max_lines(2_000)
+        .max_nodes(1_000)
+        .max_label_length(1_000)
+        .max_dot_size(MAX_BODY_SIZE as usize)
+        .max_expanded_size(MAX_BODY_SIZE as usize)
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    cache_control: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn error(status: u16, message: &str) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            cache_control: CACHE_NONE,
+            body: format!(r#"{{"error":"{}"}}"#, escape_json(message)).into_bytes(),
+        }
+    }
+}
+
+fn main() {
+    let matches = App::new("yUML rendering server")
+        .version("0.1")
+        .author("Marcel Ibes <mibes@avaya.com>")
+        .about("Renders SVG and PNG images from yUML over HTTP")
+        .arg(
+            Arg::with_name("listen")
+                .short("l")
+                .long("listen")
+                .value_name("ADDRESS")
+                .help("Sets the address to listen on")
+                .default_value("127.0.0.1:8080")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .short("t")
+                .long("timeout")
+                .value_name("SECONDS")
+                .help("Sets the maximum rendering time per diagram")
+                .default_value("10")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("workers")
+                .short("w")
+                .long("workers")
+                .value_name("COUNT")
+                .help("Sets the number of requests which are handled at the same time")
+                .default_value("4")
+                .takes_value(true),
+        )
+        .get_matches();
+
+    let address = matches.value_of("listen").expect("an address is mandatory");
+    let timeout = matches
+        .value_of("timeout")
+        .and_then(|t| t.parse().ok())
+        .map(Duration::from_secs)
+        .expect("the timeout must be a number of seconds");
+    let workers: usize = matches
+        .value_of("workers")
+        .and_then(|w| w.parse().ok())
+        .filter(|w| *w > 0)
+        .expect("the number of workers must be a positive number");
+
+    let server = Arc::new(Server::http(address).expect("can not start the server"));
+    println!("listening on http://{}", address);
+
+    // a fixed number of workers, so a burst of requests can not start an unbounded number of "dot" processes
+    let handles: Vec<_> = (0..workers)
+        .map(|_| {
+            let server = Arc::clone(&server);
+            thread::spawn(move || {
+                while let Ok(request) = server.recv() {
+                    respond(request, timeout);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+}
+
+fn respond(mut request: Request, timeout: Duration) {
+    let response = match read_body(request.as_reader()) {
+        Ok(body) => handle(request.method(), request.url(), &body, timeout),
+        Err(response) => response,
+    };
+
+    let headers = [
+        ("Content-Type", response.content_type),
+        ("Cache-Control", response.cache_control),
+    ];
+    let mut http_response = tiny_http::Response::from_data(response.body).with_status_code(response.status);
+    for (key, value) in headers {
+        if let Ok(header) = Header::from_bytes(key, value) {
+            http_response.add_header(header);
+        }
+    }
+
+    let _ = request.respond(http_response);
+}
+
+/// The request body, which is rejected rather than cut off when it exceeds `MAX_BODY_SIZE`
+fn read_body(reader: impl Read) -> Result<String, Response> {
+    let mut body = Vec::new();
+    if reader.take(MAX_BODY_SIZE + 1).read_to_end(&mut body).is_err() {
+        return Err(Response::error(400, "can not read the request body"));
+    }
+    if body.len() as u64 > MAX_BODY_SIZE {
+        let message = format!("the request body exceeds the limit of {} bytes", MAX_BODY_SIZE);
+        return Err(Response::error(413, &message));
+    }
+    String::from_utf8(body).map_err(|_| Response::error(400, "the request body must be UTF-8 encoded text"))
+}
+
+fn handle(method: &Method, url: &str, body: &str, timeout: Duration) -> Response {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let format = match query_format(query) {
+        Ok(format) => format,
+        Err(message) => return Response::error(400, &message),
+    };
+
+    match (method, path.strip_prefix("/render")) {
+        (Method::Post, Some("")) => render(body, format, timeout, CACHE_NONE),
+        (Method::Get, Some(encoded)) if encoded.len() > 1 => match decode(&encoded[1..]) {
+            Some(yuml) => render(&yuml, format, timeout, CACHE_IMMUTABLE),
+            None => Response::error(400, "the diagram must be base64 encoded UTF-8 text"),
+        },
+        (_, Some(_)) => Response::error(405, "use POST /render or GET /render/<base64>"),
+        (_, None) => Response::error(404, "not found"),
+    }
+}
+
+fn render(yuml: &str, format: OutputFormat, timeout: Duration, cache_control: &'static str) -> Response {
+    let dot = match parse_yuml_with_options(yuml, &limits()) {
+        Ok(parsed) => parsed.to_string(),
+        Err(err) => return Response::error(400, &err.to_string()),
+    };
+    if dot.is_empty() {
+        return Response::error(400, "unsupported chart type");
+    }
+
+    let mut image = Vec::new();
+    let opts = RenderOptions::default()
+        .format(format)
+        .timeout(timeout)
+        .max_output_size(MAX_IMAGE_SIZE);
+    match render_svg_from_dot_to(&dot, &mut image, &opts) {
+        Ok(_) => Response {
+            status: 200,
+            content_type: format.content_type(),
+            cache_control,
+            body: image,
+        },
+        Err(err) => Response::error(render_error_status(&err), &err.to_string()),
+    }
+}
+
+/// Diagrams which take too long or grow too large are rejected like other invalid diagrams, instead of being reported
+/// as a fault of the server
+fn render_error_status(err: &YumlError) -> u16 {
+    match err {
+        YumlError::OutputTooLarge(_) => 422,
+        YumlError::RenderTimeout(_) => 503,
+        _ => 500,
+    }
+}
+
+fn query_format(query: &str) -> Result<OutputFormat, String> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("format="))
+        .map_or(Ok(OutputFormat::Svg), |format| {
+            OutputFormat::try_from(format).map_err(|err| err.to_string())
+        })
+}
+
+/// Accepts both the standard and the URL-safe alphabet, with or without padding
+fn decode(encoded: &str) -> Option<String> {
+    let bytes = URL_SAFE_NO_PAD
+        .decode(encoded)
+        .or_else(|_| URL_SAFE.decode(encoded))
+        .or_else(|_| STANDARD.decode(encoded.replace("%2B", "+").replace("%2F", "/").replace("%3D", "=")))
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(10);
+
+    #[test]
+    fn test_routing() {
+        let response = handle(&Method::Get, "/other", "", TIMEOUT);
+        assert_eq!(response.status, 404);
+
+        let response = handle(&Method::Delete, "/render", "", TIMEOUT);
+        assert_eq!(response.status, 405);
+
+        let response = handle(&Method::Post, "/render?format=gif", "", TIMEOUT);
+        assert_eq!(response.status, 400);
+        assert_eq!(response.content_type, "application/json");
+    }
+
+    #[test]
+    fn test_invalid_diagram() {
+        let response = handle(&Method::Post, "/render", "// {type:sequence}\n[a]", TIMEOUT);
+        assert_eq!(response.status, 400);
+        assert_eq!(response.body, br#"{"error":"unsupported chart type"}"#);
+
+        let response = handle(&Method::Get, "/render/not*base64", "", TIMEOUT);
+        assert_eq!(response.status, 400);
+
+        let huge = format!("// {{type:class}}\n{}", "[A]->[B]\n".repeat(3_000));
+        let response = handle(&Method::Post, "/render", &huge, TIMEOUT);
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn test_body_size() {
+        let body = "a".repeat(MAX_BODY_SIZE as usize);
+        assert_eq!(read_body(body.as_bytes()).ok(), Some(body.clone()));
+
+        let response = read_body(format!("{}é", body).as_bytes()).unwrap_err();
+        assert_eq!(response.status, 413);
+        assert_eq!(response.content_type, "application/json");
+
+        let response = read_body(&[0xff, 0xfe][..]).unwrap_err();
+        assert_eq!(response.status, 400);
+    }
+
+    #[test]
+    fn test_render_error_status() {
+        assert_eq!(render_error_status(&YumlError::OutputTooLarge(10)), 422);
+        assert_eq!(render_error_status(&YumlError::RenderTimeout(TIMEOUT)), 503);
+        assert_eq!(render_error_status(&YumlError::Render("crashed".to_string())), 500);
+    }
+
+    #[test]
+    fn test_decode() {
+        let yuml = "// {type:class}\n[A]->[B]";
+        assert_eq!(decode(&URL_SAFE_NO_PAD.encode(yuml)).as_deref(), Some(yuml));
+        assert_eq!(decode(&STANDARD.encode(yuml)).as_deref(), Some(yuml));
+    }
+
+    #[test]
+    fn test_escape_json() {
+        assert_eq!(escape_json("a \"b\"\n\\"), r#"a \"b\"\n\\"#);
+    }
+}
@@ -0,0 +1,150 @@
+//! C bindings for [yuml-rs](https://docs.rs/yuml-rs), so the parser can be embedded in non-Rust tools.
+//!
+//! Every function returns one of the `YUML_*` status codes. On success the output pointer receives the
+//! result, on failure it receives a human readable error message. In both cases the string is owned by
+//! the caller and must be released with `yuml_free_string`.
+
+use std::{
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+use yuml_rs::{parse_yuml, render_svg_from_dot};
+
+pub const YUML_OK: c_int = 0;
+pub const YUML_ERR_NULL_POINTER: c_int = 1;
+pub const YUML_ERR_INVALID_UTF8: c_int = 2;
+pub const YUML_ERR_PARSE: c_int = 3;
+pub const YUML_ERR_RENDER: c_int = 4;
+/// A bug in the parser or the renderer; unwinding into C is undefined behavior, so the panic is reported instead
+pub const YUML_ERR_PANIC: c_int = 5;
+
+/// Parse yUML text and produce the dot description.
+///
+/// # Safety
+/// `yuml` must be a valid, NUL-terminated string and `out` must point to writable memory for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn yuml_parse_to_dot(yuml: *const c_char, out: *mut *mut c_char) -> c_int {
+    with_input(yuml, out, |text| match parse_yuml(text) {
+        Ok(parsed) => Ok(parsed.to_string().into_bytes()),
+        Err(err) => Err((YUML_ERR_PARSE, err.to_string())),
+    })
+}
+
+/// Parse yUML text and render it as SVG using the "dot" binary.
+///
+/// # Safety
+/// `yuml` must be a valid, NUL-terminated string and `out` must point to writable memory for a pointer.
+#[no_mangle]
+pub unsafe extern "C" fn yuml_render_svg(yuml: *const c_char, out: *mut *mut c_char) -> c_int {
+    with_input(yuml, out, |text| {
+        let parsed = parse_yuml(text).map_err(|err| (YUML_ERR_PARSE, err.to_string()))?;
+        render_svg_from_dot(&parsed.to_string()).map_err(|err| (YUML_ERR_RENDER, err.to_string()))
+    })
+}
+
+/// Release a string returned by one of the `yuml_*` functions.
+///
+/// # Safety
+/// `s` must be NULL or a pointer previously returned through an `out` parameter, and must not be freed twice.
+#[no_mangle]
+pub unsafe extern "C" fn yuml_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+unsafe fn with_input<F>(yuml: *const c_char, out: *mut *mut c_char, f: F) -> c_int
+where
+    F: FnOnce(&str) -> Result<Vec<u8>, (c_int, String)>,
+{
+    if out.is_null() {
+        return YUML_ERR_NULL_POINTER;
+    }
+    *out = ptr::null_mut();
+
+    if yuml.is_null() {
+        return YUML_ERR_NULL_POINTER;
+    }
+
+    let (code, bytes) = match CStr::from_ptr(yuml).to_str() {
+        Ok(text) => match panic::catch_unwind(AssertUnwindSafe(|| f(text))) {
+            Ok(Ok(bytes)) => (YUML_OK, bytes),
+            Ok(Err((code, message))) => (code, message.into_bytes()),
+            Err(payload) => (YUML_ERR_PANIC, panic_message(payload.as_ref()).into_bytes()),
+        },
+        Err(err) => (YUML_ERR_INVALID_UTF8, err.to_string().into_bytes()),
+    };
+
+    *out = as_c_string(bytes).into_raw();
+    code
+}
+
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    let message = payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown cause");
+    format!("internal error: {}", message)
+}
+
+fn as_c_string(mut bytes: Vec<u8>) -> CString {
+    // interior NUL bytes can not be represented, so the output is cut off at the first one
+    if let Some(idx) = bytes.iter().position(|b| *b == 0) {
+        bytes.truncate(idx);
+    }
+    CString::new(bytes).expect("NUL bytes are removed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(f: unsafe extern "C" fn(*const c_char, *mut *mut c_char) -> c_int, input: &[u8]) -> (c_int, String) {
+        let input = CString::new(input).unwrap();
+        let mut out: *mut c_char = ptr::null_mut();
+        unsafe {
+            let code = f(input.as_ptr(), &mut out);
+            let result = CStr::from_ptr(out).to_string_lossy().to_string();
+            yuml_free_string(out);
+            (code, result)
+        }
+    }
+
+    #[test]
+    fn test_parse_to_dot() {
+        let (code, dot) = call(yuml_parse_to_dot, b"// {type:activity}\n(start)->(end)");
+        assert_eq!(code, YUML_OK);
+        assert!(dot.starts_with("digraph G {"));
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let (code, _) = call(yuml_parse_to_dot, &[0xff, 0xfe]);
+        assert_eq!(code, YUML_ERR_INVALID_UTF8);
+    }
+
+    #[test]
+    fn test_panic() {
+        let input = CString::new("(a)").unwrap();
+        let mut out: *mut c_char = ptr::null_mut();
+        unsafe {
+            let code = with_input(input.as_ptr(), &mut out, |_| panic!("boom"));
+            assert_eq!(code, YUML_ERR_PANIC);
+            assert_eq!(CStr::from_ptr(out).to_str(), Ok("internal error: boom"));
+            yuml_free_string(out);
+        }
+    }
+
+    #[test]
+    fn test_null_pointers() {
+        let mut out: *mut c_char = ptr::null_mut();
+        unsafe {
+            assert_eq!(yuml_parse_to_dot(ptr::null(), &mut out), YUML_ERR_NULL_POINTER);
+            assert!(out.is_null());
+            yuml_free_string(out);
+        }
+    }
+}
@@ -7,6 +7,9 @@ pub fn criterion_benchmark(c: &mut Criterion) {
 
     let yuml = include_str!("../test/class.yuml");
     c.bench_function("class.yuml", |b| b.iter(|| parse_yuml(yuml)));
+
+    let dot = parse_yuml(yuml).expect("invalid class file");
+    c.bench_function("class.dot", |b| b.iter(|| dot.to_string()));
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -0,0 +1,18 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use yuml_rs::{generate_synthetic, parse_yuml};
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let yuml = generate_synthetic(10_000);
+    let mut group = c.benchmark_group("synthetic");
+    group.sample_size(10);
+
+    group.bench_function("activity 10k parse", |b| b.iter(|| parse_yuml(&yuml)));
+
+    let dot = parse_yuml(&yuml).expect("invalid synthetic file");
+    group.bench_function("activity 10k dot", |b| b.iter(|| dot.to_string()));
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,193 @@
+use crate::model::{
+    dot::{Dot, DotFile, DotShape},
+    label::{as_text_sections, decode_entities, Label},
+};
+use std::collections::HashMap;
+
+/// Give the nodes and edges of an SVG rendered from `dot_file` a readable `<title>` and an `aria-label`.
+///
+/// Graphviz titles nodes with their generated ids, like `A1`; these are replaced by the yUML labels, and edges are
+/// described by their end points, like "Fill Kettle to Boil Kettle", so screen readers can navigate the diagram.
+/// Usage:
+/// ```rust,no_run
+/// # #[cfg(feature = "render")] {
+/// use yuml_rs::{add_accessibility, parse_yuml, render_svg_from_dot, DotFile};
+///
+/// let dot_file = DotFile::try_from("digraph { a -> b }").unwrap();
+/// let svg = render_svg_from_dot(&dot_file.to_string()).expect("can not generate SVG");
+/// let svg = add_accessibility(&String::from_utf8_lossy(&svg), &dot_file);
+/// # }
+/// ```
+pub fn add_accessibility(svg: &str, dot_file: &DotFile) -> String {
+    let names: HashMap<String, String> = dot_file
+        .nodes()
+        .map(|node| (unquote(&node.uid), node_name(&node.dot, &node.uid)))
+        .collect();
+    let name = |uid: &str| {
+        let uid = unquote(uid);
+        names
+            .get(&uid)
+            .or_else(|| uid.split_once(':').and_then(|(id, _)| names.get(id)))
+            .cloned()
+            .unwrap_or(uid)
+    };
+
+    // graphviz writes the edges in the order of the dot file
+    let mut edges = dot_file.edges();
+    let mut accessible = String::with_capacity(svg.len());
+    let mut rest = svg;
+
+    while let Some(start) = rest.find("<g ") {
+        accessible.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => break,
+        };
+        let group = &rest[..end];
+        let is_node = group.contains(r#"class="node""#);
+        let is_edge = group.contains(r#"class="edge""#);
+        let after_group = &rest[end + 1..];
+        let title = after_group
+            .trim_start()
+            .strip_prefix("<title>")
+            .and_then(|title| title.split_once("</title>"))
+            .map(|(title, _)| title);
+
+        let description = match title {
+            Some(title) if is_node => Some(name(&decode_entities(title))),
+            Some(title) if is_edge => {
+                let title = decode_entities(title);
+                let edge = edges.next();
+                let (from, to) = title.split_once("->").unwrap_or((&title, ""));
+                let label = edge.and_then(|edge| text(&edge.dot.label));
+                Some(match label {
+                    Some(label) => format!("{} to {}: {}", name(from), name(to), label),
+                    None => format!("{} to {}", name(from), name(to)),
+                })
+            }
+            _ => None,
+        };
+
+        match (description, title) {
+            (Some(description), Some(title)) => {
+                let description = escape(&description);
+                let title_start = end + 1 + (after_group.len() - after_group.trim_start().len());
+                let title_end = title_start + "<title>".len() + title.len() + "</title>".len();
+                accessible.push_str(&format!(
+                    r#"{} role="img" aria-label="{}">{}<title>{}</title>"#,
+                    group,
+                    description,
+                    &rest[end + 1..title_start],
+                    description
+                ));
+                rest = &rest[title_end..];
+            }
+            _ => {
+                accessible.push_str(&rest[..end + 1]);
+                rest = after_group;
+            }
+        }
+    }
+
+    accessible.push_str(rest);
+    accessible
+}
+
+fn unquote(uid: &str) -> String {
+    match uid.strip_prefix('"').and_then(|uid| uid.strip_suffix('"')) {
+        Some(uid) => uid.replace("\\\"", "\""),
+        None => uid.to_string(),
+    }
+}
+
+fn text(label: &Option<Label>) -> Option<String> {
+    let text = as_text_sections(label.as_ref()?).join(" ").replace('\n', " ");
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Nodes without text are named after what they represent in the diagram
+fn node_name(dot: &Dot, uid: &str) -> String {
+    // the cross of a flow final is not its name
+    let label = dot.label.clone().filter(|_| dot.shape != DotShape::FlowFinal);
+    text(&label).unwrap_or_else(|| {
+        match dot.shape {
+            DotShape::Circle => "start",
+            DotShape::DoubleCircle => "end",
+            DotShape::FlowFinal => "flow final",
+            DotShape::DoubleOctagon => "error end",
+            DotShape::Diamond => "decision",
+            DotShape::Record => "parallel bar",
+            DotShape::Point => "junction",
+            _ => uid,
+        }
+        .to_string()
+    })
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yuml;
+
+    #[test]
+    fn test_final_node_names() {
+        use crate::model::activity::Element;
+
+        assert_eq!(node_name(&Dot::from(&Element::FlowFinalTag), "A3"), "flow final");
+        assert_eq!(node_name(&Dot::from(&Element::ErrorEndTag), "A4"), "error end");
+    }
+
+    #[test]
+    fn test_add_accessibility() {
+        let parsed = parse_yuml("// {type:activity}\n(start)->(Fill **Kettle**)[when \"empty\"]->(end)").unwrap();
+        let dot_file = match &parsed {
+            crate::parser::ParsedYuml::Activity(dot_file) => dot_file,
+            _ => panic!("not an activity diagram"),
+        };
+
+        // the shape of the SVG output of "dot"
+        let svg = r#"<svg>
+<g id="graph0" class="graph">
+<title>G</title>
+<!-- A1 -->
+<g id="node1" class="node">
+<title>A1</title>
+<ellipse/>
+</g>
+<!-- A2 -->
+<g id="node2" class="node">
+<title>A2</title>
+<path/>
+</g>
+<!-- A1&#45;&gt;A2 -->
+<g id="edge1" class="edge">
+<title>A1&#45;&gt;A2</title>
+</g>
+<!-- A2&#45;&gt;A3 -->
+<g id="edge2" class="edge">
+<title>A2&#45;&gt;A3:n</title>
+</g>
+</g>
+</svg>"#;
+        let accessible = add_accessibility(svg, dot_file);
+        for part in [
+            "<g id=\"graph0\" class=\"graph\">\n<title>G</title>",
+            "<g id=\"node1\" class=\"node\" role=\"img\" aria-label=\"start\">\n<title>start</title>\n<ellipse/>",
+            "<g id=\"node2\" class=\"node\" role=\"img\" aria-label=\"Fill Kettle\">\n<title>Fill Kettle</title>",
+            "<g id=\"edge1\" class=\"edge\" role=\"img\" aria-label=\"start to Fill Kettle\">\n<title>start to Fill Kettle</title>\n</g>",
+            "<title>Fill Kettle to end: when &quot;empty&quot;</title>",
+        ] {
+            assert!(accessible.contains(part), "missing {} in {}", part, accessible);
+        }
+    }
+}
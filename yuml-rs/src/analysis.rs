@@ -0,0 +1,454 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    model::{
+        dot::{DotElement, DotFile, DotShape},
+        label::{as_text_sections, Label},
+    },
+    parser::ParsedYuml,
+};
+use itertools::Itertools;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    fmt::{Display, Formatter},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateKind {
+    Start,
+    End,
+    Activity,
+    Decision,
+    Parallel,
+}
+
+/// A node of an activity diagram, identified by its dot id like `A3`
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    pub id: String,
+    pub label: String,
+    pub kind: StateKind,
+    /// `true` when the state can be reached by following the transitions from a start node
+    pub reachable: bool,
+}
+
+/// An arrow between two states, referring to their index in `Graph::states`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transition {
+    pub from: usize,
+    pub to: usize,
+    pub label: Option<String>,
+}
+
+/// The states of an activity diagram and the transitions between them; notes are left out
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Graph {
+    pub states: Vec<State>,
+    pub transitions: Vec<Transition>,
+}
+
+impl Graph {
+    /// Indices of the states reached directly from the state at `index`
+    pub fn successors(&self, index: usize) -> impl Iterator<Item = usize> + '_ {
+        self.transitions.iter().filter(move |t| t.from == index).map(|t| t.to)
+    }
+
+    /// States, other than start nodes, that can not be reached from any start node
+    pub fn unreachable(&self) -> impl Iterator<Item = &State> {
+        self.states
+            .iter()
+            .filter(|state| !state.reachable && state.kind != StateKind::Start)
+    }
+
+    /// End nodes that can not be reached from any start node
+    pub fn unreachable_ends(&self) -> impl Iterator<Item = &State> {
+        self.unreachable().filter(|state| state.kind == StateKind::End)
+    }
+
+    /// Decisions that do not choose between at least two different states
+    pub fn single_branch_decisions(&self) -> impl Iterator<Item = &State> {
+        self.states.iter().enumerate().filter_map(|(index, state)| {
+            let mut branches: Vec<usize> = self.successors(index).collect();
+            branches.sort_unstable();
+            branches.dedup();
+            (state.kind == StateKind::Decision && branches.len() < 2).then_some(state)
+        })
+    }
+}
+
+/// Convert a parsed activity diagram into a graph of states, marking the states reachable from the start nodes.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{analysis, parse_yuml};
+///
+/// let parsed = parse_yuml("// {type:activity}\n(start)->(Boil Kettle)->(end)\n(Drink Tea)->(end)").unwrap();
+/// let graph = analysis::reachability(&parsed).unwrap();
+/// assert_eq!(graph.unreachable().map(|s| s.label.as_str()).collect::<Vec<_>>(), vec!["Drink Tea"]);
+/// ```
+pub fn reachability(parsed: &ParsedYuml) -> YumlResult<Graph> {
+    match parsed {
+        ParsedYuml::Activity(df) => {
+            let mut graph = as_graph(df);
+            mark_reachable(&mut graph);
+            Ok(graph)
+        }
+        _ => Err(YumlError::Analysis(
+            "only activity diagrams can be analysed".to_string(),
+        )),
+    }
+}
+
+fn kind(shape: &DotShape) -> Option<StateKind> {
+    match shape {
+        DotShape::Circle => Some(StateKind::Start),
+        DotShape::DoubleCircle | DotShape::FlowFinal | DotShape::DoubleOctagon => Some(StateKind::End),
+        DotShape::Rectangle => Some(StateKind::Activity),
+        DotShape::Diamond => Some(StateKind::Decision),
+        DotShape::Record => Some(StateKind::Parallel),
+        _ => None,
+    }
+}
+
+fn label_text(label: &Option<Label>) -> Option<String> {
+    label.as_ref().map(|label| label.as_str().to_string())
+}
+
+/// Edges end at ports of parallel bars, like `A4:f1:n`
+fn node_id(uid: &str) -> &str {
+    uid.split_once(':').map_or(uid, |(id, _)| id)
+}
+
+/// The nodes at both ends of an edge
+fn ends(edge: &DotElement) -> (&str, &str) {
+    (node_id(&edge.uid), node_id(edge.uid2.as_deref().unwrap_or_default()))
+}
+
+/// The two halves of an arrow with a transition note
+#[derive(Default)]
+struct SplitArrow<'a> {
+    from: Option<&'a str>,
+    label: Option<String>,
+    to: Option<&'a str>,
+}
+
+fn as_graph(df: &DotFile) -> Graph {
+    let mut graph = Graph::default();
+    let mut indices: HashMap<&str, usize> = HashMap::new();
+    // arrows with a transition note are split at a point node
+    let mut points: HashMap<&str, SplitArrow> = HashMap::new();
+
+    for node in df.nodes() {
+        match (kind(&node.dot.shape), &node.dot.shape) {
+            (Some(kind), _) => {
+                let label = match kind {
+                    StateKind::Start => "start".to_string(),
+                    StateKind::End => "end".to_string(),
+                    StateKind::Activity => node
+                        .dot
+                        .label
+                        .as_ref()
+                        .map(as_text_sections)
+                        .unwrap_or_default()
+                        .join(" "),
+                    _ => label_text(&node.dot.label).unwrap_or_default(),
+                };
+                indices.insert(&node.uid, graph.states.len());
+                graph.states.push(State {
+                    id: node.uid.clone(),
+                    label: label.trim().to_string(),
+                    kind,
+                    reachable: false,
+                });
+            }
+            (None, DotShape::Point) => {
+                points.insert(&node.uid, SplitArrow::default());
+            }
+            _ => { /* notes are not part of the flow */ }
+        }
+    }
+
+    let mut transitions = vec![];
+    for edge in df.edges() {
+        let from = node_id(&edge.uid);
+        let to = node_id(edge.uid2.as_deref().unwrap_or_default());
+        if let Some(point) = points.get_mut(to) {
+            if indices.contains_key(from) {
+                point.from = Some(from);
+                point.label = label_text(&edge.dot.label);
+            }
+        } else if let Some(point) = points.get_mut(from) {
+            point.to = Some(to);
+        } else {
+            transitions.push((from, to, label_text(&edge.dot.label)));
+        }
+    }
+    transitions.extend(
+        points
+            .into_values()
+            .filter_map(|point| Some((point.from?, point.to?, point.label))),
+    );
+
+    graph.transitions = transitions
+        .into_iter()
+        .filter_map(|(from, to, label)| {
+            Some(Transition {
+                from: *indices.get(from)?,
+                to: *indices.get(to)?,
+                label,
+            })
+        })
+        .collect();
+    graph.transitions.sort_by_key(|t| (t.from, t.to));
+    graph
+}
+
+fn mark_reachable(graph: &mut Graph) {
+    let mut queue: VecDeque<usize> = graph
+        .states
+        .iter()
+        .enumerate()
+        .filter(|(_, state)| state.kind == StateKind::Start)
+        .map(|(index, _)| index)
+        .collect();
+
+    while let Some(index) = queue.pop_front() {
+        if graph.states[index].reachable {
+            continue;
+        }
+        graph.states[index].reachable = true;
+        let next: Vec<usize> = graph.successors(index).collect();
+        queue.extend(next.into_iter().filter(|next| !graph.states[*next].reachable));
+    }
+}
+
+/// Size and complexity measures of a diagram
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Stats {
+    /// Number of nodes per shape, like `rectangle` or `diamond`; the points that split arrows are left out
+    pub nodes: BTreeMap<String, usize>,
+    /// Number of edges per line style, like `solid` or `dashed`; the connectors of notes are left out
+    pub edges: BTreeMap<String, usize>,
+    /// The highest number of edges leaving one node
+    pub max_fan_out: usize,
+    /// `true` when the edges form at least one cycle
+    pub has_cycles: bool,
+    /// The number of edges on the longest path, not following an edge back into the path
+    pub depth: usize,
+}
+
+impl Stats {
+    pub fn node_count(&self) -> usize {
+        self.nodes.values().sum()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.values().sum()
+    }
+}
+
+fn fmt_counts(counts: &BTreeMap<String, usize>) -> String {
+    counts
+        .iter()
+        .map(|(kind, count)| format!("{}: {}", kind, count))
+        .join(", ")
+}
+
+impl Display for Stats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "nodes: {} ({})", self.node_count(), fmt_counts(&self.nodes))?;
+        writeln!(f, "edges: {} ({})", self.edge_count(), fmt_counts(&self.edges))?;
+        writeln!(f, "max fan-out: {}", self.max_fan_out)?;
+        writeln!(f, "cycles: {}", if self.has_cycles { "yes" } else { "no" })?;
+        writeln!(f, "depth: {}", self.depth)
+    }
+}
+
+/// Count the nodes and edges of a diagram and measure the complexity of the graph they form. Notes are counted as
+/// nodes, but are not part of the paths. Edges are taken to point from their first to their second node, also when
+/// they are drawn without an arrowhead.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{analysis, parse_yuml};
+///
+/// let parsed = parse_yuml("// {type:activity}\n(start)->(Fill Kettle)->(Boil Kettle)->(end)").unwrap();
+/// let stats = analysis::stats(&parsed).unwrap();
+/// assert_eq!((stats.node_count(), stats.edge_count(), stats.depth), (4, 3, 3));
+/// ```
+pub fn stats(parsed: &ParsedYuml) -> YumlResult<Stats> {
+    let df = parsed
+        .dot_file()
+        .ok_or_else(|| YumlError::Analysis("unsupported diagrams can not be analysed".to_string()))?;
+
+    let mut stats = Stats::default();
+    let mut indices: HashMap<&str, usize> = HashMap::new();
+    let mut points: HashSet<&str> = HashSet::new();
+    for node in df.nodes() {
+        match node.dot.shape {
+            DotShape::Point => {
+                points.insert(&node.uid);
+            }
+            DotShape::Note => {}
+            _ => {
+                let index = indices.len();
+                indices.insert(&node.uid, index);
+            }
+        }
+        if node.dot.shape != DotShape::Point {
+            *stats.nodes.entry(node.dot.shape.to_string()).or_default() += 1;
+        }
+    }
+
+    // an arrow split at a point runs from each node before the point to each node after it, in the style of the
+    // second half
+    let into_points: Vec<(&str, &str)> = df
+        .edges()
+        .map(ends)
+        .filter(|(from, to)| indices.contains_key(from) && points.contains(to))
+        .collect();
+    let flows = df.edges().flat_map(|edge| {
+        let (from, to) = ends(edge);
+        let starts = match points.contains(from) {
+            true => into_points
+                .iter()
+                .filter(|(_, point)| *point == from)
+                .map(|(start, _)| *start)
+                .collect(),
+            false => vec![from],
+        };
+        starts.into_iter().map(move |from| (from, to, edge))
+    });
+
+    let mut successors: Vec<Vec<usize>> = vec![vec![]; indices.len()];
+    for (from, to, edge) in flows {
+        if let (Some(from), Some(to)) = (indices.get(from), indices.get(to)) {
+            let style = edge
+                .dot
+                .style
+                .first()
+                .map_or("solid".to_string(), |style| style.to_string());
+            *stats.edges.entry(style).or_default() += 1;
+            successors[*from].push(*to);
+        }
+    }
+
+    stats.max_fan_out = successors.iter().map(Vec::len).max().unwrap_or_default();
+    let (has_cycles, depth) = longest_path(&successors);
+    stats.has_cycles = has_cycles;
+    stats.depth = depth;
+    Ok(stats)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Visit {
+    New,
+    OnPath,
+    Done,
+}
+
+/// Whether the graph has cycles, and the length of its longest path, ignoring the edges back into the path
+fn longest_path(successors: &[Vec<usize>]) -> (bool, usize) {
+    fn visit(node: usize, successors: &[Vec<usize>], visits: &mut [Visit], depths: &mut [usize]) -> bool {
+        let mut has_cycles = false;
+        visits[node] = Visit::OnPath;
+        for next in &successors[node] {
+            match visits[*next] {
+                Visit::OnPath => {
+                    has_cycles = true;
+                    continue;
+                }
+                Visit::New => has_cycles |= visit(*next, successors, visits, depths),
+                Visit::Done => {}
+            }
+            depths[node] = depths[node].max(depths[*next] + 1);
+        }
+        visits[node] = Visit::Done;
+        has_cycles
+    }
+
+    let mut visits = vec![Visit::New; successors.len()];
+    let mut depths = vec![0; successors.len()];
+    // start at the nodes without incoming edges, so the paths are as long as possible
+    let targets: HashSet<usize> = successors.iter().flatten().copied().collect();
+    let roots = (0..successors.len()).filter(|node| !targets.contains(node));
+    let mut has_cycles = false;
+    for node in roots.chain(0..successors.len()).collect_vec() {
+        if visits[node] == Visit::New {
+            has_cycles |= visit(node, successors, &mut visits, &mut depths);
+        }
+    }
+
+    (has_cycles, depths.into_iter().max().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yuml;
+
+    fn labels<'a>(states: impl Iterator<Item = &'a State>) -> Vec<&'a str> {
+        states.map(|state| state.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_reachability() {
+        let yuml = r#"// {type:activity}
+(start)-><a>[kettle empty]->(Fill Kettle)->|b|
+<a>[kettle full]->|b|->(Boil Kettle)->(end)
+(Spill Water)->(Mop Floor)-><c>[wet]->(Mop Floor)
+(Boil Kettle)->(Pour Water){note: hot!}
+"#;
+        let graph = reachability(&parse_yuml(yuml).unwrap()).unwrap();
+        assert_eq!(graph.states.len(), 10);
+        assert_eq!(labels(graph.unreachable()), vec!["Spill Water", "Mop Floor", "c"]);
+        assert_eq!(graph.unreachable_ends().count(), 0);
+        assert_eq!(labels(graph.single_branch_decisions()), vec!["c"]);
+
+        // the transition with a note is not interrupted by the note
+        let boil = graph.states.iter().position(|s| s.label == "Boil Kettle").unwrap();
+        let successors: Vec<&str> = graph.successors(boil).map(|i| graph.states[i].label.as_str()).collect();
+        assert_eq!(successors, vec!["end", "Pour Water"]);
+
+        let parsed = parse_yuml("// {type:activity}\n(start)->(Fill Kettle)\n(Boil Kettle)->(end)").unwrap();
+        let graph = reachability(&parsed).unwrap();
+        assert_eq!(labels(graph.unreachable_ends()), vec!["end"]);
+    }
+
+    #[test]
+    fn test_reachability_requires_activity() {
+        let parsed = parse_yuml("// {type:class}\n[A]->[B]").unwrap();
+        assert!(reachability(&parsed).is_err());
+    }
+
+    #[test]
+    fn test_stats() {
+        let yuml = r#"// {type:activity}
+(start)-><a>[kettle empty]->(Fill Kettle)->|b|
+<a>[kettle full]->|b|->(Boil Kettle)->(end)
+(Boil Kettle)->(Pour Water){note: hot!}
+(note: warm up the pot)->(Pour Water)
+"#;
+        let report = stats(&parse_yuml(yuml).unwrap()).unwrap();
+        assert_eq!(
+            report.to_string(),
+            "nodes: 9 (circle: 1, diamond: 1, doublecircle: 1, note: 2, record: 1, rectangle: 3)\n\
+             edges: 7 (solid: 7)\n\
+             max fan-out: 2\n\
+             cycles: no\n\
+             depth: 5\n"
+        );
+
+        let parsed = parse_yuml("// {type:activity}\n(start)->(a)->(b)-><c>->(a)\n<c>->(end)").unwrap();
+        let report = stats(&parsed).unwrap();
+        assert!(report.has_cycles);
+        assert_eq!((report.max_fan_out, report.depth), (2, 4));
+
+        let parsed = parse_yuml("// {type:class}\n[A]-.-[B]\n[A]->[C]\n[B]<-[C]").unwrap();
+        let report = stats(&parsed).unwrap();
+        assert_eq!(report.nodes, BTreeMap::from([("rectangle".to_string(), 3)]));
+        assert_eq!(
+            report.edges,
+            BTreeMap::from([("dashed".to_string(), 1), ("solid".to_string(), 2)])
+        );
+        assert!(!report.has_cycles);
+        assert_eq!(report.depth, 2);
+    }
+}
@@ -0,0 +1,41 @@
+/// Generate an activity diagram with `n` activities, mixing plain flows, labeled decisions and notes, to measure
+/// the parser on big inputs.
+pub fn generate_synthetic(n: usize) -> String {
+    let mut yuml = String::with_capacity(n * 40);
+    yuml.push_str("// {type:activity}\n(start)->(Step 1)\n");
+
+    for i in 1..n {
+        if i % 10 == 0 {
+            yuml.push_str(&format!("(Step {i})-><Check {i}>[ok]->(Step {})\n", i + 1));
+            yuml.push_str(&format!("<Check {i}>[retry]->(Step {})\n", i - 9));
+        } else if i % 25 == 1 {
+            yuml.push_str(&format!("(Step {i})-(note: step {i} of {n}{{bg:cornsilk}})\n"));
+            yuml.push_str(&format!("(Step {i})->(Step {})\n", i + 1));
+        } else {
+            yuml.push_str(&format!("(Step {i})->(Step {})\n", i + 1));
+        }
+    }
+
+    yuml.push_str(&format!("(Step {n})->(end)\n"));
+    yuml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate_yuml;
+
+    #[test]
+    fn test_generate_synthetic() {
+        let yuml = generate_synthetic(100);
+        let report = validate_yuml(&yuml);
+        assert!(report.is_valid());
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        let dot = crate::parse_yuml(&yuml).expect("invalid synthetic file").to_string();
+        // start, end, activities, decisions and notes
+        assert_eq!(dot.matches("shape=\"rectangle\"").count(), 100);
+        assert_eq!(dot.matches("shape=\"diamond\"").count(), 9);
+        assert_eq!(dot.matches("shape=\"note\"").count(), 4);
+    }
+}
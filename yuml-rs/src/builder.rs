@@ -0,0 +1,269 @@
+use crate::{
+    model::{
+        activity::{ArrowProps, Element, ElementProps},
+        dot::{ChartType, Directions, DotFile, Options},
+        shared::NoteProps,
+    },
+    parser::{activity::ActivityParser, DiagramParser, Statement},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Start,
+    End,
+    FlowFinal,
+    ErrorEnd,
+    Activity(String),
+    Decision(String),
+    Parallel(String),
+    Note(String),
+}
+
+impl Node {
+    fn to_yuml(&self) -> String {
+        match self {
+            Node::Start => "(start)".to_string(),
+            Node::End => "(end)".to_string(),
+            Node::FlowFinal => "(end:flow)".to_string(),
+            Node::ErrorEnd => "(end:error)".to_string(),
+            Node::Activity(label) => format!("({})", label),
+            Node::Decision(label) => format!("<{}>", label),
+            Node::Parallel(label) => format!("|{}|", label),
+            Node::Note(label) => format!("(note:{})", label),
+        }
+    }
+
+    fn as_element(&self, dir: &Directions) -> Element<'_> {
+        match self {
+            Node::Start => Element::StartTag,
+            Node::End => Element::EndTag,
+            Node::FlowFinal => Element::FlowFinalTag,
+            Node::ErrorEnd => Element::ErrorEndTag,
+            Node::Activity(label) => Element::Activity(ElementProps::new(label)),
+            Node::Decision(label) => Element::Decision(ElementProps::new(label)),
+            Node::Parallel(label) => Element::Parallel(ElementProps::new(label).direction(dir)),
+            Node::Note(label) => Element::Note(NoteProps {
+                label,
+                attributes: None,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Step {
+    from: Option<Node>,
+    label: Option<String>,
+    to: Node,
+}
+
+/// Escape the delimiters of an element, as the parser expects
+fn escape(text: &str, open: char, close: char) -> String {
+    text.replace(open, &format!("\\{}", open))
+        .replace(close, &format!("\\{}", close))
+}
+
+/// Construct an activity diagram step by step, without writing yUML.
+///
+/// Each step connects the previous element to a new one. Elements with the same label are the same node, so flows can
+/// join by referring to an activity again.
+/// Usage:
+/// ```rust
+/// use yuml_rs::ActivityBuilder;
+///
+/// let builder = ActivityBuilder::new()
+///     .start()
+///     .activity("Fill Kettle")
+///     .decision("empty?")
+///     .branch("yes", |b| b.activity("Fill Kettle"))
+///     .branch("no", |b| b.activity("Boil Kettle").end());
+/// assert!(builder.to_yuml().contains("<empty?>[no]->(Boil Kettle)"));
+/// let dot_file = builder.to_dot_file();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ActivityBuilder {
+    dir: Directions,
+    steps: Vec<Step>,
+    current: Option<Node>,
+    label: Option<String>,
+}
+
+impl ActivityBuilder {
+    pub fn new() -> Self {
+        ActivityBuilder::default()
+    }
+
+    pub fn direction(mut self, dir: Directions) -> Self {
+        self.dir = dir;
+        self
+    }
+
+    pub fn start(self) -> Self {
+        self.step(Node::Start)
+    }
+
+    pub fn end(self) -> Self {
+        self.step(Node::End)
+    }
+
+    /// End this flow only, see `(end:flow)`
+    pub fn flow_final(self) -> Self {
+        self.step(Node::FlowFinal)
+    }
+
+    pub fn error_end(self) -> Self {
+        self.step(Node::ErrorEnd)
+    }
+
+    pub fn activity(self, label: &str) -> Self {
+        self.step(Node::Activity(escape(label, '(', ')')))
+    }
+
+    pub fn decision(self, label: &str) -> Self {
+        self.step(Node::Decision(escape(label, '<', '>')))
+    }
+
+    /// Flows going into the same parallel bar are joined; names can not contain `|`
+    pub fn parallel(self, name: &str) -> Self {
+        self.step(Node::Parallel(name.replace('|', "")))
+    }
+
+    /// Label the next arrow
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(escape(label, '[', ']'));
+        self
+    }
+
+    /// Attach a note to the current element; the flow continues from that element
+    pub fn note(mut self, text: &str) -> Self {
+        let note = Node::Note(escape(text, '(', ')'));
+        let from = self.current.clone();
+        self.steps.push(Step {
+            from,
+            label: None,
+            to: note,
+        });
+        self
+    }
+
+    /// Add a flow leaving the current element along an arrow with `label`; afterwards the current element is the
+    /// same as before, so a decision can have several branches
+    pub fn branch(self, label: &str, flow: impl FnOnce(Self) -> Self) -> Self {
+        let origin = self.current.clone();
+        let mut builder = flow(self.label(label));
+        builder.current = origin;
+        builder.label = None;
+        builder
+    }
+
+    fn step(mut self, node: Node) -> Self {
+        let from = self.current.replace(node.clone());
+        // a lone element is no longer needed once it is connected
+        if let Some(Step { from: None, to, .. }) = self.steps.last() {
+            if from.as_ref() == Some(to) {
+                self.steps.pop();
+            }
+        }
+        let label = self.label.take();
+        self.steps.push(Step { from, label, to: node });
+        self
+    }
+
+    /// The diagram as yUML text, with one arrow per line
+    pub fn to_yuml(&self) -> String {
+        let mut yuml = String::from("// {type:activity}\n");
+        match self.dir {
+            Directions::LeftToRight => yuml.push_str("// {direction:leftToRight}\n"),
+            Directions::RightToLeft => yuml.push_str("// {direction:rightToLeft}\n"),
+            Directions::TopDown => {}
+        }
+
+        for step in &self.steps {
+            let line = match (&step.from, &step.label, &step.to) {
+                (None, _, to) => to.to_yuml(),
+                (Some(from), _, to @ Node::Note(_)) => format!("{}-{}", from.to_yuml(), to.to_yuml()),
+                (Some(from), Some(label), to) => format!("{}[{}]->{}", from.to_yuml(), label, to.to_yuml()),
+                (Some(from), None, to) => format!("{}->{}", from.to_yuml(), to.to_yuml()),
+            };
+            yuml.push_str(&line);
+            yuml.push('\n');
+        }
+        yuml
+    }
+
+    /// The diagram as a dot file, constructed from the elements without parsing yUML
+    pub fn to_dot_file(&self) -> DotFile {
+        let options = Options {
+            dir: self.dir,
+            chart_type: Some(ChartType::Activity),
+            ..Options::default()
+        };
+
+        let statements: Vec<Statement<Element>> = self
+            .steps
+            .iter()
+            .enumerate()
+            .map(|(line, step)| {
+                let to = step.to.as_element(&self.dir);
+                let elements = match &step.from {
+                    Some(from) => {
+                        let has_tail = !matches!(step.to, Node::Note(_));
+                        let arrow = ArrowProps::new(step.label.as_deref(), &self.dir, has_tail);
+                        vec![from.as_element(&self.dir), Element::Arrow(arrow), to]
+                    }
+                    None => vec![to],
+                };
+                Statement { line, elements }
+            })
+            .collect();
+
+        ActivityParser.as_file(&statements, &options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yuml;
+
+    fn kettle() -> ActivityBuilder {
+        ActivityBuilder::new()
+            .direction(Directions::LeftToRight)
+            .start()
+            .activity("Find (Kettle)")
+            .note("bring your own")
+            .decision("empty?")
+            .branch("yes", |b| b.activity("Fill Kettle").parallel("a"))
+            .label("no")
+            .parallel("a")
+            .activity("Boil Kettle")
+            .label("ready [100°C]")
+            .end()
+    }
+
+    #[test]
+    fn test_activity_builder_yuml() {
+        assert_eq!(
+            kettle().to_yuml(),
+            r"// {type:activity}
+// {direction:leftToRight}
+(start)->(Find \(Kettle\))
+(Find \(Kettle\))-(note:bring your own)
+(Find \(Kettle\))-><empty?>
+<empty?>[yes]->(Fill Kettle)
+(Fill Kettle)->|a|
+<empty?>[no]->|a|
+|a|->(Boil Kettle)
+(Boil Kettle)[ready \[100°C\]]->(end)
+"
+        );
+    }
+
+    #[test]
+    fn test_activity_builder_dot_file() {
+        // constructing the elements directly gives the same graph as parsing the yUML
+        let builder = kettle();
+        let parsed = parse_yuml(&builder.to_yuml()).expect("invalid yUML");
+        assert_eq!(builder.to_dot_file().to_string(), parsed.to_string());
+    }
+}
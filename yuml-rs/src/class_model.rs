@@ -0,0 +1,307 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    limits::ParseOptions,
+    macros::expand_macros,
+    model::{
+        attributes::split_attributes,
+        class::{Connector, Element},
+        dot::ChartType,
+        shared::{normalize_label, split_id, LabeledElement},
+    },
+    parser::{class::parse_class_statements, determine_file_options, parse_headers, utils::connection_windows},
+};
+
+/*
+Class model
+Class           [Customer|Forename;Surname|Save()]  name "Customer", attributes and methods split at ';'
+Relations       recorded on the owning class: the whole of an aggregation or composition, the source of an arrow,
+                the specialization of an inheritance and the dependent class of a dependency
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationKind {
+    /// A line without arrows
+    Association,
+    Directional,
+    Bidirectional,
+    Aggregation,
+    Composition,
+    Dependency,
+    Inheritance,
+    /// A dashed inheritance, of an interface
+    Realization,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relation {
+    /// Name of the related class
+    pub target: String,
+    pub kind: RelationKind,
+    /// Label at the end of the owning class, like a cardinality or role name
+    pub source_label: Option<String>,
+    /// Label at the end of the related class
+    pub target_label: Option<String>,
+    /// Label of the relation itself, like the `uses` of a dependency
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Class {
+    pub name: String,
+    pub attributes: Vec<String>,
+    pub methods: Vec<String>,
+    pub relations: Vec<Relation>,
+}
+
+/// Parse a class diagram into its classes, in the order in which they first appear.
+///
+/// A class that is mentioned several times is listed once; its attributes and methods are taken from the mentions
+/// that have them. Notes are left out.
+/// Usage:
+/// ```rust
+/// use yuml_rs::class_model::{extract_classes, RelationKind};
+///
+/// let classes = extract_classes("// {type:class}\n[Customer|Name;Email|Save()]<>1-*[Order]").unwrap();
+/// assert_eq!(classes[0].attributes, vec!["Name", "Email"]);
+/// assert_eq!(classes[0].relations[0].kind, RelationKind::Aggregation);
+/// ```
+pub fn extract_classes(yuml: &str) -> YumlResult<Vec<Class>> {
+    let yuml = expand_macros(yuml, &ParseOptions::default())?;
+    let (body, headers) = parse_headers(&yuml).map_err(|err| YumlError::InvalidFile(err.to_string()))?;
+    let options = determine_file_options(&headers);
+    if options.chart_type != Some(ChartType::Class) {
+        return Err(YumlError::Analysis(
+            "only class diagrams have a class model".to_string(),
+        ));
+    }
+
+    let (_, statements) =
+        parse_class_statements(body, &options).map_err(|err| YumlError::InvalidFile(err.to_string()))?;
+
+    // classes are identified by their explicit id or their name, as in the rendered diagram
+    let mut keys: Vec<String> = vec![];
+    let mut classes: Vec<Class> = vec![];
+    // association classes come last, as in the rendered diagram
+    let elements = statements.iter().flat_map(|s| s.elements.iter());
+    for element in elements.clone().cloned().chain(elements.filter_map(association_class)) {
+        if let Element::Class(text) = element {
+            let class = as_class(text);
+            let key = normalize_label(element.label());
+            match keys.iter().position(|k| *k == key) {
+                Some(idx) if class.attributes.is_empty() && class.methods.is_empty() => {
+                    if element.has_id() {
+                        classes[idx].name = class.name;
+                    }
+                }
+                Some(idx) => classes[idx] = class,
+                None => {
+                    keys.push(key);
+                    classes.push(class);
+                }
+            }
+        }
+    }
+
+    for (pre, connection, next) in connection_windows(&statements) {
+        let (source, target) = match (
+            keys.iter().position(|k| *k == normalize_label(pre.label())),
+            keys.iter().position(|k| *k == normalize_label(next.label())),
+        ) {
+            (Some(source), Some(target)) => (source, target),
+            _ => continue, // notes
+        };
+
+        let (owner, relation) = match as_relation(connection) {
+            Some((kind, source_label, target_label, false)) => (source, (kind, target, source_label, target_label)),
+            Some((kind, source_label, target_label, true)) => (target, (kind, source, target_label, source_label)),
+            None => continue,
+        };
+        let (kind, target, source_label, target_label) = relation;
+        let target = classes[target].name.clone();
+        let label = match connection {
+            Element::Connection(connection) => connection.label,
+            _ => None,
+        };
+        classes[owner].relations.push(Relation {
+            target,
+            kind,
+            source_label: source_label.map(str::to_string),
+            target_label: target_label.map(str::to_string),
+            label: label.map(str::to_string),
+        });
+    }
+
+    Ok(classes)
+}
+
+/// The association class declared on a connection
+fn association_class<'a>(element: &Element<'a>) -> Option<Element<'a>> {
+    match element {
+        Element::Connection(connection) => connection.association.map(Element::Class),
+        _ => None,
+    }
+}
+
+fn as_class(text: &str) -> Class {
+    let (label, _) = split_attributes(text);
+    let (_, label) = split_id(label);
+    let mut sections = label.split('|');
+    let mut members = || {
+        sections
+            .next()
+            .map(|section| {
+                section
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|member| !member.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let name: Vec<String> = members();
+    Class {
+        name: name.join(" "),
+        attributes: members(),
+        methods: members(),
+        relations: vec![],
+    }
+}
+
+/// The kind of relation with the labels on the left and right end, and whether it is owned by the right class
+fn as_relation<'a>(element: &Element<'a>) -> Option<(RelationKind, Option<&'a str>, Option<&'a str>, bool)> {
+    let connection = match element {
+        Element::Connection(connection) => connection,
+        Element::Inheritance(_) => return Some((RelationKind::Inheritance, None, None, true)),
+        Element::Note(_) | Element::Class(_) | Element::AssociationClass(_) => return None,
+    };
+
+    let label = |connector: &Connector<'a>| match connector {
+        Connector::None(props)
+        | Connector::Directional(props)
+        | Connector::Aggregation(props)
+        | Connector::Composition(props)
+        | Connector::Inheritance(props)
+        | Connector::Dependencies(props)
+        | Connector::Cardinality(props) => props.label.map(str::trim).filter(|label| !label.is_empty()),
+    };
+    let (left, right) = (label(&connection.left), label(&connection.right));
+    let relation = |kind, owned_by_right| Some((kind, left, right, owned_by_right));
+
+    let inheritance = if connection.dashed {
+        RelationKind::Realization
+    } else {
+        RelationKind::Inheritance
+    };
+    match (&connection.left, &connection.right) {
+        (Connector::Inheritance(_), _) => relation(inheritance, true),
+        (_, Connector::Inheritance(_)) => relation(inheritance, false),
+        (Connector::Composition(_), _) => relation(RelationKind::Composition, false),
+        (_, Connector::Composition(_)) => relation(RelationKind::Composition, true),
+        (Connector::Aggregation(_) | Connector::Cardinality(_), _) => relation(RelationKind::Aggregation, false),
+        (_, Connector::Aggregation(_) | Connector::Cardinality(_)) => relation(RelationKind::Aggregation, true),
+        _ if connection.dashed => match connection.left {
+            Connector::Directional(_) => relation(RelationKind::Dependency, true),
+            _ => relation(RelationKind::Dependency, false),
+        },
+        (Connector::Directional(_), Connector::Directional(_)) => relation(RelationKind::Bidirectional, false),
+        (Connector::Directional(_), _) => relation(RelationKind::Directional, true),
+        (_, Connector::Directional(_)) => relation(RelationKind::Directional, false),
+        _ => relation(RelationKind::Association, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation(target: &str, kind: RelationKind, source_label: Option<&str>, target_label: Option<&str>) -> Relation {
+        Relation {
+            target: target.to_string(),
+            kind,
+            source_label: source_label.map(str::to_string),
+            target_label: target_label.map(str::to_string),
+            label: None,
+        }
+    }
+
+    #[test]
+    fn test_extract_classes() {
+        let yuml = r#"// {type:class}
+[Customer]<>1-orders 0..*>[Order]
+[Customer|Forename; Surname;Email|Save()]
+[Order]++-[LineItem{bg:green}]
+[Customer]^[Premium Customer]
+[Order]uses-.->[PaymentStrategy]
+[LineItem]-[note: Value Object]
+[Order]<->[Invoice]
+[Invoice]<-[id=pdf|PDF Document]
+"#;
+        let classes = extract_classes(yuml).unwrap();
+        let names: Vec<&str> = classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Customer",
+                "Order",
+                "LineItem",
+                "Premium Customer",
+                "PaymentStrategy",
+                "Invoice",
+                "PDF Document"
+            ]
+        );
+
+        let customer = &classes[0];
+        assert_eq!(customer.attributes, vec!["Forename", "Surname", "Email"]);
+        assert_eq!(customer.methods, vec!["Save()"]);
+        assert_eq!(
+            customer.relations,
+            vec![relation(
+                "Order",
+                RelationKind::Aggregation,
+                Some("1"),
+                Some("orders 0..*")
+            )]
+        );
+
+        assert_eq!(
+            classes[1].relations,
+            vec![
+                relation("LineItem", RelationKind::Composition, None, None),
+                Relation {
+                    label: Some("uses".to_string()),
+                    ..relation("PaymentStrategy", RelationKind::Dependency, None, None)
+                },
+                relation("Invoice", RelationKind::Bidirectional, None, None),
+            ]
+        );
+        assert_eq!(
+            classes[3].relations,
+            vec![relation("Customer", RelationKind::Inheritance, None, None)]
+        );
+        assert_eq!(
+            classes[6].relations,
+            vec![relation("Invoice", RelationKind::Directional, None, None)]
+        );
+    }
+
+    #[test]
+    fn test_extract_uml_relations() {
+        let yuml = "// {type:class}\n[Impl]-.-^[Interface]\n[Student]-[Course]{class:Enrollment|grade}";
+        let classes = extract_classes(yuml).unwrap();
+        let names: Vec<&str> = classes.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Impl", "Interface", "Student", "Course", "Enrollment"]);
+        assert_eq!(classes[4].attributes, vec!["grade"]);
+        assert_eq!(
+            classes[0].relations,
+            vec![relation("Interface", RelationKind::Realization, None, None)]
+        );
+    }
+
+    #[test]
+    fn test_extract_classes_requires_class_diagram() {
+        assert!(extract_classes("// {type:activity}\n(start)->(end)").is_err());
+    }
+}
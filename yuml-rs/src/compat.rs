@@ -0,0 +1,37 @@
+//! The entry point of the original `yuml-diagram` API, for users migrating from it
+
+use crate::{error::YumlResult, model::theme::Theme, parse_yuml, render_svg_from_dot_to, RenderOptions};
+
+/// Render the yUML document as SVG, with light lines and text for pages with a dark background when `is_dark`.
+/// Usage:
+/// ```rust,no_run
+/// use yuml_rs::compat::process_yuml_document;
+///
+/// let svg = process_yuml_document("// {type:activity}\n(start)->(end)", true).expect("invalid yUML");
+/// ```
+pub fn process_yuml_document(text: &str, is_dark: bool) -> YumlResult<String> {
+    let parsed = parse_yuml(text)?;
+    let parsed = if is_dark { parsed.theme(Theme::dark()) } else { parsed };
+
+    let mut svg = Vec::new();
+    render_svg_from_dot_to(&parsed.to_string(), &mut svg, &RenderOptions::default())?;
+    Ok(String::from_utf8_lossy(&svg).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graphviz_version, RenderOptions};
+
+    #[test]
+    fn test_process_yuml_document() {
+        if graphviz_version(&RenderOptions::default()).is_err() {
+            // graphviz is not installed
+            return;
+        }
+
+        let svg = process_yuml_document("// {type:activity}\n(start)->(end)", true).expect("can not render");
+        assert!(svg.contains("<svg"));
+        assert!(process_yuml_document("// {type:activity}\n(start", false).is_err());
+    }
+}
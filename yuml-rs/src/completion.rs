@@ -0,0 +1,193 @@
+use crate::{
+    model::{
+        dot::ChartType,
+        shared::{normalize_label, LabeledElement},
+    },
+    parser::{
+        activity::parse_activity_statements, class::parse_class_statements, determine_file_options,
+        er::parse_er_statements, mindmap::parse_mindmap_statements, parse_headers, timeline::parse_timeline_statements,
+        Statement,
+    },
+    validate::line_of,
+};
+use nom::IResult;
+use std::{collections::HashSet, ops::Range};
+
+/// A node defined in a yUML document, which can be referred to by its label
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub label: String,
+    /// Human readable name of the element type, like `decision` or `class`
+    pub kind: &'static str,
+    /// 1-based line of the first mention of the node
+    pub line: usize,
+    /// Byte range of the label in the document
+    pub span: Range<usize>,
+}
+
+/// The nodes defined in the document, in the order of their first mention, to offer completions in editors.
+///
+/// Documents which are being edited are often invalid; the statements before the first invalid line are used then.
+/// Start and end nodes and notes are left out, as they are never referred to by their label.
+/// Usage:
+/// ```rust
+/// use yuml_rs::defined_symbols;
+///
+/// let symbols = defined_symbols("// {type:activity}\n(start)-><d1>[empty]->(Fill Kettle)\n<d1>->(");
+/// let labels: Vec<&str> = symbols.iter().map(|s| s.label.as_str()).collect();
+/// assert_eq!(labels, vec!["d1", "Fill Kettle"]);
+/// assert_eq!(symbols[0].span, 29..31);
+/// ```
+pub fn defined_symbols(yuml: &str) -> Vec<Symbol> {
+    let (body, headers) = match parse_headers(yuml) {
+        Ok(parsed) => parsed,
+        Err(_) => return vec![],
+    };
+    let options = determine_file_options(&headers);
+    let offset = line_of(yuml, body) - 1;
+
+    match options.chart_type {
+        Some(ChartType::Activity) => {
+            let statements = parse_so_far(body, |body| parse_activity_statements(body, &options));
+            as_symbols(yuml, &statements, offset)
+        }
+        Some(ChartType::Class) => {
+            let statements = parse_so_far(body, |body| parse_class_statements(body, &options));
+            as_symbols(yuml, &statements, offset)
+        }
+        Some(ChartType::Timeline) => {
+            let statements = parse_so_far(body, |body| parse_timeline_statements(body, &options));
+            as_symbols(yuml, &statements, offset)
+        }
+        Some(ChartType::Er) => {
+            let statements = parse_so_far(body, |body| parse_er_statements(body, &options));
+            as_symbols(yuml, &statements, offset)
+        }
+        Some(ChartType::Mindmap) => {
+            let statements = parse_so_far(body, |body| parse_mindmap_statements(body, &options));
+            as_symbols(yuml, &statements, offset)
+        }
+        _ => vec![],
+    }
+}
+
+/// The values a directive accepts, like `leftToRight` for `direction`; empty for directives with free-form values
+/// like `title`, and for unknown directives.
+/// Usage:
+/// ```rust
+/// use yuml_rs::directive_values;
+///
+/// assert_eq!(directive_values("direction"), &["topDown", "leftToRight", "rightToLeft"]);
+/// ```
+pub fn directive_values(key: &str) -> &'static [&'static str] {
+    match key {
+        "type" => &["activity", "class", "timeline", "er", "mindmap"],
+        "direction" => &["topDown", "leftToRight", "rightToLeft"],
+        "theme" => &["plain", "scruffy-like", "monochrome", "corporate", "dark"],
+        "splines" => &["spline", "line", "polyline", "ortho", "curved", "none"],
+        "ordering" => &["out", "in"],
+        "layout" => &["dot", "neato", "fdp", "twopi", "circo"],
+        "env" => &["prod", "staging", "dev", "test"],
+        "generate" | "concentrate" | "strict" | "namespaces" => &["true", "false"],
+        _ => &[],
+    }
+}
+
+/// Parse the statements of the body, or of the lines before the first invalid one
+fn parse_so_far<'a, T>(
+    body: &'a str,
+    parse: impl Fn(&'a str) -> IResult<&'a str, Vec<Statement<T>>>,
+) -> Vec<Statement<T>> {
+    match parse(body) {
+        Ok((_, statements)) => statements,
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            let invalid = body.len() - e.input.len();
+            let valid = body[..invalid].rfind('\n').map_or(0, |idx| idx + 1);
+            parse(&body[..valid])
+                .map(|(_, statements)| statements)
+                .unwrap_or_default()
+        }
+        Err(nom::Err::Incomplete(_)) => vec![],
+    }
+}
+
+fn as_symbols<T: LabeledElement>(yuml: &str, statements: &[Statement<T>], offset: usize) -> Vec<Symbol> {
+    let mut seen = HashSet::new();
+    let mut symbols = vec![];
+
+    for statement in statements {
+        for e in &statement.elements {
+            if e.is_connection() || e.kind() == "note" || e.label().trim().is_empty() {
+                continue;
+            }
+
+            // labels are part of the document, except those of start and end nodes
+            let start = (e.label().as_ptr() as usize).wrapping_sub(yuml.as_ptr() as usize);
+            if start > yuml.len() || !seen.insert(normalize_label(e.label())) {
+                continue;
+            }
+
+            symbols.push(Symbol {
+                label: e.label().to_string(),
+                kind: e.kind(),
+                line: offset + statement.line + 1,
+                span: start..start + e.label().len(),
+            });
+        }
+    }
+
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(yuml: &str) -> Vec<(String, &'static str, usize)> {
+        defined_symbols(yuml)
+            .into_iter()
+            .map(|s| (s.label, s.kind, s.line))
+            .collect()
+    }
+
+    #[test]
+    fn test_activity_symbols() {
+        let yuml =
+            "// {type:activity}\n(start)->|a|->(Boil Kettle)\n|a|->(Fill Kettle)-(note:cold)\n( Boil  Kettle )->(end)";
+        assert_eq!(
+            labels(yuml),
+            vec![
+                ("a".to_string(), "parallel bar", 2),
+                ("Boil Kettle".to_string(), "activity", 2),
+                ("Fill Kettle".to_string(), "activity", 3),
+            ]
+        );
+
+        let symbols = defined_symbols(yuml);
+        assert!(symbols.iter().all(|s| yuml[s.span.clone()] == s.label));
+    }
+
+    #[test]
+    fn test_symbols_so_far() {
+        let yuml = "// {type:class}\n[Customer]->[Order]\n[Order]-\n[Item";
+        assert_eq!(
+            labels(yuml),
+            vec![("Customer".to_string(), "class", 2), ("Order".to_string(), "class", 2)]
+        );
+        assert!(labels("// {type:class}\n[Customer").is_empty());
+        assert!(labels("[Customer]").is_empty());
+    }
+
+    #[test]
+    fn test_directive_values() {
+        use crate::model::theme::Theme;
+
+        for theme in directive_values("theme") {
+            assert!(Theme::try_from(*theme).is_ok());
+        }
+        for chart_type in directive_values("type") {
+            assert!(ChartType::try_from(*chart_type).is_ok());
+        }
+        assert!(directive_values("title").is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::{
+    model::{
+        dot::{ChartType, DotElement, DotShape},
+        label::{as_dot_label, as_text_sections},
+    },
+    parser::ParsedYuml,
+};
+use std::{
+    fmt::{Debug, Formatter},
+    sync::Arc,
+};
+
+/*
+Custom elements, registered by prefix
+Class           [kafka:orders], [kafka:orders{bg:red}]
+Activity        (kafka:orders)
+*/
+
+/// A notation extension for nodes written as `prefix:text`, like a `[kafka:orders]` topic in a class diagram.
+/// Closures returning the attribute block implement the trait as well.
+pub trait CustomElement: Send + Sync {
+    /// The label of the node for the `text` after the prefix, like `orders` for `[kafka:orders]`
+    fn label(&self, text: &str) -> String {
+        text.to_string()
+    }
+
+    /// The attribute block of the node, like `shape:cylinder,bg:lightyellow`, applied on top of the attributes of the
+    /// chart type and the diagram
+    fn attributes(&self, text: &str) -> String;
+}
+
+impl<F> CustomElement for F
+where
+    F: Fn(&str) -> String + Send + Sync,
+{
+    fn attributes(&self, text: &str) -> String {
+        self(text)
+    }
+}
+
+/// The custom elements of each chart type, keyed by their prefix
+#[derive(Clone, Default)]
+pub struct ElementRegistry {
+    elements: Vec<(ChartType, String, Arc<dyn CustomElement>)>,
+}
+
+impl Debug for ElementRegistry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.elements.iter().map(|(chart_type, prefix, _)| (chart_type, prefix)))
+            .finish()
+    }
+}
+
+impl ElementRegistry {
+    /// Register the `element` for nodes written as `prefix:...` in diagrams of the `chart_type`, replacing an earlier
+    /// registration of the prefix
+    pub fn register(mut self, chart_type: ChartType, prefix: &str, element: impl CustomElement + 'static) -> Self {
+        self.elements
+            .retain(|(registered, registered_prefix, _)| *registered != chart_type || registered_prefix != prefix);
+        self.elements.push((chart_type, prefix.to_string(), Arc::new(element)));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// The element registered for the text of a node, with the text after the prefix
+    fn lookup<'a>(&self, chart_type: ChartType, text: &'a str) -> Option<(&dyn CustomElement, &'a str)> {
+        self.elements
+            .iter()
+            .filter(|(registered, _, _)| *registered == chart_type)
+            .find_map(|(_, prefix, element)| {
+                let rest = text.strip_prefix(prefix.as_str())?.strip_prefix(':')?;
+                Some((element.as_ref(), rest.trim()))
+            })
+    }
+
+    /// Replace the label and apply the attributes of the nodes written with a registered prefix
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "custom_elements", level = "debug", skip_all)
+    )]
+    pub(crate) fn apply(&self, parsed: ParsedYuml) -> ParsedYuml {
+        let chart_type = match parsed.chart_type() {
+            Some(chart_type) if !self.is_empty() => chart_type,
+            _ => return parsed,
+        };
+
+        parsed.map_nodes(|node| self.apply_to_node(chart_type, node))
+    }
+
+    fn apply_to_node(&self, chart_type: ChartType, node: &mut DotElement) {
+        if matches!(node.dot.shape, DotShape::Note | DotShape::Point | DotShape::Edge) {
+            return;
+        }
+
+        // only single section nodes, like `[kafka:orders]` but not `[kafka:orders|key]`
+        let sections = node.dot.label.as_ref().map(as_text_sections).unwrap_or_default();
+        let text = match sections.as_slice() {
+            [text] => text.trim(),
+            _ => return,
+        };
+
+        if let Some((element, rest)) = self.lookup(chart_type, text) {
+            let dot = std::mem::take(&mut node.dot);
+            node.dot = dot.with_attributes(Some(&element.attributes(rest)));
+            node.dot.label = Some(as_dot_label(&element.label(rest)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_yuml_with_options, ParseOptions};
+
+    struct Topic;
+
+    impl CustomElement for Topic {
+        fn label(&self, text: &str) -> String {
+            format!("topic {}", text)
+        }
+
+        fn attributes(&self, _text: &str) -> String {
+            "shape:cylinder,bg:lightyellow".to_string()
+        }
+    }
+
+    fn dot(yuml: &str, options: &ParseOptions) -> String {
+        parse_yuml_with_options(yuml, options).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_custom_elements() {
+        let options = ParseOptions::default()
+            .custom_element(ChartType::Class, "kafka", Topic)
+            .custom_element(ChartType::Activity, "queue", |text: &str| {
+                format!("shape:box3d,tooltip:{}", text)
+            });
+
+        let class = dot(
+            "// {type:class}\n[kafka:orders]->[Customer|name]\n[kafka:x|y]",
+            &options,
+        );
+        assert!(class.contains(
+            "label=\"topic orders\", style=\"filled\", fillcolor=\"lightyellow\", arrowtail=\"none\", \
+             arrowhead=\"none\", height=0.5, fontsize=10, shape=\"cylinder\"]"
+        ));
+        assert!(class.contains("<TD>kafka:x</TD>"));
+
+        let activity = dot(
+            "// {type:activity}\n(start)->(queue:jobs)->(kafka:orders)->(end)",
+            &options,
+        );
+        assert!(activity.contains("label=\"jobs\", style=\"rounded\""));
+        assert!(activity.contains("shape=\"box3d\", tooltip=\"jobs\"]"));
+        assert!(activity.contains("label=\"kafka:orders\""));
+
+        // the element of the last registration of a prefix is used
+        let options = options.custom_element(ChartType::Class, "kafka", |_: &str| "bg:red".to_string());
+        let class = dot("// {type:class}\n[kafka:orders]", &options);
+        assert!(class.contains("label=\"orders\", style=\"filled\", fillcolor=\"red\""));
+    }
+}
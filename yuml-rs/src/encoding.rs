@@ -0,0 +1,112 @@
+use crate::error::{YumlError, YumlResult};
+use std::borrow::Cow;
+
+const UTF8_BOM: &[u8] = &[0xef, 0xbb, 0xbf];
+const UTF16LE_BOM: &[u8] = &[0xff, 0xfe];
+const UTF16BE_BOM: &[u8] = &[0xfe, 0xff];
+const UTF32LE_BOM: &[u8] = &[0xff, 0xfe, 0, 0];
+const UTF32BE_BOM: &[u8] = &[0, 0, 0xfe, 0xff];
+
+/// The text of a yUML file: UTF-8, with or without the byte order mark some Windows editors write, or UTF-16 as
+/// written by Windows tools, which is converted to UTF-8. Other encodings fail with `YumlError::Encoding`.
+pub fn decode_yuml(bytes: &[u8]) -> YumlResult<Cow<'_, str>> {
+    if bytes.starts_with(UTF32LE_BOM) || bytes.starts_with(UTF32BE_BOM) {
+        let encoding = if bytes.starts_with(UTF32LE_BOM) {
+            "UTF-32LE"
+        } else {
+            "UTF-32BE"
+        };
+        return Err(encoding_error(encoding, "convert the file to UTF-8"));
+    }
+
+    if let Some(text) = bytes.strip_prefix(UTF8_BOM) {
+        return utf8(text);
+    }
+    if let Some(text) = bytes.strip_prefix(UTF16LE_BOM) {
+        return utf16(text, "UTF-16LE", u16::from_le_bytes);
+    }
+    if let Some(text) = bytes.strip_prefix(UTF16BE_BOM) {
+        return utf16(text, "UTF-16BE", u16::from_be_bytes);
+    }
+
+    // yUML starts with ASCII, which UTF-16 without a byte order mark writes with a zero byte
+    match bytes {
+        [b, 0, ..] if *b != 0 => utf16(bytes, "UTF-16LE", u16::from_le_bytes),
+        [0, b, ..] if *b != 0 => utf16(bytes, "UTF-16BE", u16::from_be_bytes),
+        _ => utf8(bytes),
+    }
+}
+
+fn utf8(bytes: &[u8]) -> YumlResult<Cow<'_, str>> {
+    std::str::from_utf8(bytes)
+        .map(Cow::Borrowed)
+        .map_err(|err| encoding_error("UTF-8", &format!("not valid UTF-8, {}", err)))
+}
+
+fn utf16(bytes: &[u8], encoding: &'static str, decode: fn([u8; 2]) -> u16) -> YumlResult<Cow<'static, str>> {
+    let chunks = bytes.chunks_exact(2);
+    if !chunks.remainder().is_empty() {
+        return Err(encoding_error(encoding, "the file has an odd number of bytes"));
+    }
+
+    let units = chunks.map(|unit| decode([unit[0], unit[1]]));
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map(Cow::Owned)
+        .map_err(|err| encoding_error(encoding, &err.to_string()))
+}
+
+fn encoding_error(encoding: &'static str, message: &str) -> YumlError {
+    YumlError::Encoding {
+        encoding,
+        message: message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const YUML: &str = "// {type:activity}\n(start)->(Kétel)";
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn error(bytes: &[u8]) -> String {
+        decode_yuml(bytes).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_decode_yuml() {
+        assert!(matches!(decode_yuml(YUML.as_bytes()), Ok(Cow::Borrowed(YUML))));
+        assert_eq!(decode_yuml(&[UTF8_BOM, YUML.as_bytes()].concat()).unwrap(), YUML);
+        assert_eq!(decode_yuml(&[UTF16LE_BOM, &utf16le(YUML)].concat()).unwrap(), YUML);
+        assert_eq!(decode_yuml(&utf16le(YUML)).unwrap(), YUML);
+
+        let utf16be: Vec<u8> = YUML.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        assert_eq!(decode_yuml(&[UTF16BE_BOM, &utf16be].concat()).unwrap(), YUML);
+        assert_eq!(decode_yuml(&utf16be).unwrap(), YUML);
+        assert_eq!(decode_yuml(b"").unwrap(), "");
+    }
+
+    #[test]
+    fn test_encoding_errors() {
+        assert_eq!(
+            error(b"(K\xe9tel)"),
+            "Can not read UTF-8 encoded yUML: not valid UTF-8, invalid utf-8 sequence of 1 bytes from index 2"
+        );
+        assert_eq!(
+            error(&[UTF32LE_BOM, b"(\0\0\0"].concat()),
+            "Can not read UTF-32LE encoded yUML: convert the file to UTF-8"
+        );
+        assert_eq!(
+            error(&[UTF16LE_BOM, b"(\0a"].concat()),
+            "Can not read UTF-16LE encoded yUML: the file has an odd number of bytes"
+        );
+        assert_eq!(
+            error(&[UTF16LE_BOM, &[0x00, 0xd8, b'a', 0]].concat()),
+            "Can not read UTF-16LE encoded yUML: unpaired surrogate found: d800"
+        );
+    }
+}
@@ -27,8 +27,58 @@ pub enum YumlError {
     Io {
         source: std::io::Error,
     },
+    #[cfg(feature = "serde")]
+    Json {
+        source: serde_json::Error,
+    },
     #[display(fmt = "Invalid yUML file: {}", _.0)]
     InvalidFile(#[error(not(source))] String),
+    #[display(fmt = "Can not read {} encoded yUML: {}", encoding, message)]
+    #[from(ignore)]
+    Encoding {
+        encoding: &'static str,
+        message: String,
+    },
+    #[display(fmt = "Invalid yuml.me URL: {}", _0)]
+    #[from(ignore)]
+    InvalidUrl(#[error(not(source))] String),
+    #[display(fmt = "Rendering failed: {}", _0)]
+    #[from(ignore)]
+    Render(#[error(not(source))] String),
+    #[display(fmt = "Rendering timed out after {:?}", _0)]
+    #[from(ignore)]
+    RenderTimeout(#[error(not(source))] std::time::Duration),
+    #[display(fmt = "Rendered output exceeds the limit of {} bytes", _0)]
+    #[from(ignore)]
+    OutputTooLarge(#[error(not(source))] usize),
+    #[display(fmt = "Can not import diagram: {}", _0)]
+    #[from(ignore)]
+    Import(#[error(not(source))] String),
+    #[display(fmt = "Can not include fragment {}", _0)]
+    #[from(ignore)]
+    Include(#[error(not(source))] String),
+    #[display(fmt = "Can not analyse diagram: {}", _0)]
+    #[from(ignore)]
+    Analysis(#[error(not(source))] String),
+    #[display(fmt = "Diagram has issues: {}", _0)]
+    #[from(ignore)]
+    Lint(#[error(not(source))] String),
+    #[display(fmt = "Invalid edit: {}", _0)]
+    #[from(ignore)]
+    Edit(#[error(not(source))] String),
+    #[display(fmt = "Diagram exceeds the limit of {} {}: {}", max, what, actual)]
+    #[from(ignore)]
+    LimitExceeded {
+        what: &'static str,
+        max: usize,
+        actual: usize,
+    },
+    #[display(fmt = "Invalid macro '{}': {}", name, message)]
+    #[from(ignore)]
+    Macro {
+        name: String,
+        message: String,
+    },
 }
 
 pub type YumlResult<T> = Result<T, YumlError>;
@@ -0,0 +1,74 @@
+use crate::{
+    parser::ParsedYuml,
+    render::{render_svg_from_dot_to, RenderOptions},
+};
+
+/// A rendered diagram, which the [evcxr](https://github.com/evcxr/evcxr) Jupyter kernel shows inline
+#[derive(Debug, Clone, PartialEq)]
+pub struct SvgImage {
+    svg: Result<String, String>,
+}
+
+impl SvgImage {
+    /// The SVG document, `None` when the diagram could not be rendered
+    pub fn svg(&self) -> Option<&str> {
+        self.svg.as_deref().ok()
+    }
+
+    /// The output of the rich display protocol of evcxr: the image, or the reason why it could not be rendered
+    fn evcxr_content(&self) -> String {
+        match &self.svg {
+            Ok(svg) => format!("EVCXR_BEGIN_CONTENT image/svg+xml\n{}\nEVCXR_END_CONTENT", svg),
+            Err(err) => format!("EVCXR_BEGIN_CONTENT text/plain\n{}\nEVCXR_END_CONTENT", err),
+        }
+    }
+
+    /// Called by evcxr to display the value of a cell
+    pub fn evcxr_display(&self) {
+        println!("{}", self.evcxr_content());
+    }
+}
+
+impl ParsedYuml {
+    /// Render the diagram as SVG, to show it inline in a Rust Jupyter notebook.
+    /// Usage:
+    /// ```rust,no_run
+    /// use yuml_rs::parse_yuml;
+    ///
+    /// // the last expression of a notebook cell
+    /// parse_yuml("// {type:activity}\n(start)->(Fill Kettle)->(end)").unwrap().show();
+    /// ```
+    pub fn show(&self) -> SvgImage {
+        let mut svg = Vec::new();
+        let svg = render_svg_from_dot_to(&self.to_string(), &mut svg, &RenderOptions::default())
+            .map(|_| String::from_utf8_lossy(&svg).to_string())
+            .map_err(|err| err.to_string());
+        SvgImage { svg }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evcxr_content() {
+        let image = SvgImage {
+            svg: Ok("<svg/>".to_string()),
+        };
+        assert_eq!(image.svg(), Some("<svg/>"));
+        assert_eq!(
+            image.evcxr_content(),
+            "EVCXR_BEGIN_CONTENT image/svg+xml\n<svg/>\nEVCXR_END_CONTENT"
+        );
+
+        let image = SvgImage {
+            svg: Err("Render error: syntax error".to_string()),
+        };
+        assert_eq!(image.svg(), None);
+        assert_eq!(
+            image.evcxr_content(),
+            "EVCXR_BEGIN_CONTENT text/plain\nRender error: syntax error\nEVCXR_END_CONTENT"
+        );
+    }
+}
@@ -0,0 +1,44 @@
+use crate::model::dot::ChartType;
+
+/// Sample documents of the supported chart types, ready to be edited
+const EXAMPLES: &[(ChartType, &str)] = &[
+    (ChartType::Activity, include_str!("../test/activity.yuml")),
+    (ChartType::Class, include_str!("../test/class.yuml")),
+    (ChartType::Timeline, include_str!("../test/timeline.yuml")),
+    (ChartType::Er, include_str!("../test/er.yuml")),
+    (ChartType::Mindmap, include_str!("../test/mindmap.yuml")),
+];
+
+/// The sample documents of all supported chart types
+pub fn examples() -> impl Iterator<Item = (ChartType, &'static str)> {
+    EXAMPLES.iter().copied()
+}
+
+/// The sample document of the `chart_type`, `None` for unsupported chart types
+/// Usage:
+/// ```rust
+/// use yuml_rs::{example, ChartType};
+///
+/// assert!(example(ChartType::Class).unwrap().starts_with("// {type:class}"));
+/// assert!(example(ChartType::Sequence).is_none());
+/// ```
+pub fn example(chart_type: ChartType) -> Option<&'static str> {
+    examples()
+        .find(|(example_type, _)| *example_type == chart_type)
+        .map(|(_, yuml)| yuml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yuml;
+
+    #[test]
+    fn test_examples() {
+        for (chart_type, yuml) in examples() {
+            let parsed = parse_yuml(yuml).expect("invalid example");
+            assert_eq!(parsed.chart_type(), Some(chart_type));
+            assert!(parsed.warnings().is_empty(), "warnings in the {} example", chart_type);
+        }
+    }
+}
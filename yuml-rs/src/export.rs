@@ -0,0 +1,394 @@
+use crate::{
+    model::{
+        dot::{Arrow, DotElement, DotFile, DotShape, Style},
+        label::{as_text_sections, Label},
+    },
+    parser::ParsedYuml,
+};
+use std::{collections::HashMap, fmt::Write};
+
+/// Differences between the PlantUML and Mermaid flavours of state and class diagrams
+#[derive(Clone, Copy, PartialEq)]
+enum Dialect {
+    PlantUml,
+    Mermaid,
+}
+
+/// Convert a parsed diagram into equivalent [PlantUML](https://plantuml.com) source.
+///
+/// Activity diagrams are written as PlantUML state diagrams, as these support arbitrary flows including
+/// decisions (`<<choice>>`) and parallel bars (`<<fork>>`, `<<join>>`). Unsupported charts result in an empty string.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{parse_yuml, to_plantuml};
+///
+/// let parsed = parse_yuml("// {type:class}\n[Customer]<>1-orders 0..*>[Order]").unwrap();
+/// assert!(to_plantuml(&parsed).contains(r#"A1 "1" o--> "orders 0..*" A2"#));
+/// ```
+pub fn to_plantuml(parsed: &ParsedYuml) -> String {
+    match parsed {
+        ParsedYuml::Activity(df) => state_diagram(df, Dialect::PlantUml),
+        ParsedYuml::Class(df) => class_diagram(df, Dialect::PlantUml),
+        ParsedYuml::Timeline(_) | ParsedYuml::Er(_) | ParsedYuml::Mindmap(_) | ParsedYuml::Unsupported => String::new(),
+    }
+}
+
+/// Convert a parsed diagram into [Mermaid](https://mermaid.js.org) source, which GitHub renders in markdown.
+///
+/// Like `to_plantuml`, activity diagrams become state diagrams (`stateDiagram-v2`), so decisions and parallel bars
+/// keep their meaning. Unsupported charts result in an empty string.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{parse_yuml, to_mermaid};
+///
+/// let parsed = parse_yuml("// {type:activity}\n(start)->(Boil Kettle)->(end)").unwrap();
+/// assert!(to_mermaid(&parsed).contains("[*] --> A2\n"));
+/// ```
+pub fn to_mermaid(parsed: &ParsedYuml) -> String {
+    match parsed {
+        ParsedYuml::Activity(df) => state_diagram(df, Dialect::Mermaid),
+        ParsedYuml::Class(df) => class_diagram(df, Dialect::Mermaid),
+        ParsedYuml::Timeline(_) | ParsedYuml::Er(_) | ParsedYuml::Mindmap(_) | ParsedYuml::Unsupported => String::new(),
+    }
+}
+
+/// Strip the record field and compass point from an edge end, e.g. `A2:f1:n`
+fn node_id(uid: &str) -> &str {
+    uid.split(':').next().unwrap_or(uid)
+}
+
+fn edge_ends(edge: &DotElement) -> (&str, &str) {
+    (node_id(&edge.uid), node_id(edge.uid2.as_deref().unwrap_or_default()))
+}
+
+fn text(dot: &DotElement) -> String {
+    sections(dot).join("\n")
+}
+
+fn sections(dot: &DotElement) -> Vec<String> {
+    dot.dot
+        .label
+        .as_ref()
+        .map(as_text_sections)
+        .unwrap_or_default()
+        .iter()
+        .map(|section| section.trim().to_string())
+        .collect()
+}
+
+/// Neither dialect supports escaped quotes, Mermaid uses entity codes instead
+fn quoted(text: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::PlantUml => format!("\"{}\"", text.replace('"', "'").replace('\n', "\\n")),
+        Dialect::Mermaid => format!("\"{}\"", text.replace('"', "#quot;").replace('\n', "<br/>")),
+    }
+}
+
+/// Text following a colon, which runs until the end of the line
+fn inline(text: &str, dialect: Dialect) -> String {
+    match dialect {
+        Dialect::PlantUml => text.replace('\n', "\\n"),
+        Dialect::Mermaid => text.replace('\n', "<br/>"),
+    }
+}
+
+fn header(df: &DotFile, dialect: Dialect, diagram: &str) -> String {
+    let horizontal = df.direction().is_horizontal();
+    match dialect {
+        Dialect::PlantUml if horizontal => "@startuml\nleft to right direction\n".to_string(),
+        Dialect::PlantUml => "@startuml\n".to_string(),
+        Dialect::Mermaid => format!("{}\ndirection {}\n", diagram, df.direction()),
+    }
+}
+
+fn footer(uml: &mut String, dialect: Dialect) {
+    if dialect == Dialect::PlantUml {
+        uml.push_str("@enduml\n");
+    }
+}
+
+fn state_diagram(df: &DotFile, dialect: Dialect) -> String {
+    let mut uml = header(df, dialect, "stateDiagram-v2");
+    if dialect == Dialect::PlantUml {
+        uml.push_str("hide empty description\n");
+    }
+
+    let shapes: HashMap<&str, &DotShape> = df.nodes().map(|node| (node.uid.as_str(), &node.dot.shape)).collect();
+    let is_note = |uid: &str| matches!(shapes.get(uid), Some(DotShape::Note));
+    let mut connected_notes = vec![];
+
+    for node in df.nodes() {
+        let uid = &node.uid;
+        match node.dot.shape {
+            DotShape::Rectangle => {
+                let _ = writeln!(uml, "state {} as {}", quoted(&text(node), dialect), uid);
+            }
+            DotShape::Diamond => {
+                let _ = writeln!(uml, "state {} <<choice>>", uid);
+            }
+            DotShape::Record => {
+                let outgoing = df.edges().filter(|edge| node_id(&edge.uid) == uid).count();
+                let stereotype = if outgoing > 1 { "fork" } else { "join" };
+                let _ = writeln!(uml, "state {} <<{}>>", uid, stereotype);
+            }
+            _ => {}
+        }
+    }
+
+    for edge in df.edges() {
+        let (from, to) = edge_ends(edge);
+        if is_note(from) || is_note(to) {
+            connected_notes.push((from, to));
+            continue;
+        }
+
+        let from = if matches!(shapes.get(from), Some(DotShape::Circle)) {
+            "[*]"
+        } else {
+            from
+        };
+        let to = if matches!(
+            shapes.get(to),
+            Some(DotShape::DoubleCircle | DotShape::FlowFinal | DotShape::DoubleOctagon)
+        ) {
+            "[*]"
+        } else {
+            to
+        };
+        let _ = write!(uml, "{} --> {}", from, to);
+        match edge
+            .dot
+            .label
+            .as_ref()
+            .map(Label::as_str)
+            .filter(|label| !label.is_empty())
+        {
+            Some(label) => {
+                let _ = writeln!(uml, " : {}", inline(label, dialect));
+            }
+            None => uml.push('\n'),
+        }
+    }
+
+    for note in df.nodes().filter(|node| node.dot.shape == DotShape::Note) {
+        let text = text(note);
+        let target = connected_notes
+            .iter()
+            .find_map(|(from, to)| match (*from == note.uid, *to == note.uid) {
+                (true, _) => Some(*to),
+                (_, true) => Some(*from),
+                _ => None,
+            });
+
+        match (target, dialect) {
+            (Some(target), _) if !is_note(target) => {
+                let _ = writeln!(uml, "note right of {} : {}", target, inline(&text, dialect));
+            }
+            (_, Dialect::PlantUml) => {
+                let _ = writeln!(uml, "note {} as {}", quoted(&text, dialect), note.uid);
+            }
+            // Mermaid has no floating notes in state diagrams
+            (_, Dialect::Mermaid) => {
+                let _ = writeln!(uml, "%% note: {}", inline(&text, dialect));
+            }
+        }
+    }
+
+    footer(&mut uml, dialect);
+    uml
+}
+
+fn class_diagram(df: &DotFile, dialect: Dialect) -> String {
+    let mut uml = header(df, dialect, "classDiagram");
+    let notes: Vec<&str> = df
+        .nodes()
+        .filter(|node| node.dot.shape == DotShape::Note)
+        .map(|node| node.uid.as_str())
+        .collect();
+
+    for node in df.nodes() {
+        let sections = sections(node);
+        if node.dot.shape == DotShape::Note {
+            if dialect == Dialect::PlantUml {
+                let _ = writeln!(uml, "note {} as {}", quoted(&sections.join("\n"), dialect), node.uid);
+            }
+            continue;
+        }
+
+        let name = quoted(sections.first().map(String::as_str).unwrap_or_default(), dialect);
+        let declaration = match dialect {
+            Dialect::PlantUml => format!("class {} as {}", name, node.uid),
+            Dialect::Mermaid => format!("class {}[{}]", node.uid, name),
+        };
+        if sections.len() < 2 {
+            let _ = writeln!(uml, "{}", declaration);
+            continue;
+        }
+
+        let _ = writeln!(uml, "{} {{", declaration);
+        for (idx, section) in sections.iter().skip(1).enumerate() {
+            // Mermaid derives the compartments from the members
+            if idx > 0 && dialect == Dialect::PlantUml {
+                uml.push_str("  --\n");
+            }
+            for member in section.split('\n').map(str::trim).filter(|m| !m.is_empty()) {
+                let _ = writeln!(uml, "  {}", member);
+            }
+        }
+        uml.push_str("}\n");
+    }
+
+    for edge in df.edges() {
+        let (from, to) = edge_ends(edge);
+        if dialect == Dialect::Mermaid && (notes.contains(&from) || notes.contains(&to)) {
+            // Mermaid attaches notes to a class, instead of linking them
+            let (note, class) = if notes.contains(&from) { (from, to) } else { (to, from) };
+            if let Some(note) = df.nodes().find(|node| node.uid == note) {
+                let _ = writeln!(uml, "note for {} {}", class, quoted(&text(note), dialect));
+            }
+            continue;
+        }
+
+        let dot = &edge.dot;
+        let line = if dot.style.contains(&Style::Dashed) { ".." } else { "--" };
+        let tail = match dot.arrowtail {
+            Some(Arrow::Vee) => "<",
+            Some(Arrow::Empty) => "<|",
+            Some(Arrow::ODiamond) => "o",
+            Some(Arrow::Diamond) => "*",
+            _ => "",
+        };
+        let head = match dot.arrowhead {
+            Some(Arrow::Vee) => ">",
+            Some(Arrow::Empty) => "|>",
+            Some(Arrow::ODiamond) => "o",
+            Some(Arrow::Diamond) => "*",
+            _ => "",
+        };
+
+        uml.push_str(from);
+        if let Some(label) = dot.taillabel.as_deref().filter(|label| !label.is_empty()) {
+            let _ = write!(uml, " {}", quoted(label, dialect));
+        }
+        let _ = write!(uml, " {}{}{} ", tail, line, head);
+        if let Some(label) = dot.headlabel.as_deref().filter(|label| !label.is_empty()) {
+            let _ = write!(uml, "{} ", quoted(label, dialect));
+        }
+        uml.push_str(to);
+        if let Some(label) = dot.label.as_ref().map(Label::as_str).filter(|label| !label.is_empty()) {
+            let _ = write!(uml, " : {}", inline(label, dialect));
+        }
+        uml.push('\n');
+    }
+
+    footer(&mut uml, dialect);
+    uml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yuml;
+
+    #[test]
+    fn test_activity_to_plantuml() {
+        let yuml = "// {type:activity}\n(start)-><a>[kettle empty]->(Fill Kettle)->|b|\n<a>[kettle full]->|b|->(Boil Kettle)->(end)\n(Fill Kettle)-(note: fill **slowly**)";
+        let uml = to_plantuml(&parse_yuml(yuml).unwrap());
+        assert_eq!(
+            uml,
+            r#"@startuml
+hide empty description
+state A2 <<choice>>
+state "Fill Kettle" as A3
+state A4 <<join>>
+state "Boil Kettle" as A5
+[*] --> A2
+A2 --> A3 : kettle empty
+A3 --> A4
+A2 --> A4 : kettle full
+A4 --> A5
+A5 --> [*]
+note right of A3 : fill slowly
+@enduml
+"#
+        );
+    }
+
+    #[test]
+    fn test_class_to_plantuml() {
+        let yuml = "// {type:class}\n// {direction:leftToRight}\n[Customer|name;email|save()]<>1-orders 0..*>[Order]\n[Order]^[Special Order]\n[Order]-.-[note: immutable]\n[Order]uses-.->[Payment]";
+        let uml = to_plantuml(&parse_yuml(yuml).unwrap());
+        assert_eq!(
+            uml,
+            r#"@startuml
+left to right direction
+class "Customer" as A1 {
+  name
+  email
+  --
+  save()
+}
+class "Order" as A2
+class "Special Order" as A3
+note "immutable" as A4
+class "Payment" as A5
+A1 "1" o--> "orders 0..*" A2
+A2 <|-- A3
+A2 .. A4
+A2 ..> A5 : uses
+@enduml
+"#
+        );
+    }
+
+    #[test]
+    fn test_activity_to_mermaid() {
+        let yuml = "// {type:activity}\n// {direction:leftToRight}\n(start)->|a|->(Fill \"Kettle\")->|b|->(end)\n|a|->(Boil;Kettle)->|b|\n(note: loose)";
+        let uml = to_mermaid(&parse_yuml(yuml).unwrap());
+        assert_eq!(
+            uml,
+            r#"stateDiagram-v2
+direction LR
+state A2 <<fork>>
+state "Fill #quot;Kettle#quot;" as A3
+state A4 <<join>>
+state "Boil<br/>Kettle" as A6
+[*] --> A2
+A2 --> A3
+A3 --> A4
+A4 --> [*]
+A2 --> A6
+A6 --> A4
+%% note: loose
+"#
+        );
+    }
+
+    #[test]
+    fn test_class_to_mermaid() {
+        let yuml = "// {type:class}\n[Customer|name;email|save()]++-*>[Order]\n[Order]^[Special Order]\n[Order]-.-[note: immutable]";
+        let uml = to_mermaid(&parse_yuml(yuml).unwrap());
+        assert_eq!(
+            uml,
+            r#"classDiagram
+direction TB
+class A1["Customer"] {
+  name
+  email
+  save()
+}
+class A2["Order"]
+class A3["Special Order"]
+A1 *--> "*" A2
+A2 <|-- A3
+note for A2 "immutable"
+"#
+        );
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert_eq!(to_plantuml(&parse_yuml("// {type:sequence}\n[a]").unwrap()), "");
+        assert_eq!(to_mermaid(&parse_yuml("// {type:sequence}\n[a]").unwrap()), "");
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    model::{
+        activity, class,
+        dot::ChartType,
+        shared::{LabeledElement, NoteProps},
+    },
+    parser::{
+        activity::parse_activity_statements, class::parse_class_statements, determine_file_options, is_directive,
+        parse_headers, Statement,
+    },
+};
+use std::collections::HashSet;
+
+/// Re-emit a yUML document in its canonical form.
+///
+/// The `type` directive comes first, followed by the other directives sorted by name. Every statement is written on
+/// its own line without spaces between the elements, so fan-outs and comma separated statements are spread over
+/// several lines; a single blank line between groups of statements is kept. Documents using macros keep their body
+/// as it is, apart from surrounding whitespace, as the macro names can not be parsed.
+/// Usage:
+/// ```rust
+/// use yuml_rs::format_yuml;
+///
+/// let formatted = format_yuml("// {direction:leftToRight}\n// {type:class}\n[A]->[B],  [A]^[C]").unwrap();
+/// assert_eq!(formatted, "// {type:class}\n// {direction:leftToRight}\n[A]->[B]\n[A]^[C]\n");
+/// ```
+pub fn format_yuml(yuml: &str) -> YumlResult<String> {
+    let (body, mut headers) = parse_headers(yuml).map_err(|err| YumlError::InvalidFile(err.to_string()))?;
+    let options = determine_file_options(&headers);
+
+    // stable, so includes keep their order; directives between the statements move to the top
+    headers.sort_by_key(|h| (h.key != "type", h.key));
+    let mut written = HashSet::new();
+    headers.retain(|h| written.insert((h.key, h.value)));
+    let mut formatted: String = headers.iter().map(|h| format!("{}\n", h)).collect();
+
+    let has_macros = headers.iter().any(|h| h.key == "define");
+    let invalid = |err: nom::Err<nom::error::Error<&str>>| YumlError::InvalidFile(err.to_string());
+    let lines = match options.chart_type {
+        Some(ChartType::Activity) if !has_macros => {
+            let (_, statements) = parse_activity_statements(body, &options).map_err(invalid)?;
+            as_lines(&statements, activity_statement)
+        }
+        Some(ChartType::Class) if !has_macros => {
+            let (_, statements) = parse_class_statements(body, &options).map_err(invalid)?;
+            as_lines(&statements, class_statement)
+        }
+        _ => body
+            .lines()
+            .map(|line| Some(line.trim().to_string()).filter(|_| !is_directive(line)))
+            .collect(),
+    };
+
+    let mut blank = true;
+    for line in lines {
+        match line {
+            Some(line) if !line.is_empty() => {
+                formatted.push_str(&line);
+                formatted.push('\n');
+                blank = false;
+            }
+            _ if !blank => {
+                formatted.push('\n');
+                blank = true;
+            }
+            _ => {}
+        }
+    }
+
+    if blank && formatted.ends_with("\n\n") {
+        formatted.pop();
+    }
+    Ok(formatted)
+}
+
+/// One entry per line of the body, `None` for lines without statements
+fn as_lines<E: LabeledElement>(statements: &[Statement<E>], write: fn(&[E]) -> String) -> Vec<Option<String>> {
+    let mut lines = vec![];
+    let mut line = 0;
+    for statement in statements.iter().filter(|s| !s.elements.is_empty()) {
+        while line < statement.line {
+            lines.push(None);
+            line += 1;
+        }
+        lines.push(Some(write(&statement.elements)));
+        line = statement.line + 1;
+    }
+    lines
+}
+
+fn with_attributes(text: &str, attributes: Option<&str>) -> String {
+    match attributes {
+        Some(attributes) => format!("{}{{{}}}", text, attributes),
+        None => text.to_string(),
+    }
+}
+
+fn note(props: &NoteProps) -> String {
+    format!("note:{}", with_attributes(props.label, props.attributes))
+}
+
+fn activity_statement(elements: &[activity::Element]) -> String {
+    use activity::Element;
+
+    let mut text = String::new();
+    let mut transition_note = None;
+    for element in elements {
+        match element {
+            Element::StartTag => text.push_str("(start)"),
+            Element::EndTag => text.push_str("(end)"),
+            Element::FlowFinalTag => text.push_str("(end:flow)"),
+            Element::ErrorEndTag => text.push_str("(end:error)"),
+            Element::Activity(props) => {
+                let label = match props.id {
+                    Some(id) => format!("id={}|{}", id, props.label),
+                    None => props.label.to_string(),
+                };
+                text.push_str(&format!("({})", with_attributes(&label, props.attributes)));
+            }
+            Element::Parallel(props) => text.push_str(&format!("|{}|", props.label)),
+            Element::Decision(props) => text.push_str(&format!("<{}>", props.label)),
+            Element::Arrow(props) => {
+                match (props.has_tail, props.label) {
+                    (false, _) => text.push('-'),
+                    (true, Some(label)) => text.push_str(&format!("[{}]{}", label, props.line.as_arrow())),
+                    (true, None) => text.push_str(props.line.as_arrow()),
+                }
+                if let Some(attributes) = props.attributes {
+                    text.push_str(&format!("{{{}}}", attributes));
+                }
+                transition_note = props.note.as_ref();
+                continue;
+            }
+            Element::Note(props) => text.push_str(&format!("({})", note(props))),
+            Element::TransitionNote(props) => text.push_str(&format!("{{note:{}}}", props.label)),
+        }
+
+        // transition notes follow the target of their arrow
+        if let Some(props) = transition_note.take() {
+            text.push_str(&format!("{{note:{}}}", props.label));
+        }
+    }
+    text
+}
+
+fn class_statement(elements: &[class::Element]) -> String {
+    use class::{Connector, Element};
+
+    let connector = |connector: &Connector, left: bool| {
+        let (arrow, props) = match connector {
+            Connector::None(props) => ("", props),
+            Connector::Directional(props) => (if left { "<" } else { ">" }, props),
+            Connector::Aggregation(props) | Connector::Cardinality(props) => ("<>", props),
+            Connector::Composition(props) => ("++", props),
+            Connector::Inheritance(props) => ("^", props),
+            Connector::Dependencies(props) => (if left { "<" } else { ">" }, props),
+        };
+        let label = props.label.unwrap_or_default();
+        if left {
+            format!("{}{}", arrow, label)
+        } else {
+            format!("{}{}", label, arrow)
+        }
+    };
+
+    let mut statement = String::new();
+    let mut association = None;
+    for element in elements {
+        match element {
+            Element::Note(props) => statement.push_str(&format!("[{}]", note(props))),
+            Element::Class(text) => statement.push_str(&format!("[{}]", text)),
+            Element::Connection(connection) => {
+                statement.push_str(&with_attributes(
+                    &format!(
+                        "{}{}{}{}",
+                        connector(&connection.left, true),
+                        connection.label.unwrap_or_default(),
+                        if connection.dashed { "-.-" } else { "-" },
+                        connector(&connection.right, false)
+                    ),
+                    connection.attributes,
+                ));
+                association = connection.association;
+                continue;
+            }
+            Element::Inheritance(_) => statement.push('^'),
+            Element::AssociationClass(class) => statement.push_str(&format!("{{class:{}}}", class)),
+        }
+
+        // association classes follow the target of their connection
+        if let Some(class) = association.take() {
+            statement.push_str(&format!("{{class:{}}}", class));
+        }
+    }
+    statement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yuml;
+
+    fn assert_canonical(yuml: &str, expected: &str) {
+        let formatted = format_yuml(yuml).expect("can not format");
+        assert_eq!(formatted, expected);
+        assert_eq!(format_yuml(&formatted).unwrap(), formatted);
+        assert_eq!(
+            parse_yuml(&formatted).unwrap().to_string(),
+            parse_yuml(yuml).unwrap().to_string()
+        );
+    }
+
+    #[test]
+    fn test_format_activity() {
+        let yuml = "// {direction:leftToRight}\n// {type:activity}\n(start)-><d1>logged in->(Show Dashboard), <d1>[not logged in]->(id=login|Show Login{bg:red})\n\n\n(Show Dashboard)-(note: fresh data)\n(Show Dashboard)->(Load){note: slow}->{color:red}|a|\n(Split)->(A), (B)\n(Load)-.->(Retry)=>(end)\n(Retry)->(end:error), (B)->(end:flow)\n";
+        assert_canonical(
+            yuml,
+            "// {type:activity}\n// {direction:leftToRight}\n(start)-><d1>[logged in]->(Show Dashboard)\n<d1>[not logged in]->(id=login|Show Login{bg:red})\n\n(Show Dashboard)-(note: fresh data)\n(Show Dashboard)->(Load){note: slow}->{color:red}|a|\n(Split)->(A)\n(Split)->(B)\n(Load)-.->(Retry)=>(end)\n(Retry)->(end:error)\n(B)->(end:flow)\n",
+        );
+    }
+
+    #[test]
+    fn test_format_class() {
+        let yuml = "// {type:class}\n// {samerank: [A],[B]}\n[Customer|Name;Email]+1-orders *>[Order], [Order]++-[LineItem]\n[Customer]^[Premium]\n[Order]uses-.->{color:blue}[Payment]\n[Order]<->[Invoice]-[note: Value Object{bg:wheat}]";
+        assert_canonical(
+            yuml,
+            "// {type:class}\n// {samerank:[A],[B]}\n[Customer|Name;Email]<>1-orders *>[Order]\n[Order]++-[LineItem]\n[Customer]^[Premium]\n[Order]uses-.->{color:blue}[Payment]\n[Order]<->[Invoice]-[note: Value Object{bg:wheat}]\n",
+        );
+    }
+
+    #[test]
+    fn test_format_uml_relations() {
+        let yuml = "// {type:class}\n[Impl]-.-^[Interface], [Interface]^-.-[Other]\n[Student]1-*>[Course]{class:Enrollment|grade}";
+        assert_canonical(
+            yuml,
+            "// {type:class}\n[Impl]-.-^[Interface]\n[Interface]^-.-[Other]\n[Student]1-*>[Course]{class:Enrollment|grade}\n",
+        );
+    }
+
+    #[test]
+    fn test_format_directives_in_body() {
+        let yuml = "// {type:class}\n[A]->[B]\n// {direction:leftToRight}\n[B]->[C]\n// {type:class}\n";
+        assert_canonical(
+            yuml,
+            "// {type:class}\n// {direction:leftToRight}\n[A]->[B]\n\n[B]->[C]\n",
+        );
+    }
+
+    #[test]
+    fn test_format_with_macros() {
+        let yuml = "// {define:DB=[PostgreSQL]}\n// {type:class}\n  [Orders]->DB  \n";
+        assert_eq!(
+            format_yuml(yuml).unwrap(),
+            "// {type:class}\n// {define:DB=[PostgreSQL]}\n[Orders]->DB\n"
+        );
+    }
+}
@@ -0,0 +1,292 @@
+use crate::error::{YumlError, YumlResult};
+use std::collections::HashSet;
+use syn::{Fields, GenericArgument, ImplItem, Item, PathArguments, Type, Visibility};
+
+/*
+Generated class diagram
+Struct          [Customer|name: String;orders: Vec<Order>|save()]
+Enum            [Status|Open;Closed]
+Ownership       [Customer]++-orders *>[Order]    fields of a type in the file, in a collection when marked with '*'
+Sharing         [Customer]<>-account>[Account]   fields behind Rc, Arc or a reference
+Traits          [Entity]^[Customer]              implementations of a trait defined in the file
+*/
+
+/// A struct, enum or trait found in the source file
+struct TypeDef {
+    name: String,
+    attributes: Vec<String>,
+    methods: Vec<String>,
+    fields: Vec<(String, Type)>,
+}
+
+/// Generate a yUML class diagram from the structs, enums and traits of a Rust source file.
+///
+/// Fields and public methods become the sections of the classes. Fields referring to other types of the file become
+/// compositions, or aggregations when they are shared through `Rc`, `Arc` or a reference.
+/// Usage:
+/// ```rust
+/// use yuml_rs::generate::yuml_from_rust;
+///
+/// let yuml = yuml_from_rust("struct Customer { orders: Vec<Order> }\nstruct Order { total: u32 }").unwrap();
+/// assert!(yuml.contains("[Customer]++-orders *>[Order]"));
+/// ```
+pub fn yuml_from_rust(source: &str) -> YumlResult<String> {
+    let file = syn::parse_file(source).map_err(|err| YumlError::Import(err.to_string()))?;
+    let mut types: Vec<TypeDef> = vec![];
+    let mut implementations: Vec<(String, String)> = vec![];
+    collect(&file.items, &mut types, &mut implementations);
+
+    let names: HashSet<&str> = types.iter().map(|t| t.name.as_str()).collect();
+    let mut yuml = String::from("// {type:class}\n");
+    for t in &types {
+        let sections: Vec<String> = [vec![t.name.clone()], t.attributes.clone(), t.methods.clone()]
+            .into_iter()
+            .filter(|section| !section.is_empty())
+            .map(|section| section.join(";"))
+            .collect();
+        yuml.push_str(&format!("[{}]\n", sections.join("|")));
+    }
+
+    for t in &types {
+        for (field, ty) in &t.fields {
+            if let Some((target, shared, many)) = related_type(ty, &names) {
+                let connector = if shared { "<>-" } else { "++-" };
+                let many = if many { " *" } else { "" };
+                yuml.push_str(&format!("[{}]{}{}{}>[{}]\n", t.name, connector, field, many, target));
+            }
+        }
+    }
+
+    for (trait_name, type_name) in implementations {
+        if names.contains(trait_name.as_str()) && names.contains(type_name.as_str()) {
+            yuml.push_str(&format!("[{}]^[{}]\n", trait_name, type_name));
+        }
+    }
+
+    Ok(yuml)
+}
+
+fn collect(items: &[Item], types: &mut Vec<TypeDef>, implementations: &mut Vec<(String, String)>) {
+    for item in items {
+        match item {
+            Item::Struct(s) => {
+                let fields: Vec<(String, Type)> = match &s.fields {
+                    Fields::Named(named) => named
+                        .named
+                        .iter()
+                        .map(|f| {
+                            (
+                                f.ident.as_ref().map(|i| i.to_string()).unwrap_or_default(),
+                                f.ty.clone(),
+                            )
+                        })
+                        .collect(),
+                    Fields::Unnamed(unnamed) => unnamed
+                        .unnamed
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, f)| (idx.to_string(), f.ty.clone()))
+                        .collect(),
+                    Fields::Unit => vec![],
+                };
+                types.push(TypeDef {
+                    name: s.ident.to_string(),
+                    attributes: fields
+                        .iter()
+                        .map(|(name, ty)| format!("{}: {}", name, type_name(ty)))
+                        .collect(),
+                    methods: vec![],
+                    fields,
+                });
+            }
+            Item::Enum(e) => types.push(TypeDef {
+                name: e.ident.to_string(),
+                attributes: e.variants.iter().map(|v| v.ident.to_string()).collect(),
+                methods: vec![],
+                fields: vec![],
+            }),
+            Item::Trait(t) => types.push(TypeDef {
+                name: t.ident.to_string(),
+                attributes: vec![],
+                methods: t
+                    .items
+                    .iter()
+                    .filter_map(|item| match item {
+                        syn::TraitItem::Fn(f) => Some(format!("{}()", f.sig.ident)),
+                        _ => None,
+                    })
+                    .collect(),
+                fields: vec![],
+            }),
+            Item::Impl(i) => {
+                let self_name = match i.self_ty.as_ref() {
+                    Type::Path(path) => path.path.segments.last().map(|s| s.ident.to_string()),
+                    _ => None,
+                };
+                let Some(self_name) = self_name else { continue };
+                match &i.trait_ {
+                    Some((_, path, _)) => {
+                        if let Some(trait_name) = path.segments.last() {
+                            implementations.push((trait_name.ident.to_string(), self_name));
+                        }
+                    }
+                    None => {
+                        let methods = i.items.iter().filter_map(|item| match item {
+                            ImplItem::Fn(f) if matches!(f.vis, Visibility::Public(_)) => {
+                                Some(format!("{}()", f.sig.ident))
+                            }
+                            _ => None,
+                        });
+                        // impl blocks may precede the type definition
+                        match types.iter_mut().find(|t| t.name == self_name) {
+                            Some(t) => t.methods.extend(methods),
+                            None => types.push(TypeDef {
+                                name: self_name,
+                                attributes: vec![],
+                                methods: methods.collect(),
+                                fields: vec![],
+                            }),
+                        }
+                    }
+                }
+            }
+            Item::Mod(m) => {
+                if let Some((_, items)) = &m.content {
+                    collect(items, types, implementations);
+                }
+            }
+            _ => { /* other items do not appear in class diagrams */ }
+        }
+    }
+}
+
+/// The type of the file a field refers to, whether it is shared and whether it is a collection
+fn related_type(ty: &Type, names: &HashSet<&str>) -> Option<(String, bool, bool)> {
+    match ty {
+        Type::Reference(r) => related_type(&r.elem, names).map(|(name, _, many)| (name, true, many)),
+        Type::Slice(s) => related_type(&s.elem, names).map(|(name, shared, _)| (name, shared, true)),
+        Type::Array(a) => related_type(&a.elem, names).map(|(name, shared, _)| (name, shared, true)),
+        Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            let name = segment.ident.to_string();
+            if names.contains(name.as_str()) {
+                return Some((name, false, false));
+            }
+
+            // look through the generic arguments of wrappers and collections
+            let PathArguments::AngleBracketed(args) = &segment.arguments else {
+                return None;
+            };
+            let (target, shared, many) = args.args.iter().rev().find_map(|arg| match arg {
+                GenericArgument::Type(ty) => related_type(ty, names),
+                _ => None,
+            })?;
+            match name.as_str() {
+                "Rc" | "Arc" | "Weak" => Some((target, true, many)),
+                "Box" | "Option" | "Cell" | "RefCell" | "Mutex" | "RwLock" => Some((target, shared, many)),
+                _ => Some((target, shared, true)),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// A readable type name; square brackets and semicolons would end the class or its section, so arrays and slices
+/// are written with parentheses
+fn type_name(ty: &Type) -> String {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .iter()
+            .map(|segment| match &segment.arguments {
+                PathArguments::AngleBracketed(args) => {
+                    let args: Vec<String> = args
+                        .args
+                        .iter()
+                        .map(|arg| match arg {
+                            GenericArgument::Type(ty) => type_name(ty),
+                            GenericArgument::Lifetime(lifetime) => format!("'{}", lifetime.ident),
+                            _ => "_".to_string(),
+                        })
+                        .collect();
+                    format!("{}<{}>", segment.ident, args.join(", "))
+                }
+                _ => segment.ident.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("::"),
+        Type::Reference(r) => {
+            let mutability = if r.mutability.is_some() { "mut " } else { "" };
+            format!("&{}{}", mutability, type_name(&r.elem))
+        }
+        Type::Slice(s) => format!("({})", type_name(&s.elem)),
+        Type::Array(a) => format!("({}, _)", type_name(&a.elem)),
+        Type::Tuple(t) => format!("({})", t.elems.iter().map(type_name).collect::<Vec<_>>().join(", ")),
+        _ => "_".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_yuml;
+
+    #[test]
+    fn test_yuml_from_rust() {
+        let source = r#"
+            pub trait Entity {
+                fn id(&self) -> u64;
+            }
+
+            pub struct Customer {
+                pub name: String,
+                orders: Vec<Order>,
+                account: Option<Rc<Account>>,
+                tags: [u8; 4],
+            }
+
+            impl Customer {
+                pub fn save(&self) {}
+                fn validate(&self) {}
+            }
+
+            impl Entity for Customer {
+                fn id(&self) -> u64 { 1 }
+            }
+
+            struct Order(HashMap<String, u32>);
+
+            enum Account {
+                Open,
+                Closed,
+            }
+
+            mod nested {
+                struct Invoice { order: Box<Order> }
+            }
+        "#;
+
+        let yuml = yuml_from_rust(source).unwrap();
+        assert_eq!(
+            yuml,
+            "// {type:class}
+[Entity|id()]
+[Customer|name: String;orders: Vec<Order>;account: Option<Rc<Account>>;tags: (u8, _)|save()]
+[Order|0: HashMap<String, u32>]
+[Account|Open;Closed]
+[Invoice|order: Box<Order>]
+[Customer]++-orders *>[Order]
+[Customer]<>-account>[Account]
+[Invoice]++-order>[Order]
+[Entity]^[Customer]
+"
+        );
+        assert!(parse_yuml(&yuml).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_rust() {
+        assert!(yuml_from_rust("struct {").is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    model::dot::{DotFile, Layout},
+    render::{rewrite_svg, RenderOptions, Renderer},
+};
+use lazy_static::lazy_static;
+use std::{
+    ffi::{c_char, c_int, c_uint, c_void, CString},
+    ptr, slice,
+    sync::Mutex,
+};
+
+#[repr(C)]
+struct Gvc {
+    _private: [u8; 0],
+}
+
+#[repr(C)]
+struct Graph {
+    _private: [u8; 0],
+}
+
+#[link(name = "cgraph")]
+extern "C" {
+    fn agmemread(cp: *const c_char) -> *mut Graph;
+    fn agclose(g: *mut Graph) -> c_int;
+    fn agget(obj: *mut c_void, name: *const c_char) -> *mut c_char;
+    fn agsafeset(obj: *mut c_void, name: *const c_char, value: *const c_char, def: *const c_char) -> *mut c_void;
+}
+
+#[link(name = "gvc")]
+extern "C" {
+    fn gvContext() -> *mut Gvc;
+    fn gvLayout(gvc: *mut Gvc, g: *mut Graph, engine: *const c_char) -> c_int;
+    fn gvFreeLayout(gvc: *mut Gvc, g: *mut Graph) -> c_int;
+    fn gvRenderData(
+        gvc: *mut Gvc,
+        g: *mut Graph,
+        format: *const c_char,
+        result: *mut *mut c_char,
+        length: *mut c_uint,
+    ) -> c_int;
+    fn gvFreeRenderData(data: *mut c_char);
+}
+
+/// NUL-terminated names of the graph attributes which are set before the layout
+const DPI: &[u8] = b"dpi\0";
+const START: &[u8] = b"start\0";
+const EMPTY: &[u8] = b"\0";
+
+/// The graphviz context loads the plugins once; graphviz itself is not thread safe, so all calls go through the lock
+struct Context(*mut Gvc);
+
+// the context is only used while holding the lock
+unsafe impl Send for Context {}
+
+lazy_static! {
+    static ref CONTEXT: Mutex<Context> = Mutex::new(Context(unsafe { gvContext() }));
+}
+
+/// Renders in-process by linking against libgvc, which avoids spawning a "dot" process per diagram.
+///
+/// The `timeout`, `dot_path`, `extra_args` and `env` options only apply to the "dot" process and are ignored; the
+/// `max_output_size` is checked once the image is rendered.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GvcRenderer;
+
+impl Renderer for GvcRenderer {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, fields(nodes = df.nodes().count(), edges = df.edges().count()))
+    )]
+    fn render(&self, df: &DotFile, opts: &RenderOptions) -> YumlResult<Vec<u8>> {
+        self.render_dot(&df.to_string(), opts)
+    }
+}
+
+impl GvcRenderer {
+    /// Render a dot-description into an image
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "render", level = "info", skip_all, fields(format = %opts.format, dot_bytes = dot.len()))
+    )]
+    pub fn render_dot(&self, dot: &str, opts: &RenderOptions) -> YumlResult<Vec<u8>> {
+        let image = render(dot, opts)?;
+        if let Some(max) = opts.max_output_size.filter(|max| image.len() > *max) {
+            return Err(YumlError::OutputTooLarge(max));
+        }
+        match opts.rewrites_svg() {
+            true => Ok(rewrite_svg(&String::from_utf8_lossy(&image), opts).into_bytes()),
+            false => Ok(image),
+        }
+    }
+}
+
+fn c_string(text: &str) -> YumlResult<CString> {
+    CString::new(text).map_err(|_| YumlError::Render("unexpected nul character".to_string()))
+}
+
+/// Set the graph attribute `name`, unless the graph has a value for it
+///
+/// # Safety
+/// `graph` must be a graph read by `agmemread` and `name` must be NUL-terminated.
+unsafe fn set_default(graph: *mut Graph, name: &[u8], value: &CString) {
+    let current = agget(graph.cast(), name.as_ptr().cast());
+    if current.is_null() || *current == 0 {
+        agsafeset(
+            graph.cast(),
+            name.as_ptr().cast(),
+            value.as_ptr(),
+            EMPTY.as_ptr().cast(),
+        );
+    }
+}
+
+fn render(dot: &str, opts: &RenderOptions) -> YumlResult<Vec<u8>> {
+    let dot = c_string(dot)?;
+    let layout = opts.layout.unwrap_or(Layout::Dot);
+    let engine = c_string(&layout.to_string())?;
+    let format = c_string(&opts.format.to_string())?;
+    let dpi = opts.dpi.map(|dpi| c_string(&dpi.to_string())).transpose()?;
+    let seed = opts.seed.map(|seed| c_string(&seed.to_string())).transpose()?;
+
+    let context = CONTEXT.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if context.0.is_null() {
+        return Err(YumlError::Render("can not create the graphviz context".to_string()));
+    }
+
+    unsafe {
+        let graph = agmemread(dot.as_ptr());
+        if graph.is_null() {
+            return Err(YumlError::Render("invalid dot-description".to_string()));
+        }
+
+        // like `-Gdpi` and `-Gstart` of the "dot" process, the options do not replace the attributes of the graph
+        if let Some(dpi) = &dpi {
+            set_default(graph, DPI, dpi);
+        }
+        if let Some(seed) = &seed {
+            set_default(graph, START, seed);
+        }
+
+        // like `-K`, the engine is only used when the graph has no `layout` attribute
+        if gvLayout(context.0, graph, engine.as_ptr()) != 0 {
+            agclose(graph);
+            return Err(YumlError::Render(format!("can not layout with {}", layout)));
+        }
+
+        let mut data: *mut c_char = ptr::null_mut();
+        let mut length: c_uint = 0;
+        let rendered = gvRenderData(context.0, graph, format.as_ptr(), &mut data, &mut length);
+        let image = if rendered == 0 && !data.is_null() {
+            Ok(slice::from_raw_parts(data as *const u8, length as usize).to_vec())
+        } else {
+            Err(YumlError::Render(format!("can not render as {}", opts.format)))
+        };
+
+        if !data.is_null() {
+            gvFreeRenderData(data);
+        }
+        gvFreeLayout(context.0, graph);
+        agclose(graph);
+        image
+    }
+}
@@ -0,0 +1,601 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    model::{
+        dot::{
+            as_id, Arrow, Directions, Dot, DotElement, DotFile, DotShape, GraphAttributes, Layout, Options, Ordering,
+            Splines, Style,
+        },
+        label::{HtmlLabel, Label},
+    },
+};
+use std::{collections::HashMap, convert::TryFrom};
+
+/*
+Supported graphviz syntax
+Graphs          [strict] digraph|graph [name] { ... }
+Nodes           a; "long name" [label="x", shape=box]
+Edges           a -> b -> c [label="x"]; a -- b
+Defaults        graph [rankdir=LR]; node [shape=box]; edge [style=dashed]; rankdir=LR
+Subgraphs       subgraph cluster_0 { ... } and { ... } are flattened
+Comments        // line, # line, /* block */
+*/
+
+/// Parse an existing graphviz file into a `DotFile`, so it can be restyled with a `Theme` and rendered.
+///
+/// Only the attributes known to yuml-rs are interpreted, all others are passed through unchanged.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{parse_dot, Theme};
+///
+/// let dot_file = parse_dot("digraph G { rankdir=LR; a -> b [label=\"next\"] }").unwrap();
+/// let dot = dot_file.theme(Theme::corporate()).to_string();
+/// assert!(dot.contains("rankdir = LR"));
+/// ```
+pub fn parse_dot(text: &str) -> YumlResult<DotFile> {
+    let tokens = tokenize(text)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        graph: Graph::default(),
+    };
+    parser.parse_graph()?;
+    Ok(parser.graph.into_dot_file())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// Identifier, number or (HTML-like) string, with the quotes and escapes removed
+    Id(String),
+    Html(String),
+    Punct(char),
+    EdgeOp,
+}
+
+fn invalid(message: &str) -> YumlError {
+    YumlError::Import(format!("invalid dot file: {}", message))
+}
+
+fn tokenize(text: &str) -> YumlResult<Vec<Token>> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = vec![];
+    let mut idx = 0;
+    let mut line_start = true;
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        let next = chars.get(idx + 1).copied();
+        match c {
+            '\n' => {
+                line_start = true;
+                idx += 1;
+                continue;
+            }
+            c if c.is_whitespace() => {
+                idx += 1;
+                continue;
+            }
+            // preprocessor output lines start with #
+            '#' if line_start => {
+                idx = skip_until(&chars, idx, "\n");
+            }
+            '/' if next == Some('/') => idx = skip_until(&chars, idx, "\n"),
+            '/' if next == Some('*') => idx = skip_until(&chars, idx, "*/") + 2,
+            '-' if next == Some('>') || next == Some('-') => {
+                tokens.push(Token::EdgeOp);
+                idx += 2;
+            }
+            '{' | '}' | '[' | ']' | '=' | ';' | ',' | ':' => {
+                tokens.push(Token::Punct(c));
+                idx += 1;
+            }
+            '"' => {
+                let mut value = String::new();
+                idx += 1;
+                while idx < chars.len() && chars[idx] != '"' {
+                    match (chars[idx], chars.get(idx + 1)) {
+                        ('\\', Some('"')) => {
+                            value.push('"');
+                            idx += 2;
+                        }
+                        ('\\', Some('\n')) => idx += 2,
+                        (c, _) => {
+                            value.push(c);
+                            idx += 1;
+                        }
+                    }
+                }
+                if idx >= chars.len() {
+                    return Err(invalid("unterminated string"));
+                }
+                idx += 1;
+                tokens.push(Token::Id(value));
+            }
+            '<' => {
+                let mut depth = 0;
+                let start = idx;
+                while idx < chars.len() {
+                    match chars[idx] {
+                        '<' => depth += 1,
+                        '>' => depth -= 1,
+                        _ => {}
+                    }
+                    idx += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                if depth != 0 {
+                    return Err(invalid("unterminated HTML label"));
+                }
+                tokens.push(Token::Html(chars[start..idx].iter().collect()));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' || !c.is_ascii() => {
+                let start = idx;
+                while idx < chars.len() {
+                    let c = chars[idx];
+                    if c.is_alphanumeric() || c == '_' || c == '.' || !c.is_ascii() || (c == '-' && idx == start) {
+                        idx += 1;
+                    } else {
+                        break;
+                    }
+                }
+                tokens.push(Token::Id(chars[start..idx].iter().collect()));
+            }
+            c => return Err(invalid(&format!("unexpected character '{}'", c))),
+        }
+        line_start = false;
+    }
+
+    Ok(tokens)
+}
+
+fn skip_until(chars: &[char], idx: usize, end: &str) -> usize {
+    let end: Vec<char> = end.chars().collect();
+    (idx..chars.len())
+        .find(|i| chars[*i..].starts_with(&end))
+        .unwrap_or(chars.len())
+}
+
+type Attributes = Vec<(String, String)>;
+
+#[derive(Default)]
+struct Graph {
+    directed: bool,
+    dir: Directions,
+    attributes: GraphAttributes,
+    node_order: Vec<String>,
+    nodes: HashMap<String, Attributes>,
+    edges: Vec<(String, String, Attributes)>,
+}
+
+impl Graph {
+    fn add_node(&mut self, id: &str, attributes: &[(String, String)]) {
+        if !self.nodes.contains_key(id) {
+            self.node_order.push(id.to_string());
+        }
+        self.nodes
+            .entry(id.to_string())
+            .or_default()
+            .extend(attributes.iter().cloned());
+    }
+
+    fn into_dot_file(self) -> DotFile {
+        let mut dots = vec![];
+        for id in &self.node_order {
+            let attributes = &self.nodes[id];
+            dots.push(DotElement::new(&as_id(id), as_node(id, attributes)));
+        }
+        for (from, to, attributes) in &self.edges {
+            dots.push(DotElement::new_edge(
+                &as_endpoint(from),
+                &as_endpoint(to),
+                as_edge(attributes, self.directed),
+            ));
+        }
+
+        let options = Options {
+            dir: self.dir,
+            graph: self.attributes,
+            ..Options::default()
+        };
+        DotFile::new(dots, &options)
+    }
+}
+
+/// Node ids are quoted, the port and compass point are kept as-is
+fn as_endpoint(endpoint: &str) -> String {
+    match endpoint.split_once(':') {
+        Some((id, port)) => format!("{}:{}", as_id(id), port),
+        None => as_id(endpoint),
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    graph: Graph,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, punct: char) -> bool {
+        if self.peek() == Some(&Token::Punct(punct)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, punct: char) -> YumlResult<()> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(invalid(&format!("expected '{}'", punct)))
+        }
+    }
+
+    fn keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Id(id)) if id.eq_ignore_ascii_case(keyword))
+    }
+
+    fn id(&mut self) -> YumlResult<String> {
+        match self.next() {
+            Some(Token::Id(id)) | Some(Token::Html(id)) => Ok(id),
+            _ => Err(invalid("expected an identifier")),
+        }
+    }
+
+    fn parse_graph(&mut self) -> YumlResult<()> {
+        if self.keyword("strict") {
+            self.pos += 1;
+        }
+        self.graph.directed = match self.next() {
+            Some(Token::Id(id)) if id.eq_ignore_ascii_case("digraph") => true,
+            Some(Token::Id(id)) if id.eq_ignore_ascii_case("graph") => false,
+            _ => return Err(invalid("expected 'graph' or 'digraph'")),
+        };
+        if !matches!(self.peek(), Some(Token::Punct('{'))) {
+            self.id()?;
+        }
+        self.expect('{')?;
+        self.parse_statements(&[], &[])?;
+        Ok(())
+    }
+
+    /// Parse statements until the closing brace, with the node and edge defaults of the enclosing scope
+    fn parse_statements(
+        &mut self,
+        node_defaults: &[(String, String)],
+        edge_defaults: &[(String, String)],
+    ) -> YumlResult<()> {
+        let mut node_defaults = node_defaults.to_vec();
+        let mut edge_defaults = edge_defaults.to_vec();
+
+        loop {
+            match self.peek() {
+                None => return Err(invalid("expected '}'")),
+                Some(Token::Punct('}')) => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some(Token::Punct(';')) => self.pos += 1,
+                Some(Token::Punct('{')) => {
+                    self.pos += 1;
+                    self.parse_statements(&node_defaults, &edge_defaults)?;
+                }
+                _ if self.keyword("subgraph") => {
+                    self.pos += 1;
+                    if !matches!(self.peek(), Some(Token::Punct('{'))) {
+                        self.id()?;
+                    }
+                    self.expect('{')?;
+                    self.parse_statements(&node_defaults, &edge_defaults)?;
+                }
+                _ if self.keyword("graph") => {
+                    self.pos += 1;
+                    let attributes = self.parse_attributes()?;
+                    self.graph_attributes(&attributes);
+                }
+                _ if self.keyword("node") => {
+                    self.pos += 1;
+                    node_defaults.extend(self.parse_attributes()?);
+                }
+                _ if self.keyword("edge") => {
+                    self.pos += 1;
+                    edge_defaults.extend(self.parse_attributes()?);
+                }
+                _ => self.parse_statement(&node_defaults, &edge_defaults)?,
+            }
+        }
+    }
+
+    fn parse_statement(
+        &mut self,
+        node_defaults: &[(String, String)],
+        edge_defaults: &[(String, String)],
+    ) -> YumlResult<()> {
+        let first = self.endpoint()?;
+
+        // graph attribute, like rankdir=LR
+        if self.eat('=') {
+            let value = self.id()?;
+            self.graph_attributes(&[(first, value)]);
+            return Ok(());
+        }
+
+        let mut ends = vec![first];
+        while self.peek() == Some(&Token::EdgeOp) {
+            self.pos += 1;
+            ends.push(self.endpoint()?);
+        }
+        let attributes = self.parse_attributes()?;
+
+        if ends.len() == 1 {
+            let mut node_attributes = node_defaults.to_vec();
+            node_attributes.extend(attributes);
+            self.graph.add_node(&ends[0], &node_attributes);
+            return Ok(());
+        }
+
+        for end in &ends {
+            let id = end.split(':').next().unwrap_or(end);
+            if !self.graph.nodes.contains_key(id) {
+                self.graph.add_node(id, node_defaults);
+            }
+        }
+
+        let mut edge_attributes = edge_defaults.to_vec();
+        edge_attributes.extend(attributes);
+        for pair in ends.windows(2) {
+            self.graph
+                .edges
+                .push((pair[0].clone(), pair[1].clone(), edge_attributes.clone()));
+        }
+        Ok(())
+    }
+
+    /// A node id with an optional port and compass point
+    fn endpoint(&mut self) -> YumlResult<String> {
+        let mut endpoint = self.id()?;
+        while self.eat(':') {
+            endpoint.push(':');
+            endpoint.push_str(&self.id()?);
+        }
+        Ok(endpoint)
+    }
+
+    /// Zero or more `[key=value, ...]` lists
+    fn parse_attributes(&mut self) -> YumlResult<Attributes> {
+        let mut attributes = vec![];
+        while self.eat('[') {
+            while !self.eat(']') {
+                let key = self.id()?;
+                let value = if self.eat('=') { self.id()? } else { "true".to_string() };
+                attributes.push((key, value));
+                if !self.eat(',') {
+                    self.eat(';');
+                }
+            }
+        }
+        Ok(attributes)
+    }
+
+    fn graph_attributes(&mut self, attributes: &[(String, String)]) {
+        for (key, value) in attributes {
+            let graph = &mut self.graph;
+            match key.as_str() {
+                "rankdir" => {
+                    graph.dir = match value.as_str() {
+                        "LR" => Directions::LeftToRight,
+                        "RL" => Directions::RightToLeft,
+                        _ => Directions::TopDown,
+                    }
+                }
+                "splines" => graph.attributes.splines = Splines::try_from(value.as_str()).ok(),
+                "nodesep" => graph.attributes.nodesep = value.parse().ok(),
+                "concentrate" => graph.attributes.concentrate = value == "true",
+                "ordering" => graph.attributes.ordering = Ordering::try_from(value.as_str()).ok(),
+                "layout" => graph.attributes.layout = Layout::try_from(value.as_str()).ok(),
+                "start" => graph.attributes.seed = value.parse().ok(),
+                _ => { /* other graph attributes are not supported */ }
+            }
+        }
+    }
+}
+
+fn as_shape(shape: &str) -> Option<DotShape> {
+    match shape {
+        "record" | "Mrecord" => Some(DotShape::Record),
+        "circle" => Some(DotShape::Circle),
+        "doublecircle" => Some(DotShape::DoubleCircle),
+        "doubleoctagon" => Some(DotShape::DoubleOctagon),
+        "diamond" => Some(DotShape::Diamond),
+        "note" => Some(DotShape::Note),
+        "point" => Some(DotShape::Point),
+        "rectangle" | "rect" | "box" => Some(DotShape::Rectangle),
+        _ => None,
+    }
+}
+
+fn as_arrow(arrow: &str) -> Option<Arrow> {
+    match arrow {
+        "vee" => Some(Arrow::Vee),
+        "odiamond" => Some(Arrow::ODiamond),
+        "diamond" => Some(Arrow::Diamond),
+        "empty" | "onormal" => Some(Arrow::Empty),
+        "teetee" => Some(Arrow::TeeTee),
+        "teeodot" => Some(Arrow::TeeODot),
+        "crowtee" => Some(Arrow::CrowTee),
+        "crowodot" => Some(Arrow::CrowODot),
+        _ => None,
+    }
+}
+
+/// Apply the attributes which have a field in `Dot`, all others end up in `extra`
+fn apply(dot: &mut Dot, attributes: &[(String, String)]) {
+    for (key, value) in attributes {
+        let value = value.trim();
+        let handled = match key.as_str() {
+            "shape" => as_shape(value).map(|shape| dot.shape = shape).is_some(),
+            "label" => {
+                dot.label = Some(as_label(value));
+                true
+            }
+            "style" => match value.split(',').map(|s| Style::try_from(s.trim())).collect() {
+                Ok(styles) => {
+                    dot.style = styles;
+                    true
+                }
+                Err(_) => false,
+            },
+            "fillcolor" => {
+                dot.fillcolor = Some(value.to_string());
+                true
+            }
+            "fontcolor" => {
+                dot.fontcolor = Some(value.to_string());
+                true
+            }
+            "margin" => {
+                dot.margin = Some(value.to_string());
+                true
+            }
+            "dir" => {
+                dot.dir = Some(value.to_string());
+                true
+            }
+            "taillabel" => {
+                dot.taillabel = Some(value.to_string());
+                true
+            }
+            "headlabel" => {
+                dot.headlabel = Some(value.to_string());
+                true
+            }
+            "arrowhead" => as_arrow(value).map(|arrow| dot.arrowhead = Some(arrow)).is_some(),
+            "arrowtail" => as_arrow(value).map(|arrow| dot.arrowtail = Some(arrow)).is_some(),
+            "height" => value.parse().map(|v| dot.height = Some(v)).is_ok(),
+            "width" => value.parse().map(|v| dot.width = Some(v)).is_ok(),
+            "fontsize" => value.parse().map(|v| dot.fontsize = Some(v)).is_ok(),
+            "penwidth" => value.parse().map(|v| dot.penwidth = Some(v)).is_ok(),
+            "labeldistance" => value.parse().map(|v| dot.labeldistance = Some(v)).is_ok(),
+            _ => false,
+        };
+
+        if !handled {
+            dot.extra.retain(|(k, _)| k != key);
+            dot.extra.push((key.to_string(), value.to_string()));
+        }
+    }
+}
+
+/// HTML-like labels are kept when they only use the markup of yUML labels, all others are imported as text
+fn as_label(label: &str) -> Label {
+    match HtmlLabel::parse(label) {
+        Some(html) => Label::Html(html),
+        None => Label::from(label),
+    }
+}
+
+pub(super) fn as_node(id: &str, attributes: &[(String, String)]) -> Dot {
+    let mut dot = Dot {
+        shape: DotShape::Rectangle,
+        label: Some(Label::from(id)),
+        // graphviz draws ellipses by default
+        extra: vec![("shape".to_string(), "ellipse".to_string())],
+        ..Dot::default()
+    };
+    apply(&mut dot, attributes);
+    if attributes
+        .iter()
+        .any(|(key, value)| key == "shape" && as_shape(value).is_some())
+    {
+        dot.extra.retain(|(key, _)| key != "shape");
+    }
+    dot
+}
+
+pub(super) fn as_edge(attributes: &[(String, String)], directed: bool) -> Dot {
+    let mut dot = Dot {
+        shape: DotShape::Edge,
+        ..Dot::default()
+    };
+    if directed {
+        // yuml-rs writes arrowhead="none" when not set, so the graphviz default is restored explicitly
+        dot.extra.push(("arrowhead".to_string(), "normal".to_string()));
+    } else {
+        dot.dir = Some("none".to_string());
+    }
+    apply(&mut dot, attributes);
+    if dot.arrowhead.is_some() {
+        dot.extra.retain(|(key, _)| key != "arrowhead");
+    }
+    dot
+}
+
+impl TryFrom<&str> for DotFile {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        parse_dot(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dot() {
+        let text = r#"
+/* exported from another tool */
+digraph "My Graph" {
+    graph [rankdir=LR, splines=ortho, start=3];
+    node [shape=box, style="rounded,filled", fillcolor=lightgrey];
+    # preprocessor line
+    start [shape=circle, label=""];
+    "fill kettle" [label=<<B>Fill</B> Kettle>, tooltip="fill it"];
+    subgraph cluster_0 { boil; }
+    start -> "fill kettle" -> boil:n [label="next", color=red]; // trailing comment
+    boil -> start [arrowhead=vee, style=dashed]
+}
+"#;
+        let dot = parse_dot(text).unwrap().to_string();
+        for part in [
+            "rankdir = LR\n    splines = ortho\n    start = 3\n",
+            r#"    start [shape="circle", label="", style="rounded,filled", fillcolor="lightgrey", "#,
+            r#"    "fill kettle" [shape="rectangle", label=<<B>Fill</B> Kettle>, style="rounded,filled", fillcolor="lightgrey", arrowtail="none", arrowhead="none", tooltip="fill it"]"#,
+            r#"    boil [shape="rectangle", label="boil", "#,
+            r#"    start -> "fill kettle" [shape="edge", label="next", arrowtail="none", arrowhead="none", arrowhead="normal", color="red"]"#,
+            r#"    "fill kettle" -> boil:n [shape="edge", label="next", "#,
+            r#"    boil -> start [shape="edge", style="dashed", arrowtail="none", arrowhead="vee"]"#,
+        ] {
+            assert!(dot.contains(part), "missing {} in {}", part, dot);
+        }
+    }
+
+    #[test]
+    fn test_undirected_graph() {
+        let dot = parse_dot("graph { a -- b }").unwrap().to_string();
+        assert!(dot
+            .contains(r#"    a [shape="rectangle", label="a", arrowtail="none", arrowhead="none", shape="ellipse"]"#));
+        assert!(dot.contains(r#"    a -> b [shape="edge", dir="none", "#));
+    }
+
+    #[test]
+    fn test_invalid_dot() {
+        assert!(parse_dot("digraph { a -> }").is_err());
+        assert!(parse_dot("digraph { a [label=\"x] }").is_err());
+        assert!(parse_dot("flowchart LR").is_err());
+    }
+}
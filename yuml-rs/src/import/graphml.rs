@@ -0,0 +1,247 @@
+use super::dot::{as_edge, as_node};
+use crate::{
+    error::{YumlError, YumlResult},
+    model::{
+        dot::{as_id, DotElement, DotFile, Options},
+        label::decode_entities as decode,
+    },
+};
+
+/// Parse a GraphML document into a `DotFile`, so it can be styled with a `Theme` and rendered.
+///
+/// Nodes are labelled with the `label` or `name` data key, or the yEd node label, and fall back to their id.
+/// Edges are labelled in the same way. Layout information is ignored.
+/// Usage:
+/// ```rust
+/// use yuml_rs::parse_graphml;
+///
+/// let graphml = r#"<graphml><graph edgedefault="directed"><node id="a"/><node id="b"/><edge source="a" target="b"/></graph></graphml>"#;
+/// let dot = parse_graphml(graphml).unwrap().to_string();
+/// assert!(dot.contains("a -> b"));
+/// ```
+pub fn parse_graphml(text: &str) -> YumlResult<DotFile> {
+    let mut label_keys = vec![];
+    let mut directed = true;
+    let mut nodes: Vec<(String, Option<String>)> = vec![];
+    let mut edges: Vec<(String, String, Option<String>)> = vec![];
+    let mut found_graph = false;
+
+    // the element which receives the label of the current <data> element
+    let mut current: Option<Owner> = None;
+    let mut data_key: Option<String> = None;
+    let mut label_text: Option<String> = None;
+
+    let mut rest = text;
+    while let Some(start) = rest.find('<') {
+        if data_key.is_some() || label_text.is_some() {
+            let content = decode(rest[..start].trim());
+            if !content.is_empty() {
+                label_text.get_or_insert_with(String::new).push_str(&content);
+            }
+        }
+
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            rest = skip_past(rest, "-->")?;
+            continue;
+        }
+        if rest.starts_with("<![CDATA[") {
+            let end = rest.find("]]>").ok_or_else(|| invalid("unterminated CDATA section"))?;
+            if data_key.is_some() {
+                label_text.get_or_insert_with(String::new).push_str(&rest[9..end]);
+            }
+            rest = &rest[end + 3..];
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            rest = skip_past(rest, ">")?;
+            continue;
+        }
+
+        let end = rest.find('>').ok_or_else(|| invalid("unterminated tag"))?;
+        let tag = Tag::new(&rest[1..end]);
+        rest = &rest[end + 1..];
+
+        match (tag.name, tag.closing) {
+            ("key", false) => {
+                let name = tag.attribute("attr.name").unwrap_or_default().to_ascii_lowercase();
+                let yfiles_type = tag.attribute("yfiles.type").unwrap_or_default();
+                let is_label = name == "label" || name == "name" || yfiles_type.ends_with("graphics");
+                if let (true, Some(id)) = (is_label, tag.attribute("id")) {
+                    label_keys.push(id);
+                }
+            }
+            ("graph", false) => {
+                // only the outermost graph determines the direction, nested graphs are flattened
+                if !found_graph {
+                    directed = tag.attribute("edgedefault").as_deref() != Some("undirected");
+                }
+                found_graph = true;
+            }
+            ("node", false) => {
+                let id = tag.attribute("id").ok_or_else(|| invalid("a node without an id"))?;
+                nodes.push((id, None));
+                current = Some(Owner::Node(nodes.len() - 1));
+            }
+            ("edge", false) => {
+                let source = tag
+                    .attribute("source")
+                    .ok_or_else(|| invalid("an edge without a source"))?;
+                let target = tag
+                    .attribute("target")
+                    .ok_or_else(|| invalid("an edge without a target"))?;
+                edges.push((source, target, None));
+                current = Some(Owner::Edge(edges.len() - 1));
+            }
+            ("node", true) | ("edge", true) => current = None,
+            ("data", false) if !tag.self_closing => {
+                data_key = tag.attribute("key").filter(|key| label_keys.contains(key));
+                label_text = None;
+            }
+            ("data", true) => {
+                if let (Some(owner), Some(_)) = (&current, data_key.take()) {
+                    let label = label_text.take().map(|text| text.trim().to_string());
+                    match owner {
+                        Owner::Node(idx) => nodes[*idx].1 = nodes[*idx].1.take().or(label),
+                        Owner::Edge(idx) => edges[*idx].2 = edges[*idx].2.take().or(label),
+                    }
+                }
+                label_text = None;
+            }
+            _ => {}
+        }
+    }
+
+    if !found_graph {
+        return Err(invalid("missing <graph> element"));
+    }
+
+    let mut dots = vec![];
+    for (id, label) in &nodes {
+        let attributes = label
+            .iter()
+            .map(|label| ("label".to_string(), label.clone()))
+            .collect::<Vec<_>>();
+        dots.push(DotElement::new(&as_id(id), as_node(id, &attributes)));
+    }
+    for (source, target, label) in &edges {
+        let attributes = label
+            .iter()
+            .map(|label| ("label".to_string(), label.clone()))
+            .collect::<Vec<_>>();
+        dots.push(DotElement::new_edge(
+            &as_id(source),
+            &as_id(target),
+            as_edge(&attributes, directed),
+        ));
+    }
+
+    Ok(DotFile::new(dots, &Options::default()))
+}
+
+enum Owner {
+    Node(usize),
+    Edge(usize),
+}
+
+fn invalid(message: &str) -> YumlError {
+    YumlError::Import(format!("invalid GraphML: {}", message))
+}
+
+fn skip_past<'a>(text: &'a str, end: &str) -> YumlResult<&'a str> {
+    text.find(end)
+        .map(|idx| &text[idx + end.len()..])
+        .ok_or_else(|| invalid("unterminated markup"))
+}
+
+struct Tag<'a> {
+    /// Local name, without the namespace prefix
+    name: &'a str,
+    closing: bool,
+    self_closing: bool,
+    attributes: &'a str,
+}
+
+impl<'a> Tag<'a> {
+    fn new(content: &'a str) -> Self {
+        let closing = content.starts_with('/');
+        let self_closing = content.ends_with('/');
+        let content = content.trim_start_matches('/').trim_end_matches('/');
+        let (name, attributes) = content.split_once(|c: char| c.is_whitespace()).unwrap_or((content, ""));
+        let name = name.rsplit(':').next().unwrap_or(name);
+        Tag {
+            name,
+            closing,
+            self_closing,
+            attributes,
+        }
+    }
+
+    fn attribute(&self, name: &str) -> Option<String> {
+        let mut rest = self.attributes;
+        while let Some(eq) = rest.find('=') {
+            let key = rest[..eq].trim();
+            let value = rest[eq + 1..].trim_start();
+            let quote = value.chars().next()?;
+            let start = quote.len_utf8();
+            let end = value[start..].find(quote)? + start;
+            if key == name {
+                return Some(decode(&value[start..end]));
+            }
+            rest = &value[end + start..];
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Theme;
+
+    #[test]
+    fn test_parse_graphml() {
+        let text = r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns" xmlns:y="http://www.yworks.com/xml/graphml">
+  <key id="d0" for="node" attr.name="label" attr.type="string"/>
+  <key id="d1" for="node" yfiles.type="nodegraphics"/>
+  <key id="d2" for="edge" attr.name="weight" attr.type="double"/>
+  <graph id="G" edgedefault="directed">
+    <!-- <node id="ignored"/> -->
+    <node id="n0"><data key="d0">Fish &amp; Chips</data></node>
+    <node id="n1">
+      <data key="d1"><y:ShapeNode><y:NodeLabel>Kettle</y:NodeLabel></y:ShapeNode></data>
+    </node>
+    <node id="n 2"/>
+    <edge source="n0" target="n1"><data key="d2">1.0</data></edge>
+    <edge source="n1" target="n 2"/>
+  </graph>
+</graphml>"#;
+        let dot = parse_graphml(text).unwrap().theme(Theme::monochrome()).to_string();
+        for part in [
+            r#"    n0 [shape="rectangle", label="Fish & Chips", style="filled", fillcolor="gray92", "#,
+            r#"    n1 [shape="rectangle", label="Kettle", "#,
+            r#"    "n 2" [shape="rectangle", label="n 2", "#,
+            r#"    n0 -> n1 [shape="edge", "#,
+            r#"    n1 -> "n 2" [shape="edge", "#,
+        ] {
+            assert!(dot.contains(part), "missing {} in {}", part, dot);
+        }
+        assert!(!dot.contains("ignored"));
+    }
+
+    #[test]
+    fn test_undirected_graphml() {
+        let text = r#"<graphml><graph edgedefault="undirected"><node id="a"/><node id="b"/><edge source="a" target="b"/></graph></graphml>"#;
+        let dot = parse_graphml(text).unwrap().to_string();
+        assert!(dot.contains(r#"dir="none""#));
+    }
+
+    #[test]
+    fn test_invalid_graphml() {
+        assert!(parse_graphml("<graphml></graphml>").is_err());
+        assert!(parse_graphml("<graphml><graph><node/></graph></graphml>").is_err());
+        assert!(parse_graphml("<graphml><graph").is_err());
+        assert!(parse_graphml("<graphml><graph><node id=é/></graph></graphml>").is_err());
+    }
+}
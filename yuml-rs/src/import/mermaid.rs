@@ -0,0 +1,523 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    model::activity::ArrowLine,
+    parse_yuml,
+    parser::ParsedYuml,
+};
+use nom::{
+    branch::alt,
+    bytes::complete::{tag, take_until, take_while, take_while1},
+    character::complete::{space0, space1},
+    combinator::{map, opt, recognize},
+    multi::many0,
+    sequence::{delimited, pair, preceded, terminated, tuple},
+    IResult,
+};
+use std::collections::HashMap;
+
+/*
+Supported Mermaid syntax
+Flowchart       flowchart LR / graph TD
+Nodes           A[text] A(text) A([text]) A[[text]] A((start)) A(((end))) A{decision} A>note]
+Links           A --> B, A -->|label| B, A -- label --> B, A -.-> B, A ==> B, A --- B
+Class diagram   classDiagram, direction LR
+Classes         class A, class A["Label"], class A { +attr +method() }, A : +attr
+Relations       A <|-- B, A *-- B, A o-- B, A --> B, A ..> B, A ..|> B, A -- B, A .. B
+Cardinality     A "1" --> "*" B : label
+*/
+
+/// Best-effort import of simple Mermaid flowcharts and class diagrams.
+///
+/// The diagram is translated into yUML and parsed as an activity or class diagram. Styling, subgraphs and
+/// interactions are ignored.
+/// Usage:
+/// ```rust
+/// use yuml_rs::from_mermaid;
+///
+/// let parsed = from_mermaid("flowchart LR\n  A((start)) --> B[Boil Kettle] -->|done| C(((end)))").unwrap();
+/// assert!(parsed.to_string().contains("rankdir = LR"));
+/// ```
+pub fn from_mermaid(text: &str) -> YumlResult<ParsedYuml> {
+    parse_yuml(&mermaid_to_yuml(text)?)
+}
+
+fn mermaid_to_yuml(text: &str) -> YumlResult<String> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("%%"));
+
+    let header = lines.next().unwrap_or_default();
+    let mut words = header.split_whitespace();
+    match words.next() {
+        Some("flowchart") | Some("graph") => Ok(flowchart_to_yuml(words.next(), lines)),
+        Some("classDiagram") => Ok(class_diagram_to_yuml(lines)),
+        _ => Err(YumlError::Import(format!("unsupported Mermaid diagram '{}'", header))),
+    }
+}
+
+fn direction_directive(direction: Option<&str>) -> &'static str {
+    match direction {
+        Some("LR") => "// {direction:leftToRight}\n",
+        Some("RL") => "// {direction:rightToLeft}\n",
+        _ => "",
+    }
+}
+
+fn identifier(input: &str) -> IResult<&str, &str> {
+    take_while1(|c: char| c.is_alphanumeric() || c == '_')(input)
+}
+
+/// Node texts may be wrapped in quotes
+fn unquote(text: &str) -> &str {
+    let text = text.trim();
+    text.strip_prefix('"').and_then(|t| t.strip_suffix('"')).unwrap_or(text)
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Shape {
+    Activity,
+    Circle,
+    DoubleCircle,
+    Decision,
+    Note,
+}
+
+struct Node<'a> {
+    id: &'a str,
+    shape: Option<(Shape, &'a str)>,
+}
+
+struct Link<'a> {
+    label: Option<&'a str>,
+    line: ArrowLine,
+}
+
+/// Dotted links like `-.->` are dashed, thick links like `==>` are bold
+fn arrow_line(arrow: &str) -> ArrowLine {
+    match arrow {
+        _ if arrow.contains('.') => ArrowLine::Dashed,
+        _ if arrow.contains('=') => ArrowLine::Bold,
+        _ => ArrowLine::Solid,
+    }
+}
+
+fn bracketed<'a>(
+    open: &'static str,
+    close: &'static str,
+    shape: Shape,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (Shape, &'a str)> {
+    map(delimited(tag(open), take_until(close), tag(close)), move |text| {
+        (shape, unquote(text))
+    })
+}
+
+fn node(input: &str) -> IResult<&str, Node<'_>> {
+    let shape = alt((
+        bracketed("(((", ")))", Shape::DoubleCircle),
+        bracketed("((", "))", Shape::Circle),
+        bracketed("([", "])", Shape::Activity),
+        bracketed("[[", "]]", Shape::Activity),
+        bracketed("{{", "}}", Shape::Activity),
+        bracketed("[", "]", Shape::Activity),
+        bracketed("(", ")", Shape::Activity),
+        bracketed("{", "}", Shape::Decision),
+        bracketed(">", "]", Shape::Note),
+    ));
+    map(pair(identifier, opt(shape)), |(id, shape)| Node { id, shape })(input)
+}
+
+fn link(input: &str) -> IResult<&str, Link<'_>> {
+    let arrow = || {
+        recognize(tuple((
+            alt((tag("--"), tag("-."), tag("=="))),
+            take_while(|c| matches!(c, '-' | '.' | '=' | '>')),
+        )))
+    };
+    // A -- label --> B
+    let text_link = map(
+        tuple((
+            alt((tag("--"), tag("=="), tag("-."))),
+            space1,
+            take_until1_any(&["-->", "==>", ".->", "---"]),
+            arrow(),
+        )),
+        |(_, _, label, arrow)| Link {
+            label: Some(label.trim()),
+            line: arrow_line(arrow),
+        },
+    );
+    // A -->|label| B
+    let piped_link = map(
+        pair(arrow(), opt(delimited(tag("|"), take_until("|"), tag("|")))),
+        |(arrow, label)| Link {
+            label: label.map(unquote),
+            line: arrow_line(arrow),
+        },
+    );
+    delimited(space0, alt((text_link, piped_link)), space0)(input)
+}
+
+/// Take everything up to the first of the given patterns, which must not be empty
+fn take_until1_any<'a>(patterns: &'a [&'a str]) -> impl Fn(&'a str) -> IResult<&'a str, &'a str> {
+    move |input: &'a str| {
+        let idx = patterns
+            .iter()
+            .filter_map(|p| input.find(p))
+            .min()
+            .filter(|idx| *idx > 0);
+        match idx {
+            Some(idx) => Ok((&input[idx..], &input[..idx])),
+            None => Err(nom::Err::Error(nom::error::Error::new(
+                input,
+                nom::error::ErrorKind::TakeUntil,
+            ))),
+        }
+    }
+}
+
+type Chain<'a> = (Node<'a>, Vec<(Link<'a>, Node<'a>)>);
+
+fn chain(input: &str) -> IResult<&str, Chain<'_>> {
+    pair(node, many0(pair(link, node)))(input)
+}
+
+fn escape(text: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn flowchart_to_yuml<'a>(direction: Option<&str>, lines: impl Iterator<Item = &'a str>) -> String {
+    let ignored = [
+        "classDef",
+        "class ",
+        "style",
+        "linkStyle",
+        "click",
+        "subgraph",
+        "end",
+        "direction",
+    ];
+    let chains: Vec<Chain> = lines
+        .filter(|line| !ignored.iter().any(|keyword| line.starts_with(keyword)))
+        .filter_map(|line| chain(line.trim_end_matches(';')).ok().map(|(_, chain)| chain))
+        .collect();
+
+    // nodes may be referenced before their shape is declared
+    let mut shapes: HashMap<&str, (Shape, &str)> = HashMap::new();
+    for (first, links) in &chains {
+        for node in std::iter::once(first).chain(links.iter().map(|(_, node)| node)) {
+            if let Some(shape) = node.shape {
+                shapes.entry(node.id).or_insert(shape);
+            }
+        }
+    }
+
+    let as_yuml = |id: &str| match shapes.get(id).copied().unwrap_or((Shape::Activity, id)) {
+        (Shape::Circle, text) if text.eq_ignore_ascii_case("end") => "(end)".to_string(),
+        (Shape::Circle, text) if text.eq_ignore_ascii_case("start") || text.is_empty() => "(start)".to_string(),
+        (Shape::DoubleCircle, _) => "(end)".to_string(),
+        (Shape::Decision, text) => format!("<{}>", text.replace('>', "")),
+        (Shape::Note, text) => format!("(note: {})", escape(text, &[')', '{'])),
+        (_, text) => format!("({})", escape(text, &[')', '{'])),
+    };
+    let is_note = |id: &str| matches!(shapes.get(id), Some((Shape::Note, _)));
+
+    let mut yuml = format!("// {{type:activity}}\n{}", direction_directive(direction));
+    let mut linked = vec![];
+    for (first, links) in &chains {
+        if links.is_empty() {
+            continue;
+        }
+
+        yuml.push_str(&as_yuml(first.id));
+        let mut previous = first.id;
+        for (link, node) in links {
+            if is_note(previous) || is_note(node.id) {
+                yuml.push('-');
+            } else if let Some(label) = link.label.filter(|label| !label.is_empty()) {
+                yuml.push_str(&format!("[{}]{}", escape(label, &[']']), link.line.as_arrow()));
+            } else {
+                yuml.push_str(link.line.as_arrow());
+            }
+            yuml.push_str(&as_yuml(node.id));
+            linked.push(node.id);
+            previous = node.id;
+        }
+        linked.push(first.id);
+        yuml.push('\n');
+    }
+
+    // nodes without any links
+    for (first, links) in &chains {
+        if links.is_empty() && !linked.contains(&first.id) {
+            yuml.push_str(&as_yuml(first.id));
+            yuml.push('\n');
+            linked.push(first.id);
+        }
+    }
+
+    yuml
+}
+
+#[derive(Default)]
+struct Class<'a> {
+    label: Option<&'a str>,
+    attributes: Vec<String>,
+    methods: Vec<String>,
+}
+
+impl<'a> Class<'a> {
+    fn add_member(&mut self, member: &str) {
+        // generics are written as List~int~ in Mermaid; brackets and pipes would end the yUML class
+        let member = member
+            .trim()
+            .replacen('~', "<", 1)
+            .replacen('~', ">", 1)
+            .replace(['[', ']'], "")
+            .replace('|', "/");
+        if member.is_empty() {
+            return;
+        }
+
+        if member.contains('(') {
+            self.methods.push(member);
+        } else {
+            self.attributes.push(member);
+        }
+    }
+}
+
+struct Relation<'a> {
+    left: &'a str,
+    left_card: Option<&'a str>,
+    arrow: &'a str,
+    right_card: Option<&'a str>,
+    right: &'a str,
+    label: Option<&'a str>,
+}
+
+fn relation(input: &str) -> IResult<&str, Relation<'_>> {
+    let cardinality = || opt(terminated(delimited(tag("\""), take_until("\""), tag("\"")), space0));
+    let arrow = alt((
+        alt((
+            tag("<|--"),
+            tag("--|>"),
+            tag("<|.."),
+            tag("..|>"),
+            tag("*--"),
+            tag("--*"),
+        )),
+        alt((tag("o--"), tag("--o"), tag("-->"), tag("<--"), tag("..>"), tag("<.."))),
+        alt((tag("--"), tag(".."))),
+    ));
+    let label = opt(preceded(tuple((space0, tag(":"), space0)), nom::combinator::rest));
+
+    map(
+        tuple((
+            terminated(identifier, space0),
+            cardinality(),
+            terminated(arrow, space0),
+            cardinality(),
+            identifier,
+            label,
+        )),
+        |(left, left_card, arrow, right_card, right, label)| Relation {
+            left,
+            left_card,
+            arrow,
+            right_card,
+            right,
+            label: label.map(str::trim).filter(|l| !l.is_empty()),
+        },
+    )(input)
+}
+
+/// `class Name`, `class Name["Label"]` or `class Name~T~`, optionally opening a member block
+fn class_declaration(input: &str) -> IResult<&str, (&str, Option<&str>)> {
+    let label = delimited(tag("[\""), take_until("\"]"), tag("\"]"));
+    let generic = delimited(tag("~"), take_until("~"), tag("~"));
+    map(
+        preceded(
+            pair(tag("class"), space1),
+            tuple((identifier, opt(label), opt(generic))),
+        ),
+        |(id, label, _)| (id, label),
+    )(input)
+}
+
+fn class_diagram_to_yuml<'a>(lines: impl Iterator<Item = &'a str>) -> String {
+    let mut order: Vec<&str> = vec![];
+    let mut classes: HashMap<&str, Class> = HashMap::new();
+    let mut relations = vec![];
+    let mut direction = None;
+    let mut open_class: Option<&str> = None;
+
+    let declare = |id: &'a str, order: &mut Vec<&'a str>| {
+        if !order.contains(&id) {
+            order.push(id);
+        }
+    };
+
+    for line in lines {
+        if let Some(id) = open_class {
+            if line.starts_with('}') {
+                open_class = None;
+            } else {
+                classes.entry(id).or_default().add_member(line);
+            }
+            continue;
+        }
+
+        if let Some(dir) = line.strip_prefix("direction") {
+            direction = Some(dir.trim());
+        } else if let Ok((rest, (id, label))) = class_declaration(line) {
+            declare(id, &mut order);
+            let class = classes.entry(id).or_default();
+            class.label = label.or(class.label);
+            if rest.trim_start().starts_with('{') {
+                let members = rest.trim_start()[1..].trim();
+                match members.strip_suffix('}') {
+                    // single line block
+                    Some(members) => members.split(';').for_each(|m| class.add_member(m)),
+                    None => open_class = Some(id),
+                }
+            }
+        } else if let Ok((_, relation)) = relation(line) {
+            declare(relation.left, &mut order);
+            declare(relation.right, &mut order);
+            relations.push(relation);
+        } else if let Some((id, member)) = line.split_once(':') {
+            if let Ok(("", id)) = identifier(id.trim()) {
+                declare(id, &mut order);
+                classes.entry(id).or_default().add_member(member);
+            }
+        }
+    }
+
+    let label = |id: &'a str| -> String {
+        let text = classes.get(id).and_then(|c| c.label).unwrap_or(id);
+        text.replace(['[', ']', '|'], "")
+    };
+
+    let mut yuml = format!("// {{type:class}}\n{}", direction_directive(direction));
+    for id in &order {
+        let class = classes.get(id);
+        match class {
+            Some(class) if !class.attributes.is_empty() || !class.methods.is_empty() => {
+                yuml.push_str(&format!("[{}|{}", label(id), class.attributes.join(";")));
+                if !class.methods.is_empty() {
+                    yuml.push_str(&format!("|{}", class.methods.join(";")));
+                }
+                yuml.push_str("]\n");
+            }
+            _ if relations.iter().all(|r| r.left != *id && r.right != *id) => {
+                yuml.push_str(&format!("[{}]\n", label(id)));
+            }
+            _ => {}
+        }
+    }
+
+    for r in &relations {
+        let (left, right) = (label(r.left), label(r.right));
+        // labels can not contain the characters of the connectors
+        let card = |card: Option<&str>| {
+            card.unwrap_or_default()
+                .replace(['-', '<', '>', '+', '[', ',', ']'], "")
+        };
+        let connection = |left_arrow: &str, dashed: bool, right_arrow: &str| {
+            let line = if dashed { "-.-" } else { "-" };
+            // the label of a dependency is drawn in the middle of the edge, like `[A]uses-.->[B]`
+            let (left_card, right_card) = match (dashed, left_arrow, r.left_card) {
+                (true, "", None) => (card(r.label), card(r.right_card)),
+                _ => (card(r.left_card), card(r.right_card.or(r.label))),
+            };
+            format!(
+                "[{}]{}{}{}{}{}[{}]\n",
+                left, left_arrow, left_card, line, right_card, right_arrow, right
+            )
+        };
+
+        let statement = match r.arrow {
+            "<|--" => format!("[{}]^[{}]\n", left, right),
+            "--|>" => format!("[{}]^[{}]\n", right, left),
+            "<|.." => connection("^", true, ""),
+            "..|>" => connection("", true, "^"),
+            "*--" => connection("++", false, ""),
+            "--*" => connection("", false, "++"),
+            "o--" => connection("<>", false, ""),
+            "--o" => connection("", false, "<>"),
+            "-->" => connection("", false, ">"),
+            "<--" => connection("<", false, ""),
+            "..>" => connection("", true, ">"),
+            "<.." => connection("<", true, ""),
+            ".." => connection("", true, ""),
+            _ => connection("", false, ""),
+        };
+        yuml.push_str(&statement);
+    }
+
+    yuml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flowchart() {
+        let mermaid = r#"
+flowchart LR
+    %% boil some water
+    S((start)) --> D{kettle empty?}
+    D -->|yes| F[Fill Kettle] --> B(Boil Kettle)
+    D -- no --> B
+    B -.-> E(((end)))
+    D ==> E
+    B --- N>keep an eye on it]
+    style B fill:#f9f
+"#;
+        assert_eq!(
+            mermaid_to_yuml(mermaid).unwrap(),
+            "// {type:activity}\n// {direction:leftToRight}\n(start)-><kettle empty?>\n<kettle empty?>[yes]->(Fill Kettle)->(Boil Kettle)\n<kettle empty?>[no]->(Boil Kettle)\n(Boil Kettle)-.->(end)\n<kettle empty?>=>(end)\n(Boil Kettle)-(note: keep an eye on it)\n"
+        );
+        assert!(matches!(from_mermaid(mermaid), Ok(ParsedYuml::Activity(_))));
+    }
+
+    #[test]
+    fn test_class_diagram() {
+        let mermaid = r#"
+classDiagram
+    direction LR
+    class Customer {
+        +String name
+        +save() bool
+    }
+    class Order["Purchase Order"]
+    Order : +List~Item~ items
+    Customer "1" o-- "0..*" Order : places
+    Order <|-- SpecialOrder
+    Order ..> Invoice : uses
+"#;
+        assert_eq!(
+            mermaid_to_yuml(mermaid).unwrap(),
+            "// {type:class}\n// {direction:leftToRight}\n[Customer|+String name|+save() bool]\n[Purchase Order|+List<Item> items]\n[Customer]<>1-0..*[Purchase Order]\n[Purchase Order]^[SpecialOrder]\n[Purchase Order]uses-.->[Invoice]\n"
+        );
+        assert!(matches!(from_mermaid(mermaid), Ok(ParsedYuml::Class(_))));
+
+        let realization = mermaid_to_yuml("classDiagram\n    Shape <|.. Circle\n    Square ..|> Shape\n").unwrap();
+        assert!(realization.ends_with("[Shape]^-.-[Circle]\n[Square]-.-^[Shape]\n"));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(from_mermaid("sequenceDiagram\n  A->>B: hi").is_err());
+        assert!(from_mermaid("").is_err());
+    }
+}
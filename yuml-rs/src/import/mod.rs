@@ -0,0 +1,7 @@
+mod dot;
+mod graphml;
+mod mermaid;
+
+pub use dot::parse_dot;
+pub use graphml::parse_graphml;
+pub use mermaid::from_mermaid;
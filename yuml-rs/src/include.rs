@@ -0,0 +1,195 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    parser::parse_headers,
+};
+use std::{
+    borrow::Cow,
+    fs::read_to_string,
+    path::{Path, PathBuf},
+};
+
+/// Loads the fragments referenced by `// {include:...}` directives.
+///
+/// Implement this to include fragments from other sources than the file system, like a database or a web service.
+pub trait IncludeResolver {
+    /// Load the fragment at `path`, as written in the directive. `parent` is the location of the fragment which
+    /// includes it, like `shared/actors.yuml`, or `None` for the diagram itself; relative paths are relative to the
+    /// directory of the parent, see `include_location`.
+    fn resolve(&self, path: &str, parent: Option<&str>) -> YumlResult<String>;
+}
+
+/// The location of the fragment at `path` included by the fragment at `parent`, relative to the diagram: `base.yuml`
+/// included by `shared/actors.yuml` is `shared/base.yuml`
+pub fn include_location(path: &str, parent: Option<&str>) -> String {
+    match parent.and_then(|parent| Path::new(parent).parent()) {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.join(path).to_string_lossy().into_owned(),
+        _ => path.to_string(),
+    }
+}
+
+/// Resolves includes relative to a directory, usually the directory of the input file
+pub struct FileResolver {
+    base: PathBuf,
+}
+
+impl FileResolver {
+    pub fn new(base: impl Into<PathBuf>) -> Self {
+        FileResolver { base: base.into() }
+    }
+
+    /// Resolve includes relative to the directory of `file`
+    pub fn for_file(file: impl AsRef<Path>) -> Self {
+        let base = file.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        FileResolver::new(base)
+    }
+}
+
+impl IncludeResolver for FileResolver {
+    fn resolve(&self, path: &str, parent: Option<&str>) -> YumlResult<String> {
+        let location = include_location(path, parent);
+        read_to_string(self.base.join(&location)).map_err(|err| YumlError::Include(format!("{}: {}", location, err)))
+    }
+}
+
+/// Rejects all includes, as used by `parse_yuml`
+pub(crate) struct NoIncludes;
+
+impl IncludeResolver for NoIncludes {
+    fn resolve(&self, path: &str, _parent: Option<&str>) -> YumlResult<String> {
+        Err(YumlError::Include(format!(
+            "{}: includes require an IncludeResolver, use parse_yuml_with_includes",
+            path
+        )))
+    }
+}
+
+/// Insert the body of each included fragment after the directives of the diagram, in the order of the includes.
+///
+/// Included fragments may include other fragments and define macros; their remaining directives are ignored, so the
+/// options are determined by the including diagram only.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub(crate) fn expand_includes<'a>(yuml: &'a str, resolver: &dyn IncludeResolver) -> YumlResult<Cow<'a, str>> {
+    expand(yuml, resolver, &mut vec![], None)
+}
+
+/// Expand the includes of the diagram, or of the fragment at `parent`; only the directives of the diagram are kept
+fn expand<'a>(
+    yuml: &'a str,
+    resolver: &dyn IncludeResolver,
+    stack: &mut Vec<String>,
+    parent: Option<&str>,
+) -> YumlResult<Cow<'a, str>> {
+    let keep_directives = parent.is_none();
+    let (body, headers) = parse_headers(yuml).map_err(|err| YumlError::InvalidFile(err.to_string()))?;
+    if keep_directives && !headers.iter().any(|h| h.key == "include") {
+        return Ok(Cow::Borrowed(yuml));
+    }
+
+    let mut directives = String::new();
+    let mut fragments = String::new();
+    for h in &headers {
+        if h.key != "include" {
+            if keep_directives || h.key == "define" {
+                directives.push_str(&format!("{}\n", h));
+            }
+            continue;
+        }
+
+        let path = h.value.trim();
+        let location = include_location(path, parent);
+        if stack.contains(&location) {
+            return Err(YumlError::Include(format!("{}: includes itself", location)));
+        }
+
+        stack.push(location.clone());
+        let fragment = resolver.resolve(path, parent)?;
+        let fragment = expand(&fragment, resolver, stack, Some(&location))?;
+        stack.pop();
+
+        let fragment = fragment.trim_end();
+        if !fragment.is_empty() {
+            fragments.push_str(fragment);
+            fragments.push('\n');
+        }
+    }
+
+    Ok(Cow::Owned(format!("{}{}{}", directives, fragments, body)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    struct Fragments(HashMap<&'static str, &'static str>);
+
+    impl IncludeResolver for Fragments {
+        fn resolve(&self, path: &str, parent: Option<&str>) -> YumlResult<String> {
+            self.0
+                .get(include_location(path, parent).as_str())
+                .map(|fragment| fragment.to_string())
+                .ok_or_else(|| YumlError::Include(format!("{}: not found", path)))
+        }
+    }
+
+    fn fragments() -> Fragments {
+        Fragments(
+            [
+                (
+                    "actors.yuml",
+                    "// {type:activity}\n// {include:base.yuml}\n// {define:C=[Clerk]}\n[Customer]\nC\n",
+                ),
+                ("base.yuml", "[Person]"),
+                ("shared/actors.yuml", "// {include:base.yuml}\n[Customer]\n"),
+                ("shared/base.yuml", "[Account]"),
+                ("self.yuml", "// {include:self.yuml}\n"),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+
+    #[test]
+    fn test_expand_includes() {
+        let yuml = "// {type:class}\n// {include:actors.yuml}\n// {direction:leftToRight}\n[Customer]->[Order]";
+        let expanded = expand_includes(yuml, &fragments()).unwrap();
+        assert_eq!(
+            expanded,
+            "// {type:class}\n// {direction:leftToRight}\n// {define:C=[Clerk]}\n[Person]\n[Customer]\nC\n[Customer]->[Order]"
+        );
+
+        let yuml = "// {type:class}\n[A]";
+        assert!(matches!(expand_includes(yuml, &NoIncludes), Ok(Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn test_nested_includes() {
+        let yuml = "// {type:class}\n// {include:shared/actors.yuml}\n[Customer]->[Order]";
+        let expanded = expand_includes(yuml, &fragments()).unwrap();
+        assert_eq!(expanded, "// {type:class}\n[Account]\n[Customer]\n[Customer]->[Order]");
+
+        let dir = std::env::temp_dir().join(format!("yuml-include-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("shared")).unwrap();
+        std::fs::write(dir.join("base.yuml"), "[Wrong]").unwrap();
+        std::fs::write(dir.join("shared").join("base.yuml"), "[Account]").unwrap();
+        std::fs::write(
+            dir.join("shared").join("actors.yuml"),
+            "// {include:base.yuml}\n[Customer]\n",
+        )
+        .unwrap();
+
+        let expanded = expand_includes(yuml, &FileResolver::for_file(dir.join("diagram.yuml")));
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(
+            expanded.unwrap(),
+            "// {type:class}\n[Account]\n[Customer]\n[Customer]->[Order]"
+        );
+    }
+
+    #[test]
+    fn test_invalid_includes() {
+        assert!(expand_includes("// {include:missing.yuml}\n", &fragments()).is_err());
+        assert!(expand_includes("// {include:self.yuml}\n", &fragments()).is_err());
+        assert!(expand_includes("// {include:base.yuml}\n", &NoIncludes).is_err());
+    }
+}
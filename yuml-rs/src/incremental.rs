@@ -0,0 +1,190 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    parse_yuml,
+    parser::{parse_headers, ParsedYuml},
+};
+use std::ops::Range;
+
+/// A change of the text of a document, as sent by editors: the bytes in `range` are replaced by `text`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub range: Range<usize>,
+    pub text: String,
+}
+
+impl TextEdit {
+    pub fn new(range: Range<usize>, text: &str) -> Self {
+        TextEdit {
+            range,
+            text: text.to_string(),
+        }
+    }
+}
+
+/// A yUML document which is edited in place, for editor integrations like a language server or a preview.
+///
+/// After each edit, the statements on the edited lines are parsed on their own first; when they give the same graph as
+/// before the edit, the document is not parsed again. `apply` reports whether the dot description changed, so
+/// graphviz only has to run when the diagram actually looks different.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{TextEdit, YumlDocument};
+///
+/// let mut document = YumlDocument::new("// {type:class}\n[a]->[b],[b]->[c]").expect("invalid yUML");
+/// assert!(!document.apply(&TextEdit::new(24..24, " ")).expect("invalid yUML"));
+/// assert!(document.apply(&TextEdit::new(32..33, "d")).expect("invalid yUML"));
+/// ```
+pub struct YumlDocument {
+    text: String,
+    parsed: ParsedYuml,
+    dot: String,
+    /// Whether `parsed` and `dot` describe the current text, which is not the case after an edit failed to parse
+    is_current: bool,
+}
+
+impl YumlDocument {
+    pub fn new(text: &str) -> YumlResult<Self> {
+        let parsed = parse_yuml(text)?;
+        let dot = parsed.to_string();
+        Ok(YumlDocument {
+            text: text.to_string(),
+            parsed,
+            dot,
+            is_current: true,
+        })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The diagram of the last version of the text which could be parsed
+    pub fn parsed(&self) -> &ParsedYuml {
+        &self.parsed
+    }
+
+    /// The dot description of the last version of the text which could be parsed
+    pub fn dot(&self) -> &str {
+        &self.dot
+    }
+
+    /// Apply the `edit` to the text, returning whether the dot description changed.
+    ///
+    /// When the edited text can not be parsed, the error is returned and the diagram of the previous version is kept;
+    /// the next successful edit is compared with that diagram.
+    pub fn apply(&mut self, edit: &TextEdit) -> YumlResult<bool> {
+        let TextEdit { range, text } = edit;
+        if self.text.get(range.clone()).is_none() {
+            return Err(YumlError::Edit(format!(
+                "{}..{} is not a range of the text",
+                range.start, range.end
+            )));
+        }
+
+        let previous = std::mem::take(&mut self.text);
+        self.text = [&previous[..range.start], text, &previous[range.end..]].concat();
+
+        if self.is_current && self.is_unchanged(&previous, edit) {
+            return Ok(false);
+        }
+
+        self.is_current = false;
+        let parsed = parse_yuml(&self.text)?;
+        let dot = parsed.to_string();
+        let changed = dot != self.dot;
+        self.parsed = parsed;
+        self.dot = dot;
+        self.is_current = true;
+        Ok(changed)
+    }
+
+    /// Whether the statements of the lines touched by the `edit` give the same graph in the `previous` text as in the
+    /// current text. Only the elements of activity, class and entity-relationship diagrams do not depend on the
+    /// surrounding lines, and line numbers must stay the same for the warnings.
+    fn is_unchanged(&self, previous: &str, edit: &TextEdit) -> bool {
+        if !matches!(
+            self.parsed,
+            ParsedYuml::Activity(_) | ParsedYuml::Class(_) | ParsedYuml::Er(_)
+        ) || edit.text.matches('\n').count() != previous[edit.range.clone()].matches('\n').count()
+        {
+            return false;
+        }
+
+        let body = match parse_headers(previous) {
+            Ok((rest, _)) => previous.len() - rest.len(),
+            Err(_) => return false,
+        };
+        if edit.range.start < body {
+            return false;
+        }
+
+        let start = previous[..edit.range.start].rfind('\n').map_or(0, |idx| idx + 1);
+        let end = previous[edit.range.end..]
+            .find('\n')
+            .map_or(previous.len(), |idx| edit.range.end + idx);
+        let new_end = end + edit.text.len() + edit.range.start - edit.range.end;
+
+        let standalone = |lines: &str| parse_yuml(&format!("{}{}", &previous[..body], lines)).map(|p| p.to_string());
+        match (
+            standalone(&previous[start..end]),
+            standalone(&self.text[start..new_end]),
+        ) {
+            (Ok(before), Ok(after)) => before == after,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORDERS: &str = "// {type:class}\n[Customer]->[Order],[Order]->[Item]";
+
+    fn edit(document: &mut YumlDocument, find: &str, text: &str) -> YumlResult<bool> {
+        let start = document.text().find(find).expect("text to edit");
+        document.apply(&TextEdit::new(start..start + find.len(), text))
+    }
+
+    #[test]
+    fn test_whitespace_edits() {
+        let mut document = YumlDocument::new(ORDERS).unwrap();
+        let dot = document.dot().to_string();
+        assert!(!edit(&mut document, "],[", "] ,  [").unwrap());
+        assert_eq!(
+            document.text(),
+            "// {type:class}\n[Customer]->[Order] ,  [Order]->[Item]"
+        );
+        assert_eq!(document.dot(), dot);
+
+        // a line ending changes the lines of the statements, the graph stays the same
+        assert!(!edit(&mut document, "[Item]", "[Item]\n").unwrap());
+        assert_eq!(document.parsed().to_string(), dot);
+    }
+
+    #[test]
+    fn test_changing_edits() {
+        let mut document = YumlDocument::new(ORDERS).unwrap();
+        assert!(edit(&mut document, "[Item]", "[LineItem]").unwrap());
+        assert!(document.dot().contains("LineItem"));
+
+        assert!(edit(&mut document, "->[Order],", "->[LineItem],").unwrap());
+        assert!(edit(
+            &mut document,
+            "// {type:class}",
+            "// {type:class}\n// {direction:leftToRight}"
+        )
+        .unwrap());
+    }
+
+    #[test]
+    fn test_invalid_edits() {
+        let mut document = YumlDocument::new(ORDERS).unwrap();
+        let dot = document.dot().to_string();
+        assert!(edit(&mut document, "[Item]", "[Item").is_err());
+        assert_eq!(document.dot(), dot);
+        // the diagram is compared with the last valid version
+        assert!(!edit(&mut document, "[Item", "[Item]").unwrap());
+        assert!(document.apply(&TextEdit::new(0..1000, "")).is_err());
+    }
+}
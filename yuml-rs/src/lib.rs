@@ -1,19 +1,85 @@
 //! Parse yUML as SVG using the "dot" binary from the ["graphviz"](https://graphviz.org/download/) toolset.
 //!
 //! Based on the Javascript version from Jaime Olivares: [yuml-diagram](https://github.com/jaime-olivares/yuml-diagram).
-//! At the moment only Activity diagrams are supported, with no guarantees that the other variations will be added in the future.
+//! At the moment Activity, Class, Timeline, Entity-relationship and Mind map diagrams are supported, with no guarantees
+//! that the other variations will be added in the future.
 
+mod accessibility;
+pub mod analysis;
+#[cfg(feature = "bench-utils")]
+mod bench_utils;
+mod builder;
+pub mod class_model;
+#[cfg(feature = "render")]
+pub mod compat;
+mod completion;
+mod custom;
+mod encoding;
 mod error;
+#[cfg(feature = "evcxr")]
+mod evcxr;
+mod examples;
+mod export;
+mod format;
+#[cfg(feature = "generate")]
+pub mod generate;
+#[cfg(feature = "graphviz-ffi")]
+mod gvc;
+mod import;
+mod include;
+mod incremental;
+mod limits;
+mod macros;
 mod model;
 mod parser;
+#[cfg(feature = "render")]
+mod render;
+mod rgb;
+pub mod testing;
+mod url;
+mod validate;
 
-use crate::error::YumlResult;
-use error::YumlError;
-use parser::ParsedYuml;
-use std::{
-    fs::File,
-    io::Write,
-    process::{Command, Stdio},
+pub use accessibility::add_accessibility;
+#[cfg(feature = "bench-utils")]
+pub use bench_utils::generate_synthetic;
+pub use builder::ActivityBuilder;
+pub use completion::{defined_symbols, directive_values, Symbol};
+pub use custom::{CustomElement, ElementRegistry};
+pub use encoding::decode_yuml;
+pub use error::{YumlError, YumlResult};
+#[cfg(feature = "evcxr")]
+pub use evcxr::SvgImage;
+pub use examples::{example, examples};
+pub use export::{to_mermaid, to_plantuml};
+pub use format::format_yuml;
+#[cfg(feature = "graphviz-ffi")]
+pub use gvc::GvcRenderer;
+pub use import::{from_mermaid, parse_dot, parse_graphml};
+use include::NoIncludes;
+pub use include::{include_location, FileResolver, IncludeResolver};
+pub use incremental::{TextEdit, YumlDocument};
+pub use limits::{ParseOptions, DEFAULT_MAX_EXPANDED_SIZE};
+pub use model::dot::{
+    Arrow, ChartType, Directions, Dot, DotElement, DotFile, DotShape, GraphAttributes, Layout, Options, Ordering, Rank,
+    Splines, Style,
+};
+pub use model::label::{CellAlign, HtmlLabel, HtmlLabelBuilder, Label};
+pub use model::theme::{accent_color, Theme};
+pub use parser::{ParsedYuml, DIRECTIVES};
+#[cfg(feature = "async")]
+pub use render::render_svg_from_dot_async;
+#[cfg(feature = "render")]
+pub use render::{
+    as_data_uri, graphviz_version, locate_dot, render_data_uri, render_image_map, render_png_data_uri,
+    render_svg_data_uri, render_svg_from_dot_to, GraphvizRenderer, ImageMap, OutputFormat, RenderOptions, Renderer,
+    DOT_PATH_VAR, GRAPHVIZ_DOT_VAR,
+};
+pub use rgb::{is_valid_color, luma, COLOR_TABLE};
+#[cfg(feature = "render")]
+use std::fs::File;
+pub use url::{from_yuml_me_url, to_yuml_me_url};
+pub use validate::{
+    lint_yuml, validate_yuml, LintConfig, LintLevel, ParseFailure, ValidationReport, Warning, WarningKind,
 };
 
 /// Generate the interediate `DotFile` from the yUML input.
@@ -25,11 +91,127 @@ use std::{
 /// let yuml = read_to_string("activity.yaml").expect("can not read input file");
 /// let dot = parse_yuml(&yuml).expect("invalid yUML");
 /// ```
+/// Diagrams with `include` directives must be parsed with `parse_yuml_with_includes`. With the `// {strict:true}`
+/// directive, parsing fails on the issues reported by `lint_yuml`.
 pub fn parse_yuml(yuml: &str) -> YumlResult<ParsedYuml> {
-    let (_, df) = parser::parse_yuml(yuml).map_err(|e| YumlError::InvalidFile(e.to_string()))?;
+    parse_yuml_with_includes(yuml, &NoIncludes)
+}
+
+/// Like `parse_yuml`, taking the contents of a yUML file as read from disk or received over the network.
+/// Usage:
+/// ```rust,no_run
+/// use std::fs::read;
+/// use yuml_rs::parse_file;
+///
+/// let bytes = read("diagrams/activity.yuml").expect("can not read input file");
+/// let dot = parse_file(&bytes).expect("invalid yUML");
+/// ```
+/// UTF-8 and UTF-16 files are accepted, with or without byte order mark, see `decode_yuml`.
+pub fn parse_file(bytes: &[u8]) -> YumlResult<ParsedYuml> {
+    parse_yuml(&decode_yuml(bytes)?)
+}
+
+/// Like `parse_yuml`, loading the fragments of `// {include:...}` directives with the `resolver`.
+/// Usage:
+/// ```rust,no_run
+/// use std::fs::read_to_string;
+/// use yuml_rs::{parse_yuml_with_includes, FileResolver};
+///
+/// let yuml = read_to_string("diagrams/activity.yuml").expect("can not read input file");
+/// let resolver = FileResolver::for_file("diagrams/activity.yuml");
+/// let dot = parse_yuml_with_includes(&yuml, &resolver).expect("invalid yUML");
+/// ```
+pub fn parse_yuml_with_includes(yuml: &str, resolver: &dyn IncludeResolver) -> YumlResult<ParsedYuml> {
+    parse(yuml, resolver, &ParseOptions::default())
+}
+
+/// Like `parse_yuml`, failing with `YumlError::LimitExceeded` when the diagram exceeds one of the limits of the
+/// `options`, as services rendering diagrams from untrusted sources should.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{parse_yuml_with_options, ParseOptions};
+///
+/// let options = ParseOptions::default().max_lines(500).max_nodes(200);
+/// let dot = parse_yuml_with_options("// {type:activity}\n(start)->(end)", &options).expect("invalid yUML");
+/// ```
+pub fn parse_yuml_with_options(yuml: &str, options: &ParseOptions) -> YumlResult<ParsedYuml> {
+    parse(yuml, &NoIncludes, options)
+}
+
+/// Parse the diagram; with the `tracing` feature in a span with the type of the diagram and its number of nodes and
+/// edges, and a nested span per stage
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "parse_diagram",
+        level = "info",
+        skip_all,
+        fields(
+            lines = yuml.lines().count(),
+            chart_type = tracing::field::Empty,
+            nodes = tracing::field::Empty,
+            edges = tracing::field::Empty,
+        )
+    )
+)]
+fn parse(yuml: &str, resolver: &dyn IncludeResolver, options: &ParseOptions) -> YumlResult<ParsedYuml> {
+    options.check_lines(yuml)?;
+    let yuml = include::expand_includes(yuml, resolver)?;
+    options.check_lines(&yuml)?;
+    validate::check_directives(&yuml)?;
+    let yuml = macros::expand_macros(&yuml, options)?;
+    options.check_lines(&yuml)?;
+    if validate::is_strict(&yuml) {
+        lint_yuml(&yuml, &LintConfig::strict())?;
+    }
+    let (_, df) = parser::parse_yuml(&yuml).map_err(|e| YumlError::InvalidFile(e.to_string()))?;
+    let df = options.custom_elements.apply(df);
+    options.check_diagram(&yuml, &df)?;
+    #[cfg(feature = "tracing")]
+    record_diagram(&df);
     Ok(df)
 }
 
+#[cfg(feature = "tracing")]
+fn record_diagram(parsed: &ParsedYuml) {
+    let span = tracing::Span::current();
+    if let (Some(chart_type), Some(df)) = (parsed.chart_type(), parsed.dot_file()) {
+        span.record("chart_type", tracing::field::display(chart_type));
+        span.record("nodes", df.nodes().count());
+        span.record("edges", df.edges().count());
+    }
+}
+
+/// Run the parsers, the validator, the formatter, the exporters and the importers on arbitrary bytes, as a fuzz
+/// target. Invalid input must be reported as an error, so any panic is a bug.
+/// Usage, with `cargo fuzz`:
+/// ```rust,ignore
+/// fuzz_target!(|data: &[u8]| yuml_rs::fuzz_yuml(data));
+/// ```
+pub fn fuzz_yuml(data: &[u8]) {
+    let yuml = match std::str::from_utf8(data) {
+        Ok(yuml) => yuml,
+        Err(_) => return,
+    };
+
+    if let Ok(parsed) = parse_yuml(yuml) {
+        // labels can not break out of their attributes, so the dot file can always be read again
+        let dot = parsed.to_string();
+        if let Some(Err(err)) = parsed.dot_file().map(|_| parse_dot(&dot)) {
+            panic!("{} in the dot file of {:?}:\n{}", err, yuml, dot);
+        }
+        let _ = parsed.warnings();
+        let _ = to_plantuml(&parsed);
+        let _ = to_mermaid(&parsed);
+    }
+    let _ = validate_yuml(yuml);
+    let _ = format_yuml(yuml);
+    let _ = from_yuml_me_url(yuml);
+    let _ = parse_dot(yuml).map(|dot| dot.to_string());
+    let _ = parse_graphml(yuml).map(|dot| dot.to_string());
+    let _ = from_mermaid(yuml);
+}
+
 /// Render SVG using the "dot" binary, taking a valid dot-description as input.
 /// Usage:
 /// ```rust,no_run
@@ -38,36 +220,23 @@ pub fn parse_yuml(yuml: &str) -> YumlResult<ParsedYuml> {
 ///
 /// let yuml = read_to_string("activity.yaml").expect("can not read input file");
 /// let dot = parse_yuml(&yuml).expect("invalid yUML");
-/// render_svg_from_dot(&dot.to_string()).expect("can not generate SVG");
+/// let svg = render_svg_from_dot(&dot.to_string()).expect("can not generate SVG");
 /// ```
-/// # Panics
-/// Panics when the "dot" binary is not installed, or when the dot input is invalid.
-pub fn render_svg_from_dot(dot: &str) -> YumlResult<impl std::io::Read> {
-    // dot -Tsvg sample_dot.txt
-    let dot_process = Command::new("dot")
-        .arg("-Tsvg")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()
-        .expect("failed to execute process");
-
-    dot_process
-        .stdin
-        .unwrap()
-        .write_all(dot.as_bytes())
-        .expect("can not stream to dot process");
-
-    let data_out = dot_process.stdout.unwrap();
-    Ok(data_out)
+/// Use `render_svg_from_dot_to` to stream the output, or to enforce a timeout.
+/// Set the `YUML_DOT` or `GRAPHVIZ_DOT` environment variable when the "dot" binary is not on the PATH, nor in one of the
+/// directories searched by `locate_dot`.
+#[cfg(feature = "render")]
+pub fn render_svg_from_dot(dot: &str) -> YumlResult<Vec<u8>> {
+    let mut svg = Vec::new();
+    render_svg_from_dot_to(dot, &mut svg, &RenderOptions::default())?;
+    Ok(svg)
 }
 
 /// Similar to `render_svg_from_dot` but writes the output directly to a file
+#[cfg(feature = "render")]
 pub fn write_svg_from_dot(dot: &str, target_file: &str) -> YumlResult<()> {
-    let mut data_out = render_svg_from_dot(dot)?;
-    let mut output_file = File::create(target_file)?;
-    std::io::copy(&mut data_out, &mut output_file)?;
-
-    Ok(())
+    let output_file = File::create(target_file)?;
+    render_svg_from_dot_to(dot, output_file, &RenderOptions::default())
 }
 
 #[cfg(test)]
@@ -81,4 +250,216 @@ mod tests {
         let dot = parse_yuml(text).expect("can not generate activity dot");
         assert_eq!(dot.to_string(), expected);
     }
+
+    #[test]
+    fn test_parse_file() {
+        let text = include_str!("../test/activity.yuml");
+        let expected = include_str!("../test/activity.dot");
+        let bytes = [&[0xef, 0xbb, 0xbf], text.as_bytes()].concat();
+        assert_eq!(parse_file(&bytes).expect("can not parse file").to_string(), expected);
+        assert_eq!(
+            parse_file(text.as_bytes()).expect("can not parse file").to_string(),
+            expected
+        );
+        assert_eq!(
+            parse_file(b"// {type:activity}\n(start)->(\xff)")
+                .unwrap_err()
+                .to_string(),
+            "Can not read UTF-8 encoded yUML: not valid UTF-8, invalid utf-8 sequence of 1 bytes from index 29"
+        );
+    }
+
+    #[test]
+    fn test_activity_compat() {
+        let text = include_str!("../test/activity.yuml");
+        let expected = include_str!("../test/activity_compat.dot");
+        let dot = parse_yuml(text).expect("can not generate activity dot");
+        assert_eq!(format!("{:#}", dot), expected);
+    }
+
+    #[test]
+    fn test_crlf_and_trailing_whitespace() {
+        let text = include_str!("../test/activity_crlf.yuml");
+        let expected = include_str!("../test/activity.dot");
+        let dot = parse_yuml(text).expect("can not generate activity dot");
+        assert_eq!(dot.to_string(), expected);
+
+        let text = include_str!("../test/class_crlf.yuml");
+        let expected = parse_yuml(include_str!("../test/class.yuml")).expect("can not generate class dot");
+        let dot = parse_yuml(text).expect("can not generate class dot");
+        assert_eq!(dot.to_string(), expected.to_string());
+        assert!(dot.warnings().is_empty());
+    }
+
+    #[test]
+    fn test_macros() {
+        let text = "// {type:class}\n// {define:DB=[PostgreSQL{bg:lightblue}]}\n[Orders]->DB\n[Billing]->DB";
+        let dot = parse_yuml(text).expect("can not expand macros").to_string();
+        assert_eq!(dot.matches(r#"label="PostgreSQL""#).count(), 1);
+        assert!(dot.contains("A1 -> A2 ") && dot.contains("A3 -> A2 "));
+    }
+
+    /// The tokens of the generated diagrams, so most of them get past the first characters of the parsers
+    const FUZZ_TOKENS: &[&str] = &[
+        "// {type:activity}\n",
+        "// {type:class}\n",
+        "// {type:timeline}\n",
+        "// {type:er}\n",
+        "// {type:mindmap}\n",
+        "// {direction:leftToRight}\n",
+        "// {title:",
+        "// {define:X=",
+        "// {samerank:",
+        "(",
+        ")",
+        "[",
+        "]",
+        "<",
+        ">",
+        "|",
+        "{",
+        "}",
+        "-",
+        "->",
+        "-.->",
+        "<>",
+        "++",
+        "^",
+        "note:",
+        "start",
+        "end",
+        "bg:",
+        "tooltip:",
+        "id:",
+        "#",
+        "\\",
+        ":",
+        ";",
+        ",",
+        "*",
+        "1",
+        "0..*",
+        "..",
+        "'",
+        "\"",
+        "\n",
+        "\t",
+        "  ",
+        "é",
+        "a",
+        "X",
+        "=",
+        "digraph {",
+        "<node id=",
+        "<edge source=",
+        "/>",
+        "graph TD\n",
+    ];
+
+    #[test]
+    fn test_fuzz_yuml() {
+        // a xorshift generator, so every run checks the same diagrams
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..5000 {
+            let len = next() % 40;
+            let yuml: String = (0..len)
+                .map(|_| FUZZ_TOKENS[(next() % FUZZ_TOKENS.len() as u64) as usize])
+                .collect();
+            let yuml = format!("{}{}", FUZZ_TOKENS[(next() % 5) as usize], yuml);
+            fuzz_yuml(yuml.as_bytes());
+            let bytes = yuml.as_bytes();
+            fuzz_yuml(&bytes[..bytes.len() / 2]);
+        }
+    }
+
+    #[test]
+    fn test_directives_in_body() {
+        let text = "// {type:activity}\n(start)->(Boil Kettle)\n// {direction:leftToRight}\n(Boil Kettle)->(end)";
+        let dot = parse_yuml(text).expect("can not parse").to_string();
+        assert!(dot.contains("rankdir = LR"));
+        assert!(dot.contains("A2 -> A3 "));
+
+        let text = "// {type:activity}\n(start)->(end)\n// {type:class}";
+        assert_eq!(
+            parse_yuml(text).err().map(|err| err.to_string()),
+            Some("options error: line 3: 'type' is set to 'activity' on line 1 and to 'class' here".to_string())
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let text = "// {type:activity}\n(start)->(Boil Kettle)->\n(Boil Kettle)->(end)";
+        let parsed = parse_yuml(text).expect("can not parse");
+        let warnings: Vec<String> = parsed.warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec!["line 2: arrow after 'Boil Kettle' does not point to anything"]
+        );
+        assert_eq!(
+            parsed.strict().err().map(|err| err.to_string()),
+            Some("Diagram has issues: line 2: arrow after 'Boil Kettle' does not point to anything".to_string())
+        );
+
+        let text = "// {type:class}\n[A]->[B]";
+        assert!(parse_yuml(text).and_then(ParsedYuml::strict).is_ok());
+
+        let text = "// {type:class}\n// {strict:true}\n[A{colour:red}]->[B]";
+        assert!(parse_yuml(text).is_err());
+    }
+
+    /// Labels which try to end their attribute and add statements to the graph
+    const INJECTIONS: &[&str] = &[
+        r#"x"] ; node [color=red"#,
+        r#"x\"] ; node [color=red"#,
+        r#"x\"#,
+        r#"x\\"#,
+        r#"<x> ] ; node [color=red ; <y>"#,
+        "<x>\"] ; node [color=red",
+        r#"<<IMG SRC="/etc/passwd"/>>"#,
+    ];
+
+    #[test]
+    fn test_dot_injection() {
+        for injection in INJECTIONS {
+            for yuml in [
+                format!("// {{type:class}}\n[{0}]-{0}>[B|{0}]\n[B]-[note: {0}]", injection),
+                format!(
+                    "// {{type:activity}}\n(start)->({0})-{0}>(end)\n<d>[{0}]->(end)",
+                    injection
+                ),
+                format!("// {{type:activity}}\n(start)->(A{{tooltip:{0}}})", injection),
+                format!(
+                    "// {{type:class}}\n// {{title:{0}}}\n// {{bgcolor:{0}}}\n[A]",
+                    injection
+                ),
+                format!("// {{type:er}}\n[{0}|id]1-{0}-*[Order]", injection),
+                format!("// {{type:mindmap}}\n{0}\n  {0}", injection),
+            ] {
+                // the brackets of some injections end the node early, which is a syntax error of the yUML
+                let parsed = match parse_yuml(&yuml) {
+                    Ok(parsed) => parsed,
+                    Err(_) => continue,
+                };
+                let dot = parsed.uid_prefix(injection).to_string();
+                let imported = parse_dot(&dot).unwrap_or_else(|err| panic!("{}\n{}", err, dot));
+                let expected = parse_yuml(&yuml).unwrap();
+                let expected = expected.dot_file().unwrap();
+                assert_eq!(imported.nodes().count(), expected.nodes().count(), "{}", dot);
+                assert_eq!(imported.edges().count(), expected.edges().count(), "{}", dot);
+                assert!(
+                    !dot.lines().any(|line| line.trim_start().starts_with("node [color")),
+                    "{}",
+                    dot
+                );
+                assert!(!dot.contains("label=<<IMG"), "{}", dot);
+            }
+        }
+    }
 }
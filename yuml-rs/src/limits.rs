@@ -0,0 +1,145 @@
+use crate::{
+    completion::defined_symbols,
+    custom::{CustomElement, ElementRegistry},
+    error::{YumlError, YumlResult},
+    model::dot::ChartType,
+    parser::ParsedYuml,
+};
+
+/// Maximum size in bytes of the yUML after expanding macros, unless `ParseOptions::max_expanded_size` is set
+pub const DEFAULT_MAX_EXPANDED_SIZE: usize = 1 << 20;
+
+/// Limits on the size of a diagram, so services rendering diagrams from untrusted sources do not let graphviz run out
+/// of memory on gigantic diagrams. No limits are enforced by default, except on the expansion of macros.
+///
+/// Custom elements extend the notation, see `ParseOptions::custom_element`.
+#[derive(Debug, Clone, Default)]
+pub struct ParseOptions {
+    /// Maximum number of lines, before and after including fragments
+    pub max_lines: Option<usize>,
+    /// Maximum number of nodes, including notes
+    pub max_nodes: Option<usize>,
+    /// Maximum number of characters in the label of a node
+    pub max_label_length: Option<usize>,
+    /// Maximum size of the dot description in bytes
+    pub max_dot_size: Option<usize>,
+    /// Maximum size in bytes of the yUML after expanding macros, `DEFAULT_MAX_EXPANDED_SIZE` when not set
+    pub max_expanded_size: Option<usize>,
+    /// Notation extensions, like `[kafka:orders]` nodes
+    pub custom_elements: ElementRegistry,
+}
+
+impl ParseOptions {
+    pub fn max_lines(mut self, max: usize) -> Self {
+        self.max_lines = Some(max);
+        self
+    }
+
+    pub fn max_nodes(mut self, max: usize) -> Self {
+        self.max_nodes = Some(max);
+        self
+    }
+
+    pub fn max_label_length(mut self, max: usize) -> Self {
+        self.max_label_length = Some(max);
+        self
+    }
+
+    pub fn max_dot_size(mut self, max: usize) -> Self {
+        self.max_dot_size = Some(max);
+        self
+    }
+
+    pub fn max_expanded_size(mut self, max: usize) -> Self {
+        self.max_expanded_size = Some(max);
+        self
+    }
+
+    /// Register a custom element for nodes written as `prefix:...`, see `ElementRegistry::register`
+    pub fn custom_element(
+        mut self,
+        chart_type: ChartType,
+        prefix: &str,
+        element: impl CustomElement + 'static,
+    ) -> Self {
+        self.custom_elements = self.custom_elements.register(chart_type, prefix, element);
+        self
+    }
+
+    /// Macros that use other macros several times grow exponentially, so their expansion is always limited
+    pub(crate) fn expanded_size_limit(&self) -> usize {
+        self.max_expanded_size.unwrap_or(DEFAULT_MAX_EXPANDED_SIZE)
+    }
+
+    pub(crate) fn check_lines(&self, yuml: &str) -> YumlResult<()> {
+        check(self.max_lines, yuml.lines().count(), "lines")
+    }
+
+    /// Check the limits on the parsed diagram of the expanded `yuml`
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+    pub(crate) fn check_diagram(&self, yuml: &str, parsed: &ParsedYuml) -> YumlResult<()> {
+        if let Some(df) = parsed.dot_file() {
+            check(self.max_nodes, df.nodes().count(), "nodes")?;
+        }
+
+        if self.max_label_length.is_some() {
+            let longest = defined_symbols(yuml)
+                .iter()
+                .map(|symbol| symbol.label.chars().count())
+                .max()
+                .unwrap_or_default();
+            check(self.max_label_length, longest, "characters in a label")?;
+        }
+
+        if self.max_dot_size.is_some() {
+            check(self.max_dot_size, parsed.to_string().len(), "bytes of dot")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn check(max: Option<usize>, actual: usize, what: &'static str) -> YumlResult<()> {
+    match max {
+        Some(max) if actual > max => Err(YumlError::LimitExceeded { what, max, actual }),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse_yuml_with_options, ParseOptions};
+
+    const KETTLE: &str = "// {type:activity}\n(start)->(Fill Kettle)\n(Fill Kettle)->(Boil Kettle)->(end)";
+
+    fn error(options: &ParseOptions) -> Option<String> {
+        parse_yuml_with_options(KETTLE, options)
+            .err()
+            .map(|err| err.to_string())
+    }
+
+    #[test]
+    fn test_limits() {
+        assert!(parse_yuml_with_options(KETTLE, &ParseOptions::default()).is_ok());
+        let options = ParseOptions::default()
+            .max_lines(3)
+            .max_nodes(4)
+            .max_label_length(11)
+            .max_dot_size(10_000);
+        assert!(parse_yuml_with_options(KETTLE, &options).is_ok());
+
+        assert_eq!(
+            error(&ParseOptions::default().max_lines(2)),
+            Some("Diagram exceeds the limit of 2 lines: 3".to_string())
+        );
+        assert_eq!(
+            error(&ParseOptions::default().max_nodes(3)),
+            Some("Diagram exceeds the limit of 3 nodes: 4".to_string())
+        );
+        assert_eq!(
+            error(&ParseOptions::default().max_label_length(10)),
+            Some("Diagram exceeds the limit of 10 characters in a label: 11".to_string())
+        );
+        assert!(error(&ParseOptions::default().max_dot_size(100)).is_some());
+    }
+}
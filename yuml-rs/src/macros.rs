@@ -0,0 +1,172 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    limits::ParseOptions,
+    parser::parse_headers,
+};
+use std::borrow::Cow;
+
+/*
+Macros
+Definition      // {define:DB=[PostgreSQL{bg:lightblue}]}
+Usage           [Order Service]->DB, [Billing]->DB
+*/
+
+/// Substitute the macros of `// {define:NAME=replacement}` directives in the body of the diagram.
+///
+/// Names are matched as whole words outside of element text, so `[DB Admin]` is left alone. Replacements may use
+/// macros themselves, as long as they do not refer back to the macro being expanded. The expanded body may not exceed
+/// `ParseOptions::max_expanded_size`.
+///
+/// The directives are kept as they are, so the statements stay on their lines and reported line numbers still match
+/// the document.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub(crate) fn expand_macros<'a>(yuml: &'a str, options: &ParseOptions) -> YumlResult<Cow<'a, str>> {
+    let (body, headers) = parse_headers(yuml).map_err(|err| YumlError::InvalidFile(err.to_string()))?;
+    if !headers.iter().any(|h| h.key == "define") {
+        return Ok(Cow::Borrowed(yuml));
+    }
+
+    let mut macros: Vec<(&str, &str)> = vec![];
+    for h in headers.iter().filter(|h| h.key == "define") {
+        let (name, replacement) = match h.value.split_once('=') {
+            Some((name, replacement)) => (name.trim(), replacement.trim()),
+            None => return Err(invalid(h.value.trim(), "expected a definition like NAME=replacement")),
+        };
+        if !is_name(name) {
+            return Err(invalid(name, "names consist of letters, digits and underscores"));
+        }
+        if macros.iter().any(|(defined, _)| *defined == name) {
+            return Err(invalid(name, "defined more than once"));
+        }
+        macros.push((name, replacement));
+    }
+
+    let max_size = options.expanded_size_limit();
+    let directives = &yuml[..yuml.len() - body.len()];
+    let body = substitute(body, &macros, &mut vec![], max_size)?;
+    Ok(Cow::Owned(format!("{}{}", directives, body)))
+}
+
+fn invalid(name: &str, message: &str) -> YumlError {
+    YumlError::Macro {
+        name: name.to_string(),
+        message: message.to_string(),
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_name(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| !c.is_ascii_digit()) && name.chars().all(is_word_char)
+}
+
+fn substitute<'a>(
+    text: &str,
+    macros: &[(&'a str, &str)],
+    expanding: &mut Vec<&'a str>,
+    max_size: usize,
+) -> YumlResult<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut depth = 0usize;
+    let mut rest = text;
+
+    while let Some(c) = rest.chars().next() {
+        if depth == 0 && is_word_char(c) {
+            let end = rest.find(|c| !is_word_char(c)).unwrap_or(rest.len());
+            let word = &rest[..end];
+            match macros.iter().find(|(name, _)| *name == word) {
+                Some((name, _)) if expanding.contains(name) => {
+                    return Err(invalid(name, "refers to itself"));
+                }
+                Some((name, replacement)) => {
+                    expanding.push(name);
+                    expanded.push_str(&substitute(replacement, macros, expanding, max_size)?);
+                    expanding.pop();
+                    if expanded.len() > max_size {
+                        return Err(invalid(name, &format!("expands to more than {} bytes", max_size)));
+                    }
+                }
+                None => expanded.push_str(word),
+            }
+            rest = &rest[end..];
+            continue;
+        }
+
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+        expanded.push(c);
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_macros() {
+        let yuml = "// {type:class}\n// {define:DB=[PostgreSQL{bg:lightblue}]}\n// {define: CACHE = [Redis]<-DB }\n[Orders]->DB\n[DB Admin]->CACHE\n[Billing]->CACHE_2";
+        assert_eq!(
+            expand_macros(yuml, &ParseOptions::default()).unwrap(),
+            "// {type:class}\n// {define:DB=[PostgreSQL{bg:lightblue}]}\n// {define: CACHE = [Redis]<-DB }\n[Orders]->[PostgreSQL{bg:lightblue}]\n[DB Admin]->[Redis]<-[PostgreSQL{bg:lightblue}]\n[Billing]->CACHE_2"
+        );
+
+        let yuml = "// {type:class}\n[A]";
+        assert!(matches!(
+            expand_macros(yuml, &ParseOptions::default()),
+            Ok(Cow::Borrowed(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_macros() {
+        let message = |yuml: &str| expand_macros(yuml, &ParseOptions::default()).unwrap_err().to_string();
+        assert_eq!(
+            message("// {define:A=[B]-A}\n[X]->A"),
+            "Invalid macro 'A': refers to itself"
+        );
+        assert_eq!(
+            message("// {define:A=B}\n// {define:B=A}\nA"),
+            "Invalid macro 'A': refers to itself"
+        );
+        assert_eq!(
+            message("// {define:A=[B]}\n// {define:A=[C]}\n"),
+            "Invalid macro 'A': defined more than once"
+        );
+        assert_eq!(
+            message("// {define:my db=[B]}\n"),
+            "Invalid macro 'my db': names consist of letters, digits and underscores"
+        );
+        assert!(message("// {define:DB}\n").starts_with("Invalid macro 'DB'"));
+    }
+
+    #[test]
+    fn test_macro_bomb() {
+        // every macro doubles the previous one, so the last expands to 2^n copies of the first
+        let bomb = |n: usize| {
+            let defines: String = (1..=n)
+                .map(|i| format!("// {{define:M{}=M{} M{}}}\n", i, i - 1, i - 1))
+                .collect();
+            format!("// {{type:class}}\n// {{define:M0=[A]}}\n{}[B]->M{}", defines, n)
+        };
+
+        assert!(expand_macros(&bomb(12), &ParseOptions::default()).is_ok());
+        assert_eq!(
+            expand_macros(&bomb(12), &ParseOptions::default().max_expanded_size(10_000))
+                .unwrap_err()
+                .to_string(),
+            "Invalid macro 'M11': expands to more than 10000 bytes"
+        );
+        assert_eq!(
+            crate::parse_yuml(&bomb(40)).unwrap_err().to_string(),
+            "Invalid macro 'M18': expands to more than 1048576 bytes"
+        );
+    }
+}
@@ -1,22 +1,29 @@
 use super::{
     dot::{Arrow, Directions, Dot, DotElement, DotShape, Style},
+    label::{as_dot_label, Label},
     shared::{ElementDetails, LabeledElement, NoteProps},
 };
-use itertools::Itertools;
 use std::cell::RefCell;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Element<'a> {
     StartTag,
     EndTag,
+    /// `(end:flow)` ends a single flow, while the other flows continue
+    FlowFinalTag,
+    /// `(end:error)` ends the activity with an error
+    ErrorEndTag,
     Activity(ElementProps<'a>),
     Parallel(ElementProps<'a>),
     Decision(ElementProps<'a>),
     Arrow(ArrowProps<'a>),
     Note(NoteProps<'a>),
+    /// `{note: ...}` after the target of an arrow; attached to that arrow once the statement is parsed
+    TransitionNote(NoteProps<'a>),
 }
 
-pub fn as_note<'a>(note: (&'a str, Option<&'a str>)) -> Element {
+pub fn as_note<'a>(note: (&'a str, Option<&'a str>)) -> Element<'a> {
     let label = note.0;
     let attributes = note.1;
     Element::Note(NoteProps { label, attributes })
@@ -33,83 +40,208 @@ impl<'a> LabeledElement for Element<'a> {
         match self {
             Element::StartTag => "start",
             Element::EndTag => "end",
-            Element::Activity(props) | Element::Parallel(props) | Element::Decision(props) => props.label,
+            Element::FlowFinalTag => "end:flow",
+            Element::ErrorEndTag => "end:error",
+            Element::Activity(props) | Element::Parallel(props) | Element::Decision(props) => {
+                props.id.unwrap_or(props.label)
+            }
             Element::Arrow(details) => details.label.unwrap_or_default(),
-            Element::Note(props) => props.label,
+            Element::Note(props) | Element::TransitionNote(props) => props.label,
+        }
+    }
+
+    fn has_id(&self) -> bool {
+        matches!(self, Element::Activity(props) if props.id.is_some())
+    }
+
+    fn attributes(&self) -> Option<&'a str> {
+        match self {
+            Element::Activity(props) => props.attributes,
+            Element::Note(props) => props.attributes,
+            Element::Arrow(props) => props.attributes,
+            _ => None,
         }
     }
 
     fn is_connection(&self) -> bool {
         matches!(self, Element::Arrow(_))
     }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Element::StartTag => "start",
+            Element::EndTag => "end",
+            Element::FlowFinalTag => "flow final",
+            Element::ErrorEndTag => "error end",
+            Element::Activity(_) => "activity",
+            Element::Parallel(_) => "parallel bar",
+            Element::Decision(_) => "decision",
+            Element::Arrow(_) => "arrow",
+            Element::Note(_) => "note",
+            Element::TransitionNote(_) => "transition note",
+        }
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ElementProps<'a> {
     pub label: &'a str,
-    pub incoming_connections: RefCell<u8>,
+    /// Explicit id to reference the element by, instead of its label
+    pub id: Option<&'a str>,
+    pub attributes: Option<&'a str>,
+    pub chart_direction: Directions,
+}
+
+/// The line of an arrow: `->`, `-.->` for optional or asynchronous flows, or `=>` to stand out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ArrowLine {
+    #[default]
+    Solid,
+    Dashed,
+    Bold,
 }
 
-#[derive(Debug)]
+impl ArrowLine {
+    pub fn as_arrow(&self) -> &'static str {
+        match self {
+            ArrowLine::Solid => "->",
+            ArrowLine::Dashed => "-.->",
+            ArrowLine::Bold => "=>",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct ArrowProps<'a> {
     pub label: Option<&'a str>,
-    pub target_connection_id: RefCell<u8>,
+    /// Connectors of notes are dashed, see `ArrowProps::line` for dashed flows
     pub dashed: RefCell<bool>,
+    pub line: ArrowLine,
     pub chart_direction: Directions,
     pub has_tail: bool,
+    pub note: Option<NoteProps<'a>>,
+    /// Attribute block after the arrow, like `->{color:red}`
+    pub attributes: Option<&'a str>,
 }
 
 impl<'a> ElementProps<'a> {
     pub fn new(label: &'a str) -> Self {
         Self {
             label,
-            incoming_connections: RefCell::new(0),
+            id: None,
+            attributes: None,
+            chart_direction: Directions::default(),
         }
     }
+
+    pub fn id(mut self, id: Option<&'a str>) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn attributes(mut self, attributes: Option<&'a str>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    pub fn direction(mut self, chart_direction: &Directions) -> Self {
+        self.chart_direction = *chart_direction;
+        self
+    }
 }
 
 impl<'a> ArrowProps<'a> {
     pub fn new(label: Option<&'a str>, chart_direction: &Directions, has_tail: bool) -> Self {
         Self {
             label,
-            target_connection_id: RefCell::new(0),
             dashed: RefCell::new(false),
             chart_direction: *chart_direction,
             has_tail,
+            line: ArrowLine::default(),
+            note: None,
+            attributes: None,
         }
     }
+
+    pub fn line(mut self, line: ArrowLine) -> Self {
+        self.line = line;
+        self
+    }
+
+    pub fn attributes(mut self, attributes: Option<&'a str>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+}
+
+/// Split the edge of an arrow at a point node and connect the transition note to that point, so the note is
+/// anchored at the middle of the arrow
+pub fn split_at_note(edge: DotElement, note: &NoteProps, point_id: usize, note_id: usize) -> Vec<DotElement> {
+    let point = format!("A{}", point_id);
+    let note_uid = format!("A{}", note_id);
+    let DotElement { uid, uid2, mut dot } = edge;
+
+    // the label stays on the first half, the arrowhead moves to the second half
+    let second_half = Dot {
+        shape: DotShape::Edge,
+        style: dot.style.clone(),
+        dir: dot.dir.clone(),
+        arrowhead: dot.arrowhead.take(),
+        fontsize: dot.fontsize,
+        penwidth: dot.penwidth,
+        extra: dot.extra.clone(),
+        ..Dot::default()
+    };
+    let point_dot = Dot {
+        shape: DotShape::Point,
+        height: Some(0.01),
+        width: Some(0.01),
+        ..Dot::default()
+    };
+    let note_dot = Dot::from(&Element::Note(NoteProps {
+        label: note.label,
+        attributes: note.attributes,
+    }));
+    let connector = Dot {
+        shape: DotShape::Edge,
+        style: vec![Style::Dashed],
+        dir: Some("none".to_string()),
+        ..Dot::default()
+    };
+
+    vec![
+        DotElement::new(&point, point_dot),
+        DotElement::new(&note_uid, note_dot),
+        DotElement::new_edge(&uid, &point, dot),
+        DotElement::new_edge(&point, uid2.as_deref().unwrap_or_default(), second_half),
+        DotElement::new_edge(&note_uid, &point, connector),
+    ]
 }
 
 impl<'a> From<&ElementDetails<'a, Element<'a>>> for DotElement {
     fn from(e: &ElementDetails<'a, Element<'a>>) -> Self {
         match e.element {
-            Element::StartTag | Element::EndTag => DotElement {
+            Element::StartTag | Element::EndTag | Element::FlowFinalTag | Element::ErrorEndTag => DotElement {
                 dot: Dot::from(e.element),
                 uid: format!("A{}", e.id.unwrap_or_default()),
                 uid2: None,
             },
-            Element::Activity(_) | Element::Parallel(_) | Element::Decision(_) | Element::Note(_) => DotElement {
+            Element::Activity(_)
+            | Element::Parallel(_)
+            | Element::Decision(_)
+            | Element::Note(_)
+            | Element::TransitionNote(_) => DotElement {
                 dot: Dot::from(e.element),
                 uid: format!("A{}", e.id.unwrap_or_default()),
                 uid2: None,
             },
-            Element::Arrow(props) => {
-                let target_connection_id = *(props.target_connection_id.borrow());
-                let (uid1, uid2) = if let Some(relation) = &e.relation {
-                    let uid1 = format!("A{}", relation.previous_id);
-                    let uid2 = if target_connection_id > 0 {
-                        format!(
-                            "A{}:f{}:{}",
-                            relation.next_id,
-                            target_connection_id,
-                            props.chart_direction.head_port()
-                        )
-                    } else {
-                        format!("A{}", relation.next_id)
-                    };
-                    (uid1, uid2)
-                } else {
-                    ("A0".to_string(), "A0".to_string())
+            Element::Arrow(_) => {
+                let (uid1, uid2) = match &e.relation {
+                    Some(relation) => (format!("A{}", relation.previous_id), format!("A{}", relation.next_id)),
+                    None => ("A0".to_string(), "A0".to_string()),
                 };
 
                 DotElement {
@@ -137,25 +269,46 @@ impl<'a> From<&Element<'a>> for Dot {
                 width: Some(0.3),
                 ..Dot::default()
             },
+            Element::FlowFinalTag => Dot {
+                shape: DotShape::FlowFinal,
+                height: Some(0.3),
+                width: Some(0.3),
+                label: Some(Label::from("✕")),
+                fontsize: Some(14),
+                ..Dot::default()
+            },
+            Element::ErrorEndTag => Dot {
+                shape: DotShape::DoubleOctagon,
+                height: Some(0.3),
+                width: Some(0.3),
+                penwidth: Some(2),
+                ..Dot::default()
+            },
             Element::Activity(props) => Dot {
                 shape: DotShape::Rectangle,
                 height: Some(0.5),
                 margin: Some("0.20,0.05".to_string()),
-                label: Some(props.label.to_string()),
+                label: Some(as_dot_label(props.label)),
                 style: vec![Style::Rounded],
                 fontsize: Some(10),
                 ..Dot::default()
-            },
+            }
+            .with_attributes(props.attributes),
+            // the facets of the record are added once the incoming flows are known, see `FacetAllocator`
             Element::Parallel(props) => {
-                let incoming_connections = *props.incoming_connections.borrow();
-                let label = (1..=incoming_connections).map(|i| format!("<f{}>", i)).join("|");
+                // the bar lies across the flow of the chart
+                let (height, width) = if props.chart_direction.is_horizontal() {
+                    (0.5, 0.05)
+                } else {
+                    (0.05, 0.5)
+                };
 
                 Dot {
                     shape: DotShape::Record,
-                    height: Some(0.05),
-                    width: Some(0.5),
+                    height: Some(height),
+                    width: Some(width),
                     penwidth: Some(4),
-                    label: Some(label),
+                    label: Some(Label::from("")),
                     style: vec![Style::Filled],
                     fontsize: Some(1),
                     ..Dot::default()
@@ -165,43 +318,36 @@ impl<'a> From<&Element<'a>> for Dot {
                 shape: DotShape::Diamond,
                 height: Some(0.5),
                 width: Some(0.5),
-                label: Some(props.label.to_string()),
+                label: Some(Label::from(props.label)),
                 fontsize: Some(0),
                 ..Dot::default()
             },
+            // connectors of notes are dashed
             Element::Arrow(props) => Dot {
                 shape: DotShape::Edge,
-                style: vec![Style::Solid],
+                style: vec![if *props.dashed.borrow() || props.line == ArrowLine::Dashed {
+                    Style::Dashed
+                } else {
+                    Style::Solid
+                }],
+                penwidth: (props.line == ArrowLine::Bold).then_some(2),
                 dir: Some("both".to_string()),
                 arrowhead: if props.has_tail { Some(Arrow::Vee) } else { None },
                 fontsize: Some(10),
                 labeldistance: Some(1),
-                label: props.label.as_ref().map(|s| s.to_string()),
+                label: props.label.map(Label::from),
+                ..Dot::default()
+            }
+            .with_edge_attributes(props.attributes),
+            Element::Note(props) | Element::TransitionNote(props) => Dot {
+                shape: DotShape::Note,
+                height: Some(0.5),
+                margin: Some("0.20,0.05".to_string()),
+                label: Some(as_dot_label(props.label)),
+                fontsize: Some(10),
                 ..Dot::default()
-            },
-            // A1 [shape="note" , margin="0.20,0.05" , label="You can stick notes on diagrams too!\\{bg:cornsilk\\}" , style="filled" , fillcolor="cornsilk" , fontcolor="black" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]
-            Element::Note(props) => {
-                let (fillcolor, style) = if let Some(attr) = &props.attributes {
-                    if attr.starts_with("bg:") {
-                        (Some(attr.trim_start_matches("bg:").to_string()), vec![Style::Filled])
-                    } else {
-                        (None, vec![])
-                    }
-                } else {
-                    (None, vec![])
-                };
-
-                Dot {
-                    shape: DotShape::Note,
-                    height: Some(0.5),
-                    margin: Some("0.20,0.05".to_string()),
-                    label: Some(props.label.to_string()),
-                    fontsize: Some(10),
-                    fillcolor,
-                    style,
-                    ..Dot::default()
-                }
             }
+            .with_attributes(props.attributes),
         }
     }
 }
@@ -0,0 +1,291 @@
+use super::dot::{Dot, Rank, Style};
+use std::convert::TryFrom;
+
+/*
+Attribute blocks on nodes
+Background      [Customer{bg:orange}]
+Graphviz        [Cache{shape:cylinder}], (Job{style:dashed,color:red})
+Rank            [Customer{rank:min}], (Pay{rank:max})
+Edges           (a)->{color:red,penwidth:2}(b), [Customer]->{style:bold}[Order]
+Links           [Customer{href:https://example.com/customer,tooltip:Customers}], (a)->{href:/docs/b.html}(b)
+Graph           // {graphviz: nodesep=0.3; overlap=false}
+*/
+
+/// Graphviz node attributes which can be set through an attribute block
+pub const NODE_ATTRIBUTES: &[&str] = &[
+    "shape",
+    "style",
+    "color",
+    "fillcolor",
+    "fontcolor",
+    "fontname",
+    "fontsize",
+    "penwidth",
+    "peripheries",
+    "width",
+    "height",
+    "margin",
+    "tooltip",
+    "href",
+    "target",
+];
+
+/// Graphviz edge attributes which can be set through an attribute block after an arrow
+pub const EDGE_ATTRIBUTES: &[&str] = &[
+    "color",
+    "penwidth",
+    "style",
+    "fontcolor",
+    "fontsize",
+    "arrowsize",
+    "tooltip",
+    "href",
+    "target",
+];
+
+/// Graphviz graph attributes which can be set through the `graphviz` directive; attributes referring to files or
+/// links are left out, as diagrams may come from untrusted sources
+pub const GRAPH_ATTRIBUTES: &[&str] = &[
+    "center",
+    "clusterrank",
+    "compound",
+    "defaultdist",
+    "dim",
+    "dimen",
+    "epsilon",
+    "esep",
+    "fontcolor",
+    "fontsize",
+    "forcelabels",
+    "K",
+    "labeljust",
+    "labelloc",
+    "levelsgap",
+    "maxiter",
+    "mclimit",
+    "mindist",
+    "mode",
+    "model",
+    "newrank",
+    "nodesep",
+    "nslimit",
+    "nslimit1",
+    "ordering",
+    "outputorder",
+    "overlap",
+    "overlap_scaling",
+    "overlap_shrink",
+    "pack",
+    "packmode",
+    "pad",
+    "quadtree",
+    "quantum",
+    "ranksep",
+    "ratio",
+    "remincross",
+    "repulsiveforce",
+    "rotate",
+    "searchsize",
+    "sep",
+    "size",
+    "smoothing",
+    "splines",
+    "voro_margin",
+];
+
+/// Schemes of the links which can be set with `href`; others, like `javascript:`, would run when the link is clicked
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+/// `true` for a relative URL, like `/docs/b.html` or `#order`, or an http, https or mailto URL
+pub fn is_safe_link(url: &str) -> bool {
+    if url.chars().any(|c| c.is_control() || c.is_whitespace()) {
+        return false;
+    }
+
+    // a colon before the path, query or fragment ends the scheme
+    match url.find([':', '/', '?', '#']) {
+        Some(idx) if url[idx..].starts_with(':') => LINK_SCHEMES.contains(&url[..idx].to_ascii_lowercase().as_str()),
+        _ => true,
+    }
+}
+
+/// `false` for the attributes which are not applied because of their value, like an `href` to a `javascript:` URL
+pub fn is_safe_attribute(key: &str, value: &str) -> bool {
+    key != "href" || is_safe_link(value)
+}
+
+/// A graph attribute of the `graphviz` directive, or the reason it is not applied
+pub type GraphAttribute<'a> = Result<(&'a str, &'a str), String>;
+
+/// Split the value of a `graphviz` directive, like `nodesep=0.3; overlap=false`, into its key-value pairs. Keys must
+/// be in `GRAPH_ATTRIBUTES` and values may only contain characters which need no escaping, like `7.5,10!`, so the
+/// directive can not break out of the graph attributes.
+pub fn parse_graph_attributes(value: &str) -> Vec<GraphAttribute<'_>> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("graphviz attribute '{}' has no value", pair))?;
+            if !GRAPH_ATTRIBUTES.contains(&key) {
+                return Err(format!("graphviz attribute '{}' is not supported", key));
+            }
+            if value.is_empty()
+                || !value
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || ".,:_+-#%!".contains(c))
+            {
+                return Err(format!("invalid value '{}' for graphviz attribute '{}'", value, key));
+            }
+            Ok((key, value))
+        })
+        .collect()
+}
+
+/// Split a trailing, unescaped `{...}` attribute block from the node text
+pub fn split_attributes(text: &str) -> (&str, Option<&str>) {
+    if !text.ends_with('}') {
+        return (text, None);
+    }
+
+    match text.rfind('{') {
+        Some(idx) if !text[..idx].ends_with('\\') => (&text[..idx], Some(&text[idx + 1..text.len() - 1])),
+        _ => (text, None),
+    }
+}
+
+/// Split an attribute block like `bg:orange,shape:cylinder` into its key-value pairs
+pub fn parse_attributes(attributes: &str) -> Vec<(&str, &str)> {
+    attributes
+        .split(',')
+        .filter_map(|attr| attr.split_once(':'))
+        .map(|(key, value)| (key.trim(), value.trim()))
+        .filter(|(key, _)| !key.is_empty())
+        .collect()
+}
+
+impl Dot {
+    /// Apply the attribute block of a node; `bg` sets the fill color, `rank` the rank constraint, other whitelisted
+    /// graphviz attributes are passed through as-is, unknown attributes and unsafe links are ignored.
+    pub fn with_attributes(mut self, attributes: Option<&str>) -> Self {
+        for (key, value) in attributes.map(parse_attributes).unwrap_or_default() {
+            match key {
+                "bg" => {
+                    self.fillcolor = Some(value.to_string());
+                    if !self.style.contains(&Style::Filled) {
+                        self.style.push(Style::Filled);
+                    }
+                }
+                "rank" => self.rank = Rank::try_from(value).ok(),
+                "style" => self.merge_style(value),
+                _ if NODE_ATTRIBUTES.contains(&key) && is_safe_attribute(key, value) => {
+                    self.extra.push((key.to_string(), value.to_string()))
+                }
+                _ => { /* ignore unsupported attributes */ }
+            }
+        }
+
+        self
+    }
+
+    /// Apply the attribute block of an edge; whitelisted graphviz attributes are passed through as-is, unknown
+    /// attributes and unsafe links are ignored.
+    pub fn with_edge_attributes(mut self, attributes: Option<&str>) -> Self {
+        for (key, value) in attributes.map(parse_attributes).unwrap_or_default() {
+            if key == "style" {
+                self.merge_style(value);
+            } else if EDGE_ATTRIBUTES.contains(&key) && is_safe_attribute(key, value) {
+                self.extra.push((key.to_string(), value.to_string()));
+            }
+        }
+
+        self
+    }
+
+    /// Add a style like `dashed` or `bold` to the styles of the element, so the dot file has a single `style`; a line
+    /// style replaces the current one. Unknown styles are ignored.
+    fn merge_style(&mut self, value: &str) {
+        let style = match Style::try_from(value) {
+            Ok(style) => style,
+            Err(_) => return,
+        };
+
+        if style.is_line() {
+            self.style.retain(|current| !current.is_line());
+        }
+        if !self.style.contains(&style) {
+            self.style.push(style);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_attributes() {
+        assert_eq!(
+            split_attributes("Cache{shape:cylinder}"),
+            ("Cache", Some("shape:cylinder"))
+        );
+        assert_eq!(split_attributes("Cache"), ("Cache", None));
+        assert_eq!(split_attributes(r"f\{x}"), (r"f\{x}", None));
+        assert_eq!(split_attributes("a{b}{bg:red}"), ("a{b}", Some("bg:red")));
+    }
+
+    #[test]
+    fn test_with_attributes() {
+        let dot = Dot {
+            style: vec![Style::Rounded],
+            ..Dot::default()
+        }
+        .with_attributes(Some("bg:red, shape:cylinder,onclick:alert(1),style:dashed"));
+
+        assert_eq!(dot.fillcolor.as_deref(), Some("red"));
+        assert!(dot.style == vec![Style::Rounded, Style::Filled, Style::Dashed]);
+        assert_eq!(dot.extra, vec![("shape".to_string(), "cylinder".to_string())]);
+
+        let dot = Dot {
+            style: vec![Style::Solid],
+            ..Dot::default()
+        }
+        .with_edge_attributes(Some("style:bold,style:dotted,style:zigzag"));
+        assert!(dot.style == vec![Style::Bold, Style::Dotted]);
+        assert!(dot.extra.is_empty());
+    }
+
+    #[test]
+    fn test_links() {
+        for url in [
+            "https://example.com/customer",
+            "HTTP://example.com",
+            "mailto:sales@example.com",
+            "/docs/b.html",
+            "b.html?x=1:2",
+            "#order",
+        ] {
+            assert!(is_safe_link(url), "{}", url);
+        }
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            "java\tscript:alert(1)",
+            "data:text/html,x",
+            "file:///etc/passwd",
+            "vbscript:x",
+        ] {
+            assert!(!is_safe_link(url), "{}", url);
+        }
+
+        let dot = Dot::default().with_attributes(Some("href:javascript:alert(1),tooltip:Customers"));
+        assert_eq!(dot.extra, vec![("tooltip".to_string(), "Customers".to_string())]);
+        let dot = Dot::default().with_edge_attributes(Some("href:javascript:alert(1),color:red"));
+        assert_eq!(dot.extra, vec![("color".to_string(), "red".to_string())]);
+        let dot = Dot::default().with_edge_attributes(Some("href:/docs/b.html"));
+        assert_eq!(dot.extra, vec![("href".to_string(), "/docs/b.html".to_string())]);
+    }
+}
@@ -1,51 +1,91 @@
 use super::{
-    dot::{Arrow, Dot, DotElement, DotShape, Style},
-    shared::{ElementDetails, LabeledElement, NoteProps},
+    attributes::split_attributes,
+    dot::{Arrow, Directions, Dot, DotElement, DotShape, Style},
+    label::{as_dot_label, as_html_table, Label},
+    shared::{split_id, ElementDetails, LabeledElement, NoteProps},
 };
-use itertools::Itertools;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Element<'a> {
     Note(NoteProps<'a>),
     Class(&'a str),
     Connection(Connection<'a>),
-    Inheritance,
+    Inheritance(Directions),
+    /// `{class: ...}` after the target of a connection; attached to that connection once the statement is parsed
+    AssociationClass(&'a str),
 }
 
 impl<'a> LabeledElement for Element<'a> {
     fn label(&self) -> &'a str {
         match self {
             Element::Note(props) => props.label,
-            Element::Class(label) => {
-                if label.contains('|') {
+            Element::Class(text) | Element::AssociationClass(text) => {
+                let (label, _) = split_attributes(text);
+                if let (Some(id), _) = split_id(label) {
+                    id
+                } else if label.contains('|') {
                     label.split('|').next().unwrap()
                 } else {
                     label
                 }
             }
             Element::Connection(_details) => "",
-            Element::Inheritance => "",
+            Element::Inheritance(_) => "",
+        }
+    }
+
+    fn has_id(&self) -> bool {
+        matches!(self, Element::Class(text) if split_id(split_attributes(text).0).0.is_some())
+    }
+
+    fn attributes(&self) -> Option<&'a str> {
+        match self {
+            Element::Note(props) => props.attributes,
+            Element::Class(text) | Element::AssociationClass(text) => split_attributes(text).1,
+            Element::Connection(connection) => connection.attributes,
+            Element::Inheritance(_) => None,
         }
     }
 
     fn is_connection(&self) -> bool {
-        matches!(self, Element::Connection(_)) || matches!(self, Element::Inheritance)
+        matches!(self, Element::Connection(_)) || matches!(self, Element::Inheritance(_))
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Element::Note(_) => "note",
+            Element::Class(_) => "class",
+            Element::Connection(_) => "connection",
+            Element::Inheritance(_) => "inheritance",
+            Element::AssociationClass(_) => "association class",
+        }
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Connection<'a> {
     pub left: Connector<'a>,
     pub right: Connector<'a>,
     pub dashed: bool,
+    /// Label in the middle of the connection, like the `uses` of a dependency
+    pub label: Option<&'a str>,
+    /// Text of the association class of the connection, like `Enrollment|grade` of `{class:Enrollment|grade}`
+    pub association: Option<&'a str>,
+    /// Attribute block after the connection, like `->{color:red}`
+    pub attributes: Option<&'a str>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Connector<'a> {
     None(RelationProps<'a>),
     Directional(RelationProps<'a>),
     Aggregation(RelationProps<'a>),
     Composition(RelationProps<'a>),
+    /// `^` on a connection, like the realization `[Impl]-.-^[Interface]`
+    Inheritance(RelationProps<'a>),
     Dependencies(RelationProps<'a>),
     Cardinality(RelationProps<'a>),
 }
@@ -56,12 +96,44 @@ impl<'a> Default for Connector<'a> {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct RelationProps<'a> {
     pub label: Option<&'a str>,
 }
 
-pub fn as_note<'a>(note: (&'a str, Option<&'a str>)) -> Element {
+/// Split the namespace off the name of a class, like `billing` of `billing::Invoice|number`, returning it with the
+/// text of the class without the namespace
+pub fn split_namespace(text: &str) -> Option<(&str, String)> {
+    let (label, _) = split_attributes(text);
+    let start = label.len() - split_id(label).1.len();
+    let name = label[start..].split('|').next().unwrap_or_default();
+    let end = name.rfind("::")?;
+    let namespace = name[..end].trim();
+    if namespace.is_empty() {
+        return None;
+    }
+    Some((namespace, format!("{}{}", &text[..start], &text[start + end + 2..])))
+}
+
+/// Draw a class in the cluster of its namespace, without the namespace in its label
+pub fn in_namespace(node: DotElement, element: &Element) -> DotElement {
+    match element {
+        Element::Class(text) => match split_namespace(text) {
+            Some((namespace, text)) => DotElement {
+                dot: Dot {
+                    cluster: Some(namespace.to_string()),
+                    ..Dot::from(&Element::Class(&text))
+                },
+                ..node
+            },
+            None => node,
+        },
+        _ => node,
+    }
+}
+
+pub fn as_note<'a>(note: (&'a str, Option<&'a str>)) -> Element<'a> {
     let label = note.0;
     let attributes = note.1;
     Element::Note(NoteProps { label, attributes })
@@ -70,7 +142,7 @@ pub fn as_note<'a>(note: (&'a str, Option<&'a str>)) -> Element {
 impl<'a> From<&ElementDetails<'a, Element<'a>>> for DotElement {
     fn from(e: &ElementDetails<'a, Element<'a>>) -> Self {
         match e.element {
-            Element::Note(_) | Element::Class(_) => DotElement {
+            Element::Note(_) | Element::Class(_) | Element::AssociationClass(_) => DotElement {
                 dot: Dot::from(e.element),
                 uid: format!("A{}", e.id.unwrap_or_default()),
                 uid2: None,
@@ -90,10 +162,11 @@ impl<'a> From<&ElementDetails<'a, Element<'a>>> for DotElement {
                     uid2: Some(uid2),
                 }
             }
-            Element::Inheritance => {
+            Element::Inheritance(chart_direction) => {
+                // the parent is ranked before its specializations, so the edge follows the chart direction
                 let (uid1, uid2) = if let Some(relation) = &e.relation {
-                    let uid1 = format!("A{}", relation.previous_id);
-                    let uid2 = format!("A{}", relation.next_id);
+                    let uid1 = format!("A{}:{}", relation.previous_id, chart_direction.tail_port());
+                    let uid2 = format!("A{}:{}", relation.next_id, chart_direction.head_port());
                     (uid1, uid2)
                 } else {
                     ("A0".to_string(), "A0".to_string())
@@ -112,44 +185,22 @@ impl<'a> From<&ElementDetails<'a, Element<'a>>> for DotElement {
 impl<'a> From<&Element<'a>> for Dot {
     fn from(e: &Element<'a>) -> Self {
         match e {
-            Element::Note(props) => {
-                let (fillcolor, style) = if let Some(attr) = &props.attributes {
-                    if attr.starts_with("bg:") {
-                        (Some(attr.trim_start_matches("bg:").to_string()), vec![Style::Filled])
-                    } else {
-                        (None, vec![])
-                    }
-                } else {
-                    (None, vec![])
-                };
-
-                Dot {
-                    shape: DotShape::Note,
-                    height: Some(0.5),
-                    margin: Some("0.20,0.05".to_string()),
-                    label: Some(props.label.to_string()),
-                    fontsize: Some(10),
-                    fillcolor,
-                    style,
-                    ..Dot::default()
-                }
+            Element::Note(props) => Dot {
+                shape: DotShape::Note,
+                height: Some(0.5),
+                margin: Some("0.20,0.05".to_string()),
+                label: Some(as_dot_label(props.label)),
+                fontsize: Some(10),
+                ..Dot::default()
             }
-            Element::Class(label) => {
+            .with_attributes(props.attributes),
+            Element::Class(text) | Element::AssociationClass(text) => {
+                let (label, attributes) = split_attributes(text);
+                let (_, label) = split_id(label);
                 let (label, margin) = if label.contains('|') {
-                    let rows = label
-                        .split('|')
-                        .into_iter()
-                        .map(|row| format!("<TR><TD>{}</TD></TR>", row))
-                        .join("");
-
-                    let table = format!(
-                        "<<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\" CELLPADDING=\"9\">{}</TABLE>>",
-                        rows
-                    );
-
-                    (table, None)
+                    (as_html_table(label.split('|')), None)
                 } else {
-                    (label.to_string(), Some("0.20,0.05".to_string()))
+                    (as_dot_label(label), Some("0.20,0.05".to_string()))
                 };
 
                 Dot {
@@ -160,6 +211,7 @@ impl<'a> From<&Element<'a>> for Dot {
                     fontsize: Some(10),
                     ..Dot::default()
                 }
+                .with_attributes(attributes)
             }
             Element::Connection(connection) => {
                 let (left_arrow_style, left_props) = extract_props(&connection.left);
@@ -177,12 +229,14 @@ impl<'a> From<&Element<'a>> for Dot {
                     arrowhead: right_arrow_style,
                     fontsize: Some(10),
                     labeldistance: Some(2),
+                    label: connection.label.map(Label::from),
                     taillabel: left_props.label.as_ref().map(|s| s.to_string()),
                     headlabel: right_props.label.as_ref().map(|s| s.to_string()),
                     ..Dot::default()
                 }
+                .with_edge_attributes(connection.attributes)
             }
-            Element::Inheritance => Dot {
+            Element::Inheritance(_) => Dot {
                 shape: DotShape::Edge,
                 style: vec![Style::Solid],
                 dir: Some("both".to_string()),
@@ -199,7 +253,64 @@ fn extract_props<'a>(props: &'a Connector<'a>) -> (Option<Arrow>, &'a RelationPr
         Connector::Directional(props) => (Some(Arrow::Vee), props),
         Connector::Aggregation(props) | Connector::Cardinality(props) => (Some(Arrow::ODiamond), props),
         Connector::Composition(props) => (Some(Arrow::Diamond), props),
-        Connector::Dependencies(props) => (Some(Arrow::Empty), props),
+        Connector::Inheritance(props) | Connector::Dependencies(props) => (Some(Arrow::Empty), props),
         Connector::None(props) => (None, props),
     }
 }
+
+/// Split the edge of a connection at a point node in its middle, which is linked to the association class with a
+/// dashed line. The tail of the connection stays on the first half, the head moves to the second half.
+pub fn split_at_association_class(edge: DotElement, point_id: usize, class_uid: &str) -> Vec<DotElement> {
+    let point = format!("A{}", point_id);
+    let DotElement { uid, uid2, mut dot } = edge;
+
+    let second_half = Dot {
+        shape: DotShape::Edge,
+        style: dot.style.clone(),
+        dir: dot.dir.clone(),
+        arrowhead: dot.arrowhead.take(),
+        headlabel: dot.headlabel.take(),
+        fontsize: dot.fontsize,
+        labeldistance: dot.labeldistance,
+        extra: dot.extra.clone(),
+        ..Dot::default()
+    };
+    let point_dot = Dot {
+        shape: DotShape::Point,
+        height: Some(0.01),
+        width: Some(0.01),
+        ..Dot::default()
+    };
+    let connector = Dot {
+        shape: DotShape::Edge,
+        style: vec![Style::Dashed],
+        dir: Some("none".to_string()),
+        ..Dot::default()
+    };
+
+    vec![
+        DotElement::new(&point, point_dot),
+        DotElement::new_edge(&uid, &point, dot),
+        DotElement::new_edge(&point, uid2.as_deref().unwrap_or_default(), second_half),
+        DotElement::new_edge(&point, class_uid, connector),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_namespace() {
+        assert_eq!(
+            split_namespace("billing::Invoice|number"),
+            Some(("billing", "Invoice|number".to_string()))
+        );
+        assert_eq!(
+            split_namespace("id=inv|com::billing::Invoice{bg:red}"),
+            Some(("com::billing", "id=inv|Invoice{bg:red}".to_string()))
+        );
+        assert_eq!(split_namespace("Invoice|total: billing::Money"), None);
+        assert_eq!(split_namespace("::Invoice"), None);
+    }
+}
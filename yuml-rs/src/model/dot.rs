@@ -1,9 +1,13 @@
-use crate::error::{OptionsError, YumlError};
-use itertools::Itertools;
+use super::{label::Label, theme::Theme};
+use crate::{
+    error::{OptionsError, YumlError},
+    validate::Warning,
+};
 use std::convert::TryFrom;
 use std::fmt::{Display, Formatter, Write};
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChartType {
     Class,
     UseCase,
@@ -12,21 +16,20 @@ pub enum ChartType {
     Deployment,
     Package,
     Sequence,
+    Timeline,
+    Er,
+    Mindmap,
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Directions {
     LeftToRight,
     RightToLeft,
+    #[default]
     TopDown,
 }
 
-impl Default for Directions {
-    fn default() -> Self {
-        Directions::TopDown
-    }
-}
-
 impl Display for Directions {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -38,6 +41,7 @@ impl Display for Directions {
 }
 
 impl Directions {
+    /// Compass point where edges enter a node, following the flow of the chart
     pub fn head_port(&self) -> &str {
         match self {
             Directions::LeftToRight => "w",
@@ -45,6 +49,19 @@ impl Directions {
             Directions::TopDown => "n",
         }
     }
+
+    /// Compass point where edges leave a node, following the flow of the chart
+    pub fn tail_port(&self) -> &str {
+        match self {
+            Directions::LeftToRight => "e",
+            Directions::RightToLeft => "w",
+            Directions::TopDown => "s",
+        }
+    }
+
+    pub fn is_horizontal(&self) -> bool {
+        matches!(self, Directions::LeftToRight | Directions::RightToLeft)
+    }
 }
 
 impl Display for ChartType {
@@ -57,6 +74,9 @@ impl Display for ChartType {
             ChartType::Deployment => f.write_str("deployment"),
             ChartType::Package => f.write_str("package"),
             ChartType::Sequence => f.write_str("sequence"),
+            ChartType::Timeline => f.write_str("timeline"),
+            ChartType::Er => f.write_str("er"),
+            ChartType::Mindmap => f.write_str("mindmap"),
         }
     }
 }
@@ -86,25 +106,225 @@ impl TryFrom<&str> for ChartType {
             "deployment" => Ok(ChartType::Deployment),
             "package" => Ok(ChartType::Package),
             "sequence" => Ok(ChartType::Sequence),
+            "timeline" => Ok(ChartType::Timeline),
+            "er" => Ok(ChartType::Er),
+            "mindmap" => Ok(ChartType::Mindmap),
             _ => Err(OptionsError::new(
-                "invalid value for 'type'. Allowed values are: class, usecase, activity, state, deployment, package.",
+                "invalid value for 'type'. Allowed values are: class, usecase, activity, state, deployment, package, timeline, er, mindmap.",
             )
             .into()),
         }
     }
 }
 
-#[derive(Default)]
+/// The options of a diagram, as set by its directives
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Options {
     pub dir: Directions,
     pub generate: bool,
-    pub is_dark: bool,
+    /// Set by the `strict` directive: issues found by the linter fail parsing
+    pub strict: bool,
+    pub theme: Theme,
     pub chart_type: Option<ChartType>,
+    pub font: Option<String>,
+    pub ranksep: Option<f32>,
+    pub bgcolor: Option<String>,
+    /// Space around the drawing, in inches
+    pub margin: Option<f32>,
+    /// Set by the `title` directive, drawn above the diagram
+    pub title: Option<String>,
+    pub scale: Option<u32>,
+    pub dpi: Option<u32>,
+    /// Labels of the nodes of each `samerank` directive
+    pub same_ranks: Vec<Vec<String>>,
+    /// Set by the `namespaces` directive: classes named like `billing::Invoice` are grouped per namespace
+    pub namespaces: bool,
+    pub graph: GraphAttributes,
+    /// Prepended to the ids of all nodes, to keep them unique when several diagrams share a page
+    pub uid_prefix: Option<String>,
+    /// Set by the `env` directive: outlines and edges are drawn in the accent color of the environment
+    pub env: Option<String>,
+}
+
+/// Edge routing method of graphviz
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Splines {
+    Spline,
+    Line,
+    Polyline,
+    Ortho,
+    Curved,
+    None,
+}
+
+impl Display for Splines {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Splines::Spline => f.write_str("spline"),
+            Splines::Line => f.write_str("line"),
+            Splines::Polyline => f.write_str("polyline"),
+            Splines::Ortho => f.write_str("ortho"),
+            Splines::Curved => f.write_str("curved"),
+            Splines::None => f.write_str("none"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Splines {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "spline" | "true" => Ok(Splines::Spline),
+            "line" | "false" => Ok(Splines::Line),
+            "polyline" => Ok(Splines::Polyline),
+            "ortho" => Ok(Splines::Ortho),
+            "curved" => Ok(Splines::Curved),
+            "none" => Ok(Splines::None),
+            _ => Err(OptionsError::new(
+                "invalid value for 'splines'. Allowed values are: spline <i>(default)</i>, line, polyline, ortho, curved, none.",
+            )
+            .into()),
+        }
+    }
+}
+
+/// Constrains the left-to-right order of the edges around a node
+#[derive(PartialEq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Ordering {
+    Out,
+    In,
+}
+
+impl Display for Ordering {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ordering::Out => f.write_str("out"),
+            Ordering::In => f.write_str("in"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Ordering {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "out" => Ok(Ordering::Out),
+            "in" => Ok(Ordering::In),
+            _ => Err(OptionsError::new("invalid value for 'ordering'. Allowed values are: out, in.").into()),
+        }
+    }
+}
+
+/// Layout engine of graphviz; `dot` draws hierarchies, the others place the nodes freely
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Layout {
+    #[default]
+    Dot,
+    Neato,
+    Fdp,
+    Twopi,
+    Circo,
+}
+
+impl Display for Layout {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Layout::Dot => f.write_str("dot"),
+            Layout::Neato => f.write_str("neato"),
+            Layout::Fdp => f.write_str("fdp"),
+            Layout::Twopi => f.write_str("twopi"),
+            Layout::Circo => f.write_str("circo"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Layout {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "dot" => Ok(Layout::Dot),
+            "neato" => Ok(Layout::Neato),
+            "fdp" => Ok(Layout::Fdp),
+            "twopi" => Ok(Layout::Twopi),
+            "circo" => Ok(Layout::Circo),
+            _ => Err(OptionsError::new(
+                "invalid value for 'layout'. Allowed values are: dot <i>(default)</i>, neato, fdp, twopi, circo.",
+            )
+            .into()),
+        }
+    }
+}
+
+/// Graph-wide layout settings; unset values keep the graphviz defaults
+#[derive(PartialEq, Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphAttributes {
+    pub splines: Option<Splines>,
+    /// Minimum space between two adjacent nodes in the same rank, in inches
+    pub nodesep: Option<f32>,
+    /// Merge multi-edges into a single edge, sharing parts of their paths
+    pub concentrate: bool,
+    pub ordering: Option<Ordering>,
+    pub layout: Option<Layout>,
+    /// Seed of the initial node positions of the neato and fdp engines (`start`), for reproducible layouts
+    pub seed: Option<u32>,
+    /// Other graphviz graph attributes, written after all others, see `DotFile::set_graph_attr`
+    pub extra: Vec<(String, String)>,
+}
+
+impl Display for GraphAttributes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(splines) = &self.splines {
+            f.write_fmt(format_args!("    splines = {}\n", splines))?;
+        }
+        if let Some(nodesep) = &self.nodesep {
+            f.write_fmt(format_args!("    nodesep = {}\n", nodesep))?;
+        }
+        if self.concentrate {
+            f.write_str("    concentrate = true\n")?;
+        }
+        if let Some(ordering) = &self.ordering {
+            f.write_fmt(format_args!("    ordering = {}\n", ordering))?;
+        }
+        if let Some(layout) = &self.layout {
+            f.write_fmt(format_args!("    layout = {}\n", layout))?;
+        }
+        if let Some(seed) = &self.seed {
+            f.write_fmt(format_args!("    start = {}\n", seed))?;
+        }
+        for (key, value) in &self.extra {
+            f.write_fmt(format_args!("    {} = {}\n", as_id(key), as_id(value)))?;
+        }
+        Ok(())
+    }
+}
+
+/// Graphviz renders at 72 points per inch; a 100% scale corresponds to this resolution
+const BASE_DPI: f32 = 72.0;
+
+impl Options {
+    /// Resolution requested through the `dpi` or `scale` directives, where `dpi` takes precedence
+    pub fn effective_dpi(&self) -> Option<f32> {
+        match (self.dpi, self.scale) {
+            (Some(dpi), _) => Some(dpi as f32),
+            (None, Some(scale)) => Some(BASE_DPI * scale as f32 / 100.0),
+            (None, None) => None,
+        }
+    }
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DotShape {
     Record,
+    #[default]
     Circle,
     DoubleCircle,
     Diamond,
@@ -112,12 +332,9 @@ pub enum DotShape {
     Edge,
     Point,
     Rectangle,
-}
-
-impl Default for DotShape {
-    fn default() -> Self {
-        DotShape::Circle
-    }
+    /// A circle with a cross, which ends a single flow of an activity diagram
+    FlowFinal,
+    DoubleOctagon,
 }
 
 impl Display for DotShape {
@@ -131,17 +348,20 @@ impl Display for DotShape {
             DotShape::Edge => f.write_str("edge"),
             DotShape::Point => f.write_str("point"),
             DotShape::Rectangle => f.write_str("rectangle"),
+            DotShape::FlowFinal => f.write_str("circle"),
+            DotShape::DoubleOctagon => f.write_str("doubleoctagon"),
         }
     }
 }
 
-#[derive(Default)]
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Dot {
     pub shape: DotShape,
     pub height: Option<f32>,
     pub width: Option<f32>,
     pub margin: Option<String>,
-    pub label: Option<String>,
+    pub label: Option<Label>,
     pub fontsize: Option<i32>,
     pub style: Vec<Style>,
     pub fillcolor: Option<String>,
@@ -153,8 +373,16 @@ pub struct Dot {
     pub taillabel: Option<String>,
     pub headlabel: Option<String>,
     pub labeldistance: Option<u32>,
+    /// Set by the `rank` attribute; written as a subgraph instead of an attribute
+    pub rank: Option<Rank>,
+    /// Name of the cluster the node is drawn in, like the namespace of a class; written as a subgraph
+    pub cluster: Option<String>,
+    /// Additional graphviz attributes, written after all others so they take precedence
+    pub extra: Vec<(String, String)>,
 }
 
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DotElement {
     pub uid: String,
     pub uid2: Option<String>,
@@ -179,54 +407,390 @@ impl DotElement {
     }
 }
 
-impl Display for DotElement {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+impl DotElement {
+    fn fmt_with_fill(&self, f: &mut Formatter<'_>, fill: Option<&str>) -> std::fmt::Result {
         if let Some(uid2) = &self.uid2 {
-            f.write_fmt(format_args!("    {} -> {} {}", self.uid, uid2, self.dot))
+            f.write_fmt(format_args!("    {} -> {} ", self.uid, uid2))?;
         } else {
-            f.write_fmt(format_args!("    {} {}", self.uid, self.dot))
+            f.write_fmt(format_args!("    {} ", self.uid))?;
         }
+        self.dot.fmt_with_fill(f, fill)
     }
 }
 
+impl Display for DotElement {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_with_fill(f, None)
+    }
+}
+
+const DEFAULT_RANKSEP: f32 = 0.5;
+
+/// The graph of a parsed diagram.
+///
+/// The output is deterministic: nodes come first, numbered in the order in which they first appear in the yUML,
+/// followed by the edges in declaration order. Edits to the input therefore only change the affected lines.
+/// Formatting with the alternate flag, `{:#}`, writes the attribute lists in the format of earlier versions, with a
+/// ` , ` after every attribute.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DotFile {
     dots: Vec<DotElement>,
     dir: Directions,
     sep: f32,
+    theme: Theme,
+    /// The `font` and `bgcolor` directives take precedence over the theme
+    font: Option<String>,
+    bgcolor: Option<String>,
+    margin: Option<f32>,
+    title: Option<String>,
+    dpi: Option<f32>,
+    graph: GraphAttributes,
+    /// Rank constraints, written as `{ rank=... }` subgraphs after the nodes and edges
+    ranks: Vec<(Rank, Vec<String>)>,
+    /// Clusters by name, written as `subgraph cluster_...` after the rank constraints
+    clusters: Vec<(String, Vec<String>)>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    warnings: Vec<Warning>,
 }
 
 impl DotFile {
-    pub fn new(dots: Vec<DotElement>, options: &Options) -> Self {
-        DotFile {
+    pub fn new(mut dots: Vec<DotElement>, options: &Options) -> Self {
+        // stable, so the order within nodes and within edges is kept
+        dots.sort_by_key(|dot| dot.uid2.is_some());
+
+        // nodes with the same rank attribute or cluster are grouped in one subgraph
+        let mut ranks: Vec<(Rank, Vec<String>)> = vec![];
+        let mut clusters: Vec<(String, Vec<String>)> = vec![];
+        for dot in dots.iter().filter(|dot| dot.uid2.is_none()) {
+            if let Some(rank) = &dot.dot.rank {
+                group(&mut ranks, rank.clone(), &dot.uid);
+            }
+            if let Some(cluster) = &dot.dot.cluster {
+                group(&mut clusters, cluster.clone(), &dot.uid);
+            }
+        }
+
+        let dot_file = DotFile {
             dots,
             dir: options.dir,
-            sep: 0.5,
+            sep: options.ranksep.unwrap_or(DEFAULT_RANKSEP),
+            theme: match &options.env {
+                Some(env) => options.theme.clone().accent(env),
+                None => options.theme.clone(),
+            },
+            font: options.font.clone(),
+            bgcolor: options.bgcolor.clone(),
+            margin: options.margin,
+            title: options.title.clone(),
+            dpi: options.effective_dpi(),
+            graph: options.graph.clone(),
+            ranks,
+            clusters,
+            warnings: vec![],
+        };
+
+        match &options.uid_prefix {
+            Some(prefix) => dot_file.with_uid_prefix(prefix),
+            None => dot_file,
         }
     }
 
+    /// A dot file without nodes, laid out with the `options` of a diagram; see `add_node` and `add_edge`
+    pub fn new_empty(options: &Options) -> Self {
+        DotFile::new(vec![], options)
+    }
+
+    /// Add a node, like a watermark or an environment badge; `uid` is quoted when it is not a plain graphviz id.
+    /// Usage:
+    /// ```rust
+    /// use yuml_rs::{parse_yuml, Dot, DotShape};
+    ///
+    /// let mut parsed = parse_yuml("// {type:activity}\n(start)->(end)").expect("invalid yUML");
+    /// let dot_file = parsed.dot_file_mut().expect("supported chart type");
+    /// let badge = Dot {
+    ///     shape: DotShape::Note,
+    ///     label: Some("staging".into()),
+    ///     ..Dot::default()
+    /// };
+    /// dot_file.add_node("badge", badge).add_edge("badge", "A1", Dot::default());
+    /// assert!(parsed.to_string().contains("    badge -> A1 "));
+    /// ```
+    pub fn add_node(&mut self, uid: &str, dot: Dot) -> &mut Self {
+        let node = DotElement::new(&as_uid(uid), dot);
+        if let Some(rank) = &node.dot.rank {
+            group(&mut self.ranks, rank.clone(), &node.uid);
+        }
+        if let Some(cluster) = &node.dot.cluster {
+            group(&mut self.clusters, cluster.clone(), &node.uid);
+        }
+
+        // nodes come before the edges
+        let idx = self
+            .dots
+            .iter()
+            .position(|dot| dot.uid2.is_some())
+            .unwrap_or(self.dots.len());
+        self.dots.insert(idx, node);
+        self
+    }
+
+    /// Add an edge between the nodes with the uids `from` and `to`, which are quoted like the uid of `add_node`
+    pub fn add_edge(&mut self, from: &str, to: &str, dot: Dot) -> &mut Self {
+        let dot = Dot {
+            shape: DotShape::Edge,
+            ..dot
+        };
+        self.dots.push(DotElement::new_edge(&as_uid(from), &as_uid(to), dot));
+        self
+    }
+
+    /// Set a graph attribute that has no directive, like `overlap` or `pad`, replacing an earlier value of the key
+    pub fn set_graph_attr(&mut self, key: &str, value: &str) -> &mut Self {
+        let extra = &mut self.graph.extra;
+        match extra.iter_mut().find(|(existing, _)| existing == key) {
+            Some((_, existing)) => *existing = value.to_string(),
+            None => extra.push((key.to_string(), value.to_string())),
+        }
+        self
+    }
+
+    /// Prepend `prefix` to the ids of all nodes, keeping the ports of the edges
+    pub fn with_uid_prefix(mut self, prefix: &str) -> Self {
+        for dot in &mut self.dots {
+            dot.uid = prefixed_uid(prefix, &dot.uid);
+            dot.uid2 = dot.uid2.as_deref().map(|uid| prefixed_uid(prefix, uid));
+        }
+        for uids in self
+            .ranks
+            .iter_mut()
+            .map(|(_, uids)| uids)
+            .chain(self.clusters.iter_mut().map(|(_, uids)| uids))
+        {
+            for uid in uids.iter_mut() {
+                *uid = prefixed_uid(prefix, uid);
+            }
+        }
+        self
+    }
+
+    pub fn graph_attributes(&self) -> &GraphAttributes {
+        &self.graph
+    }
+
+    /// Replace the layout settings of the directives
+    pub fn with_graph_attributes(mut self, graph: GraphAttributes) -> Self {
+        self.graph = graph;
+        self
+    }
+
+    /// Place the nodes with the given uids on the same rank
+    pub fn same_rank(mut self, uids: Vec<String>) -> Self {
+        if uids.len() > 1 {
+            self.ranks.push((Rank::Same, uids));
+        }
+        self
+    }
+
+    pub fn dpi(&self) -> Option<f32> {
+        self.dpi
+    }
+
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    pub fn with_warnings(mut self, warnings: Vec<Warning>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    pub fn direction(&self) -> Directions {
+        self.dir
+    }
+
+    pub fn with_direction(mut self, dir: Directions) -> Self {
+        self.dir = dir;
+        self
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &DotElement> {
+        self.dots.iter().filter(|dot| dot.uid2.is_none())
+    }
+
+    /// The nodes, to change their attributes; the rank and cluster of a node can only be set by `add_node`
+    pub fn nodes_mut(&mut self) -> impl Iterator<Item = &mut DotElement> {
+        self.dots.iter_mut().filter(|dot| dot.uid2.is_none())
+    }
+
+    pub fn edges(&self) -> impl Iterator<Item = &DotElement> {
+        self.dots.iter().filter(|dot| dot.uid2.is_some())
+    }
+
+    /// Update the nodes in place, like the nodes of custom elements
+    pub(crate) fn map_nodes(mut self, update: impl FnMut(&mut DotElement)) -> Self {
+        self.nodes_mut().for_each(update);
+        self
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> crate::error::YumlResult<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
     pub fn sep(mut self, sep: f32) -> Self {
         self.sep = sep;
         self
     }
+
+    pub fn theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Draw the outlines and edges in the accent color of `key`, like the `env` directive, see `Theme::accent`
+    pub fn accent(mut self, key: &str) -> Self {
+        self.theme = self.theme.accent(key);
+        self
+    }
+
+    /// Replace the background of the theme and the `bgcolor` directive, like `white` for images viewed on a dark page
+    pub fn background(mut self, color: &str) -> Self {
+        self.bgcolor = Some(color.to_string());
+        self
+    }
+
+    /// The title of the `title` directive, to caption the diagram
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Space around the drawing, in inches
+    pub fn margin(mut self, margin: f32) -> Self {
+        self.margin = Some(margin);
+        self
+    }
 }
 
+/// Add the uid of a node to the subgraph of its rank or cluster
+fn group<K: PartialEq>(groups: &mut Vec<(K, Vec<String>)>, key: K, uid: &str) {
+    match groups.iter_mut().find(|(existing, _)| *existing == key) {
+        Some((_, uids)) => uids.push(uid.to_string()),
+        None => groups.push((key, vec![uid.to_string()])),
+    }
+}
+
+/// Quote the uid of a node unless it is a plain graphviz id; quoted uids are quoted again, to escape their content
+fn as_uid(uid: &str) -> String {
+    match uid.strip_prefix('"').and_then(|uid| uid.strip_suffix('"')) {
+        Some(quoted) => quote(quoted),
+        None => as_id(uid),
+    }
+}
+
+fn prefixed_uid(prefix: &str, uid: &str) -> String {
+    match uid.strip_prefix('"').and_then(|uid| uid.rsplit_once('"')) {
+        // quoted ids may contain colons, ports only follow the closing quote
+        Some((id, port)) => format!("{}{}", quote(&format!("{}{}", prefix, id)), port),
+        None => match uid.split_once(':') {
+            Some((id, port)) => format!("{}:{}", as_id(&format!("{}{}", prefix, id)), port),
+            None => as_id(&format!("{}{}", prefix, uid)),
+        },
+    }
+}
+
+/// Graphviz IDs may be unquoted when they only contain alphanumeric characters
+pub(crate) fn as_id(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        value.to_string()
+    } else {
+        quote(value)
+    }
+}
+
+/// Quote a graphviz string, which may come from an untrusted diagram. Escape sequences like `\n` and `\"` are kept,
+/// other quotes are escaped and a trailing backslash is followed by a space, so the value can not end the string.
+pub(crate) fn quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                quoted.push('\\');
+                quoted.extend(chars.next());
+            }
+            '"' => quoted.push_str("\\\""),
+            _ => quoted.push(c),
+        }
+    }
+    if quoted.ends_with('\\') {
+        quoted.push(' ');
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Titles stand out from the labels of the nodes, which are drawn at 10 points
+const TITLE_FONTSIZE: u32 = 16;
+
 impl Display for DotFile {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         f.write_str("digraph G {\n")?;
-        f.write_str("  graph [ bgcolor=transparent, fontname=Helvetica ]\n")?;
-        f.write_str("  node [ shape=none, margin=0, color=black, fontcolor=black, fontname=Helvetica ]\n")?;
-        f.write_str("  edge [ color=black, fontcolor=black, fontname=Helvetica ]\n")?;
+        let theme = &self.theme;
+        let font = as_id(self.font.as_ref().unwrap_or(&theme.font));
+        let bgcolor = as_id(self.bgcolor.as_ref().unwrap_or(&theme.background));
+        f.write_fmt(format_args!("  graph [ bgcolor={}, fontname={}", bgcolor, font))?;
+        if let Some(dpi) = self.dpi {
+            f.write_fmt(format_args!(", dpi={}", dpi))?;
+        }
+        if let Some(margin) = self.margin {
+            f.write_fmt(format_args!(", margin={}", margin))?;
+        }
+        if let Some(title) = &self.title {
+            f.write_fmt(format_args!(
+                ", label={}, labelloc=t, fontsize={}",
+                as_id(title),
+                TITLE_FONTSIZE
+            ))?;
+        }
+        f.write_str(" ]\n")?;
+        f.write_fmt(format_args!(
+            "  node [ shape=none, margin=0, color={}, fontcolor={}, fontname={} ]\n",
+            as_id(&theme.line_color),
+            as_id(&theme.font_color),
+            font
+        ))?;
+        f.write_fmt(format_args!(
+            "  edge [ color={}, fontcolor={}, fontname={} ]\n",
+            as_id(&theme.edge_color),
+            as_id(&theme.font_color),
+            font
+        ))?;
         f.write_fmt(format_args!("    ranksep = {}\n", self.sep))?;
         f.write_fmt(format_args!("    rankdir = {}\n", self.dir))?;
+        self.graph.fmt(f)?;
         for dot in &self.dots {
-            f.write_str(&dot.to_string())?;
+            dot.fmt_with_fill(f, theme.node_fill.as_deref())?;
             f.write_char('\n')?;
         }
+        for (rank, uids) in &self.ranks {
+            f.write_fmt(format_args!("    {{ rank={}; {}; }}\n", rank, uids.join("; ")))?;
+        }
+        for (idx, (cluster, uids)) in self.clusters.iter().enumerate() {
+            f.write_fmt(format_args!(
+                "    subgraph cluster_{} {{ label={}; {}; }}\n",
+                idx + 1,
+                as_id(cluster),
+                uids.join("; ")
+            ))?;
+        }
         f.write_char('}')
     }
 }
 
-#[derive(PartialEq)]
+#[allow(dead_code)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct EdgeProps {
     pub arrowtail: Option<Arrow>,
     pub arrowhead: Option<Arrow>,
@@ -235,14 +799,16 @@ pub struct EdgeProps {
     pub style: Style,
 }
 
-#[derive(PartialEq)]
+#[allow(dead_code)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct SignalProps {
     pub prefix: Option<String>,
     pub suffix: Option<String>,
     pub style: Style,
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Arrow {
     Vee,
     ODiamond,
@@ -250,18 +816,66 @@ pub enum Arrow {
     Empty,
     Filled,
     Open,
+    /// Crow's foot markers of entity-relationship diagrams
+    TeeTee,
+    TeeODot,
+    CrowTee,
+    CrowODot,
 }
 
-#[derive(PartialEq, Clone)]
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Style {
     Solid,
     Dashed,
+    Dotted,
+    Bold,
     Filled,
     Rounded,
     Invis,
     Async,
 }
 
+/// Graphviz rank constraint of a node
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Rank {
+    Same,
+    Min,
+    Max,
+    Source,
+    Sink,
+}
+
+impl Display for Rank {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Rank::Same => f.write_str("same"),
+            Rank::Min => f.write_str("min"),
+            Rank::Max => f.write_str("max"),
+            Rank::Source => f.write_str("source"),
+            Rank::Sink => f.write_str("sink"),
+        }
+    }
+}
+
+impl TryFrom<&str> for Rank {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "same" => Ok(Rank::Same),
+            "min" => Ok(Rank::Min),
+            "max" => Ok(Rank::Max),
+            "source" => Ok(Rank::Source),
+            "sink" => Ok(Rank::Sink),
+            _ => Err(
+                OptionsError::new("invalid value for 'rank'. Allowed values are: same, min, max, source, sink.").into(),
+            ),
+        }
+    }
+}
+
 impl Display for Arrow {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -271,6 +885,37 @@ impl Display for Arrow {
             Arrow::Diamond => f.write_str("diamond"),
             Arrow::Filled => f.write_str("arrow-filled"),
             Arrow::Open => f.write_str("arrow-open"),
+            Arrow::TeeTee => f.write_str("teetee"),
+            Arrow::TeeODot => f.write_str("teeodot"),
+            Arrow::CrowTee => f.write_str("crowtee"),
+            Arrow::CrowODot => f.write_str("crowodot"),
+        }
+    }
+}
+
+impl Style {
+    /// Styles of the outline of a node or the line of an edge, of which only one applies
+    pub(crate) fn is_line(&self) -> bool {
+        matches!(self, Style::Solid | Style::Dashed | Style::Dotted)
+    }
+}
+
+impl TryFrom<&str> for Style {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "solid" => Ok(Style::Solid),
+            "dashed" => Ok(Style::Dashed),
+            "dotted" => Ok(Style::Dotted),
+            "bold" => Ok(Style::Bold),
+            "filled" => Ok(Style::Filled),
+            "rounded" => Ok(Style::Rounded),
+            "invis" => Ok(Style::Invis),
+            _ => Err(OptionsError::new(
+                "invalid value for 'style'. Allowed values are: solid, dashed, dotted, bold, filled, rounded, invis.",
+            )
+            .into()),
         }
     }
 }
@@ -280,6 +925,8 @@ impl Display for Style {
         match self {
             Style::Solid => f.write_str("solid"),
             Style::Dashed => f.write_str("dashed"),
+            Style::Dotted => f.write_str("dotted"),
+            Style::Bold => f.write_str("bold"),
             Style::Filled => f.write_str("filled"),
             Style::Rounded => f.write_str("rounded"),
             Style::Invis => f.write_str("invis"),
@@ -290,90 +937,162 @@ impl Display for Style {
 
 impl Display for Dot {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str("[")?;
+        self.fmt_with_fill(f, None)
+    }
+}
+
+impl Dot {
+    /// Shapes which receive the fill color of the theme
+    fn is_fillable(&self) -> bool {
+        matches!(self.shape, DotShape::Rectangle | DotShape::Diamond | DotShape::Note)
+    }
+
+    /// Write the attributes, using `fill` for fillable nodes without a color of their own
+    fn fmt_with_fill(&self, f: &mut Formatter<'_>, fill: Option<&str>) -> std::fmt::Result {
+        self.attribute_list(f.alternate(), fill).fmt(f)
+    }
+
+    /// The graphviz attributes as written in the dot file, without the quotes and escapes around their values
+    pub fn attributes(&self) -> Vec<(String, String)> {
+        self.attribute_list(false, None)
+            .entries
+            .into_iter()
+            .map(|(key, value, _)| (key.to_string(), value))
+            .collect()
+    }
+
+    fn attribute_list(&self, compat: bool, fill: Option<&str>) -> Attributes<'_> {
+        let theme_fill = fill.filter(|_| self.fillcolor.is_none() && self.is_fillable());
+        let fillcolor = self.fillcolor.as_deref().or(theme_fill);
+        let mut style = self.style.iter().map(Style::to_string).collect::<Vec<_>>();
+        if theme_fill.is_some() && !self.style.contains(&Style::Filled) {
+            style.push(Style::Filled.to_string());
+        }
+
+        let mut attrs = Attributes::new(compat);
 
         // strings
-        f.write_fmt(format_args!(r#"shape="{}" , "#, self.shape))?;
+        attrs.quoted("shape", self.shape);
         if let Some(margin) = &self.margin {
-            f.write_fmt(format_args!(r#"margin="{}" , "#, margin))?;
+            attrs.quoted("margin", margin);
         }
 
         match &self.label {
-            Some(lbl) => {
-                if lbl.starts_with("<<") {
-                    f.write_fmt(format_args!(
-                        r#"label={} , "#,
-                        self.label.as_deref().unwrap_or_default()
-                    ))?
-                } else {
-                    f.write_fmt(format_args!(
-                        r#"label="{}" , "#,
-                        self.label.as_deref().unwrap_or_default()
-                    ))?
-                }
-            }
-            None => f.write_fmt(format_args!(r#"label="" , "#,))?,
+            // record labels use <port> markers, but are never HTML-like
+            Some(Label::Html(html)) if self.shape != DotShape::Record => attrs.raw("label", html.as_str()),
+            Some(lbl) if !lbl.is_empty() => attrs.quoted("label", lbl.as_str()),
+            // nodes without a label would show their id instead
+            _ if self.shape != DotShape::Edge => attrs.quoted("label", ""),
+            _ => attrs.quoted_unless_empty("label", ""),
         }
 
-        f.write_fmt(format_args!(
-            r#"style="{}" , "#,
-            self.style.iter().map(Style::to_string).join(",")
-        ))?;
+        attrs.quoted_unless_empty("style", &style.join(","));
 
-        if let Some(fillcolor) = &self.fillcolor {
-            f.write_fmt(format_args!(r#"fillcolor="{}" , "#, fillcolor))?;
+        if let Some(fillcolor) = fillcolor {
+            attrs.quoted("fillcolor", fillcolor);
         }
         if let Some(fontcolor) = &self.fontcolor {
-            f.write_fmt(format_args!(r#"fontcolor="{}" , "#, fontcolor))?;
+            attrs.quoted("fontcolor", fontcolor);
         }
 
         if let Some(dir) = &self.dir {
-            f.write_fmt(format_args!(r#"dir="{}" , "#, dir))?;
+            attrs.quoted("dir", dir);
         }
 
-        if let Some(arrowtail) = &self.arrowtail {
-            f.write_fmt(format_args!(r#"arrowtail="{}" , "#, arrowtail))?;
-        } else {
-            f.write_fmt(format_args!(r#"arrowtail="none" , "#))?;
+        match &self.arrowtail {
+            Some(arrowtail) => attrs.quoted("arrowtail", arrowtail),
+            None => attrs.quoted("arrowtail", "none"),
         }
-
-        if let Some(arrowhead) = &self.arrowhead {
-            f.write_fmt(format_args!(r#"arrowhead="{}" , "#, arrowhead))?;
-        } else {
-            f.write_fmt(format_args!(r#"arrowhead="none" , "#))?;
+        match &self.arrowhead {
+            Some(arrowhead) => attrs.quoted("arrowhead", arrowhead),
+            None => attrs.quoted("arrowhead", "none"),
         }
 
         if let Some(taillabel) = &self.taillabel {
-            f.write_fmt(format_args!(r#"taillabel="{}" , "#, taillabel))?;
+            attrs.quoted("taillabel", taillabel);
         }
         if let Some(headlabel) = &self.headlabel {
-            f.write_fmt(format_args!(r#"headlabel="{}" , "#, headlabel))?;
+            attrs.quoted("headlabel", headlabel);
         }
 
         // non-strings
         if let Some(labeldistance) = &self.labeldistance {
-            f.write_fmt(format_args!("labeldistance={} , ", labeldistance))?;
+            attrs.raw("labeldistance", labeldistance);
         }
-
         if let Some(height) = &self.height {
-            f.write_fmt(format_args!("height={} , ", height))?;
+            attrs.raw("height", height);
         }
-
         if let Some(width) = &self.width {
-            f.write_fmt(format_args!("width={} , ", width))?;
+            attrs.raw("width", width);
         }
         if let Some(fontsize) = &self.fontsize {
-            f.write_fmt(format_args!("fontsize={} , ", fontsize))?;
+            attrs.raw("fontsize", fontsize);
         }
         if let Some(penwidth) = &self.penwidth {
-            f.write_fmt(format_args!("penwidth={} , ", penwidth))?;
+            attrs.raw("penwidth", penwidth);
         }
 
-        f.write_str("]")
+        for (key, value) in &self.extra {
+            attrs.quoted(key, value);
+        }
+
+        attrs
     }
 }
 
-#[derive(Clone)]
+/// The attribute list of a node or edge, like `[shape="circle", label=""]`.
+///
+/// In compat mode every attribute is followed by ` , ` and empty styles and edge labels are kept, as in the output of
+/// earlier versions.
+struct Attributes<'a> {
+    compat: bool,
+    /// The keys and values, and whether the value is quoted
+    entries: Vec<(&'a str, String, bool)>,
+}
+
+impl<'a> Attributes<'a> {
+    fn new(compat: bool) -> Self {
+        Attributes {
+            compat,
+            entries: vec![],
+        }
+    }
+
+    fn raw(&mut self, key: &'a str, value: impl Display) {
+        self.entries.push((key, value.to_string(), false));
+    }
+
+    fn quoted(&mut self, key: &'a str, value: impl Display) {
+        self.entries.push((key, value.to_string(), true));
+    }
+
+    fn quoted_unless_empty(&mut self, key: &'a str, value: &str) {
+        if self.compat || !value.is_empty() {
+            self.quoted(key, value);
+        }
+    }
+
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_char('[')?;
+        for (idx, (key, value, quoted)) in self.entries.iter().enumerate() {
+            // the keys of extra attributes are not checked against an allowlist when set through the API
+            let key = as_id(key);
+            let value = match quoted {
+                true => quote(value),
+                false => value.to_string(),
+            };
+            match (self.compat, idx) {
+                (true, _) => f.write_fmt(format_args!("{}={} , ", key, value))?,
+                (false, 0) => f.write_fmt(format_args!("{}={}", key, value))?,
+                (false, _) => f.write_fmt(format_args!(", {}={}", key, value))?,
+            }
+        }
+        f.write_char(']')
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Actor {
     pub actor_type: String,
     pub name: String,
@@ -392,7 +1111,7 @@ mod tests {
             height: Some(1.0),
             width: Some(2.0),
             margin: Some("m".to_string()),
-            label: Some("l".to_string()),
+            label: Some(Label::from("l")),
             fontsize: Some(3),
             style: vec![Style::Solid],
             fillcolor: None,
@@ -404,12 +1123,237 @@ mod tests {
             taillabel: None,
             headlabel: None,
             labeldistance: None,
+            rank: Some(Rank::Min),
+            cluster: None,
+            extra: vec![("peripheries".to_string(), "2".to_string())],
         }
         .to_string();
 
         assert_eq!(
             node,
-            r#"[shape="note" , margin="m" , label="l" , style="solid" , fontcolor="fc" , arrowtail="none" , arrowhead="none" , height=1 , width=2 , fontsize=3 , ]"#
+            r#"[shape="note", margin="m", label="l", style="solid", fontcolor="fc", arrowtail="none", arrowhead="none", height=1, width=2, fontsize=3, peripheries="2"]"#
+        );
+    }
+
+    #[test]
+    fn test_display_empty_attributes() {
+        let edge = Dot {
+            shape: DotShape::Edge,
+            ..Dot::default()
+        };
+        assert_eq!(
+            edge.to_string(),
+            r#"[shape="edge", arrowtail="none", arrowhead="none"]"#
+        );
+        assert_eq!(
+            format!("{:#}", edge),
+            r#"[shape="edge" , label="" , style="" , arrowtail="none" , arrowhead="none" , ]"#
+        );
+
+        // an empty label keeps graphviz from showing the id of the node
+        assert_eq!(
+            Dot::default().to_string(),
+            r#"[shape="circle", label="", arrowtail="none", arrowhead="none"]"#
+        );
+    }
+
+    #[test]
+    fn test_quote() {
+        assert_eq!(quote(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(quote(r#"a\"b\nc"#), r#""a\"b\nc""#);
+        assert_eq!(quote(r"C:\"), r#""C:\ ""#);
+        assert_eq!(quote(r"C:\\"), r#""C:\\ ""#);
+
+        let dot = Dot {
+            label: Some(Label::from("<a> ] ; node [color=red ; <b>")),
+            fillcolor: Some(r#"red"] ; x ["#.to_string()),
+            extra: vec![("color] ; x [".to_string(), "red".to_string())],
+            ..Dot::default()
+        };
+        assert_eq!(
+            dot.to_string(),
+            r#"[shape="circle", label="<a> ] ; node [color=red ; <b>", fillcolor="red\"] ; x [", arrowtail="none", arrowhead="none", "color] ; x ["="red"]"#
         );
+
+        let mut df = DotFile::new_empty(&Options::default());
+        df.add_node(r#""a" ] ; x [ "b""#, Dot::default());
+        assert_eq!(
+            df.nodes().next().map(|node| node.uid.as_str()),
+            Some(r#""a\" ] ; x [ \"b""#)
+        );
+    }
+
+    #[test]
+    fn test_add_nodes() {
+        let node = |rank| Dot { rank, ..Dot::default() };
+        let mut df = DotFile::new_empty(&Options::default());
+        df.add_node("A1", node(None))
+            .add_edge("A1", "build badge", Dot::default())
+            .add_node("build badge", node(Some(Rank::Max)))
+            .set_graph_attr("overlap", "false")
+            .set_graph_attr("pad", "0.2")
+            .set_graph_attr("overlap", "scale");
+
+        let uids: Vec<&str> = df.dots.iter().map(|dot| dot.uid.as_str()).collect();
+        assert_eq!(uids, vec!["A1", "\"build badge\"", "A1"]);
+        assert_eq!(
+            df.edges().next().and_then(|edge| edge.uid2.as_deref()),
+            Some("\"build badge\"")
+        );
+        assert_eq!(df.edges().next().map(|edge| edge.dot.shape), Some(DotShape::Edge));
+
+        let result = df.to_string();
+        assert!(result.contains("    overlap = scale\n    pad = \"0.2\"\n"));
+        assert!(result.contains("    { rank=max; \"build badge\"; }\n"));
+    }
+
+    #[test]
+    fn test_nodes_before_edges() {
+        let dots = vec![
+            DotElement::new_edge("A1", "A2", Dot::default()),
+            DotElement::new("A1", Dot::default()),
+            DotElement::new_edge("A2", "A1", Dot::default()),
+            DotElement::new("A2", Dot::default()),
+        ];
+        let dot_file = DotFile::new(dots, &Options::default());
+
+        let order: Vec<(&str, Option<&str>)> = dot_file
+            .dots
+            .iter()
+            .map(|dot| (dot.uid.as_str(), dot.uid2.as_deref()))
+            .collect();
+        assert_eq!(
+            order,
+            vec![("A1", None), ("A2", None), ("A1", Some("A2")), ("A2", Some("A1"))]
+        );
+    }
+
+    fn dot_file(dot: Dot) -> DotFile {
+        DotFile::new(vec![DotElement::new("A1", dot)], &Options::default())
+    }
+
+    #[test]
+    fn test_equal_dots_serialize_equally() {
+        use crate::parse_yuml;
+
+        for yuml in [
+            include_str!("../../test/activity.yuml"),
+            include_str!("../../test/activity_2.yuml"),
+            include_str!("../../test/activity_w_note.yuml"),
+            include_str!("../../test/big_activity.yuml"),
+            include_str!("../../test/class.yuml"),
+            "// {type:er}\n[customer|id PK;name]1-places-*[order]",
+            "// {type:timeline}\n[Q1|Design]->[Q2|Build]",
+            "// {type:mindmap}\nRoadmap\n  Design\n  Build",
+        ] {
+            let parsed = parse_yuml(yuml).expect("invalid file");
+            let again = parse_yuml(yuml).expect("invalid file");
+            assert_eq!(parsed, again);
+            assert_eq!(parsed.clone(), parsed);
+            assert_eq!(parsed.to_string(), again.to_string());
+        }
+
+        // line endings and trailing whitespace do not end up in the model
+        assert_eq!(
+            parse_yuml(include_str!("../../test/activity_crlf.yuml")).unwrap(),
+            parse_yuml(include_str!("../../test/activity.yuml")).unwrap()
+        );
+        assert_eq!(
+            parse_yuml(include_str!("../../test/class_crlf.yuml")).unwrap(),
+            parse_yuml(include_str!("../../test/class.yuml")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_different_dots_serialize_differently() {
+        let base = Dot {
+            label: Some(Label::from("A")),
+            ..Dot::default()
+        };
+        let changes: Vec<fn(&mut Dot)> = vec![
+            |dot| dot.shape = DotShape::Record,
+            |dot| dot.height = Some(1.5),
+            |dot| dot.width = Some(1.5),
+            |dot| dot.margin = Some("0.1".to_string()),
+            |dot| dot.label = Some(Label::from("B")),
+            |dot| dot.fontsize = Some(12),
+            |dot| dot.style = vec![Style::Dashed],
+            |dot| dot.fillcolor = Some("red".to_string()),
+            |dot| dot.fontcolor = Some("red".to_string()),
+            |dot| dot.penwidth = Some(2),
+            |dot| dot.dir = Some("both".to_string()),
+            |dot| dot.arrowtail = Some(Arrow::Vee),
+            |dot| dot.arrowhead = Some(Arrow::Diamond),
+            |dot| dot.taillabel = Some("1".to_string()),
+            |dot| dot.headlabel = Some("*".to_string()),
+            |dot| dot.labeldistance = Some(2),
+            |dot| dot.rank = Some(Rank::Source),
+            |dot| dot.cluster = Some("billing".to_string()),
+            |dot| dot.extra = vec![("peripheries".to_string(), "2".to_string())],
+        ];
+
+        for (idx, change) in changes.iter().enumerate() {
+            let mut dot = base.clone();
+            change(&mut dot);
+            assert_ne!(dot, base, "change {}", idx);
+            assert_ne!(
+                dot_file(dot).to_string(),
+                dot_file(base.clone()).to_string(),
+                "change {}",
+                idx
+            );
+        }
+        assert_eq!(dot_file(base.clone()), dot_file(base));
+    }
+
+    #[test]
+    fn test_different_options_serialize_differently() {
+        let dots = || vec![DotElement::new("A1", Dot::default())];
+        let base = DotFile::new(dots(), &Options::default());
+        let changes: Vec<fn(&mut Options)> = vec![
+            |options| options.dir = Directions::LeftToRight,
+            |options| options.theme = Theme::try_from("dark").unwrap(),
+            |options| options.font = Some("Menlo".to_string()),
+            |options| options.ranksep = Some(1.0),
+            |options| options.bgcolor = Some("white".to_string()),
+            |options| options.margin = Some(0.5),
+            |options| options.title = Some("Title".to_string()),
+            |options| options.scale = Some(200),
+            |options| options.dpi = Some(300),
+            |options| options.graph.splines = Some(Splines::Ortho),
+            |options| options.uid_prefix = Some("d1_".to_string()),
+        ];
+
+        for (idx, change) in changes.iter().enumerate() {
+            let mut options = Options::default();
+            change(&mut options);
+            let dot_file = DotFile::new(dots(), &options);
+            assert_ne!(dot_file, base, "change {}", idx);
+            assert_ne!(dot_file.to_string(), base.to_string(), "change {}", idx);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let options = Options {
+            font: Some("Menlo".to_string()),
+            ..Options::default()
+        };
+        let dots = vec![DotElement::new(
+            "A1",
+            Dot {
+                shape: DotShape::Rectangle,
+                label: Some(Label::from("Hello")),
+                style: vec![Style::Rounded],
+                ..Dot::default()
+            },
+        )];
+        let dot_file = DotFile::new(dots, &options);
+
+        let json = dot_file.to_json().expect("can not serialize");
+        let restored: DotFile = serde_json::from_str(&json).expect("can not deserialize");
+        assert_eq!(restored, dot_file);
+        assert_eq!(restored.to_string(), dot_file.to_string());
     }
 }
@@ -0,0 +1,137 @@
+use super::{
+    attributes::split_attributes,
+    dot::{Arrow, Dot, DotElement, DotShape, Style},
+    label::{as_dot_label, as_html_table, Label},
+    shared::{ElementDetails, LabeledElement},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Element<'a> {
+    /// The text of an entity, like `customer|id;name{bg:wheat}`
+    Entity(&'a str),
+    Relationship(RelationshipProps<'a>),
+}
+
+/// How many entities take part at one end of a relationship
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Cardinality {
+    One,
+    ZeroOrOne,
+    OneOrMany,
+    ZeroOrMany,
+}
+
+impl TryFrom<&str> for Cardinality {
+    type Error = ();
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "1" => Ok(Cardinality::One),
+            "0..1" | "?" => Ok(Cardinality::ZeroOrOne),
+            "1..*" | "+" => Ok(Cardinality::OneOrMany),
+            "0..*" | "*" => Ok(Cardinality::ZeroOrMany),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Cardinality {
+    /// Crow's foot marker; graphviz draws the first shape next to the node
+    fn as_arrow(&self) -> Arrow {
+        match self {
+            Cardinality::One => Arrow::TeeTee,
+            Cardinality::ZeroOrOne => Arrow::TeeODot,
+            Cardinality::OneOrMany => Arrow::CrowTee,
+            Cardinality::ZeroOrMany => Arrow::CrowODot,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RelationshipProps<'a> {
+    pub left: Option<Cardinality>,
+    pub right: Option<Cardinality>,
+    pub label: Option<&'a str>,
+}
+
+impl<'a> LabeledElement for Element<'a> {
+    fn label(&self) -> &'a str {
+        match self {
+            Element::Entity(text) => {
+                let (label, _) = split_attributes(text);
+                label.split('|').next().unwrap_or(label)
+            }
+            Element::Relationship(props) => props.label.unwrap_or_default(),
+        }
+    }
+
+    fn attributes(&self) -> Option<&'a str> {
+        match self {
+            Element::Entity(text) => split_attributes(text).1,
+            Element::Relationship(_) => None,
+        }
+    }
+
+    fn is_connection(&self) -> bool {
+        matches!(self, Element::Relationship(_))
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Element::Entity(_) => "entity",
+            Element::Relationship(_) => "relationship",
+        }
+    }
+}
+
+impl<'a> From<&ElementDetails<'a, Element<'a>>> for DotElement {
+    fn from(e: &ElementDetails<'a, Element<'a>>) -> Self {
+        match (e.element, &e.relation) {
+            (Element::Relationship(_), Some(relation)) => DotElement::new_edge(
+                &format!("A{}", relation.previous_id),
+                &format!("A{}", relation.next_id),
+                Dot::from(e.element),
+            ),
+            _ => DotElement::new(&format!("A{}", e.id.unwrap_or_default()), Dot::from(e.element)),
+        }
+    }
+}
+
+impl<'a> From<&Element<'a>> for Dot {
+    fn from(e: &Element<'a>) -> Self {
+        match e {
+            Element::Entity(text) => {
+                // an entity is a table with its name above the attributes
+                let (label, attributes) = split_attributes(text);
+                let (label, margin) = if label.contains('|') {
+                    (as_html_table(label.split('|')), None)
+                } else {
+                    (as_dot_label(label), Some("0.20,0.05".to_string()))
+                };
+
+                Dot {
+                    shape: DotShape::Rectangle,
+                    height: Some(0.5),
+                    margin,
+                    label: Some(label),
+                    fontsize: Some(10),
+                    ..Dot::default()
+                }
+                .with_attributes(attributes)
+            }
+            Element::Relationship(props) => Dot {
+                shape: DotShape::Edge,
+                style: vec![Style::Solid],
+                dir: Some("both".to_string()),
+                arrowtail: props.left.as_ref().map(Cardinality::as_arrow),
+                arrowhead: props.right.as_ref().map(Cardinality::as_arrow),
+                fontsize: Some(10),
+                label: props.label.map(Label::from),
+                ..Dot::default()
+            },
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use super::dot::Directions;
+use std::collections::HashMap;
+
+/*
+Facets of parallel bars
+Record          <f1>|<f2>|<f3>             one facet for each flow going into the bar
+Ports           A3:f2:n (topDown), A3:f2:w (leftToRight), A3:f2:e (rightToLeft)
+*/
+
+/// Hands out the facets of parallel bars, so every flow joining a bar enters it at its own point
+#[derive(Debug, Default)]
+pub struct FacetAllocator {
+    facets: HashMap<usize, usize>,
+}
+
+impl FacetAllocator {
+    /// The next facet of the node with the given id, numbered from 1
+    pub fn allocate(&mut self, node_id: usize) -> usize {
+        let facets = self.facets.entry(node_id).or_default();
+        *facets += 1;
+        *facets
+    }
+
+    /// The number of facets allocated for the node with the given id
+    pub fn facets(&self, node_id: usize) -> usize {
+        self.facets.get(&node_id).copied().unwrap_or_default()
+    }
+
+    /// Label of the record shape of the bar, with a field for each of its facets
+    pub fn record_label(&self, node_id: usize) -> String {
+        (1..=self.facets(node_id))
+            .map(|facet| format!("<f{}>", facet))
+            .collect::<Vec<_>>()
+            .join("|")
+    }
+
+    /// The port of a facet, entering the node from the side the flow of the chart comes from
+    pub fn port(node_uid: &str, facet: usize, dir: &Directions) -> String {
+        format!("{}:f{}:{}", node_uid, facet, dir.head_port())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_facet_allocator() {
+        let mut facets = FacetAllocator::default();
+        for _ in 0..12 {
+            facets.allocate(3);
+        }
+        assert_eq!(facets.allocate(5), 1);
+        assert_eq!(facets.facets(3), 12);
+        assert_eq!(facets.facets(4), 0);
+        assert!(facets.record_label(3).ends_with("|<f9>|<f10>|<f11>|<f12>"));
+        assert_eq!(facets.record_label(4), "");
+
+        assert_eq!(FacetAllocator::port("A3", 10, &Directions::TopDown), "A3:f10:n");
+        assert_eq!(FacetAllocator::port("A3", 2, &Directions::LeftToRight), "A3:f2:w");
+    }
+}
@@ -0,0 +1,604 @@
+use std::collections::HashMap;
+
+/*
+Rich-text markup in node labels
+Line break      \n or ;
+Bold            **important**
+Italic          __emphasis__
+Entities        &rarr; &#8594;
+Escapes         \( \) \; \*
+*/
+
+/// The label of a node or an edge
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Label {
+    /// Text, which is quoted when written; escape sequences of graphviz, like `\n`, are kept
+    Text(String),
+    /// An HTML-like label, which is written as-is
+    Html(HtmlLabel),
+}
+
+impl Label {
+    /// The text, or the markup of an HTML-like label
+    pub fn as_str(&self) -> &str {
+        match self {
+            Label::Text(text) => text,
+            Label::Html(html) => html.as_str(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.as_str().is_empty()
+    }
+}
+
+impl From<String> for Label {
+    fn from(text: String) -> Self {
+        Label::Text(text)
+    }
+}
+
+impl From<&str> for Label {
+    fn from(text: &str) -> Self {
+        Label::Text(text.to_string())
+    }
+}
+
+impl PartialEq<&str> for Label {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// The markup of an HTML-like label, like `<<B>Customer</B>>`. It is built from escaped text by `as_dot_label` and
+/// `HtmlLabelBuilder`; markup read back from a dot file or JSON is only accepted with the tags these write, so a label
+/// can not reference files, like `<IMG SRC="...">`, nor end early.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct HtmlLabel(String);
+
+impl HtmlLabel {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The markup, if it only has the tags of the labels of yUML diagrams
+    pub(crate) fn parse(markup: &str) -> Option<HtmlLabel> {
+        is_label_markup(markup).then(|| HtmlLabel(markup.to_string()))
+    }
+}
+
+impl TryFrom<String> for HtmlLabel {
+    type Error = &'static str;
+
+    fn try_from(markup: String) -> Result<Self, Self::Error> {
+        HtmlLabel::parse(&markup).ok_or("unsupported markup in HTML-like label")
+    }
+}
+
+impl From<HtmlLabel> for String {
+    fn from(html: HtmlLabel) -> Self {
+        html.0
+    }
+}
+
+/// Tags of the labels built by `as_dot_label` and `HtmlLabelBuilder`, and the attributes of their tables and cells
+const LABEL_TAGS: &[&str] = &["B", "/B", "I", "/I", "BR/", "TABLE", "/TABLE", "TR", "/TR", "TD", "/TD"];
+const LABEL_TAG_ATTRIBUTES: &[&str] = &[
+    "BORDER",
+    "CELLBORDER",
+    "CELLSPACING",
+    "CELLPADDING",
+    "ALIGN",
+    "BALIGN",
+    "BGCOLOR",
+];
+
+fn is_label_markup(markup: &str) -> bool {
+    let inner = match markup.strip_prefix('<').and_then(|markup| markup.strip_suffix('>')) {
+        Some(inner) => inner,
+        None => return false,
+    };
+
+    let mut rest = inner;
+    loop {
+        match rest.find(['<', '>']) {
+            None => return true,
+            Some(idx) if rest[idx..].starts_with('>') => return false,
+            Some(idx) => match rest[idx + 1..].split_once('>') {
+                Some((tag, after)) if is_label_tag(tag) => rest = after,
+                _ => return false,
+            },
+        }
+    }
+}
+
+/// A tag like `TD ALIGN="LEFT"`, without the angle brackets
+fn is_label_tag(tag: &str) -> bool {
+    let (name, mut attributes) = tag.split_once(' ').unwrap_or((tag, ""));
+    if !LABEL_TAGS.contains(&name) {
+        return false;
+    }
+    if !attributes.is_empty() && !matches!(name, "TABLE" | "TD") {
+        return false;
+    }
+
+    while !attributes.is_empty() {
+        let parsed = attributes.split_once("=\"").and_then(|(key, rest)| {
+            let (value, rest) = rest.split_once('"')?;
+            let valid = LABEL_TAG_ATTRIBUTES.contains(&key) && !value.contains('<');
+            valid.then(|| rest.strip_prefix(' ').unwrap_or(rest))
+        });
+        match parsed {
+            Some(rest) => attributes = rest,
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Translate the rich-text markup of a yUML label into a graphviz label.
+/// Labels without markup are returned unchanged, others become an HTML-like label: `<...>`.
+pub fn as_dot_label(text: &str) -> Label {
+    if is_rich(text) {
+        Label::Html(HtmlLabel(format!("<{}>", as_html(text))))
+    } else {
+        Label::Text(text.to_string())
+    }
+}
+
+/// Horizontal alignment of the text of a table row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellAlign {
+    Left,
+    Center,
+    Right,
+}
+
+impl CellAlign {
+    fn as_str(&self) -> &'static str {
+        match self {
+            CellAlign::Left => "LEFT",
+            CellAlign::Center => "CENTER",
+            CellAlign::Right => "RIGHT",
+        }
+    }
+}
+
+/// Build a graphviz HTML-like table with one row per section, like the boxes of classes and entities.
+/// The text of the rows uses the rich-text markup of yUML labels.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{CellAlign, HtmlLabelBuilder};
+///
+/// let label = HtmlLabelBuilder::new()
+///     .padding(4)
+///     .bold_header(true)
+///     .row("Customer")
+///     .aligned_row("name;email", CellAlign::Left)
+///     .build();
+/// assert!(label.as_str().contains("<TR><TD><B>Customer</B></TD></TR>"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HtmlLabelBuilder {
+    rows: Vec<(String, Option<CellAlign>)>,
+    padding: u32,
+    bold_header: bool,
+    header_color: Option<String>,
+}
+
+impl Default for HtmlLabelBuilder {
+    fn default() -> Self {
+        HtmlLabelBuilder {
+            rows: vec![],
+            padding: 9,
+            bold_header: false,
+            header_color: None,
+        }
+    }
+}
+
+impl HtmlLabelBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Space between the text and the border of the cells, in points
+    pub fn padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    /// Write the text of the first row in bold
+    pub fn bold_header(mut self, bold: bool) -> Self {
+        self.bold_header = bold;
+        self
+    }
+
+    /// Fill the first row with a color
+    pub fn header_color(mut self, color: &str) -> Self {
+        self.header_color = Some(color.to_string());
+        self
+    }
+
+    pub fn row(mut self, text: &str) -> Self {
+        self.rows.push((text.to_string(), None));
+        self
+    }
+
+    /// A row of which all lines are aligned, instead of centered
+    pub fn aligned_row(mut self, text: &str, align: CellAlign) -> Self {
+        self.rows.push((text.to_string(), Some(align)));
+        self
+    }
+
+    pub fn rows<'a>(self, sections: impl IntoIterator<Item = &'a str>) -> Self {
+        sections.into_iter().fold(self, HtmlLabelBuilder::row)
+    }
+
+    pub fn build(&self) -> Label {
+        let rows: String = self
+            .rows
+            .iter()
+            .enumerate()
+            .map(|(idx, (text, align))| {
+                let is_header = idx == 0;
+                let mut cell = String::from("<TD");
+                if let Some(align) = align {
+                    // `BALIGN` aligns the lines after a line break
+                    cell.push_str(&format!(" ALIGN=\"{0}\" BALIGN=\"{0}\"", align.as_str()));
+                }
+                if let Some(color) = self.header_color.as_ref().filter(|_| is_header) {
+                    cell.push_str(&format!(" BGCOLOR=\"{}\"", escape_attribute(color)));
+                }
+                let text = match self.bold_header && is_header {
+                    true => format!("<B>{}</B>", as_html(text)),
+                    false => as_html(text),
+                };
+                format!("<TR>{}>{}</TD></TR>", cell, text)
+            })
+            .collect();
+
+        Label::Html(HtmlLabel(format!(
+            "<<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\" CELLPADDING=\"{}\">{}</TABLE>>",
+            self.padding, rows
+        )))
+    }
+}
+
+/// Graphviz HTML-like table with one row per section, as used by class boxes
+pub fn as_html_table<'a>(sections: impl IntoIterator<Item = &'a str>) -> Label {
+    HtmlLabelBuilder::new().rows(sections).build()
+}
+
+/// Recover the text of a graphviz label, with one entry per section of a class box.
+/// This reverses `as_dot_label` and `as_html_table`, dropping the markup.
+pub fn as_text_sections(label: &Label) -> Vec<String> {
+    let label = match label {
+        Label::Text(text) => return vec![unescape(text)],
+        Label::Html(html) => &html.0,
+    };
+
+    let html = &label[1..label.len() - 1];
+    if html.starts_with("<TABLE") {
+        // cells may have attributes, like `<TD ALIGN="LEFT">`
+        html.split("<TR><TD")
+            .skip(1)
+            .map(|row| {
+                let cell = row.split_once('>').map_or("", |(_, cell)| cell);
+                as_text(cell.split("</TD></TR>").next().unwrap_or_default())
+            })
+            .collect()
+    } else {
+        vec![as_text(html)]
+    }
+}
+
+fn as_text(html: &str) -> String {
+    html.replace("<BR/>", "\n")
+        .replace("<B>", "")
+        .replace("</B>", "")
+        .replace("<I>", "")
+        .replace("</I>", "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            unescaped.push(c);
+            escaped = false;
+        }
+    }
+    unescaped
+}
+
+/// Decode the XML entities of the text, like `&lt;` and `&#65;`; unknown entities are kept
+pub fn decode_entities(text: &str) -> String {
+    let entities: HashMap<&str, &str> = [("lt", "<"), ("gt", ">"), ("amp", "&"), ("quot", "\""), ("apos", "'")]
+        .into_iter()
+        .collect();
+
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let replacement = entity.and_then(|(name, end)| {
+            let value = match name.strip_prefix('#') {
+                Some(code) => match code.strip_prefix('x') {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => code.parse().ok(),
+                }
+                .and_then(char::from_u32)
+                .map(String::from),
+                None => entities.get(name).map(|value| value.to_string()),
+            };
+            value.map(|value| (value, end))
+        });
+        match replacement {
+            Some((value, end)) => {
+                decoded.push_str(&value);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn is_rich(text: &str) -> bool {
+    // text like `<T>` is escaped inside an HTML-like label
+    (text.starts_with('<') && text.ends_with('>'))
+        || text.contains("\\n")
+        || text.contains(';')
+        || !emphasis_markers(text).is_empty()
+        || (0..text.len()).any(|idx| entity_at(text, idx).is_some())
+}
+
+/// `true` for the characters around an emphasis marker which delimit it, like in Markdown; `None` is the start or the
+/// end of the label. Markers of the other kind delimit as well, so they can be nested: `**__both__**`.
+fn is_delimiter(c: Option<char>) -> bool {
+    match c {
+        Some(c) => c.is_whitespace() || matches!(c, ';' | '*' | '_'),
+        None => true,
+    }
+}
+
+/// The positions of the `**` and `__` markers which open or close emphasis. Markers are only markup when they are
+/// delimited on the outside and touch text on the inside, and have a matching marker; others, like in `__init__()`,
+/// `**kwargs` or `snake__case`, are text.
+fn emphasis_markers(text: &str) -> Vec<usize> {
+    let mut markers = vec![];
+    let mut open: [Option<usize>; 2] = [None, None];
+    let mut idx = 0;
+
+    while let Some(c) = text[idx..].chars().next() {
+        let rest = &text[idx..];
+        if let Some(escaped) = rest.strip_prefix('\\').and_then(|r| r.chars().next()) {
+            idx += 1 + escaped.len_utf8();
+            continue;
+        }
+
+        let kind = match rest {
+            _ if rest.starts_with("**") => 0,
+            _ if rest.starts_with("__") => 1,
+            _ => {
+                idx += c.len_utf8();
+                continue;
+            }
+        };
+
+        let before = text[..idx].chars().next_back();
+        let after = text[idx + 2..].chars().next();
+        let opens = is_delimiter(before) && after.is_some_and(|c| !c.is_whitespace());
+        let closes = before.is_some_and(|c| !c.is_whitespace()) && is_delimiter(after);
+        match open[kind] {
+            Some(start) if closes && start + 2 < idx => {
+                markers.extend([start, idx]);
+                open[kind] = None;
+                // a marker of the other kind which is opened inside, but not closed, can not be closed after it
+                open[1 - kind] = open[1 - kind].filter(|other| *other < start);
+            }
+            None if opens => open[kind] = Some(idx),
+            _ => {}
+        }
+        idx += 2;
+    }
+
+    markers.sort_unstable();
+    markers
+}
+
+/// Escape the label for use inside an HTML-like label, translating the markup into HTML tags
+pub fn as_html(text: &str) -> String {
+    let markers = emphasis_markers(text);
+    let mut html = String::with_capacity(text.len());
+    let mut bold = false;
+    let mut italic = false;
+    let mut idx = 0;
+
+    while idx < text.len() {
+        let rest = &text[idx..];
+        let c = rest.chars().next().unwrap_or_default();
+
+        if let Some(escaped) = rest.strip_prefix('\\').and_then(|r| r.chars().next()) {
+            match escaped {
+                'n' => html.push_str("<BR/>"),
+                _ => push_escaped(&mut html, escaped),
+            }
+            idx += 1 + escaped.len_utf8();
+        } else if markers.binary_search(&idx).is_ok() && rest.starts_with("**") {
+            html.push_str(if bold { "</B>" } else { "<B>" });
+            bold = !bold;
+            idx += 2;
+        } else if markers.binary_search(&idx).is_ok() {
+            html.push_str(if italic { "</I>" } else { "<I>" });
+            italic = !italic;
+            idx += 2;
+        } else if c == ';' {
+            html.push_str("<BR/>");
+            idx += 1;
+        } else if let Some(entity) = entity_at(text, idx) {
+            html.push_str(entity);
+            idx += entity.len();
+        } else {
+            push_escaped(&mut html, c);
+            idx += c.len_utf8();
+        }
+    }
+
+    html
+}
+
+/// Escape the value of an attribute of an HTML-like label
+fn escape_attribute(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    value.chars().for_each(|c| push_escaped(&mut escaped, c));
+    escaped
+}
+
+fn push_escaped(html: &mut String, c: char) {
+    match c {
+        '&' => html.push_str("&amp;"),
+        '<' => html.push_str("&lt;"),
+        '>' => html.push_str("&gt;"),
+        '"' => html.push_str("&quot;"),
+        _ => html.push(c),
+    }
+}
+
+/// A named (`&rarr;`) or numeric (`&#8594;`, `&#x2192;`) character reference starting at `idx`
+fn entity_at(text: &str, idx: usize) -> Option<&str> {
+    let rest = text.get(idx..)?;
+    if !rest.starts_with('&') {
+        return None;
+    }
+
+    let end = rest.find(';')?;
+    let name = &rest[1..end];
+    let valid = if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        !hex.is_empty() && hex.chars().all(|c| c.is_ascii_hexdigit())
+    } else if let Some(dec) = name.strip_prefix('#') {
+        !dec.is_empty() && dec.chars().all(|c| c.is_ascii_digit())
+    } else {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric())
+    };
+
+    if valid {
+        Some(&rest[..=end])
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_entities() {
+        assert_eq!(decode_entities("a &lt;b&gt; &#65;&#x42; & c"), "a <b> AB & c");
+    }
+
+    #[test]
+    fn test_plain_labels() {
+        assert_eq!(as_dot_label("Fill Kettle"), "Fill Kettle");
+        assert_eq!(as_dot_label(r" V1 \(vdest\): 99999"), r" V1 \(vdest\): 99999");
+        assert_eq!(as_dot_label("Tom & Jerry → done"), "Tom & Jerry → done");
+    }
+
+    #[test]
+    fn test_rich_labels() {
+        assert_eq!(as_dot_label(r"first\nsecond"), "<first<BR/>second>");
+        assert_eq!(as_dot_label("first;second"), "<first<BR/>second>");
+        assert_eq!(as_dot_label("a **b** __c__"), "<a <B>b</B> <I>c</I>>");
+        assert_eq!(as_dot_label("**open"), "**open");
+        assert_eq!(as_dot_label("**__both__**"), "<<B><I>both</I></B>>");
+        assert_eq!(as_dot_label("**a __b** c__"), "<<B>a __b</B> c__>");
+        assert_eq!(as_dot_label("a &rarr; b & c;"), "<a &rarr; b &amp; c<BR/>>");
+        assert_eq!(as_dot_label(r"List<T>\;x;y"), "<List&lt;T&gt;;x<BR/>y>");
+        assert_eq!(as_dot_label("<T>"), "<&lt;T&gt;>");
+    }
+
+    #[test]
+    fn test_literal_markers() {
+        assert_eq!(as_dot_label("__init__()"), "__init__()");
+        assert_eq!(as_dot_label("**kwargs"), "**kwargs");
+        assert_eq!(as_dot_label("snake__case"), "snake__case");
+        assert_eq!(as_dot_label("a**b**c"), "a**b**c");
+        assert_eq!(as_dot_label("__init__();**kwargs"), "<__init__()<BR/>**kwargs>");
+        assert_eq!(as_dot_label("x ** y ** z"), "x ** y ** z");
+    }
+
+    #[test]
+    fn test_html_table() {
+        assert_eq!(
+            as_html_table(["Customer", "name;email"]),
+            "<<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\" CELLPADDING=\"9\"><TR><TD>Customer</TD></TR><TR><TD>name<BR/>email</TD></TR></TABLE>>"
+        );
+        assert!(matches!(as_html_table(["a"]), Label::Html(_)));
+        assert!(matches!(as_dot_label("<a> ] ; node [ <b>"), Label::Html(_)));
+    }
+
+    #[test]
+    fn test_parse_html_label() {
+        let table = HtmlLabelBuilder::new()
+            .header_color("#eee")
+            .aligned_row("a;b", CellAlign::Left)
+            .build();
+        assert!(HtmlLabel::parse(table.as_str()).is_some());
+        assert!(HtmlLabel::parse(as_dot_label("a **b** __c__;d &rarr; e").as_str()).is_some());
+        assert!(HtmlLabel::parse(r#"<<IMG SRC="/etc/passwd"/>>"#).is_none());
+        assert!(HtmlLabel::parse(r#"<<TABLE><TR><TD HREF="javascript:x">a</TD></TR></TABLE>>"#).is_none());
+        assert!(HtmlLabel::parse("<a> ] ; node [ <b>").is_none());
+        assert!(HtmlLabel::parse("<a>").is_some());
+        assert!(HtmlLabel::parse("a").is_none());
+    }
+
+    #[test]
+    fn test_html_label_builder() {
+        let label = HtmlLabelBuilder::new()
+            .padding(4)
+            .bold_header(true)
+            .header_color("#eee")
+            .row("Customer")
+            .aligned_row("name;email", CellAlign::Left)
+            .build();
+        assert_eq!(
+            label,
+            "<<TABLE BORDER=\"0\" CELLBORDER=\"1\" CELLSPACING=\"0\" CELLPADDING=\"4\"><TR><TD BGCOLOR=\"#eee\"><B>Customer</B></TD></TR><TR><TD ALIGN=\"LEFT\" BALIGN=\"LEFT\">name<BR/>email</TD></TR></TABLE>>"
+        );
+        assert_eq!(as_text_sections(&label), vec!["Customer", "name\nemail"]);
+    }
+
+    #[test]
+    fn test_text_sections() {
+        assert_eq!(
+            as_text_sections(&Label::from(r"Fill \(Kettle\)")),
+            vec!["Fill (Kettle)"]
+        );
+        assert_eq!(as_text_sections(&as_dot_label("a **b**;List<T>")), vec!["a b\nList<T>"]);
+        assert_eq!(
+            as_text_sections(&as_html_table(["Customer", "name;email"])),
+            vec!["Customer", "name\nemail"]
+        );
+    }
+}
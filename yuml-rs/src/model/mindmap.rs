@@ -0,0 +1,91 @@
+use super::{
+    dot::{Dot, DotElement, DotShape, Style},
+    label::as_dot_label,
+    shared::LabeledElement,
+};
+
+/// Colors of the branches of the central topic, repeated when there are more branches
+const BRANCH_COLORS: &[&str] = &[
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#17becf",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Element<'a> {
+    Topic(TopicProps<'a>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TopicProps<'a> {
+    pub label: &'a str,
+    pub attributes: Option<&'a str>,
+    /// 0 for a central topic
+    pub depth: usize,
+    /// Index of the topic this one is indented under
+    pub parent: Option<usize>,
+    /// Index of the branch of the central topic this topic belongs to
+    pub branch: Option<usize>,
+}
+
+impl<'a> LabeledElement for Element<'a> {
+    fn label(&self) -> &'a str {
+        match self {
+            Element::Topic(props) => props.label,
+        }
+    }
+
+    fn attributes(&self) -> Option<&'a str> {
+        match self {
+            Element::Topic(props) => props.attributes,
+        }
+    }
+
+    fn is_connection(&self) -> bool {
+        false
+    }
+
+    fn kind(&self) -> &'static str {
+        "topic"
+    }
+}
+
+impl<'a> TopicProps<'a> {
+    fn color(&self) -> Option<&'static str> {
+        self.branch.map(|branch| BRANCH_COLORS[branch % BRANCH_COLORS.len()])
+    }
+
+    /// The node of the topic at `index`, followed by the edge from its parent
+    pub fn as_dots(&self, index: usize) -> Vec<DotElement> {
+        let uid = format!("A{}", index + 1);
+        let color = self.color().map(|color| ("color".to_string(), color.to_string()));
+
+        // topics further away from the center are drawn lighter
+        let node = Dot {
+            shape: DotShape::Rectangle,
+            height: Some(0.5),
+            margin: Some("0.20,0.05".to_string()),
+            label: Some(as_dot_label(self.label)),
+            style: vec![Style::Rounded],
+            fontsize: Some(if self.depth == 0 { 14 } else { 10 }),
+            penwidth: Some(if self.depth < 2 { 2 } else { 1 }),
+            extra: color.iter().cloned().collect(),
+            ..Dot::default()
+        }
+        .with_attributes(self.attributes);
+
+        let edge = self.parent.map(|parent| {
+            let dot = Dot {
+                shape: DotShape::Edge,
+                style: vec![Style::Solid],
+                dir: Some("none".to_string()),
+                penwidth: Some((3 - self.depth.min(2)) as i32),
+                extra: color.iter().cloned().collect(),
+                ..Dot::default()
+            };
+            DotElement::new_edge(&format!("A{}", parent + 1), &uid, dot)
+        });
+
+        std::iter::once(DotElement::new(&uid, node)).chain(edge).collect()
+    }
+}
@@ -1,4 +1,11 @@
 pub mod activity;
+pub mod attributes;
 pub mod class;
 pub mod dot;
+pub mod er;
+pub mod facet;
+pub mod label;
+pub mod mindmap;
 pub mod shared;
+pub mod theme;
+pub mod timeline;
@@ -1,23 +1,72 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NoteProps<'a> {
     pub label: &'a str,
     pub attributes: Option<&'a str>,
 }
 
+/// Split an explicit id from the text of a node, like `[id=customer|Customer Aggregate]`
+pub fn split_id(text: &str) -> (Option<&str>, &str) {
+    match text.strip_prefix("id=").and_then(|rest| rest.split_once('|')) {
+        Some((id, label)) if !id.trim().is_empty() => (Some(id.trim()), label),
+        _ => (None, text),
+    }
+}
+
+/// The key by which references to a node are matched: surrounding whitespace is ignored and runs of whitespace
+/// count as a single space, so `( Fill Kettle )` refers to `(Fill  Kettle{bg:red})`
+pub fn normalize_label(label: &str) -> String {
+    label.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
 pub trait LabeledElement {
+    /// Identity of the element: its explicit id, or else its label
     fn label(&self) -> &'_ str;
+    /// Whether the element is declared with an explicit id, which makes it the definition of the node
+    fn has_id(&self) -> bool {
+        false
+    }
+    /// The attribute block of the element, like `bg:orange` in `[Customer{bg:orange}]`
+    fn attributes(&self) -> Option<&'_ str> {
+        None
+    }
     fn is_connection(&self) -> bool;
+    /// Human readable name of the element type, used in diagnostics
+    fn kind(&self) -> &'static str;
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ElementDetails<'a, T: LabeledElement> {
     pub id: Option<usize>,
     pub element: &'a T,
     pub relation: Option<Relation>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Relation {
     pub previous_id: usize,
     pub next_id: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_id() {
+        assert_eq!(
+            split_id("id=customer|Customer Aggregate"),
+            (Some("customer"), "Customer Aggregate")
+        );
+        assert_eq!(split_id("id= c |C|name"), (Some("c"), "C|name"));
+        assert_eq!(split_id("Customer|name"), (None, "Customer|name"));
+        assert_eq!(split_id("id=|C"), (None, "id=|C"));
+    }
+
+    #[test]
+    fn test_normalize_label() {
+        assert_eq!(normalize_label(" Fill \t Kettle "), "Fill Kettle");
+        assert_eq!(normalize_label("Fill Kettle"), "Fill Kettle");
+        assert_eq!(normalize_label(""), "");
+    }
+}
@@ -0,0 +1,152 @@
+use crate::error::{OptionsError, YumlError};
+use std::convert::TryFrom;
+
+/// Colors and font used for the graph, its nodes and its edges
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Theme {
+    pub background: String,
+    pub font: String,
+    pub font_color: String,
+    /// Outline of nodes and the color of parallel bars
+    pub line_color: String,
+    pub edge_color: String,
+    /// Fill for activities, classes, decisions and notes without a `bg` attribute
+    pub node_fill: Option<String>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::plain()
+    }
+}
+
+impl Theme {
+    /// Black lines on a transparent background
+    pub fn plain() -> Self {
+        Theme {
+            background: "transparent".to_string(),
+            font: "Helvetica".to_string(),
+            font_color: "black".to_string(),
+            line_color: "black".to_string(),
+            edge_color: "black".to_string(),
+            node_fill: None,
+        }
+    }
+
+    /// Hand-drawn look, resembling the "scruffy" style of yuml.me
+    pub fn scruffy_like() -> Self {
+        Theme {
+            font: "Comic Sans MS".to_string(),
+            font_color: "#333333".to_string(),
+            line_color: "#333333".to_string(),
+            edge_color: "#333333".to_string(),
+            node_fill: Some("lemonchiffon".to_string()),
+            ..Theme::plain()
+        }
+    }
+
+    /// Shades of gray on white, suitable for printing
+    pub fn monochrome() -> Self {
+        Theme {
+            background: "white".to_string(),
+            node_fill: Some("gray92".to_string()),
+            ..Theme::plain()
+        }
+    }
+
+    /// Light lines and text on a transparent background, for pages with a dark background
+    pub fn dark() -> Self {
+        Theme {
+            font_color: "#e6e6e6".to_string(),
+            line_color: "#e6e6e6".to_string(),
+            edge_color: "#e6e6e6".to_string(),
+            ..Theme::plain()
+        }
+    }
+
+    pub fn corporate() -> Self {
+        Theme {
+            background: "white".to_string(),
+            font: "Arial".to_string(),
+            font_color: "#1f3a5f".to_string(),
+            line_color: "#1f3a5f".to_string(),
+            edge_color: "#5b7290".to_string(),
+            node_fill: Some("#dce6f2".to_string()),
+        }
+    }
+
+    /// Draw the outlines and edges in the accent color of `key`, like the `env` directive does for `prod` or `dev`
+    pub fn accent(self, key: &str) -> Self {
+        let color = accent_color(key);
+        Theme {
+            line_color: color.clone(),
+            edge_color: color,
+            ..self
+        }
+    }
+}
+
+/// Hues of the common environments, so production stands out in red and the others are told apart at a glance
+const ENVIRONMENT_HUES: &[(&[&str], u32)] = &[
+    (&["prod", "production", "live"], 0),
+    (&["staging", "stage", "preprod", "acceptance"], 30),
+    (&["dev", "development", "local"], 120),
+    (&["test", "testing", "qa", "uat"], 210),
+];
+
+/// A color for `key`, like the name of an environment, which is the same in every diagram and every release. Common
+/// environments have a fixed hue, others follow from a FNV-1a hash of the key; saturation and lightness keep the lines
+/// readable on light backgrounds.
+pub fn accent_color(key: &str) -> String {
+    let key = key.trim().to_lowercase();
+    let hue = ENVIRONMENT_HUES
+        .iter()
+        .find(|(names, _)| names.contains(&key.as_str()))
+        .map(|(_, hue)| *hue)
+        .unwrap_or_else(|| {
+            let hash = key.bytes().fold(0x811c_9dc5_u32, |hash, byte| {
+                (hash ^ byte as u32).wrapping_mul(0x0100_0193)
+            });
+            // the final mix of murmur3 spreads similar keys, like `dev1` and `dev2`, over the color wheel
+            let hash = (hash ^ (hash >> 16)).wrapping_mul(0x85eb_ca6b);
+            let hash = (hash ^ (hash >> 13)).wrapping_mul(0xc2b2_ae35);
+            (hash ^ (hash >> 16)) % 360
+        });
+    let (r, g, b) = hsl_to_rgb(hue as f32, 0.7, 0.4);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn hsl_to_rgb(hue: f32, saturation: f32, lightness: f32) -> (u8, u8, u8) {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f32| ((value + m) * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+impl TryFrom<&str> for Theme {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "plain" => Ok(Theme::plain()),
+            "scruffy-like" => Ok(Theme::scruffy_like()),
+            "monochrome" => Ok(Theme::monochrome()),
+            "corporate" => Ok(Theme::corporate()),
+            "dark" => Ok(Theme::dark()),
+            _ => Err(OptionsError::new(
+                "invalid value for 'theme'. Allowed values are: plain <i>(default)</i>, scruffy-like, monochrome, corporate, dark.",
+            )
+            .into()),
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use super::{
+    dot::{Arrow, Dot, DotElement, DotShape, Style},
+    label::{as_dot_label, as_html_table},
+    shared::{ElementDetails, LabeledElement},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Element<'a> {
+    Phase(PhaseProps<'a>),
+    Arrow,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PhaseProps<'a> {
+    pub label: &'a str,
+    pub attributes: Option<&'a str>,
+    /// Start and end of the phase, as written
+    pub range: Option<(&'a str, &'a str)>,
+}
+
+impl<'a> LabeledElement for Element<'a> {
+    fn label(&self) -> &'a str {
+        match self {
+            Element::Phase(props) => props.label,
+            Element::Arrow => "",
+        }
+    }
+
+    /// The mention of a phase with its dates defines the node
+    fn has_id(&self) -> bool {
+        matches!(self, Element::Phase(props) if props.range.is_some())
+    }
+
+    fn attributes(&self) -> Option<&'a str> {
+        match self {
+            Element::Phase(props) => props.attributes,
+            Element::Arrow => None,
+        }
+    }
+
+    fn is_connection(&self) -> bool {
+        matches!(self, Element::Arrow)
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Element::Phase(_) => "phase",
+            Element::Arrow => "arrow",
+        }
+    }
+}
+
+impl<'a> From<&ElementDetails<'a, Element<'a>>> for DotElement {
+    fn from(e: &ElementDetails<'a, Element<'a>>) -> Self {
+        match (e.element, &e.relation) {
+            (Element::Arrow, Some(relation)) => DotElement::new_edge(
+                &format!("A{}", relation.previous_id),
+                &format!("A{}", relation.next_id),
+                Dot::from(e.element),
+            ),
+            _ => DotElement::new(&format!("A{}", e.id.unwrap_or_default()), Dot::from(e.element)),
+        }
+    }
+}
+
+impl<'a> From<&Element<'a>> for Dot {
+    fn from(e: &Element<'a>) -> Self {
+        match e {
+            Element::Phase(props) => {
+                // the dates are written below the name of the phase
+                let (label, margin) = match props.range {
+                    Some((start, end)) => {
+                        let range = format!("{} – {}", start, end);
+                        (as_html_table([props.label, range.as_str()]), None)
+                    }
+                    None => (as_dot_label(props.label), Some("0.20,0.05".to_string())),
+                };
+
+                Dot {
+                    shape: DotShape::Rectangle,
+                    height: Some(0.5),
+                    margin,
+                    label: Some(label),
+                    style: vec![Style::Rounded],
+                    fontsize: Some(10),
+                    ..Dot::default()
+                }
+                .with_attributes(props.attributes)
+            }
+            Element::Arrow => Dot {
+                shape: DotShape::Edge,
+                style: vec![Style::Solid],
+                dir: Some("both".to_string()),
+                arrowhead: Some(Arrow::Vee),
+                fontsize: Some(10),
+                ..Dot::default()
+            },
+        }
+    }
+}
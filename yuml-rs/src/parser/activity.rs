@@ -1,160 +1,245 @@
-use super::utils::populate_uids;
+use super::utils::{resolve_connections, take_until_balanced, take_until_unescaped};
 use super::*;
 use crate::model::{
-    activity::{as_note, ArrowProps, Element, ElementProps},
-    shared::{ElementDetails, LabeledElement, Relation},
+    activity::{as_note, split_at_note, ArrowLine, ArrowProps, Element, ElementProps},
+    attributes::split_attributes,
+    facet::FacetAllocator,
+    label::Label,
+    shared::{split_id, LabeledElement, NoteProps},
 };
 
 /*
 Syntax as specified in yuml.me
 Start	           (start)
 End	               (end)
+Flow final         (end:flow) ends a single flow
+Error end          (end:error)
 Activity           (Find Products)
 Flow	           (start)->(Find Products)
 Multiple Assoc.    (start)->(Find Products)->(end)
 Decisions          (start)-><d1>
 Decisions w/Label  (start)-><d1>logged in->(Show Dashboard), <d1>not logged in->(Show Login Page)
+                   (start)-><d1>[logged in]->(Show Dashboard)
 Parallel	       (Action1)->|a|,(Action 2)->|a|
 Note               (Action1)-(note: A note message here)
+Note w/Attributes  (Action1)-(note: A note message here{bg:beige}) or (Action1)-(note: A note message here){bg:beige}
+Transition note    (Action1)->(Action2){note: retried 3 times}
+Edge attributes    (Action1)->{color:red,penwidth:2}(Action2), (a)-><d1>[yes]->{style:bold}(b)
+Edge styles        (Action1)-.->(Action2) for optional flows, (Action1)=>(Action2) for the main flow
+Explicit id        (id=fill|Fill Kettle)->(Boil), (Boil)->(fill)
+Delimiters         (Compute f(x))-><x \< 0>[x[0] > 1]->(end), unbalanced ones are escaped: (Open \()
+Fan-out            (Split)->(Action1),(Action2),(Action3)
 Comment            // Comments
 */
 
-pub fn note_or_actvity(yuml: &str) -> IResult<&str, Element> {
+pub fn note_or_actvity(yuml: &str) -> IResult<&str, Element<'_>> {
     let note_string = take_until("}");
     let note_props = delimited(tag("{"), note_string, tag("}"));
     let note_text = alt((take_until("{"), rest));
     let extract_attributes = map(tuple((note_text, opt(note_props))), as_note);
     let extract_note = map_parser(preceded(tag("note:"), rest), extract_attributes);
-    let extract_activity = map(rest, |s| Element::Activity(ElementProps::new(s)));
+    let extract_activity = map(rest, |s| {
+        let (label, attributes) = split_attributes(s);
+        let (id, label) = split_id(label);
+        Element::Activity(ElementProps::new(label).id(id).attributes(attributes))
+    });
     let mut n_or_a = alt((extract_note, extract_activity));
 
     n_or_a(yuml)
 }
 
-fn parse_activity_elem(yuml: &str) -> IResult<&str, Element> {
-    let activity = preceded(tag("("), parse_until_end_of_activity);
+fn parse_activity_elem(yuml: &str) -> IResult<&str, Element<'_>> {
+    let activity = preceded(tag("("), take_until_balanced('(', ')'));
     let mut activity = map_res(activity, |s| note_or_actvity(s).map(|(_, b)| b));
-    activity(yuml)
+    let (rest, element) = activity(yuml)?;
+
+    // the attributes of a note may follow its closing parenthesis, unlike a transition note
+    match element {
+        Element::Note(NoteProps {
+            label,
+            attributes: None,
+        }) => {
+            let block = verify(take_until_unescaped('}'), |s: &str| !s.starts_with("note:"));
+            let (rest, attributes) = opt(preceded(tag("{"), block))(rest)?;
+            Ok((rest, Element::Note(NoteProps { label, attributes })))
+        }
+        element => Ok((rest, element)),
+    }
 }
 
-pub fn parse_activity<'a, 'o>(yuml: &'a str, options: &'o Options) -> IResult<&'a str, DotFile> {
+/// Parse the body of the diagram, keeping the elements of each statement together
+pub fn parse_activity_statements<'a>(
+    yuml: &'a str,
+    options: &Options,
+) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
     let start_tag = map(tag("(start)"), |_s: &str| Element::StartTag);
     let end_tag = map(tag("(end)"), |_s: &str| Element::EndTag);
-    let alphanumeric_string = take_until(">");
-    let decision = map(delimited(tag("<"), alphanumeric_string, tag(">")), |s| {
+    let flow_final_tag = map(tag("(end:flow)"), |_s: &str| Element::FlowFinalTag);
+    let error_end_tag = map(tag("(end:error)"), |_s: &str| Element::ErrorEndTag);
+    let decision = map(preceded(tag("<"), take_until_balanced('<', '>')), |s| {
         Element::Decision(ElementProps::new(s))
     });
-    let alphanumeric_string = take_until("|");
-    let parallel = map(delimited(tag("|"), alphanumeric_string, tag("|")), |s| {
-        Element::Parallel(ElementProps::new(s))
+    let parallel = map(preceded(tag("|"), take_until_unescaped('|')), |s| {
+        Element::Parallel(ElementProps::new(s).direction(&options.dir))
+    });
+    let bracketed_label = preceded(tag("["), take_until_balanced('[', ']'));
+    // a bare label can not run into the next element
+    let bare_label = verify(take_until_arrow, |lbl: &str| {
+        !lbl.contains(['(', ')', '<', '>', '|', '[', ']', '\n'])
     });
-    let alphanumeric_string = take_until("->");
-    let arrow_w_label = map(terminated(alphanumeric_string, tag("->")), |lbl| {
-        Element::Arrow(ArrowProps::new(Some(lbl), &options.dir, true))
+    let arrow_w_label = map(
+        tuple((alt((bracketed_label, bare_label)), arrow_line)),
+        |(lbl, line)| Element::Arrow(ArrowProps::new(Some(lbl), &options.dir, true).line(line)),
+    );
+    let arrow_wo_label = map(arrow_line, |line| {
+        Element::Arrow(ArrowProps::new(None, &options.dir, true).line(line))
     });
-    let arrow_wo_label = map(tag("->"), |_| Element::Arrow(ArrowProps::new(None, &options.dir, true)));
     let no_tail_arrow_wo_label = map(tag("-"), |_| Element::Arrow(ArrowProps::new(None, &options.dir, false)));
 
-    let arrow = alt((arrow_wo_label, arrow_w_label, no_tail_arrow_wo_label));
+    // an attribute block after an arrow styles the edge, a transition note follows the target instead
+    let edge_attributes = preceded(
+        tag("{"),
+        verify(take_until_unescaped('}'), |s: &str| !s.starts_with("note:")),
+    );
+    let arrow = map(
+        tuple((
+            alt((arrow_wo_label, arrow_w_label, no_tail_arrow_wo_label)),
+            opt(edge_attributes),
+        )),
+        |(arrow, attributes)| match arrow {
+            Element::Arrow(props) => Element::Arrow(props.attributes(attributes)),
+            element => element,
+        },
+    );
+    let transition_note = map(preceded(tag("{note:"), take_until_unescaped('}')), |label| {
+        Element::TransitionNote(NoteProps {
+            label,
+            attributes: None,
+        })
+    });
 
-    let parse_element = alt((start_tag, end_tag, decision, parse_activity_elem, parallel, arrow));
-    let parse_line = many_till(parse_element, alt((eof, line_ending)));
-    let mut parse_lines = many_till(parse_line, eof);
+    let parse_element = alt((
+        start_tag,
+        end_tag,
+        flow_final_tag,
+        error_end_tag,
+        decision,
+        parse_activity_elem,
+        parallel,
+        transition_note,
+        arrow,
+    ));
+    let parse_statement = map_res(statement(parse_element), |(elements, end)| {
+        attach_transition_notes(elements).map(|elements| (elements, end))
+    });
+    let mut parse_statements = many_till(parse_statement, eof);
 
-    let (rest, (lines, _)) = parse_lines(yuml)?;
-    let elements: Vec<Element> = lines
-        .into_iter()
-        .flat_map(|(elements, _le)| elements.into_iter())
-        .collect();
+    let (rest, (statements, _)) = parse_statements(yuml)?;
+    Ok((rest, as_statements(statements)))
+}
 
-    let dots = as_dots(&elements);
-    let activity_file = DotFile::new(dots, options);
-    Ok((rest, activity_file))
+fn arrow_line(yuml: &str) -> IResult<&str, ArrowLine> {
+    alt((
+        map(tag("->"), |_| ArrowLine::Solid),
+        map(tag("-.->"), |_| ArrowLine::Dashed),
+        map(tag("=>"), |_| ArrowLine::Bold),
+    ))(yuml)
 }
 
-fn as_dots(elements: &[Element]) -> Vec<DotElement> {
-    let (uids, element_details) = populate_uids(elements);
+/// Take the label in front of the first arrow, whatever its line
+fn take_until_arrow(yuml: &str) -> IResult<&str, &str> {
+    let end = ["->", "-.->", "=>"].iter().filter_map(|arrow| yuml.find(arrow)).min();
+    match end {
+        Some(end) => Ok((&yuml[end..], &yuml[..end])),
+        None => Err(nom::Err::Error(nom::error::Error::new(
+            yuml,
+            nom::error::ErrorKind::TakeUntil,
+        ))),
+    }
+}
 
-    // we must collect to ensure the incoming connections are all processed, before creating the dot file
-    #[allow(clippy::needless_collect)]
-    let arrow_details: Vec<ElementDetails<Element>> = elements
-        .iter()
-        .circular_tuple_windows::<(_, _, _)>()
-        .filter(|(pre, _e, next)| !pre.is_connection() && !next.is_connection())
-        .filter_map(|(pre, e, next)| {
-            if let Element::Arrow(props) = e {
-                Some((pre, e, props, next))
-            } else {
-                None
+/// Move each transition note onto the arrow leading to the element it follows
+fn attach_transition_notes(elements: Vec<Element>) -> Result<Vec<Element>, &'static str> {
+    let mut attached: Vec<Element> = Vec::with_capacity(elements.len());
+    for element in elements {
+        if let Element::TransitionNote(note) = element {
+            let follows_target = attached.last().is_some_and(|target| !target.is_connection());
+            let arrow = attached.len().checked_sub(2).and_then(|idx| attached.get_mut(idx));
+            match arrow {
+                Some(Element::Arrow(props)) if follows_target && props.note.is_none() => props.note = Some(note),
+                _ => return Err("a transition note must follow the target of an arrow"),
             }
-        })
-        .filter_map(|(pre, e, props, next)| {
-            // if I am an arrow
+        } else {
+            attached.push(element);
+        }
+    }
+    Ok(attached)
+}
+
+pub struct ActivityParser;
+
+impl<'a> DiagramParser<'a, Element<'a>> for ActivityParser {
+    fn parse_statements(&self, yuml: &'a str, options: &Options) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+        parse_activity_statements(yuml, options)
+    }
+
+    fn as_dots(&self, statements: &[Statement<Element<'a>>], _options: &Options) -> Vec<DotElement> {
+        as_dots(statements)
+    }
+}
+
+fn as_dots(statements: &[Statement<Element>]) -> Vec<DotElement> {
+    let (element_details, arrow_details) = resolve_connections(statements, |pre, e, next| {
+        if let Element::Arrow(props) = e {
             if pre.is_note() || next.is_note() {
-                let mut dashed = props.dashed.borrow_mut();
-                *dashed = true;
+                *props.dashed.borrow_mut() = true;
             }
+        }
+    });
 
-            let previous_id = uids.get(pre.label()).map(|(idx, _e)| *idx).unwrap_or_default();
-            let (next_id, next_e) = match uids.get(next.label()) {
-                Some((idx, e)) => (*idx, e),
-                None => {
-                    // arrow pointing in the void
-                    return None;
+    // every flow joining a parallel bar enters through its own facet
+    let mut facets = FacetAllocator::default();
+    let arrows: Vec<DotElement> = arrow_details
+        .iter()
+        .map(|e| {
+            let mut arrow = DotElement::from(e);
+            if let (Some(relation), Element::Arrow(props)) = (&e.relation, e.element) {
+                let target = element_details[relation.next_id - 1].element;
+                if matches!(target, Element::Parallel(_)) {
+                    let facet = facets.allocate(relation.next_id);
+                    let uid = format!("A{}", relation.next_id);
+                    arrow.uid2 = Some(FacetAllocator::port(&uid, facet, &props.chart_direction));
                 }
-            };
-
-            let target_connection = if let Element::Parallel(props) = next_e {
-                let mut incoming_connections = props.incoming_connections.borrow_mut();
-                *incoming_connections += 1;
-                *incoming_connections
-            } else {
-                0
-            };
-
-            let mut target_connection_id = props.target_connection_id.borrow_mut();
-            *target_connection_id = target_connection;
-
-            let r = Relation { previous_id, next_id };
-            Some(ElementDetails {
-                id: None,
-                element: e,
-                relation: Some(r),
-            })
+            }
+            arrow
         })
         .collect();
 
-    element_details
-        .into_iter()
-        .chain(arrow_details.into_iter())
-        .map(|e| DotElement::from(e.borrow()))
-        .collect()
-}
-
-fn parse_until_end_of_activity(yuml: &str) -> IResult<&str, &str> {
-    let mut last_char: Option<char> = None;
-    for (idx, c) in yuml.char_indices() {
-        if c == ')' {
-            if let Some(lc) = last_char.as_ref() {
-                if *lc != '\\' {
-                    return Ok((&yuml[idx + 1..], &yuml[..idx]));
-                }
-            } else {
-                return Ok((&yuml[idx + 1..], &yuml[..idx]));
-            }
+    let nodes = element_details.iter().map(|e| {
+        let mut node = DotElement::from(e);
+        if let (Element::Parallel(_), Some(id)) = (e.element, e.id) {
+            node.dot.label = Some(Label::from(facets.record_label(id)));
         }
+        node
+    });
 
-        last_char = Some(c)
-    }
+    // the point nodes and notes of transition notes are numbered after all other elements
+    let mut uid = element_details.len();
+    let arrows = arrow_details.iter().zip(arrows).flat_map(|(e, arrow)| match e.element {
+        Element::Arrow(ArrowProps { note: Some(note), .. }) => {
+            uid += 2;
+            split_at_note(arrow, note, uid - 1, uid)
+        }
+        _ => vec![arrow],
+    });
 
-    Err(nom::Err::Error(nom::error::Error::new(
-        yuml,
-        nom::error::ErrorKind::RegexpFind,
-    )))
+    nodes.chain(arrows).collect()
 }
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::theme::Theme;
 
     fn parse(yuml: &str) -> DotFile {
         if let (rest, ParsedYuml::Activity(dot_file)) = parse_yuml(yuml).expect("invalid file") {
@@ -190,92 +275,360 @@ mod tests {
     #[test]
     fn parse_empty_activity() {
         const YUML: &str = r#"()"#;
-        const A1: &str = r#"A1 [shape="rectangle" , margin="0.20,0.05" , label="" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
         validate(YUML, &[A1]);
     }
 
     #[test]
     fn parse_single_activity() {
         const YUML: &str = "(Hello)";
-        const A1: &str = r#"A1 [shape="rectangle" , margin="0.20,0.05" , label="Hello" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="Hello", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
         validate(YUML, &[A1]);
     }
 
     #[test]
     fn parse_single_decision() {
         const YUML: &str = "<a>";
-        const A1: &str = r#"A1 [shape="diamond" , label="a" , style="" , arrowtail="none" , arrowhead="none" , height=0.5 , width=0.5 , fontsize=0 , ]"#;
+        const A1: &str =
+            r#"A1 [shape="diamond", label="a", arrowtail="none", arrowhead="none", height=0.5, width=0.5, fontsize=0]"#;
         validate(YUML, &[A1]);
     }
 
     #[test]
     fn parse_single_connector() {
         const YUML: &str = "|a|";
-        const A1: &str = r#"A1 [shape="record" , label="" , style="filled" , arrowtail="none" , arrowhead="none" , height=0.05 , width=0.5 , fontsize=1 , penwidth=4 , ]"#;
+        const A1: &str = r#"A1 [shape="record", label="", style="filled", arrowtail="none", arrowhead="none", height=0.05, width=0.5, fontsize=1, penwidth=4]"#;
         validate(YUML, &[A1]);
     }
 
     #[test]
     fn parse_single_note_with_attr() {
         const YUML: &str = "(note:Hello{bg:cornsilk})";
-        const A1: &str = r#"A1 [shape="note" , margin="0.20,0.05" , label="Hello" , style="filled" , fillcolor="cornsilk" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="note", margin="0.20,0.05", label="Hello", style="filled", fillcolor="cornsilk", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
         validate(YUML, &[A1]);
     }
 
     #[test]
     fn parse_single_note() {
         const YUML: &str = "(note:Hello)";
-        const A1: &str = r#"A1 [shape="note" , margin="0.20,0.05" , label="Hello" , style="" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="note", margin="0.20,0.05", label="Hello", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
         validate(YUML, &[A1]);
     }
 
     #[test]
     fn parse_single_note_with_escaped_chars() {
         const YUML: &str = r#"(note: V1 \(vdest\): 99999{bg:cornsilk})"#;
-        const A1: &str = r#"A1 [shape="note" , margin="0.20,0.05" , label=" V1 \(vdest\): 99999" , style="filled" , fillcolor="cornsilk" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 ,"#;
+        const A1: &str = r#"A1 [shape="note", margin="0.20,0.05", label=" V1 \(vdest\): 99999", style="filled", fillcolor="cornsilk", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        validate(YUML, &[A1]);
+    }
+
+    #[test]
+    fn parse_labels_with_delimiters() {
+        const YUML: &str = r"(Compute f(g(x)))-><x \< f(0)>[x[0] > 1]->(note: see \(1\)), <x \< f(0)>->(Open \()";
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="Compute f(g(x))", "#;
+        const A2: &str = r#"A2 [shape="diamond", label="x \< f(0)", "#;
+        const A3: &str = r#"A3 [shape="note", margin="0.20,0.05", label=" see \(1\)", "#;
+        const A4: &str = r#"A4 [shape="rectangle", margin="0.20,0.05", label="Open \(", "#;
+        const CON: &str = r#"A2 -> A3 [shape="edge", label="x[0] > 1", "#;
+        validate(YUML, &[A1, A2, A3, A4, CON, "A2 -> A4 "]);
+    }
+
+    #[test]
+    fn parse_rich_text_activity() {
+        const YUML: &str = r"(**Fill** Kettle\nto the brim)";
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label=<<B>Fill</B> Kettle<BR/>to the brim>, style="rounded", "#;
         validate(YUML, &[A1]);
     }
 
+    #[test]
+    fn parse_activity_with_attributes() {
+        const YUML: &str = "(Job{style:dashed,bg:red})->(Job)";
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="Job", style="rounded,dashed,filled", fillcolor="red", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const CON: &str = r#"A1 -> A1 [shape="edge""#;
+        validate(YUML, &[A1, CON]);
+    }
+
     #[test]
     fn parse_single_connection() {
         const YUML: &str = "(a)-(b)";
-        const A1: &str = r#"A1 [shape="rectangle" , margin="0.20,0.05" , label="a" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const A2: &str = r#"A2 [shape="rectangle" , margin="0.20,0.05" , label="b" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const CON: &str = r#"A1 -> A2 [shape="edge" , label="" , style="solid" , dir="both" , arrowtail="none" , arrowhead="none" , labeldistance=1 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="a", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const A2: &str = r#"A2 [shape="rectangle", margin="0.20,0.05", label="b", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const CON: &str = r#"A1 -> A2 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10]"#;
         validate(YUML, &[A1, A2, CON]);
     }
 
     #[test]
     fn parse_double_connection() {
         const YUML: &str = "(a)-(b)-(c)";
-        const A1: &str = r#"A1 [shape="rectangle" , margin="0.20,0.05" , label="a" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const A2: &str = r#"A2 [shape="rectangle" , margin="0.20,0.05" , label="b" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const A3: &str = r#"A3 [shape="rectangle" , margin="0.20,0.05" , label="c" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const CON: &str = r#"A1 -> A2 [shape="edge" , label="" , style="solid" , dir="both" , arrowtail="none" , arrowhead="none" , labeldistance=1 , fontsize=10 , ]"#;
-        const CON2: &str = r#"A2 -> A3 [shape="edge" , label="" , style="solid" , dir="both" , arrowtail="none" , arrowhead="none" , labeldistance=1 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="a", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const A2: &str = r#"A2 [shape="rectangle", margin="0.20,0.05", label="b", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const A3: &str = r#"A3 [shape="rectangle", margin="0.20,0.05", label="c", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const CON: &str = r#"A1 -> A2 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10]"#;
+        const CON2: &str = r#"A2 -> A3 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10]"#;
         validate(YUML, &[A1, A2, A3, CON, CON2]);
     }
 
     #[test]
     fn parse_connection_with_note() {
         const YUML: &str = "(a)-(note:Hello)-(b)";
-        const A1: &str = r#"A1 [shape="rectangle" , margin="0.20,0.05" , label="a" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const A2: &str = r#"A2 [shape="note" , margin="0.20,0.05" , label="Hello" , style="" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const A3: &str = r#"A3 [shape="rectangle" , margin="0.20,0.05" , label="b" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const CON: &str = r#"A1 -> A2 [shape="edge" , label="" , style="solid" , dir="both" , arrowtail="none" , arrowhead="none" , labeldistance=1 , fontsize=10 , ]"#;
-        const CON2: &str = r#"A2 -> A3 [shape="edge" , label="" , style="solid" , dir="both" , arrowtail="none" , arrowhead="none" , labeldistance=1 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="a", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const A2: &str = r#"A2 [shape="note", margin="0.20,0.05", label="Hello", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const A3: &str = r#"A3 [shape="rectangle", margin="0.20,0.05", label="b", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const CON: &str = r#"A1 -> A2 [shape="edge", style="dashed", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10]"#;
+        const CON2: &str = r#"A2 -> A3 [shape="edge", style="dashed", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10]"#;
         validate(YUML, &[A1, A2, A3, CON, CON2]);
     }
 
+    #[test]
+    fn parse_arrow_with_attributes() {
+        const YUML: &str = "(a)->{color:red,penwidth:2}(b)-><c>[yes]->{style:bold}(d){note: critical}";
+        const CON: &str = r#"A1 -> A2 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", labeldistance=1, fontsize=10, color="red", penwidth="2"]"#;
+        const CON2: &str = r#"A2 -> A3 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", labeldistance=1, fontsize=10]"#;
+        // both halves of an arrow with a transition note are styled
+        const HALF: &str = r#"A3 -> A5 [shape="edge", label="yes", style="solid,bold", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10]"#;
+        const HALF2: &str = r#"A5 -> A4 [shape="edge", style="solid,bold", dir="both", arrowtail="none", arrowhead="vee", fontsize=10]"#;
+        validate(YUML, &[CON, CON2, HALF, HALF2]);
+    }
+
+    #[test]
+    fn parse_final_nodes() {
+        const YUML: &str = "(start)-><d>[ok]->(end)\n<d>[skip]->(end:flow)\n<d>[fail]->(end:error)\n(a)->(end:flow)";
+        const A3: &str = r#"A3 [shape="doublecircle", label="", "#;
+        const A4: &str =
+            r#"A4 [shape="circle", label="✕", arrowtail="none", arrowhead="none", height=0.3, width=0.3, fontsize=14]"#;
+        const A5: &str = r#"A5 [shape="doubleoctagon", label="", arrowtail="none", arrowhead="none", height=0.3, width=0.3, penwidth=2]"#;
+        // all flow finals are one node, like the ends
+        const CON: &str = "A6 -> A4 ";
+        validate(YUML, &[A3, A4, A5, CON]);
+    }
+
+    #[test]
+    fn parse_arrow_lines() {
+        const YUML: &str = "(a)-.->(b)=>(c)-><d>no-.->(a)\n<d>[yes]=>(e){note: done}";
+        const CON: &str = r#"A1 -> A2 [shape="edge", style="dashed", dir="both", arrowtail="none", arrowhead="vee", labeldistance=1, fontsize=10]"#;
+        const CON2: &str = r#"A2 -> A3 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", labeldistance=1, fontsize=10, penwidth=2]"#;
+        const CON3: &str = r#"A4 -> A1 [shape="edge", label="no", style="dashed", "#;
+        // both halves of an arrow with a transition note are bold
+        const HALF: &str = r#"A4 -> A6 [shape="edge", label="yes", style="solid", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10, penwidth=2]"#;
+        const HALF2: &str = r#"A6 -> A5 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", fontsize=10, penwidth=2]"#;
+        validate(YUML, &[CON, CON2, CON3, HALF, HALF2]);
+    }
+
+    #[test]
+    fn parse_note_with_trailing_attributes() {
+        const YUML: &str = "(a)-(note: A note){bg:beige}->(b){note: retried}";
+        const A2: &str =
+            r#"A2 [shape="note", margin="0.20,0.05", label=" A note", style="filled", fillcolor="beige", "#;
+        const CON: &str = r#"A1 -> A2 [shape="edge", style="dashed", dir="both", arrowtail="none", arrowhead="none", "#;
+        const CON2: &str = r#"A2 -> A4 [shape="edge", style="dashed", dir="both", arrowtail="none", "#;
+        validate(YUML, &[A2, CON, CON2, r#"label=" retried""#]);
+    }
+
+    #[test]
+    fn parse_fan_out() {
+        const YUML: &str = "(a)->|b|->(c),(d)\n(e),(f)";
+        const CON: &str = r#"A2 -> A3 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", "#;
+        const CON2: &str = r#"A2 -> A4 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", "#;
+        validate(YUML, &[CON, CON2]);
+
+        // lone elements on a line of their own are not connected
+        let result = parse(&insert_header(YUML)).to_string();
+        assert!(!result.contains("-> A5") && !result.contains("-> A6"));
+    }
+
+    #[test]
+    fn parse_explicit_id() {
+        const YUML: &str = "(id=fill|Fill Kettle)->(Boil)\n(Boil)->(fill)";
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="Fill Kettle", "#;
+        const CON: &str = r#"A2 -> A1 [shape="edge""#;
+        validate(YUML, &[A1, CON]);
+        assert!(!parse(&insert_header(YUML)).to_string().contains("A3"));
+    }
+
+    #[test]
+    fn parse_transition_note() {
+        const YUML: &str = "(a)->(b){note: retried 3 times}->(c)";
+        const POINT: &str =
+            r#"A4 [shape="point", label="", arrowtail="none", arrowhead="none", height=0.01, width=0.01]"#;
+        const NOTE: &str = r#"A5 [shape="note", margin="0.20,0.05", label=" retried 3 times", "#;
+        const CON: &str = r#"A1 -> A4 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="none", labeldistance=1, fontsize=10]"#;
+        const CON2: &str =
+            r#"A4 -> A2 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", fontsize=10]"#;
+        const NOTE_CON: &str = r#"A5 -> A4 [shape="edge", style="dashed", dir="none", "#;
+        const CON3: &str = r#"A2 -> A3 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", "#;
+        validate(YUML, &[POINT, NOTE, CON, CON2, NOTE_CON, CON3]);
+
+        let result = parse(&insert_header(YUML)).to_string();
+        assert!(!result.contains("A1 -> A2"));
+
+        assert!(parse_yuml(&insert_header("(a){note: orphan}")).is_err());
+        assert!(parse_yuml(&insert_header("(a)->{note: orphan}(b)")).is_err());
+    }
+
     #[test]
     fn parse_single_arrow_connection() {
         const YUML: &str = "(a)->(b)";
-        const A1: &str = r#"A1 [shape="rectangle" , margin="0.20,0.05" , label="a" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const A2: &str = r#"A2 [shape="rectangle" , margin="0.20,0.05" , label="b" , style="rounded" , arrowtail="none" , arrowhead="none" , height=0.5 , fontsize=10 , ]"#;
-        const CON: &str = r#"A1 -> A2 [shape="edge" , label="" , style="solid" , dir="both" , arrowtail="none" , arrowhead="vee" , labeldistance=1 , fontsize=10 , ]"#;
+        const A1: &str = r#"A1 [shape="rectangle", margin="0.20,0.05", label="a", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const A2: &str = r#"A2 [shape="rectangle", margin="0.20,0.05", label="b", style="rounded", arrowtail="none", arrowhead="none", height=0.5, fontsize=10]"#;
+        const CON: &str = r#"A1 -> A2 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", labeldistance=1, fontsize=10]"#;
         validate(YUML, &[A1, A2, CON]);
     }
 
+    #[test]
+    fn parse_styling_directives() {
+        const YUML: &str = "// {type:activity}\n// {font:Courier New}\n// {ranksep:1.25}\n// {bgcolor:white}\n(a)";
+        let result = parse(YUML).to_string();
+        assert!(contains_all(
+            &[
+                r#"graph [ bgcolor=white, fontname="Courier New" ]"#,
+                r#"edge [ color=black, fontcolor=black, fontname="Courier New" ]"#,
+                "ranksep = 1.25"
+            ],
+            &result
+        ));
+    }
+
+    #[test]
+    fn parse_page_directives() {
+        let result = parse("// {type:activity}\n// {bg:white}\n// {margin:0.25}\n(a)").to_string();
+        assert!(result.contains("  graph [ bgcolor=white, fontname=Helvetica, margin=0.25 ]\n"));
+
+        let result = parse("// {type:activity}\n(a)").background("#202020").to_string();
+        assert!(result.contains(r##"  graph [ bgcolor="#202020", fontname=Helvetica ]"##));
+    }
+
+    #[test]
+    fn parse_title_directive() {
+        let parsed = parse_yuml("// {type:activity}\n// {title:Checkout \"Flow\"}\n(a)")
+            .unwrap()
+            .1;
+        assert_eq!(parsed.title(), Some("Checkout \"Flow\""));
+        assert!(parsed.to_string().contains(
+            r#"  graph [ bgcolor=transparent, fontname=Helvetica, label="Checkout \"Flow\"", labelloc=t, fontsize=16 ]"#
+        ));
+
+        assert_eq!(parse_yuml("// {type:activity}\n(a)").unwrap().1.title(), None);
+    }
+
+    #[test]
+    fn parse_stable_output() {
+        const YUML: &str = "// {type:activity}\n(start)->(a)->(b)\n(b)->(end)";
+        let before = parse(YUML).to_string();
+        let after = parse(&format!("{}\n(a)->(c)", YUML)).to_string();
+
+        let (nodes_before, edges_before) = before.split_once("A1 -> ").unwrap();
+        let (nodes_after, edges_after) = after.split_once("A1 -> ").unwrap();
+        assert!(nodes_after.starts_with(nodes_before));
+        assert!(edges_after.starts_with(edges_before.trim_end_matches('}')));
+        assert!(nodes_after.contains(r#"A5 [shape="rectangle", margin="0.20,0.05", label="c""#));
+    }
+
+    #[test]
+    fn parse_theme_directive() {
+        const YUML: &str = "// {type:activity}\n// {theme:corporate}\n// {font:Verdana}\n(a)->|b|->(c)\n(a)->|b|";
+        let result = parse(YUML).to_string();
+        assert!(contains_all(
+            &[
+                r#"graph [ bgcolor=white, fontname=Verdana ]"#,
+                r##"node [ shape=none, margin=0, color="#1f3a5f", fontcolor="#1f3a5f", fontname=Verdana ]"##,
+                r##"edge [ color="#5b7290", fontcolor="#1f3a5f", fontname=Verdana ]"##,
+                r##"label="a", style="rounded,filled", fillcolor="#dce6f2", "##,
+                r#"label="<f1>|<f2>", style="filled", arrowtail"#,
+            ],
+            &result
+        ));
+
+        let result = parse(YUML).theme(Theme::plain()).to_string();
+        assert!(result.contains(r#"graph [ bgcolor=transparent, fontname=Verdana ]"#));
+        assert!(!result.contains("fillcolor"));
+    }
+
+    #[test]
+    fn parse_scale_directives() {
+        let result = parse("// {type:activity}\n// {scale:150}\n(a)").to_string();
+        assert!(result.contains("  graph [ bgcolor=transparent, fontname=Helvetica, dpi=108 ]\n"));
+
+        let result = parse("// {type:activity}\n// {scale:150}\n// {dpi:192}\n(a)").to_string();
+        assert!(result.contains(", dpi=192 ]"));
+
+        let result = parse("// {type:activity}\n// {scale:0}\n(a)").to_string();
+        assert!(!result.contains("dpi="));
+    }
+
+    #[test]
+    fn parse_decision_labels() {
+        const YUML: &str = "<d1>[yes]->(a)\n<d1>not logged in->(b)\n<d1>[\\[x\\] done]->(c)";
+        const CON: &str = r#"A1 -> A2 [shape="edge", label="yes", "#;
+        const CON2: &str = r#"A1 -> A3 [shape="edge", label="not logged in", "#;
+        const CON3: &str = r#"A1 -> A4 [shape="edge", label="\[x\] done", "#;
+        validate(YUML, &[CON, CON2, CON3]);
+    }
+
+    #[test]
+    fn parse_label_does_not_swallow_nodes() {
+        const YUML: &str = "(a)-(b)->(c)";
+        const CON: &str = r#"A1 -> A2 [shape="edge", "#;
+        const CON2: &str = r#"A2 -> A3 [shape="edge", "#;
+        validate(YUML, &[CON, CON2]);
+    }
+
+    #[test]
+    fn parse_comma_separated_statements() {
+        const YUML: &str = "(start)-><d1>logged in->(Show Dashboard), <d1>not logged in->(Show Login Page)";
+        const CON: &str = r#"A2 -> A3 [shape="edge", label="logged in", "#;
+        const CON2: &str = r#"A2 -> A4 [shape="edge", label="not logged in", "#;
+        validate(YUML, &[CON, CON2]);
+
+        let result = parse(&insert_header("(a)->(b),(c)")).to_string();
+        assert!(!result.contains("A2 -> A3"));
+    }
+
+    #[test]
+    fn parse_left_to_right_parallel() {
+        const YUML: &str = "// {type:activity}\n// {direction:leftToRight}\n(a)->|b|\n(c)->|b|";
+        const BAR: &str = r#"A2 [shape="record", label="<f1>|<f2>", style="filled", arrowtail="none", arrowhead="none", height=0.5, width=0.05, fontsize=1, penwidth=4]"#;
+        const CON: &str = r#"A1 -> A2:f1:w [shape="edge""#;
+        const CON2: &str = r#"A3 -> A2:f2:w [shape="edge""#;
+        let result = parse(YUML).to_string();
+        assert!(contains_all(&["rankdir = LR", BAR, CON, CON2], &result));
+    }
+
+    #[test]
+    fn parse_right_to_left_parallel() {
+        const YUML: &str = "// {type:activity}\n// {direction:rightToLeft}\n(a)->|b|";
+        const BAR: &str = r#"A2 [shape="record", label="<f1>", style="filled", arrowtail="none", arrowhead="none", height=0.5, width=0.05, "#;
+        const CON: &str = r#"A1 -> A2:f1:e [shape="edge""#;
+        let result = parse(YUML).to_string();
+        assert!(contains_all(&["rankdir = RL", BAR, CON], &result));
+    }
+
+    #[test]
+    fn parse_cross_line_references() {
+        let yuml = "// {type:activity}\n(start)->(Fill Kettle{bg:red})-><a>\n<a>[kettle full]->|b|\n<a>[empty]->( Fill  Kettle )\n|b|->(end)";
+        let result = parse(yuml).to_string();
+        assert!(!result.contains("A6"));
+        assert!(contains_all(
+            &[
+                "A3 -> A4:f1:n ",
+                "A3 -> A2 ",
+                r#"label="Fill Kettle", style="rounded,filled""#
+            ],
+            &result
+        ));
+    }
+
+    #[test]
+    fn parse_parallel_with_many_facets() {
+        let flows: Vec<String> = (1..=11).map(|i| format!("(a{})->|b|", i)).collect();
+        let yuml = format!("// {{type:activity}}\n{}\n|b|->(c)", flows.join("\n"));
+        let result = parse(&yuml).to_string();
+        assert!(result.contains(r#"label="<f1>|<f2>|<f3>|<f4>|<f5>|<f6>|<f7>|<f8>|<f9>|<f10>|<f11>""#));
+        assert!(contains_all(
+            &["A1 -> A2:f1:n ", "A12 -> A2:f11:n ", "A2 -> A13 "],
+            &result
+        ));
+    }
+
     #[test]
     fn test_parse_activity() {
         let yuml = include_str!("../../test/activity.yuml");
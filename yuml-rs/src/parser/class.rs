@@ -1,10 +1,14 @@
-use super::utils::populate_uids;
+use super::utils::{resolve_connections, take_until_balanced, take_until_unescaped};
 use super::*;
 use crate::model::{
-    class::{as_note, Connection, Connector, Element, RelationProps},
-    shared::{ElementDetails, LabeledElement, Relation},
+    class::{as_note, in_namespace, split_at_association_class, Connection, Connector, Element, RelationProps},
+    dot::Dot,
+    shared::normalize_label,
+};
+use nom::{
+    bytes::complete::{is_not, take_until1},
+    combinator::not,
 };
-use nom::bytes::complete::{is_not, take_until1};
 
 /*
 Syntax as specified in yuml.me
@@ -14,12 +18,22 @@ Bidirectional   [Customer]<->[Order]
 Aggregation     [Customer]+-[Order] or [Customer]<>-[Order]
 Composition     [Customer]++-[Order]
 Inheritance     [Customer]^[Cool Customer], [Customer]^[Uncool Customer]
+Realization     [Impl]-.-^[Interface] or [Interface]^-.-[Impl]
 Dependencies    [Customer]uses-.->[PaymentStrategy]
+Association cls [Student]*-*[Course]{class:Enrollment|grade}
+Namespaces      [billing::Invoice]->[shipping::Parcel], grouped per namespace with the directive {namespaces:true}
 Cardinality     [Customer]<1-1..2>[Address]
 Labels          [Person]customer-billingAddress[Address]
 Notes           [Person]-[Address],[Address]-[note: Value Object]
+Statements      [Customer]->[Order], [Order]->[LineItem]
+Fan-out         [Gateway]->[Orders],[Payments],[Shipping]
 Full Class      [Customer|Forename;Surname;Email|Save()]
 Color splash    [Customer{bg:orange}]<>1->*[Order{bg:green}]
+Multiplicity    [Customer]++1-*>[Order] or [Customer]++1->*[Order]
+Attributes      [Cache{shape:cylinder}]
+Edge attributes [Customer]->{color:red,penwidth:2}[Order]
+Explicit id     [id=customer|Customer Aggregate|Name]->[Order], [Order]->[customer]
+Delimiters      [Matrix|rows: [[f64]]]->[note: see [1]], unbalanced ones are escaped: [Open \[]
 Comment         // Comments
 */
 
@@ -28,6 +42,7 @@ fn as_connector<'a>((arrow, label): (Option<&'a str>, Option<&'a str>)) -> Conne
         match arrow {
             "<>" | "+" => Connector::Aggregation(RelationProps { label }),
             "++" => Connector::Composition(RelationProps { label }),
+            "^" => Connector::Inheritance(RelationProps { label }),
             _ => Connector::Directional(RelationProps { label }),
         }
     } else {
@@ -35,105 +50,178 @@ fn as_connector<'a>((arrow, label): (Option<&'a str>, Option<&'a str>)) -> Conne
     }
 }
 
-pub fn parse_class<'a, 'o>(yuml: &'a str, options: &'o Options) -> IResult<&'a str, DotFile> {
+/// The arrows of a connector, `++` before `+` so a composition is never read as an aggregation
+fn arrow<'a>(direction: &'static str) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str> {
+    alt((tag("++"), tag("<>"), tag("+"), tag("^"), tag(direction)))
+}
+
+/// An arrow at the end of the label of the left connector, like the `++` of `[A]1++-[B]`, belongs to the connector
+fn split_left_arrow<'a>((arrow, label): (Option<&'a str>, Option<&'a str>)) -> (Option<&'a str>, Option<&'a str>) {
+    match (arrow, label) {
+        (None, Some(label)) => match ["++", "<>", "+", "^", "<"].iter().find(|arrow| label.ends_with(*arrow)) {
+            Some(arrow) => {
+                let (label, arrow) = label.split_at(label.len() - arrow.len());
+                (Some(arrow), Some(label).filter(|l| !l.is_empty()))
+            }
+            None => (None, Some(label)),
+        },
+        _ => (arrow, label),
+    }
+}
+
+/// Parse the body of the diagram, keeping the elements of each statement together
+pub fn parse_class_statements<'a>(yuml: &'a str, options: &Options) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
     let note_string = take_until("}");
     let note_props = delimited(tag("{"), note_string, tag("}"));
-    let note = take_until("{");
+    let note = alt((take_until("{"), rest));
     let extract_attributes = map(tuple((note, opt(note_props))), as_note);
-    let alphanumeric_string = take_until("]");
     let note = map_parser(
-        delimited(tag("[note:"), alphanumeric_string, tag("]")),
+        preceded(tag("[note:"), take_until_balanced('[', ']')),
         extract_attributes,
     );
+    let class = map(preceded(tag("["), take_until_balanced('[', ']')), Element::Class);
 
-    let alphanumeric_string = take_until("]");
-    let class = map(delimited(tag("["), alphanumeric_string, tag("]")), |lbl| {
-        Element::Class(lbl)
-    });
-
-    let right_label = is_not("<>+");
+    let right_label = || is_not("<>+^[{,\r\n");
     let left_label = take_until1("-");
-    let left_arrow = alt((tag("<>"), tag("++"), tag("<"), tag("+")));
-    let left_arrow_w_label = map(tuple((opt(left_arrow), opt(left_label))), as_connector);
-    let right_arrow = alt((tag("<>"), tag("++"), tag(">"), tag("+")));
-    let right_arrow_w_label = map(tuple((opt(right_label), opt(right_arrow))), |(lbl, arrow)| {
-        as_connector((arrow, lbl))
+    let left_arrow_w_label = map(tuple((opt(arrow("<")), opt(left_label))), |connector| {
+        as_connector(split_left_arrow(connector))
     });
+    // the label goes before or after the arrow: `1..*>` and `>1..*`
+    let right_arrow_w_label = map(
+        tuple((opt(right_label()), opt(arrow(">")), opt(right_label()))),
+        |(before, arrow, after)| as_connector((arrow, before.or(after))),
+    );
     let connection = alt((tag("-.-"), tag("-")));
+    let edge_attributes = preceded(tag("{"), take_until_unescaped('}'));
     let connector = map(
-        tuple((opt(left_arrow_w_label), connection, opt(right_arrow_w_label))),
-        |(left, con, right)| {
+        tuple((
+            opt(left_arrow_w_label),
+            connection,
+            opt(right_arrow_w_label),
+            opt(edge_attributes),
+        )),
+        |(left, con, right, attributes)| {
             let dotted = con == "-.-";
-            let left = left.unwrap_or_default();
             let right = right.unwrap_or_default();
+            // the stereotype of a dependency labels the whole edge: `[Customer]uses-.->[PaymentStrategy]`
+            let (left, label) = match left.unwrap_or_default() {
+                Connector::None(RelationProps { label }) if dotted => (Connector::default(), label),
+                left => (left, None),
+            };
             Element::Connection(Connection {
                 dashed: dotted,
                 left,
                 right,
+                label,
+                association: None,
+                attributes,
             })
         },
     );
-    let inheritance = map(tag("^"), |_| Element::Inheritance);
+    // `^-.-` is the arrow of a realization
+    let inheritance = map(terminated(tag("^"), not(tag("-"))), |_| {
+        Element::Inheritance(options.dir)
+    });
+    let association_class = map(preceded(tag("{class:"), take_until_unescaped('}')), |text: &str| {
+        Element::AssociationClass(text.trim())
+    });
 
-    let parse_element = alt((note, class, inheritance, connector));
-    let parse_line = many_till(parse_element, alt((eof, line_ending)));
-    let mut parse_lines = many_till(parse_line, eof);
+    let parse_element = alt((note, class, inheritance, association_class, connector));
+    let parse_statement = map_res(statement(parse_element), |(elements, end)| {
+        attach_association_classes(elements).map(|elements| (elements, end))
+    });
+    let mut parse_statements = many_till(parse_statement, eof);
 
-    let (rest, (lines, _)) = parse_lines(yuml)?;
-    let elements: Vec<Element> = lines
-        .into_iter()
-        .flat_map(|(elements, _le)| elements.into_iter())
-        .collect();
+    let (rest, (statements, _)) = parse_statements(yuml)?;
+    Ok((rest, as_statements(statements)))
+}
 
-    let dots = as_dots(&elements);
-    let class_file = DotFile::new(dots, options).sep(0.7);
-    Ok((rest, class_file))
+/// Move each association class onto the connection leading to the class it follows
+fn attach_association_classes(elements: Vec<Element>) -> Result<Vec<Element>, &'static str> {
+    let mut attached: Vec<Element> = Vec::with_capacity(elements.len());
+    for element in elements {
+        if let Element::AssociationClass(text) = element {
+            let follows_target = matches!(attached.last(), Some(Element::Class(_)));
+            let connection = attached.len().checked_sub(2).and_then(|idx| attached.get_mut(idx));
+            match connection {
+                Some(Element::Connection(connection)) if follows_target && connection.association.is_none() => {
+                    connection.association = Some(text)
+                }
+                _ => return Err("an association class must follow the target of a connection"),
+            }
+        } else {
+            attached.push(element);
+        }
+    }
+    Ok(attached)
 }
 
-fn as_dots(elements: &[Element]) -> Vec<DotElement> {
-    let (uids, element_details) = populate_uids(elements);
-
-    // we must collect to ensure the incoming connections are all processed, before creating the dot file
-    #[allow(clippy::needless_collect)]
-    let arrow_details: Vec<ElementDetails<Element>> = elements
-        .iter()
-        .circular_tuple_windows::<(_, _, _)>()
-        .filter(|(pre, _e, next)| !pre.is_connection() && !next.is_connection())
-        .filter_map(|(pre, e, next)| match e {
-            Element::Connection(_props) => Some((pre, e, next)),
-            Element::Inheritance => Some((pre, e, next)),
-            _ => None,
-        })
-        .filter_map(|(pre, e, next)| {
-            // if I am a connection
-            let previous_id = uids.get(pre.label()).map(|(idx, _e)| *idx).unwrap_or_default();
-            let (next_id, _next_e) = match uids.get(next.label()) {
-                Some((idx, e)) => (*idx, e),
-                None => {
-                    // arrow pointing in the void
-                    return None;
+pub struct ClassParser;
+
+impl<'a> DiagramParser<'a, Element<'a>> for ClassParser {
+    fn parse_statements(&self, yuml: &'a str, options: &Options) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+        parse_class_statements(yuml, options)
+    }
+
+    fn as_dots(&self, statements: &[Statement<Element<'a>>], options: &Options) -> Vec<DotElement> {
+        let (element_details, connection_details) = resolve_connections(statements, |_, _, _| {});
+        let namespaced = |node: DotElement, element: &Element| {
+            if options.namespaces {
+                in_namespace(node, element)
+            } else {
+                node
+            }
+        };
+        let mut uids: HashMap<String, String> = element_details
+            .iter()
+            .filter_map(|e| Some((normalize_label(e.element.label()), format!("A{}", e.id?))))
+            .collect();
+
+        // the point nodes of association classes, and the classes which are not defined elsewhere, are numbered
+        // after all other elements
+        let mut uid = element_details.len();
+        let mut classes = vec![];
+        let mut connections = vec![];
+        for e in &connection_details {
+            let edge = DotElement::from(e);
+            match e.element {
+                Element::Connection(Connection {
+                    association: Some(text),
+                    ..
+                }) => {
+                    let class = Element::Class(text);
+                    let class_uid = uids.entry(normalize_label(class.label())).or_insert_with(|| {
+                        uid += 1;
+                        let node = DotElement::new(&format!("A{}", uid), Dot::from(&class));
+                        classes.push(namespaced(node, &class));
+                        format!("A{}", uid)
+                    });
+                    uid += 1;
+                    connections.extend(split_at_association_class(edge, uid, class_uid));
                 }
-            };
+                _ => connections.push(edge),
+            }
+        }
 
-            let r = Relation { previous_id, next_id };
-            Some(ElementDetails {
-                id: None,
-                element: e,
-                relation: Some(r),
-            })
-        })
-        .collect();
-
-    element_details
-        .into_iter()
-        .chain(arrow_details.into_iter())
-        .map(|e| DotElement::from(e.borrow()))
-        .collect()
+        element_details
+            .iter()
+            .map(|e| namespaced(DotElement::from(e), e.element))
+            .chain(classes)
+            .chain(connections)
+            .collect()
+    }
+
+    fn layout(&self, file: DotFile, options: &Options) -> DotFile {
+        // horizontal charts need more room between ranks for the connector labels
+        let sep = if options.dir.is_horizontal() { 1.0 } else { 0.7 };
+        file.sep(options.ranksep.unwrap_or(sep))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::model::dot::{GraphAttributes, Layout, Ordering, Splines};
 
     #[test]
     fn test_parse_class() {
@@ -145,4 +233,334 @@ mod tests {
             panic!("Invalid file");
         }
     }
+
+    #[test]
+    fn test_parse_connectors() {
+        let none = |label| Connector::None(RelationProps { label });
+        let directional = |label| Connector::Directional(RelationProps { label });
+        let aggregation = |label| Connector::Aggregation(RelationProps { label });
+        let composition = |label| Connector::Composition(RelationProps { label });
+        let inheritance = |label| Connector::Inheritance(RelationProps { label });
+
+        for (yuml, left, right, dashed) in [
+            ("[A]-[B]", none(None), none(None), false),
+            ("[A]->[B]", none(None), directional(None), false),
+            ("[A]<->[B]", directional(None), directional(None), false),
+            ("[A]+-[B]", aggregation(None), none(None), false),
+            ("[A]<>-[B]", aggregation(None), none(None), false),
+            ("[A]-<>[B]", none(None), aggregation(None), false),
+            ("[A]++-[B]", composition(None), none(None), false),
+            ("[A]-++[B]", none(None), composition(None), false),
+            ("[A]++-*>[B]", composition(None), directional(Some("*")), false),
+            ("[A]++1-*>[B]", composition(Some("1")), directional(Some("*")), false),
+            ("[A]++1->*[B]", composition(Some("1")), directional(Some("*")), false),
+            ("[A]<>1->*[B]", aggregation(Some("1")), directional(Some("*")), false),
+            ("[A]+1-0..*[B]", aggregation(Some("1")), none(Some("0..*")), false),
+            ("[A]1++-[B]", composition(Some("1")), none(None), false),
+            ("[A]1+-[B]", aggregation(Some("1")), none(None), false),
+            ("[A]-1..*++[B]", none(None), composition(Some("1..*")), false),
+            ("[A]-++1..*[B]", none(None), composition(Some("1..*")), false),
+            (
+                "[A]<1-1..2>[B]",
+                directional(Some("1")),
+                directional(Some("1..2")),
+                false,
+            ),
+            ("[A]<uses-.-[B]", directional(Some("uses")), none(None), true),
+            ("[A]-.-^[B]", none(None), inheritance(None), true),
+            ("[A]^-.-[B]", inheritance(None), none(None), true),
+            (
+                "[A]customer-billingAddress[B]",
+                none(Some("customer")),
+                none(Some("billingAddress")),
+                false,
+            ),
+        ] {
+            let (rest, statements) = parse_class_statements(yuml, &Options::default()).expect(yuml);
+            assert!(rest.is_empty(), "{}", yuml);
+            let expected = Element::Connection(Connection {
+                left,
+                right,
+                dashed,
+                label: None,
+                association: None,
+                attributes: None,
+            });
+            assert_eq!(statements[0].elements[1], expected, "{}", yuml);
+        }
+    }
+
+    #[test]
+    fn test_parse_dependency() {
+        let (_, statements) = parse_class_statements("[A]uses-.->[B]", &Options::default()).unwrap();
+        let expected = Element::Connection(Connection {
+            left: Connector::None(RelationProps { label: None }),
+            right: Connector::Directional(RelationProps { label: None }),
+            dashed: true,
+            label: Some("uses"),
+            association: None,
+            attributes: None,
+        });
+        assert_eq!(statements[0].elements[1], expected);
+
+        let dot = parse_yuml("// {type:class}\n[A]uses-.->[B]").unwrap().1.to_string();
+        assert!(dot.contains(r#"A1 -> A2 [shape="edge", label="uses", style="dashed", dir="both", arrowtail="none", arrowhead="vee", labeldistance=2, fontsize=10]"#));
+    }
+
+    #[test]
+    fn test_parse_realization() {
+        let dot = parse_yuml("// {type:class}\n[Impl]-.-^[Interface]\n[Interface]^-.-[Other]")
+            .unwrap()
+            .1
+            .to_string();
+        assert!(dot
+            .contains(r#"A1 -> A2 [shape="edge", style="dashed", dir="both", arrowtail="none", arrowhead="empty", "#));
+        assert!(dot
+            .contains(r#"A2 -> A3 [shape="edge", style="dashed", dir="both", arrowtail="empty", arrowhead="none", "#));
+
+        // plain inheritance is not affected
+        let dot = parse_yuml("// {type:class}\n[A]^[B]").unwrap().1.to_string();
+        assert!(dot.contains(r#"A1:s -> A2:n [shape="edge", style="solid", dir="both", arrowtail="empty", "#));
+    }
+
+    #[test]
+    fn test_parse_association_class() {
+        let yuml = "// {type:class}\n[Student]1-*>[Course]{class:Enrollment|grade}";
+        let dot = parse_yuml(yuml).unwrap().1.to_string();
+        for part in [
+            r#"A3 [shape="rectangle", label=<<TABLE"#,
+            r#"A4 [shape="point", label="""#,
+            r#"A1 -> A4 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="none", taillabel="1", "#,
+            r#"A4 -> A2 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", headlabel="*", "#,
+            r#"A4 -> A3 [shape="edge", style="dashed", dir="none", "#,
+        ] {
+            assert!(dot.contains(part), "missing {}", part);
+        }
+        assert!(!dot.contains("A1 -> A2"));
+
+        // classes defined elsewhere are linked, instead of drawn twice
+        let yuml = "// {type:class}\n[Enrollment]->[Grade]\n[Student]-[Course]{class:Enrollment}";
+        let dot = parse_yuml(yuml).unwrap().1.to_string();
+        assert!(dot.contains(r#"A5 -> A1 [shape="edge", style="dashed""#));
+        assert!(!dot.contains("A6"));
+
+        assert!(parse_yuml("// {type:class}\n{class:Enrollment}[Student]").is_err());
+        assert!(parse_yuml("// {type:class}\n[Student]{class:Enrollment}").is_err());
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let yuml = "// {type:class}\n// {namespaces:true}\n[billing::Invoice|number]->[shipping::Parcel]\n[billing::Payment]->[billing::Invoice]\n[shipping::Invoice]\n[Customer]";
+        let dot = parse_yuml(yuml).unwrap().1.to_string();
+        assert!(dot.contains(r#"A1 [shape="rectangle", label=<<TABLE BORDER="0" CELLBORDER="1" CELLSPACING="0" CELLPADDING="9"><TR><TD>Invoice</TD></TR>"#));
+        assert!(dot.contains(r#"A2 [shape="rectangle", margin="0.20,0.05", label="Parcel""#));
+        assert!(dot.contains(r#"A5 [shape="rectangle", margin="0.20,0.05", label="Customer""#));
+        assert!(dot.contains("    subgraph cluster_1 { label=billing; A1; A3; }\n"));
+        assert!(dot.contains("    subgraph cluster_2 { label=shipping; A2; A4; }\n"));
+        assert!(!dot.contains("cluster_3"));
+
+        // the namespace is part of the label, unless the directive is set
+        let dot = parse_yuml("// {type:class}\n[billing::Invoice]").unwrap().1.to_string();
+        assert!(dot.contains(r#"label="billing::Invoice""#));
+        assert!(!dot.contains("subgraph"));
+    }
+
+    #[test]
+    fn test_class_ranksep() {
+        let parse_class_file = |yuml: &str| {
+            if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+                class_file.to_string()
+            } else {
+                panic!("Invalid file");
+            }
+        };
+
+        assert!(parse_class_file("// {type:class}\n[A]").contains("ranksep = 0.7"));
+        assert!(parse_class_file("// {type:class}\n// {ranksep:1.0}\n[A]").contains("ranksep = 1\n"));
+    }
+
+    #[test]
+    fn test_class_uid_prefix() {
+        let yuml = "// {type:class}\n// {samerank: [A],[B]}\n[A]^[B]\n[B]->[C]";
+        let result = parse_yuml(yuml).expect("invalid file").1.uid_prefix("d1_").to_string();
+        assert!(result.contains("    d1_A1 [shape="));
+        assert!(result.contains("    d1_A1:s -> d1_A2:n ["));
+        assert!(result.contains("    d1_A2 -> d1_A3 ["));
+        assert!(result.contains("{ rank=same; d1_A1; d1_A2; }"));
+
+        let result = parse_yuml(yuml)
+            .expect("invalid file")
+            .1
+            .uid_prefix("diagram-1.")
+            .to_string();
+        assert!(result.contains(r#"    "diagram-1.A1":s -> "diagram-1.A2":n ["#));
+    }
+
+    #[test]
+    fn test_class_fan_out() {
+        let yuml = "// {type:class}\n[Gateway]uses->[A],[B], [C]\n[D]";
+        if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            let result = class_file.to_string();
+            for edge in ["A1 -> A2 ", "A1 -> A3 ", "A1 -> A4 "] {
+                assert!(result.contains(edge), "missing {}", edge);
+            }
+            assert!(result.matches(r#"taillabel="uses""#).count() == 3);
+            assert!(!result.contains("-> A5"));
+        } else {
+            panic!("Invalid file");
+        }
+    }
+
+    #[test]
+    fn test_class_explicit_id() {
+        let yuml = "// {type:class}\n[customer]->[Order]\n[id=customer|Customer Aggregate{bg:red}]++-[Order]";
+        if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            let result = class_file.to_string();
+            assert!(result.contains(r#"A1 [shape="rectangle", margin="0.20,0.05", label="Customer Aggregate", style="filled", fillcolor="red", "#));
+            assert!(result.contains("A1 -> A2 "));
+            assert!(!result.contains("A3"));
+        } else {
+            panic!("Invalid file");
+        }
+    }
+
+    #[test]
+    fn test_class_rank_constraints() {
+        let parse_class_file = |yuml: &str| {
+            if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+                class_file.to_string()
+            } else {
+                panic!("Invalid file");
+            }
+        };
+
+        let yuml =
+            "// {type:class}\n// {samerank: [B],[C|name],[Unknown]}\n[A{rank:min}]->[B]\n[A]->[C|name]\n[D{rank:max}]";
+        let result = parse_class_file(yuml);
+        assert!(result.ends_with("    { rank=min; A1; }\n    { rank=max; A4; }\n    { rank=same; A2; A3; }\n}"));
+        assert!(!result.contains("rank:"));
+
+        // a single node does not constrain the layout
+        assert!(!parse_class_file("// {type:class}\n// {samerank: [A]}\n[A]->[B]").contains("rank="));
+    }
+
+    #[test]
+    fn test_class_graph_attributes() {
+        let yuml =
+            "// {type:class}\n// {splines:ortho}\n// {nodesep:0.8}\n// {concentrate:true}\n// {ordering:out}\n// {layout:fdp}\n// {seed:42}\n[A]->[B]";
+        if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            let expected = GraphAttributes {
+                splines: Some(Splines::Ortho),
+                nodesep: Some(0.8),
+                concentrate: true,
+                ordering: Some(Ordering::Out),
+                layout: Some(Layout::Fdp),
+                seed: Some(42),
+                extra: vec![],
+            };
+            assert_eq!(class_file.graph_attributes(), &expected);
+            assert!(class_file.to_string().contains(
+                "rankdir = TB\n    splines = ortho\n    nodesep = 0.8\n    concentrate = true\n    ordering = out\n    layout = fdp\n    start = 42\n"
+            ));
+
+            let result = class_file.with_graph_attributes(GraphAttributes::default()).to_string();
+            assert!(!result.contains("splines"));
+        } else {
+            panic!("Invalid file");
+        }
+
+        // invalid values keep the graphviz defaults
+        let yuml = "// {type:class}\n// {splines:wobbly}\n[A]->[B]";
+        if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            assert_eq!(class_file.graph_attributes().splines, None);
+        }
+    }
+
+    #[test]
+    fn test_class_inheritance_direction() {
+        let parse_class_file = |yuml: &str| {
+            if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+                class_file.to_string()
+            } else {
+                panic!("Invalid file");
+            }
+        };
+
+        const EDGE: &str =
+            r#"[shape="edge", style="solid", dir="both", arrowtail="empty", arrowhead="none", fontsize=10]"#;
+        let result = parse_class_file("// {type:class}\n[A]^[B]");
+        assert!(result.contains(&format!("A1:s -> A2:n {}", EDGE)));
+        assert!(result.contains("ranksep = 0.7"));
+
+        let result = parse_class_file("// {type:class}\n// {direction:leftToRight}\n[A]^[B]");
+        assert!(result.contains(&format!("A1:e -> A2:w {}", EDGE)));
+        assert!(result.contains("ranksep = 1\n"));
+
+        let result = parse_class_file("// {type:class}\n// {direction:rightToLeft}\n[A]^[B]");
+        assert!(result.contains(&format!("A1:w -> A2:e {}", EDGE)));
+    }
+
+    #[test]
+    fn test_class_comma_separated_statements() {
+        let yuml = "// {type:class}\n[Person]-[Address],[Address]-[note: Value Object]";
+        if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            let result = class_file.to_string();
+            assert!(result.contains(r#"A1 [shape="rectangle", margin="0.20,0.05", label="Person", "#));
+            assert!(result.contains(r#"A2 [shape="rectangle", margin="0.20,0.05", label="Address", "#));
+            assert!(result.contains(r#"A3 [shape="note", margin="0.20,0.05", label=" Value Object", "#));
+            assert!(result.contains("A1 -> A2 "));
+            assert!(result.contains("A2 -> A3 "));
+        } else {
+            panic!("Invalid file");
+        }
+    }
+
+    #[test]
+    fn test_class_labels_with_delimiters() {
+        let yuml = r"// {type:class}
+[Matrix|rows: [[f64]]]->[note: see [1]]
+[Matrix]->[Open \[]";
+        if let (rest, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            assert!(rest.is_empty());
+            let result = class_file.to_string();
+            assert!(result.contains("<TD>rows: [[f64]]</TD>"));
+            assert!(result.contains(r#"label=" see [1]""#));
+            assert!(result.contains(r#"label="Open \[""#));
+            assert!(result.contains("A1 -> A3 ") && !result.contains("A4"));
+        } else {
+            panic!("Invalid file");
+        }
+    }
+
+    #[test]
+    fn test_class_connection_with_attributes() {
+        let yuml = "// {type:class}\n[a]->{color:red,penwidth:2}[b]\n[b]<>1-*{style:dashed}[c]";
+        if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            let dot = class_file.to_string();
+            assert!(dot.contains(r#"A1 -> A2 [shape="edge", style="solid", dir="both", arrowtail="none", arrowhead="vee", labeldistance=2, fontsize=10, color="red", penwidth="2"]"#));
+            assert!(dot.contains(r#"A2 -> A3 [shape="edge", style="dashed", dir="both", arrowtail="odiamond", arrowhead="none", taillabel="1", headlabel="*", labeldistance=2, fontsize=10]"#));
+        } else {
+            panic!("Invalid file");
+        }
+    }
+
+    #[test]
+    fn test_class_with_literal_markers() {
+        let yuml = "// {type:class}\n[Foo|__init__();**kwargs|snake__case]";
+        let dot = parse_yuml(yuml).expect("invalid file").1.to_string();
+        assert!(dot.contains("<TR><TD>__init__()<BR/>**kwargs</TD></TR><TR><TD>snake__case</TD></TR>"));
+    }
+
+    #[test]
+    fn test_class_with_attributes() {
+        let yuml = "// {type:class}\n[Cache{shape:cylinder,bg:orange}]->[Customer|name{bg:green}]\n[Cache]->[Customer]";
+        if let (_, ParsedYuml::Class(class_file)) = parse_yuml(yuml).expect("invalid file") {
+            let result = class_file.to_string();
+            assert!(result.contains(r#"A1 [shape="rectangle", margin="0.20,0.05", label="Cache", style="filled", fillcolor="orange", arrowtail="none", arrowhead="none", height=0.5, fontsize=10, shape="cylinder"]"#));
+            assert!(result.contains(r#"<TR><TD>name</TD></TR></TABLE>>, style="filled", fillcolor="green", "#));
+            assert!(!result.contains("A3"));
+        } else {
+            panic!("Invalid file");
+        }
+    }
 }
@@ -0,0 +1,125 @@
+use super::utils::{resolve_connections, take_until_balanced};
+use super::*;
+use crate::model::er::{Cardinality, Element, RelationshipProps};
+use nom::bytes::complete::is_not;
+
+/*
+Entity-relationship diagram, with crow's foot notation
+Entity          [customer]
+Attributes      [customer|id PK;name;email]
+Relationship    [customer]1--*[order]
+Cardinality     1 exactly one, 0..1 or ? zero or one, 1..* or + one or many, 0..* or * zero or many
+Label           [customer]1-places-*[order]
+Color splash    [customer{bg:wheat}]
+Statements      [customer]1--*[order], [order]1--1..*[line item]
+Comment         // Comments
+*/
+
+fn as_relationship<'a>((left, label, right): (Option<&'a str>, Option<&'a str>, Option<&'a str>)) -> Element<'a> {
+    let cardinality = |c: Option<&str>| c.and_then(|c| Cardinality::try_from(c).ok());
+    Element::Relationship(RelationshipProps {
+        left: cardinality(left),
+        right: cardinality(right),
+        label: label.map(str::trim).filter(|label| !label.is_empty()),
+    })
+}
+
+/// Parse the body of the diagram, keeping the elements of each statement together
+pub fn parse_er_statements<'a>(yuml: &'a str, _options: &Options) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+    let entity = map(preceded(tag("["), take_until_balanced('[', ']')), Element::Entity);
+
+    let cardinality = || {
+        alt((
+            tag("0..1"),
+            tag("1..*"),
+            tag("0..*"),
+            tag("1"),
+            tag("?"),
+            tag("+"),
+            tag("*"),
+        ))
+    };
+    let label = alt((
+        map(tag("-"), |_| None),
+        map(terminated(is_not("-[],\r\n"), tag("-")), Some),
+    ));
+    let relationship = map(
+        tuple((opt(cardinality()), preceded(tag("-"), label), opt(cardinality()))),
+        as_relationship,
+    );
+
+    let parse_element = alt((entity, relationship));
+    let parse_statement = statement(parse_element);
+    let mut parse_statements = many_till(parse_statement, eof);
+
+    let (rest, (statements, _)) = parse_statements(yuml)?;
+    Ok((rest, as_statements(statements)))
+}
+
+pub struct ErParser;
+
+impl<'a> DiagramParser<'a, Element<'a>> for ErParser {
+    fn parse_statements(&self, yuml: &'a str, options: &Options) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+        parse_er_statements(yuml, options)
+    }
+
+    fn as_dots(&self, statements: &[Statement<Element<'a>>], _options: &Options) -> Vec<DotElement> {
+        let (element_details, relationship_details) = resolve_connections(statements, |_, _, _| {});
+        element_details
+            .iter()
+            .chain(relationship_details.iter())
+            .map(DotElement::from)
+            .collect()
+    }
+
+    fn layout(&self, file: DotFile, options: &Options) -> DotFile {
+        // room for the labels of the relationships between the ranks
+        file.sep(options.ranksep.unwrap_or(0.7))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yuml: &str) -> String {
+        match parse_yuml(yuml).expect("invalid file") {
+            (rest, ParsedYuml::Er(er_file)) => {
+                assert!(rest.is_empty());
+                er_file.to_string()
+            }
+            _ => panic!("Invalid file"),
+        }
+    }
+
+    #[test]
+    fn test_parse_er() {
+        let yuml = "// {type:er}\n[customer|id PK;name{bg:wheat}]1-places-*[order]\n[order]1--1..*[line item], [order]?--[invoice]";
+        let result = parse(yuml);
+        assert!(result.contains(
+            r#"<TR><TD>customer</TD></TR><TR><TD>id PK<BR/>name</TD></TR></TABLE>>, style="filled", fillcolor="wheat""#
+        ));
+        assert!(result.contains(r#"A1 -> A2 [shape="edge", label="places", style="solid", dir="both", arrowtail="teetee", arrowhead="crowodot", "#));
+        assert!(result.contains(
+            r#"A2 -> A3 [shape="edge", style="solid", dir="both", arrowtail="teetee", arrowhead="crowtee", "#
+        ));
+        assert!(result
+            .contains(r#"A2 -> A4 [shape="edge", style="solid", dir="both", arrowtail="teeodot", arrowhead="none", "#));
+    }
+
+    #[test]
+    fn test_parse_er_cardinalities() {
+        for (cardinality, arrow) in [
+            ("1", "teetee"),
+            ("0..1", "teeodot"),
+            ("?", "teeodot"),
+            ("1..*", "crowtee"),
+            ("+", "crowtee"),
+            ("0..*", "crowodot"),
+            ("*", "crowodot"),
+        ] {
+            let result = parse(&format!("// {{type:er}}\n[a]--{}[b]", cardinality));
+            assert!(result.contains(&format!(r#"arrowhead="{}""#, arrow)), "{}", cardinality);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use super::*;
+use crate::model::mindmap::{Element, TopicProps};
+use nom::character::complete::{line_ending, space0};
+
+/*
+Mind map, laid out radially around the central topic
+Topic           Coffee
+Subtopic        indent a topic below its parent, with spaces or tabs
+Color splash    Beans{bg:wheat}
+Tree            Coffee
+                  Beans
+                    Arabica
+                  Brewing
+*/
+
+/// Tabs indent as far as four spaces
+fn indentation(indent: &str) -> usize {
+    indent.chars().map(|c| if c == '\t' { 4 } else { 1 }).sum()
+}
+
+/// Nest the topics by their indentation; blank lines and directives are skipped
+fn as_topics<'a>(lines: Vec<(&'a str, &'a str)>) -> Vec<Statement<Element<'a>>> {
+    let mut statements: Vec<Statement<Element<'a>>> = vec![];
+    // indentation and index of the topics enclosing the current line
+    let mut ancestors: Vec<(usize, usize)> = vec![];
+    let mut branches = 0;
+
+    for (line, (indent, text)) in lines.into_iter().enumerate() {
+        let text = text.trim_end();
+        if text.is_empty() || is_directive(text) {
+            continue;
+        }
+
+        let indent = indentation(indent);
+        while matches!(ancestors.last(), Some((parent_indent, _)) if *parent_indent >= indent) {
+            ancestors.pop();
+        }
+
+        let parent = ancestors.last().map(|(_, index)| *index);
+        let branch = match (ancestors.len(), parent) {
+            (0, _) => None,
+            (1, _) => {
+                branches += 1;
+                Some(branches - 1)
+            }
+            (_, Some(parent)) => match &statements[parent].elements[..] {
+                [Element::Topic(props)] => props.branch,
+                _ => None,
+            },
+            (_, None) => None,
+        };
+
+        let (label, attributes) = split_attributes(text);
+        let topic = Element::Topic(TopicProps {
+            label: label.trim(),
+            attributes,
+            depth: ancestors.len(),
+            parent,
+            branch,
+        });
+
+        ancestors.push((indent, statements.len()));
+        statements.push(Statement {
+            line,
+            elements: vec![topic],
+        });
+    }
+
+    statements
+}
+
+/// Parse the body of the diagram, one topic per line
+pub fn parse_mindmap_statements<'a>(
+    yuml: &'a str,
+    _options: &Options,
+) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+    let parse_line = terminated(tuple((space0, not_line_ending)), alt((line_ending, eof)));
+    let mut parse_lines = many_till(parse_line, eof);
+
+    let (rest, (lines, _)) = parse_lines(yuml)?;
+    Ok((rest, as_topics(lines)))
+}
+
+pub struct MindmapParser;
+
+impl<'a> DiagramParser<'a, Element<'a>> for MindmapParser {
+    fn parse_statements(&self, yuml: &'a str, options: &Options) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+        parse_mindmap_statements(yuml, options)
+    }
+
+    fn as_dots(&self, statements: &[Statement<Element<'a>>], _options: &Options) -> Vec<DotElement> {
+        statements
+            .iter()
+            .enumerate()
+            .flat_map(|(index, statement)| match &statement.elements[..] {
+                [Element::Topic(props)] => props.as_dots(index),
+                _ => vec![],
+            })
+            .collect()
+    }
+
+    fn layout(&self, file: DotFile, _options: &Options) -> DotFile {
+        // twopi places the topics in circles around the center, unless another layout is chosen
+        let mut graph = file.graph_attributes().clone();
+        graph.layout = graph.layout.or(Some(Layout::Twopi));
+        file.with_graph_attributes(graph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yuml: &str) -> String {
+        match parse_yuml(yuml).expect("invalid file") {
+            (rest, ParsedYuml::Mindmap(mindmap_file)) => {
+                assert!(rest.is_empty());
+                mindmap_file.to_string()
+            }
+            _ => panic!("Invalid file"),
+        }
+    }
+
+    #[test]
+    fn test_parse_mindmap() {
+        let yuml = "// {type:mindmap}\nCoffee\n  Beans{bg:wheat}\n    Arabica\n\n    Robusta\n  Brewing\n\tEspresso\n";
+        let result = parse(yuml);
+        assert!(result.contains("layout = twopi"));
+        assert!(result.contains(r#"A1 [shape="rectangle", margin="0.20,0.05", label="Coffee", style="rounded", "#));
+        assert!(result.contains(r#"label="Beans", style="rounded,filled", fillcolor="wheat", "#));
+        assert!(result.contains(r#"A1 -> A2 [shape="edge", style="solid", dir="none", "#));
+        assert!(result.contains("A2 -> A3 ") && result.contains("A2 -> A4 "));
+        assert!(result.contains("A1 -> A5 ") && result.contains("A5 -> A6 "));
+
+        // the topics of a branch share its color
+        let colors: Vec<&str> = result
+            .lines()
+            .filter(|line| line.contains("->"))
+            .map(|line| &line[line.find("color=").unwrap()..])
+            .collect();
+        assert_eq!(colors[0], colors[1]);
+        assert_eq!(colors[1], colors[2]);
+        assert_ne!(colors[2], colors[3]);
+        assert_eq!(colors[3], colors[4]);
+    }
+
+    #[test]
+    fn test_mindmap_layout_directive() {
+        let result = parse("// {type:mindmap}\n// {layout:circo}\nCoffee\n  Beans");
+        assert!(result.contains("layout = circo") && !result.contains("twopi"));
+
+        let result = parse("// {type:mindmap}\nCoffee\n  Beans\n// {layout:circo}");
+        assert!(result.contains("layout = circo") && !result.contains("label=\"// {layout:circo}\""));
+    }
+}
@@ -1,30 +1,160 @@
-use self::{activity::parse_activity, class::parse_class};
-use crate::model::dot::{ChartType, Directions, DotElement, DotFile, Options};
+use self::{
+    activity::ActivityParser, class::ClassParser, er::ErParser, mindmap::MindmapParser, timeline::TimelineParser,
+    utils::same_ranks,
+};
+use crate::model::{
+    attributes::{parse_graph_attributes, split_attributes},
+    dot::{ChartType, Directions, DotElement, DotFile, Layout, Options, Ordering, Splines},
+    shared::LabeledElement,
+    theme::Theme,
+};
+use crate::{
+    error::{YumlError, YumlResult},
+    validate::{
+        conflicting_definitions, dangling_arrows, invalid_graph_attributes, line_of, unknown_directives,
+        unknown_element_colors, Warning,
+    },
+};
 use itertools::Itertools;
 use nom::{
     branch::alt,
     bytes::complete::{tag, take_until},
     character::{
-        complete::{alphanumeric0, newline},
+        complete::{alphanumeric0, not_line_ending, space0},
         streaming::line_ending,
     },
-    combinator::{eof, map, map_parser, map_res, opt, rest},
+    combinator::{eof, map, map_parser, map_res, opt, recognize, rest, verify},
     multi::{many0, many_till},
     sequence::{delimited, preceded, separated_pair, terminated, tuple},
     IResult,
 };
-use std::{borrow::Borrow, collections::HashMap};
+use std::collections::HashMap;
 
-mod activity;
-mod class;
+pub mod activity;
+pub mod class;
+pub mod er;
+pub mod mindmap;
+pub mod timeline;
 pub mod utils;
 
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParsedYuml {
     Activity(DotFile),
     Class(DotFile),
+    Timeline(DotFile),
+    Er(DotFile),
+    Mindmap(DotFile),
     Unsupported,
 }
 
+/// A sequence of connected elements, terminated by a line ending or a comma
+#[derive(Debug, Clone, PartialEq)]
+pub struct Statement<E> {
+    /// 0-based line within the diagram body where the statement is written
+    pub line: usize,
+    pub elements: Vec<E>,
+}
+
+/// A chart type with elements `E`: its statements are parsed with nom, after which the nodes and connections are
+/// resolved by the shared machinery in `utils`
+pub trait DiagramParser<'a, E: LabeledElement + 'a> {
+    /// Parse the body of the diagram, keeping the elements of each statement together
+    fn parse_statements(&self, yuml: &'a str, options: &Options) -> IResult<&'a str, Vec<Statement<E>>>;
+
+    /// The nodes followed by the connections between them
+    fn as_dots(&self, statements: &[Statement<E>], options: &Options) -> Vec<DotElement>;
+
+    /// Chart specific settings of the dot file
+    fn layout(&self, file: DotFile, _options: &Options) -> DotFile {
+        file
+    }
+
+    /// Lay out the statements as a dot file
+    fn as_file(&self, statements: &[Statement<E>], options: &Options) -> DotFile {
+        let file = same_ranks(statements.iter().flat_map(|s| s.elements.iter()), options)
+            .into_iter()
+            .fold(
+                DotFile::new(self.as_dots(statements, options), options),
+                DotFile::same_rank,
+            );
+        self.layout(file, options)
+    }
+
+    /// Parse the body of the diagram as a dot file; `offset` is the number of lines before the body, to report
+    /// warnings with the lines of the whole document
+    fn parse(&self, yuml: &'a str, options: &Options, offset: usize) -> IResult<&'a str, DotFile> {
+        let (rest, statements) = self.parse_statements(yuml, options)?;
+        let file = self.as_file(&statements, options).with_warnings(
+            [
+                dangling_arrows(&statements, offset),
+                unknown_element_colors(&statements, offset),
+                conflicting_definitions(&statements, offset),
+            ]
+            .concat(),
+        );
+        Ok((rest, file))
+    }
+}
+
+/// Statements end at a line ending, which may be preceded by trailing whitespace, or at a comma to write multiple
+/// statements on one line. Directives between the statements are skipped, they are collected by `parse_headers`.
+pub fn statement_end(yuml: &str) -> IResult<&str, &str> {
+    let line_end = recognize(preceded(space0, alt((eof, line_ending))));
+    let comma = recognize(tuple((space0, tag(","), space0)));
+    let directive = recognize(terminated(parse_directive, alt((eof, line_ending))));
+    alt((line_end, comma, directive))(yuml)
+}
+
+/// The elements of a statement up to its end; statements may be indented
+pub fn statement<'a, E>(
+    element: impl FnMut(&'a str) -> IResult<&'a str, E>,
+) -> impl FnMut(&'a str) -> IResult<&'a str, (Vec<E>, &'a str)> {
+    preceded(space0, many_till(element, statement_end))
+}
+
+/// Whether the line consists of a directive, like `// {direction:leftToRight}`
+pub fn is_directive(line: &str) -> bool {
+    parse_directive(line).is_ok_and(|(rest, _)| rest.trim().is_empty())
+}
+
+/// Number the statements by line, expanding fan-outs like `[Gateway]->[A],[B],[C]`: a lone element after a statement
+/// ending in a connection, on the same line, is connected from the same source
+pub fn as_statements<E: LabeledElement + Clone>(parsed: Vec<(Vec<E>, &str)>) -> Vec<Statement<E>> {
+    let mut line = 0;
+    let mut fan_out: Option<(E, E)> = None;
+    parsed
+        .into_iter()
+        .map(|(mut elements, end)| {
+            if let (Some((source, connection)), [target]) = (&fan_out, elements.as_slice()) {
+                if !target.is_connection() {
+                    elements.insert(0, connection.clone());
+                    elements.insert(0, source.clone());
+                }
+            }
+
+            fan_out = match elements.as_slice() {
+                [.., source, connection, target]
+                    if end.trim() == ","
+                        && connection.is_connection()
+                        && !source.is_connection()
+                        && !target.is_connection() =>
+                {
+                    Some((source.clone(), connection.clone()))
+                }
+                _ => None,
+            };
+
+            let statement = Statement { line, elements };
+            if end.ends_with('\n') {
+                line += 1;
+            }
+            statement
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Header<'a> {
     pub key: &'a str,
     pub value: &'a str,
@@ -34,22 +164,182 @@ fn as_header<'a>(kv: (&'a str, &'a str)) -> Header<'a> {
     Header { key: kv.0, value: kv.1 }
 }
 
+/// Written as a directive line, quoting values whose surrounding whitespace must be kept
+impl std::fmt::Display for Header<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.value != self.value.trim() {
+            write!(f, "// {{{}:\"{}\"}}", self.key, self.value)
+        } else {
+            write!(f, "// {{{}:{}}}", self.key, self.value)
+        }
+    }
+}
+
 impl std::fmt::Display for ParsedYuml {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            ParsedYuml::Activity(df) | ParsedYuml::Class(df) => df.fmt(f),
+            ParsedYuml::Activity(df)
+            | ParsedYuml::Class(df)
+            | ParsedYuml::Timeline(df)
+            | ParsedYuml::Er(df)
+            | ParsedYuml::Mindmap(df) => df.fmt(f),
             ParsedYuml::Unsupported => f.write_str(""),
         }
     }
 }
 
-fn determine_file_options(headers: &[Header]) -> Options {
+impl ParsedYuml {
+    /// Replace the dot file of a supported diagram
+    fn map_dot_file(self, f: impl FnOnce(DotFile) -> DotFile) -> Self {
+        match self {
+            ParsedYuml::Activity(df) => ParsedYuml::Activity(f(df)),
+            ParsedYuml::Class(df) => ParsedYuml::Class(f(df)),
+            ParsedYuml::Timeline(df) => ParsedYuml::Timeline(f(df)),
+            ParsedYuml::Er(df) => ParsedYuml::Er(f(df)),
+            ParsedYuml::Mindmap(df) => ParsedYuml::Mindmap(f(df)),
+            ParsedYuml::Unsupported => ParsedYuml::Unsupported,
+        }
+    }
+
+    /// Replace the theme selected by the `theme` directive
+    pub fn theme(self, theme: Theme) -> Self {
+        self.map_dot_file(|df| df.theme(theme))
+    }
+
+    /// Replace the page color of the theme and the `bgcolor` directive, see `DotFile::background`
+    pub fn background(self, color: &str) -> Self {
+        self.map_dot_file(|df| df.background(color))
+    }
+
+    /// Tint the outlines and edges with the accent color of `key`, like an environment, see `DotFile::accent`
+    pub fn accent(self, key: &str) -> Self {
+        self.map_dot_file(|df| df.accent(key))
+    }
+
+    /// Prepend `prefix` to the ids of all nodes, see `DotFile::with_uid_prefix`
+    pub fn uid_prefix(self, prefix: &str) -> Self {
+        self.map_dot_file(|df| df.with_uid_prefix(prefix))
+    }
+
+    /// Update the nodes of the dot file, see `DotFile::map_nodes`
+    pub(crate) fn map_nodes(self, update: impl FnMut(&mut DotElement)) -> Self {
+        self.map_dot_file(|df| df.map_nodes(update))
+    }
+
+    /// The title of the `title` directive, like `Checkout Flow` for `// {title:Checkout Flow}`
+    pub fn title(&self) -> Option<&str> {
+        self.dot_file().and_then(DotFile::title)
+    }
+
+    /// The dot file of the diagram, `None` for unsupported chart types
+    pub fn dot_file(&self) -> Option<&DotFile> {
+        match self {
+            ParsedYuml::Activity(df)
+            | ParsedYuml::Class(df)
+            | ParsedYuml::Timeline(df)
+            | ParsedYuml::Er(df)
+            | ParsedYuml::Mindmap(df) => Some(df),
+            ParsedYuml::Unsupported => None,
+        }
+    }
+
+    /// The dot file of the diagram, to add nodes and edges before rendering; `None` for unsupported chart types
+    pub fn dot_file_mut(&mut self) -> Option<&mut DotFile> {
+        match self {
+            ParsedYuml::Activity(df)
+            | ParsedYuml::Class(df)
+            | ParsedYuml::Timeline(df)
+            | ParsedYuml::Er(df)
+            | ParsedYuml::Mindmap(df) => Some(df),
+            ParsedYuml::Unsupported => None,
+        }
+    }
+
+    /// The type of the diagram, `None` for unsupported chart types
+    pub fn chart_type(&self) -> Option<ChartType> {
+        match self {
+            ParsedYuml::Activity(_) => Some(ChartType::Activity),
+            ParsedYuml::Class(_) => Some(ChartType::Class),
+            ParsedYuml::Timeline(_) => Some(ChartType::Timeline),
+            ParsedYuml::Er(_) => Some(ChartType::Er),
+            ParsedYuml::Mindmap(_) => Some(ChartType::Mindmap),
+            ParsedYuml::Unsupported => None,
+        }
+    }
+
+    /// The direction of the `direction` directive, `None` for unsupported chart types
+    pub fn direction(&self) -> Option<Directions> {
+        self.dot_file().map(DotFile::direction)
+    }
+
+    /// The number of nodes, including notes
+    pub fn node_count(&self) -> usize {
+        self.dot_file().map_or(0, |df| df.nodes().count())
+    }
+
+    /// The number of edges between the nodes
+    pub fn edge_count(&self) -> usize {
+        self.dot_file().map_or(0, |df| df.edges().count())
+    }
+
+    /// Issues found while parsing, like arrows which are left out as they do not point to anything
+    pub fn warnings(&self) -> &[Warning] {
+        self.dot_file().map_or(&[], DotFile::warnings)
+    }
+
+    fn prepend_warnings(self, warnings: Vec<Warning>) -> Self {
+        self.map_dot_file(|df| {
+            let warnings = [warnings, df.warnings().to_vec()].concat();
+            df.with_warnings(warnings)
+        })
+    }
+
+    /// Fail when parsing resulted in warnings, instead of rendering an incomplete diagram
+    pub fn strict(self) -> YumlResult<Self> {
+        match self.warnings() {
+            [] => Ok(self),
+            warnings => Err(YumlError::Lint(warnings.iter().map(|w| w.to_string()).join("; "))),
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> crate::error::YumlResult<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+pub fn determine_file_options(headers: &[Header]) -> Options {
     let mut options = Options::default();
 
     for h in headers.iter() {
         match h.key {
             "type" => options.chart_type = ChartType::try_from(h.value).ok(),
             "direction" => options.dir = Directions::try_from(h.value).unwrap_or_default(),
+            "font" => options.font = Some(h.value.trim().to_string()),
+            "ranksep" => options.ranksep = h.value.trim().parse().ok(),
+            "bgcolor" | "bg" => options.bgcolor = Some(h.value.trim().to_string()),
+            "title" => options.title = Some(h.value.trim().to_string()).filter(|title| !title.is_empty()),
+            "margin" => options.margin = h.value.trim().parse().ok().filter(|margin| *margin >= 0.0),
+            "scale" => options.scale = h.value.trim().parse().ok().filter(|scale| *scale > 0),
+            "dpi" => options.dpi = h.value.trim().parse().ok().filter(|dpi| *dpi > 0),
+            "theme" => options.theme = Theme::try_from(h.value.trim()).unwrap_or_default(),
+            "samerank" => options.same_ranks.push(parse_rank_labels(h.value)),
+            "splines" => options.graph.splines = Splines::try_from(h.value.trim()).ok(),
+            "nodesep" => options.graph.nodesep = h.value.trim().parse().ok().filter(|sep| *sep >= 0.0),
+            "concentrate" => options.graph.concentrate = h.value.trim() == "true",
+            "ordering" => options.graph.ordering = Ordering::try_from(h.value.trim()).ok(),
+            "layout" => options.graph.layout = Layout::try_from(h.value.trim()).ok(),
+            "seed" => options.graph.seed = h.value.trim().parse().ok(),
+            "strict" => options.strict = h.value.trim() == "true",
+            "namespaces" => options.namespaces = h.value.trim() == "true",
+            "env" => options.env = Some(h.value.trim().to_string()).filter(|env| !env.is_empty()),
+            "graphviz" => {
+                // later directives override earlier ones
+                for (key, value) in parse_graph_attributes(h.value).into_iter().flatten() {
+                    options.graph.extra.retain(|(existing, _)| existing != key);
+                    options.graph.extra.push((key.to_string(), value.to_string()));
+                }
+            }
             _ => { /* ignore unsupported headers */ }
         }
     }
@@ -57,30 +347,226 @@ fn determine_file_options(headers: &[Header]) -> Options {
     options
 }
 
-pub fn parse_yuml(yuml: &str) -> IResult<&str, ParsedYuml> {
-    let alphanumeric_string = alphanumeric0;
-    let alphanumeric_string_2 = alphanumeric0;
-    let parse_key_value = separated_pair(alphanumeric_string, tag(":"), alphanumeric_string_2);
-    let parse_header = delimited(tag("{"), parse_key_value, tag("}"));
-    let parse_header = terminated(preceded(tag("// "), parse_header), newline);
-    let parse_header = map(parse_header, as_header);
+/// Directives understood by the parser
+pub const DIRECTIVES: &[&str] = &[
+    "type",
+    "direction",
+    "generate",
+    "font",
+    "ranksep",
+    "bgcolor",
+    "bg",
+    "margin",
+    "title",
+    "scale",
+    "dpi",
+    "theme",
+    "samerank",
+    "splines",
+    "nodesep",
+    "concentrate",
+    "ordering",
+    "layout",
+    "seed",
+    "strict",
+    "namespaces",
+    "env",
+    "graphviz",
+    "include",
+    "define",
+];
+
+/// Labels of the elements listed in a `samerank` directive, like `[A],[B]` or `(a),(b)`
+fn parse_rank_labels(value: &str) -> Vec<String> {
+    let mut labels = vec![];
+    let mut rest = value;
+    while let Some(start) = rest.find(['[', '(', '<']) {
+        let close = match &rest[start..start + 1] {
+            "[" => ']',
+            "(" => ')',
+            _ => '>',
+        };
+        let end = match rest[start..].find(close) {
+            Some(end) => end,
+            None => break,
+        };
+        let (label, _) = split_attributes(&rest[start + 1..start + end]);
+        labels.push(label.split('|').next().unwrap_or(label).trim().to_string());
+        rest = &rest[start + end + 1..];
+    }
+    labels
+}
+
+fn parse_directive(yuml: &str) -> IResult<&str, Header<'_>> {
+    let key = delimited(space0, alphanumeric0, space0);
+    // the value runs until the last closing brace of the line, so it may contain braces itself
+    let value_string = map(
+        verify(not_line_ending, |line: &str| line.trim_end().ends_with('}')),
+        |line: &str| unquote(line.trim_end().strip_suffix('}').unwrap_or(line).trim()),
+    );
+    let parse_key_value = separated_pair(key, tag(":"), value_string);
+    let opening = tuple((space0, tag("//"), space0, tag("{")));
+    let mut parse_header = map(preceded(opening, parse_key_value), as_header);
+    parse_header(yuml)
+}
+
+/// Values may be quoted to keep surrounding whitespace, like `// {title:" Orders "}`
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+/// The directives of the document, those before the body followed by those between its statements; the remaining
+/// input starts at the body
+pub fn parse_headers(yuml: &str) -> IResult<&str, Vec<Header<'_>>> {
+    let parse_header = terminated(parse_directive, alt((tag("\r\n"), tag("\n"), eof)));
     let prefix_empty_lines = many0(line_ending);
     let mut parse_headers = tuple((prefix_empty_lines, many0(parse_header)));
 
-    let (rest, (_, headers)) = parse_headers(yuml)?;
+    let (rest, (_, mut headers)) = parse_headers(yuml)?;
+    // directives may also be written between the statements of the body
+    headers.extend(
+        rest.lines()
+            .filter(|line| is_directive(line))
+            .filter_map(|line| parse_directive(line).ok())
+            .map(|(_, header)| header),
+    );
+    Ok((rest, headers))
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn parse_yuml(yuml: &str) -> IResult<&str, ParsedYuml> {
+    let (rest, headers) = parse_headers(yuml)?;
     let options = determine_file_options(&headers);
 
-    let (rest, result) = match options.chart_type {
-        Some(ChartType::Activity) => {
-            let (rest, activity_file) = parse_activity(rest, &options)?;
-            (rest, ParsedYuml::Activity(activity_file))
-        }
-        Some(ChartType::Class) => {
-            let (rest, class_file) = parse_class(rest, &options)?;
-            (rest, ParsedYuml::Class(class_file))
+    // warnings refer to the lines of the whole document
+    let offset = line_of(yuml, rest) - 1;
+
+    let (rest, parsed) = match options.chart_type {
+        Some(ChartType::Activity) => map(
+            |yuml| ActivityParser.parse(yuml, &options, offset),
+            ParsedYuml::Activity,
+        )(rest),
+        Some(ChartType::Class) => map(|yuml| ClassParser.parse(yuml, &options, offset), ParsedYuml::Class)(rest),
+        Some(ChartType::Timeline) => map(
+            |yuml| TimelineParser.parse(yuml, &options, offset),
+            ParsedYuml::Timeline,
+        )(rest),
+        Some(ChartType::Er) => map(|yuml| ErParser.parse(yuml, &options, offset), ParsedYuml::Er)(rest),
+        Some(ChartType::Mindmap) => map(|yuml| MindmapParser.parse(yuml, &options, offset), ParsedYuml::Mindmap)(rest),
+        _ => Ok((rest, ParsedYuml::Unsupported)),
+    }?;
+    let warnings = [
+        unknown_directives(yuml, &headers),
+        invalid_graph_attributes(yuml, &headers),
+    ]
+    .concat();
+    Ok((rest, parsed.prepend_warnings(warnings)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_headers() {
+        let yuml = "\n//{type: activity}\n  //  { direction : leftToRight }  \r\n// {title:\" Orders \"}\n// {define:X=[A{bg:red}]}\n(a)";
+        let (rest, headers) = parse_headers(yuml).unwrap();
+        assert_eq!(rest, "(a)");
+        let pairs: Vec<(&str, &str)> = headers.iter().map(|h| (h.key, h.value)).collect();
+        assert_eq!(
+            pairs,
+            vec![
+                ("type", "activity"),
+                ("direction", "leftToRight"),
+                ("title", " Orders "),
+                ("define", "X=[A{bg:red}]"),
+            ]
+        );
+        assert_eq!(headers[2].to_string(), "// {title:\" Orders \"}");
+        assert_eq!(headers[3].to_string(), "// {define:X=[A{bg:red}]}");
+
+        // a document may consist of directives only
+        let (rest, headers) = parse_headers("// {type:class}").unwrap();
+        assert_eq!((rest, headers.len()), ("", 1));
+    }
+
+    #[test]
+    fn test_blank_lines_and_indentation() {
+        for (header, body) in [
+            (
+                "// {type:activity}",
+                "(start)-><d1>\n<d1>[yes]->(Boil)\n<d1>[no]->(end)",
+            ),
+            ("// {type:class}", "[Customer]<>-orders*>[Order]\n[Order]++-[LineItem]"),
+            (
+                "// {type:timeline}",
+                "[Design 2024-01..2024-03]->[Build]\n[Build]->[Launch]",
+            ),
+            ("// {type:er}", "[Customer|id]1-places-*[Order]\n[Order]1--*[Line]"),
+        ] {
+            let compact = format!("{}\n{}", header, body);
+            let spaced = format!("{}\n\n  {}\n \t \n\n", header, body.replace('\n', "\n\n\t"));
+            let (_, expected) = parse_yuml(&compact).unwrap();
+            let (_, parsed) = parse_yuml(&spaced).unwrap();
+            assert_eq!(parsed.to_string(), expected.to_string(), "{}", header);
         }
-        _ => (rest, ParsedYuml::Unsupported),
-    };
+    }
+
+    #[test]
+    fn test_metadata() {
+        let (_, parsed) = parse_yuml("// {type:class}\n// {direction:leftToRight}\n[A]->[B]\n[A]-[note: N]").unwrap();
+        assert_eq!(parsed.chart_type(), Some(ChartType::Class));
+        assert_eq!(parsed.direction(), Some(Directions::LeftToRight));
+        assert_eq!((parsed.node_count(), parsed.edge_count()), (3, 2));
+
+        let (_, parsed) = parse_yuml("// {type:sequence}\n[A]").unwrap();
+        assert_eq!(parsed.chart_type(), None);
+        assert_eq!((parsed.direction(), parsed.node_count()), (None, 0));
+    }
+
+    #[test]
+    fn test_unknown_directive_warnings() {
+        let (_, parsed) = parse_yuml("// {type:class}\n// {colour:red}\n[A]").unwrap();
+        let warnings: Vec<String> = parsed.warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(warnings, vec!["line 2: unknown directive 'colour'"]);
+    }
 
-    Ok((rest, result))
+    #[test]
+    fn test_env_directive() {
+        let dot = |yuml| parse_yuml(yuml).unwrap().1.to_string();
+        let prod = dot("// {type:class}\n// {env:prod}\n[A]->[B]");
+        assert!(prod.contains("  node [ shape=none, margin=0, color=\"#ad1f1f\", fontcolor=black"));
+        assert!(prod.contains("  edge [ color=\"#ad1f1f\", fontcolor=black"));
+        assert_eq!(
+            dot("// {type:activity}\n// {env: Prod }\n(start)")
+                .matches("#ad1f1f")
+                .count(),
+            2
+        );
+        assert!(dot("// {type:class}\n// {env:dev}\n[A]").contains("color=\"#1fad1f\""));
+        // other keys are hashed
+        assert!(dot("// {type:class}\n// {env:sandbox}\n[A]").contains("color=\"#ad1f53\""));
+
+        let (_, parsed) = parse_yuml("// {type:class}\n[A]").unwrap();
+        assert!(parsed.accent("prod").to_string().contains("color=\"#ad1f1f\""));
+    }
+
+    #[test]
+    fn test_graphviz_directive() {
+        let yuml = "// {type:class}\n// {graphviz: nodesep=0.3; overlap=false}\n// {graphviz: nodesep=1; pad=0\"]; node [color=red}\n[A]";
+        let (_, parsed) = parse_yuml(yuml).unwrap();
+        let dot = parsed.to_string();
+        assert!(dot.contains("    overlap = false\n    nodesep = 1\n"));
+        assert!(!dot.contains("pad") && !dot.contains("color=red"));
+        assert_eq!(parsed.warnings().len(), 2);
+    }
+
+    #[test]
+    fn test_parse_rank_labels() {
+        assert_eq!(parse_rank_labels(" [A],[B|x;y],[C{bg:red}]"), vec!["A", "B", "C"]);
+        assert_eq!(parse_rank_labels("(a), <d1>, (b"), vec!["a", "d1"]);
+    }
 }
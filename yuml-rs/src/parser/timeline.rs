@@ -0,0 +1,108 @@
+use super::utils::{resolve_connections, take_until_balanced};
+use super::*;
+use crate::model::{
+    attributes::split_attributes,
+    timeline::{Element, PhaseProps},
+};
+use nom::{bytes::complete::is_not, combinator::not, multi::many1};
+
+/*
+Timeline, laid out from left to right
+Phase           [Phase 1]
+Dates           [Phase 1]2024-01..2024-03       start and end of the phase, written as-is
+Sequence        [Phase 1]2024-01..2024-03->[Phase 2]2024-04..2024-06->[Release]
+Color splash    [Phase 1{bg:orange}]
+Comment         // Comments
+*/
+
+fn as_phase<'a>((text, range): (&'a str, Option<(&'a str, &'a str)>)) -> Element<'a> {
+    let (label, attributes) = split_attributes(text);
+    Element::Phase(PhaseProps {
+        label,
+        attributes,
+        range,
+    })
+}
+
+/// Parse the body of the diagram, keeping the elements of each statement together
+pub fn parse_timeline_statements<'a>(
+    yuml: &'a str,
+    _options: &Options,
+) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+    // dates run until the next arrow, so they may contain dashes themselves
+    let start = verify(take_until(".."), |date: &str| !date.contains(['[', ']', ',', '\n']));
+    let end = recognize(many1(alt((is_not("-[,\r\n"), terminated(tag("-"), not(tag(">")))))));
+    let range = separated_pair(start, tag(".."), end);
+    let phase = map(
+        tuple((preceded(tag("["), take_until_balanced('[', ']')), opt(range))),
+        as_phase,
+    );
+    let arrow = map(tag("->"), |_| Element::Arrow);
+
+    let parse_element = alt((phase, arrow));
+    let parse_statement = statement(parse_element);
+    let mut parse_statements = many_till(parse_statement, eof);
+
+    let (rest, (statements, _)) = parse_statements(yuml)?;
+    Ok((rest, as_statements(statements)))
+}
+
+pub struct TimelineParser;
+
+impl<'a> DiagramParser<'a, Element<'a>> for TimelineParser {
+    fn parse_statements(&self, yuml: &'a str, options: &Options) -> IResult<&'a str, Vec<Statement<Element<'a>>>> {
+        parse_timeline_statements(yuml, options)
+    }
+
+    fn as_dots(&self, statements: &[Statement<Element<'a>>], _options: &Options) -> Vec<DotElement> {
+        let (element_details, arrow_details) = resolve_connections(statements, |_, _, _| {});
+        element_details
+            .iter()
+            .chain(arrow_details.iter())
+            .map(DotElement::from)
+            .collect()
+    }
+
+    fn layout(&self, file: DotFile, options: &Options) -> DotFile {
+        // timelines are horizontal, they only run backwards with the `rightToLeft` direction
+        match options.dir {
+            Directions::RightToLeft => file,
+            _ => file.with_direction(Directions::LeftToRight),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yuml: &str) -> String {
+        match parse_yuml(yuml).expect("invalid file") {
+            (rest, ParsedYuml::Timeline(timeline_file)) => {
+                assert!(rest.is_empty());
+                timeline_file.to_string()
+            }
+            _ => panic!("Invalid file"),
+        }
+    }
+
+    #[test]
+    fn test_parse_timeline() {
+        let yuml =
+            "// {type:timeline}\n[Phase 1]2024-01..2024-03->[Phase 2{bg:orange}]2024-04..2024-06\n[Phase 2]->[Release]";
+        let result = parse(yuml);
+        assert!(result.contains("rankdir = LR"));
+        assert!(result.contains("<TR><TD>Phase 1</TD></TR><TR><TD>2024-01 – 2024-03</TD></TR>"));
+        assert!(result.contains("<TR><TD>Phase 2</TD></TR><TR><TD>2024-04 – 2024-06</TD></TR></TABLE>>, style=\"rounded,filled\", fillcolor=\"orange\""));
+        assert!(result.contains(r#"A3 [shape="rectangle", margin="0.20,0.05", label="Release", "#));
+        assert!(result.contains("A1 -> A2 ") && result.contains("A2 -> A3 "));
+    }
+
+    #[test]
+    fn test_phase_defined_by_dates() {
+        let result = parse("// {type:timeline}\n// {direction:rightToLeft}\n[Beta]->[GA]\n[GA]Q3 2024..Q4 2024");
+        assert!(result.contains("rankdir = RL"));
+        assert!(result.contains("<TD>Q3 2024 – Q4 2024</TD>"));
+        assert!(!result.contains("A3"));
+    }
+}
@@ -1,9 +1,58 @@
-use crate::model::shared::{ElementDetails, LabeledElement};
+use crate::model::shared::{normalize_label, ElementDetails, LabeledElement, Relation};
 
 use super::*;
 
+/// Take everything up to the first `delimiter` which is not escaped with a backslash, consuming the delimiter.
+/// The escape characters are kept in the output, graphviz drops them when rendering the label.
+pub fn take_until_unescaped(delimiter: char) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |yuml: &str| {
+        let mut escaped = false;
+        for (idx, c) in yuml.char_indices() {
+            if c == delimiter && !escaped {
+                return Ok((&yuml[idx + c.len_utf8()..], &yuml[..idx]));
+            }
+
+            escaped = c == '\\' && !escaped;
+        }
+
+        Err(nom::Err::Error(nom::error::Error::new(
+            yuml,
+            nom::error::ErrorKind::TakeUntil,
+        )))
+    }
+}
+
+/// Take everything up to the `close` delimiter matching an `open` delimiter which is already consumed, consuming the
+/// closing delimiter. Nested pairs of delimiters are part of the output, like the call in `(Compute f(x))`, and
+/// delimiters escaped with a backslash are never matched, so unbalanced ones can be written as `\(` and `\)`.
+pub fn take_until_balanced(open: char, close: char) -> impl Fn(&str) -> IResult<&str, &str> {
+    move |yuml: &str| {
+        let mut escaped = false;
+        let mut depth = 0;
+        for (idx, c) in yuml.char_indices() {
+            if !escaped {
+                if c == close && depth == 0 {
+                    return Ok((&yuml[idx + c.len_utf8()..], &yuml[..idx]));
+                } else if c == close {
+                    depth -= 1;
+                } else if c == open {
+                    depth += 1;
+                }
+            }
+
+            escaped = c == '\\' && !escaped;
+        }
+
+        Err(nom::Err::Error(nom::error::Error::new(
+            yuml,
+            nom::error::ErrorKind::TakeUntil,
+        )))
+    }
+}
+
+/// The uids of the nodes, keyed on their normalized label
 pub struct Uids<'a, T: LabeledElement> {
-    uids: HashMap<&'a str, (usize, &'a T)>,
+    uids: HashMap<String, (usize, &'a T)>,
     uid: usize,
 }
 
@@ -17,47 +66,160 @@ impl<'a, T: LabeledElement> Default for Uids<'a, T> {
 }
 
 impl<'a, T: LabeledElement> Uids<'a, T> {
-    pub fn insert_uid(&mut self, label: &'a str, e: &'a T) -> usize {
+    pub fn insert_uid(&mut self, label: &str, e: &'a T) -> usize {
         self.uid += 1;
-        self.uids.insert(label, (self.uid, e));
+        self.uids.insert(normalize_label(label), (self.uid, e));
         self.uid
     }
 
-    pub fn contains_key(&self, key: &str) -> bool {
-        self.uids.contains_key(key)
+    pub fn id(&self, key: &str) -> Option<usize> {
+        self.get(key).map(|(id, _)| *id)
     }
 
-    pub fn get(&'a self, key: &str) -> Option<&'a (usize, &'a T)> {
-        self.uids.get(key)
+    /// Replace the element which defines the node with the given id
+    pub fn redefine(&mut self, label: &str, id: usize, e: &'a T) {
+        self.uids.insert(normalize_label(label), (id, e));
     }
-}
 
-pub fn populate_uids<T: LabeledElement>(elements: &[T]) -> (Uids<T>, Vec<ElementDetails<T>>) {
-    let mut uids = Uids::default();
+    pub fn get(&self, key: &str) -> Option<&(usize, &'a T)> {
+        self.uids.get(&normalize_label(key))
+    }
+}
 
-    // we must collect to borrow uids in subsequent iterator
-    let element_details: Vec<ElementDetails<T>> = elements
+/// All (previous, connection, next) triples, where a connection never links elements of different statements
+pub fn connection_windows<T: LabeledElement>(statements: &[Statement<T>]) -> impl Iterator<Item = (&T, &T, &T)> {
+    statements
         .iter()
-        .filter_map(|e| {
-            if e.is_connection() {
-                // ignore arrows for now
-                None
-            } else {
-                let lbl = e.label();
-                if uids.contains_key(lbl) {
-                    None
-                } else {
-                    let id = uids.insert_uid(lbl, e);
-                    Some((id, e))
-                }
-            }
-        })
-        .map(|(id, element)| ElementDetails {
-            id: Some(id),
-            element,
-            relation: None,
+        .flat_map(|statement| statement.elements.iter().tuple_windows::<(_, _, _)>())
+        .filter(|(pre, e, next)| e.is_connection() && !pre.is_connection() && !next.is_connection())
+}
+
+/// Number the nodes of the statements and resolve the connections between them, in the order in which they are
+/// written. `connect` is called with the source, the connection and the element defining the target node of each
+/// connection, before it is returned. Connections without a node on one of their ends are left out.
+pub fn resolve_connections<'a, T: LabeledElement>(
+    statements: &'a [Statement<T>],
+    mut connect: impl FnMut(&'a T, &'a T, &'a T),
+) -> (Vec<ElementDetails<'a, T>>, Vec<ElementDetails<'a, T>>) {
+    let (uids, element_details) = populate_uids(statements.iter().flat_map(|s| s.elements.iter()));
+
+    let connections = connection_windows(statements)
+        .filter_map(|(pre, e, next)| {
+            let previous_id = uids.id(pre.label()).unwrap_or_default();
+            let (next_id, next_e) = uids.get(next.label())?;
+            connect(pre, e, next_e);
+
+            Some(ElementDetails {
+                id: None,
+                element: e,
+                relation: Some(Relation {
+                    previous_id,
+                    next_id: *next_id,
+                }),
+            })
         })
         .collect();
 
+    (element_details, connections)
+}
+
+/// Resolve the labels of the `samerank` directives to the uids of the nodes, unknown labels are ignored
+pub fn same_ranks<'a, T: LabeledElement + 'a>(
+    elements: impl IntoIterator<Item = &'a T>,
+    options: &Options,
+) -> Vec<Vec<String>> {
+    if options.same_ranks.is_empty() {
+        return vec![];
+    }
+
+    let (uids, _) = populate_uids(elements);
+    options
+        .same_ranks
+        .iter()
+        .map(|labels| {
+            labels
+                .iter()
+                .filter_map(|label| uids.get(label))
+                .map(|(id, _)| format!("A{}", id))
+                .collect()
+        })
+        .collect()
+}
+
+pub fn populate_uids<'a, T: LabeledElement + 'a>(
+    elements: impl IntoIterator<Item = &'a T>,
+) -> (Uids<'a, T>, Vec<ElementDetails<'a, T>>) {
+    let mut uids = Uids::default();
+    let mut element_details: Vec<ElementDetails<T>> = vec![];
+
+    // ignore arrows for now
+    for e in elements.into_iter().filter(|e| !e.is_connection()) {
+        let lbl = e.label();
+        match uids.id(lbl) {
+            // an element with an explicit id defines the node, even when it is referenced before
+            Some(id) if e.has_id() && !element_details[id - 1].element.has_id() => {
+                uids.redefine(lbl, id, e);
+                element_details[id - 1].element = e;
+            }
+            Some(_) => {}
+            None => {
+                let id = uids.insert_uid(lbl, e);
+                element_details.push(ElementDetails {
+                    id: Some(id),
+                    element: e,
+                    relation: None,
+                });
+            }
+        }
+    }
+
     (uids, element_details)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_until_unescaped() {
+        let take = take_until_unescaped(')');
+        assert_eq!(take("abc)rest"), Ok(("rest", "abc")));
+        assert_eq!(take(r"f\(x\))rest"), Ok(("rest", r"f\(x\)")));
+        assert_eq!(take(r"a\\)rest"), Ok(("rest", r"a\\")));
+        assert!(take(r"abc\)").is_err());
+    }
+
+    #[test]
+    fn test_take_until_balanced() {
+        let take = take_until_balanced('(', ')');
+        assert_eq!(take("abc)rest"), Ok(("rest", "abc")));
+        assert_eq!(take("Compute f(g(x)))rest"), Ok(("rest", "Compute f(g(x))")));
+        assert_eq!(take(r"a \( b)rest"), Ok(("rest", r"a \( b")));
+        assert_eq!(take(r"a \) b)rest"), Ok(("rest", r"a \) b")));
+        assert!(take("f(x)").is_err());
+
+        let take = take_until_balanced('<', '>');
+        assert_eq!(take("a<b>c>->"), Ok(("->", "a<b>c")));
+    }
+
+    #[test]
+    fn test_resolve_connections() {
+        use crate::model::class::Element;
+
+        let (_, statements) = class::parse_class_statements("[a]->[b]\n[b]->\n[c]^[a]", &Options::default()).unwrap();
+        let mut targets = vec![];
+        let (nodes, connections) = resolve_connections(&statements, |_, _, next| targets.push(next.label()));
+        assert_eq!(
+            nodes.iter().map(|n| n.id).collect::<Vec<_>>(),
+            vec![Some(1), Some(2), Some(3)]
+        );
+        let relations: Vec<(usize, usize)> = connections
+            .iter()
+            .filter_map(|c| c.relation.as_ref())
+            .map(|r| (r.previous_id, r.next_id))
+            .collect();
+        assert_eq!(relations, vec![(1, 2), (3, 1)]);
+        assert_eq!(targets, vec!["b", "a"]);
+        assert!(matches!(connections[1].element, Element::Inheritance(_)));
+    }
+}
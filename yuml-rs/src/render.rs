@@ -0,0 +1,745 @@
+use crate::{
+    accessibility::escape,
+    error::{OptionsError, YumlError, YumlResult},
+    model::dot::{DotFile, Layout},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{
+    convert::TryFrom,
+    env,
+    fmt::{Display, Formatter},
+    io::{Read, Write},
+    path::PathBuf,
+    process::{Child, ChildStderr, ChildStdout, Command, Stdio},
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+const CHUNK_SIZE: usize = 8 * 1024;
+/// Environment variable with the path of the graphviz executable, for when "dot" is not on the PATH
+pub const DOT_PATH_VAR: &str = "YUML_DOT";
+/// Environment variable with the path of the graphviz executable, shared with other graphviz tools
+pub const GRAPHVIZ_DOT_VAR: &str = "GRAPHVIZ_DOT";
+const DOT_EXECUTABLE: &str = if cfg!(windows) { "dot.exe" } else { "dot" };
+/// Keeps a console window from flashing up for every diagram rendered by a GUI application on Windows
+#[cfg(windows)]
+const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+
+/// Image formats produced by the "dot" binary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    #[default]
+    Svg,
+    Png,
+    /// Client-side HTML image map of the links of the diagram, see `render_image_map`
+    Cmapx,
+}
+
+impl OutputFormat {
+    /// MIME type of the rendered image
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Svg => "image/svg+xml",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Cmapx => "text/html",
+        }
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputFormat::Svg => f.write_str("svg"),
+            OutputFormat::Png => f.write_str("png"),
+            OutputFormat::Cmapx => f.write_str("cmapx"),
+        }
+    }
+}
+
+impl TryFrom<&str> for OutputFormat {
+    type Error = YumlError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "svg" => Ok(OutputFormat::Svg),
+            "png" => Ok(OutputFormat::Png),
+            "cmapx" => Ok(OutputFormat::Cmapx),
+            _ => Err(OptionsError::new(
+                "invalid value for 'format'. Allowed values are: svg <i>(default)</i>, png, cmapx.",
+            )
+            .into()),
+        }
+    }
+}
+
+/// Options controlling how the "dot" process is invoked
+#[derive(Debug, Clone, Default)]
+pub struct RenderOptions {
+    pub format: OutputFormat,
+    /// Kill the "dot" process and fail when rendering takes longer than this
+    pub timeout: Option<Duration>,
+    /// Kill the "dot" process and fail when the rendered output grows beyond this number of bytes
+    pub max_output_size: Option<usize>,
+    /// Resolution of the rendered output (`-Gdpi`); a `dpi` or `scale` directive of the diagram takes precedence
+    pub dpi: Option<f32>,
+    /// Prepended to the element ids of the SVG output, so several diagrams can be embedded in one page
+    pub id_prefix: Option<String>,
+    /// Replaces the font families of the SVG output, like `Inter, sans-serif`, so the diagram does not depend on
+    /// the fonts graphviz found while rendering
+    pub font_family: Option<String>,
+    /// Layout engine (`-K`); a `layout` directive of the diagram takes precedence
+    pub layout: Option<Layout>,
+    /// Seeds the layout of the neato and fdp engines (`-Gstart`), so a diagram is rendered the same on every run; a
+    /// `seed` directive of the diagram takes precedence
+    pub seed: Option<u32>,
+    /// Path of the graphviz executable; falls back to the `YUML_DOT` and `GRAPHVIZ_DOT` environment variables and then
+    /// to the "dot" executable found by `locate_dot`
+    pub dot_path: Option<PathBuf>,
+    /// Extra arguments passed to the graphviz executable
+    pub extra_args: Vec<String>,
+    /// Extra environment variables for the graphviz process
+    pub env: Vec<(String, String)>,
+}
+
+impl RenderOptions {
+    pub fn format(mut self, format: OutputFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn max_output_size(mut self, max: usize) -> Self {
+        self.max_output_size = Some(max);
+        self
+    }
+
+    pub fn dpi(mut self, dpi: f32) -> Self {
+        self.dpi = Some(dpi);
+        self
+    }
+
+    pub fn id_prefix(mut self, prefix: &str) -> Self {
+        self.id_prefix = Some(prefix.to_string());
+        self
+    }
+
+    pub fn font_family(mut self, family: &str) -> Self {
+        self.font_family = Some(family.to_string());
+        self
+    }
+
+    pub fn layout(mut self, layout: Layout) -> Self {
+        self.layout = Some(layout);
+        self
+    }
+
+    pub fn seed(mut self, seed: u32) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn dot_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.dot_path = Some(path.into());
+        self
+    }
+
+    pub fn arg(mut self, arg: &str) -> Self {
+        self.extra_args.push(arg.to_string());
+        self
+    }
+
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Whether the SVG output is rewritten once it is rendered, see `rewrite_svg`
+    pub(crate) fn rewrites_svg(&self) -> bool {
+        self.format == OutputFormat::Svg && (self.id_prefix.is_some() || self.font_family.is_some())
+    }
+
+    /// Options that can only be honored by spawning the graphviz executable
+    #[cfg(feature = "graphviz-ffi")]
+    fn requires_process(&self) -> bool {
+        self.timeout.is_some() || self.dot_path.is_some() || !self.extra_args.is_empty() || !self.env.is_empty()
+    }
+
+    fn program(&self) -> PathBuf {
+        self.dot_path
+            .clone()
+            .or_else(|| env::var_os(DOT_PATH_VAR).map(PathBuf::from))
+            .or_else(|| env::var_os(GRAPHVIZ_DOT_VAR).map(PathBuf::from))
+            .or_else(locate_dot)
+            .unwrap_or_else(|| PathBuf::from(DOT_EXECUTABLE))
+    }
+
+    /// The graphviz process, without the arguments
+    fn process(&self) -> Command {
+        let mut command = Command::new(self.program());
+        command.envs(self.env.iter().map(|(key, value)| (key, value)));
+        #[cfg(windows)]
+        {
+            use std::os::windows::process::CommandExt;
+            command.creation_flags(CREATE_NO_WINDOW);
+        }
+        command
+    }
+
+    fn command(&self) -> Command {
+        let mut command = self.process();
+        command.args(self.args());
+        command
+    }
+
+    fn args(&self) -> Vec<String> {
+        let mut args = vec![format!("-T{}", self.format)];
+        if let Some(dpi) = self.dpi {
+            args.push(format!("-Gdpi={}", dpi));
+        }
+        if let Some(layout) = self.layout {
+            args.push(format!("-K{}", layout));
+        }
+        if let Some(seed) = self.seed {
+            args.push(format!("-Gstart={}", seed));
+        }
+        args.extend(self.extra_args.iter().cloned());
+        args
+    }
+}
+
+/// Locate the "dot" executable on the PATH, or else in the directories graphviz is commonly installed in, like
+/// `C:\Program Files\Graphviz\bin` on Windows
+pub fn locate_dot() -> Option<PathBuf> {
+    let path = env::var_os("PATH").unwrap_or_default();
+    find_executable(env::split_paths(&path).chain(install_dirs()))
+}
+
+fn find_executable(dirs: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    dirs.into_iter()
+        .map(|dir| dir.join(DOT_EXECUTABLE))
+        .find(|program| program.is_file())
+}
+
+#[cfg(windows)]
+fn install_dirs() -> Vec<PathBuf> {
+    ["ProgramFiles", "ProgramFiles(x86)", "ProgramW6432"]
+        .iter()
+        .filter_map(|var| env::var_os(var).map(PathBuf::from))
+        .chain(env::var_os("LOCALAPPDATA").map(|dir| PathBuf::from(dir).join("Programs")))
+        .map(|dir| dir.join("Graphviz").join("bin"))
+        .collect()
+}
+
+/// Applications started from the Finder do not see the PATH of the shell
+#[cfg(target_os = "macos")]
+fn install_dirs() -> Vec<PathBuf> {
+    ["/opt/homebrew/bin", "/usr/local/bin", "/opt/local/bin"]
+        .iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+#[cfg(not(any(windows, target_os = "macos")))]
+fn install_dirs() -> Vec<PathBuf> {
+    ["/usr/local/bin", "/usr/bin", "/snap/bin"]
+        .iter()
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// The version of the graphviz executable selected by the `opts`, like `dot - graphviz version 2.43.0 (0)`; fails when
+/// graphviz is not installed
+pub fn graphviz_version(opts: &RenderOptions) -> YumlResult<String> {
+    let output = opts.process().arg("-V").output()?;
+    Ok(String::from_utf8_lossy(&output.stderr).trim().to_string())
+}
+
+/// Turns a dot file into an image.
+///
+/// Implement this to plug in another layout backend, like a remote graphviz service or a fake for tests.
+pub trait Renderer {
+    fn render(&self, df: &DotFile, opts: &RenderOptions) -> YumlResult<Vec<u8>>;
+}
+
+/// Renders by spawning the "dot" binary, as used by the `render_*` functions
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GraphvizRenderer;
+
+impl Renderer for GraphvizRenderer {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "info", skip_all, fields(nodes = df.nodes().count(), edges = df.edges().count()))
+    )]
+    fn render(&self, df: &DotFile, opts: &RenderOptions) -> YumlResult<Vec<u8>> {
+        let mut image = Vec::new();
+        self.render_dot_to(&df.to_string(), &mut image, opts)?;
+        Ok(image)
+    }
+}
+
+impl GraphvizRenderer {
+    /// Render a dot-description and stream the result into `out`.
+    ///
+    /// The "dot" process is killed when it does not finish within the configured timeout, or when its output exceeds
+    /// the maximum size; the output written until then is not removed from `out`.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "render", level = "info", skip_all, fields(format = %opts.format, dot_bytes = dot.len()))
+    )]
+    pub fn render_dot_to<W: Write>(&self, dot: &str, mut out: W, opts: &RenderOptions) -> YumlResult<()> {
+        if opts.rewrites_svg() {
+            // the document can only be rewritten once it is complete
+            let mut svg = Vec::new();
+            let plain = RenderOptions {
+                id_prefix: None,
+                font_family: None,
+                ..opts.clone()
+            };
+            self.render_dot_to(dot, &mut svg, &plain)?;
+            out.write_all(rewrite_svg(&String::from_utf8_lossy(&svg), opts).as_bytes())?;
+            out.flush()?;
+            return Ok(());
+        }
+
+        let mut child = opts
+            .command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // feed stdin from a separate thread, so a large output can not dead-lock the pipes
+        let mut stdin = child.stdin.take().expect("stdin is piped");
+        let input = dot.to_string();
+        let writer = thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let chunks = read_chunks(stdout);
+        let stderr = child.stderr.take().expect("stderr is piped");
+        let errors = read_errors(stderr);
+        let deadline = opts.timeout.map(|timeout| Instant::now() + timeout);
+        let mut size = 0;
+
+        loop {
+            let chunk = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    match chunks.recv_timeout(remaining) {
+                        Ok(chunk) => chunk,
+                        Err(RecvTimeoutError::Disconnected) => break,
+                        Err(RecvTimeoutError::Timeout) => {
+                            kill(&mut child);
+                            return Err(YumlError::RenderTimeout(opts.timeout.unwrap_or_default()));
+                        }
+                    }
+                }
+                None => match chunks.recv() {
+                    Ok(chunk) => chunk,
+                    Err(_) => break,
+                },
+            };
+
+            match chunk {
+                Ok(chunk) => {
+                    size += chunk.len();
+                    if let Some(max) = opts.max_output_size.filter(|max| size > *max) {
+                        kill(&mut child);
+                        return Err(YumlError::OutputTooLarge(max));
+                    }
+                    out.write_all(&chunk)?
+                }
+                Err(err) => {
+                    kill(&mut child);
+                    return Err(err.into());
+                }
+            }
+        }
+
+        // a failing write means "dot" exited early; the exit status below reports why
+        let _ = writer.join();
+        let status = child.wait()?;
+        let message = errors.join().unwrap_or_default();
+        if !status.success() {
+            return Err(YumlError::Render(message.trim().to_string()));
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Render SVG using the "dot" binary and stream the result into `out`.
+/// Other image formats can be selected through `RenderOptions::format`.
+///
+/// The "dot" process is killed when it does not finish within the configured timeout, or when its output exceeds the
+/// maximum size. With the `graphviz-ffi` feature the image is rendered in-process, unless the options require the
+/// "dot" process.
+pub fn render_svg_from_dot_to<W: Write>(dot: &str, mut out: W, opts: &RenderOptions) -> YumlResult<()> {
+    #[cfg(feature = "graphviz-ffi")]
+    if !opts.requires_process() {
+        out.write_all(&crate::gvc::GvcRenderer.render_dot(dot, opts)?)?;
+        out.flush()?;
+        return Ok(());
+    }
+
+    GraphvizRenderer.render_dot_to(dot, &mut out, opts)
+}
+
+/// Encode an image as a data URI, like `data:image/svg+xml;base64,...`, to embed it in an HTML `img` tag or a
+/// Markdown link without writing a file
+pub fn as_data_uri(image: &[u8], format: OutputFormat) -> String {
+    format!("data:{};base64,{}", format.content_type(), STANDARD.encode(image))
+}
+
+/// Render the dot-description in the format of the options as a data URI, see `as_data_uri`
+pub fn render_data_uri(dot: &str, opts: &RenderOptions) -> YumlResult<String> {
+    let mut image = Vec::new();
+    render_svg_from_dot_to(dot, &mut image, opts)?;
+    Ok(as_data_uri(&image, opts.format))
+}
+
+/// Render SVG as a data URI.
+/// Usage:
+/// ```rust,no_run
+/// use yuml_rs::{parse_yuml, render_svg_data_uri};
+///
+/// let dot = parse_yuml("// {type:activity}\n(start)->(end)").unwrap().to_string();
+/// let img = format!("<img src=\"{}\">", render_svg_data_uri(&dot).unwrap());
+/// ```
+pub fn render_svg_data_uri(dot: &str) -> YumlResult<String> {
+    render_data_uri(dot, &RenderOptions::default())
+}
+
+/// Render PNG as a data URI
+pub fn render_png_data_uri(dot: &str) -> YumlResult<String> {
+    render_data_uri(dot, &RenderOptions::default().format(OutputFormat::Png))
+}
+
+/// A PNG image with the client-side image map of its links, for HTML pages that can not inline SVG. Nodes and edges
+/// become clickable with the `href` attribute, like `[Customer{href:customer.html}]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImageMap {
+    pub png: Vec<u8>,
+    /// The `<map>` element, as written by graphviz
+    pub map: String,
+}
+
+impl ImageMap {
+    /// The name of the map, which the `usemap` attribute of the image refers to
+    pub fn name(&self) -> &str {
+        self.map
+            .split_once("name=\"")
+            .and_then(|(_, rest)| rest.split_once('"'))
+            .map_or("", |(name, _)| name)
+    }
+
+    /// An HTML snippet with the image, loaded from `src`, followed by its map
+    pub fn html(&self, src: &str, alt: &str) -> String {
+        format!(
+            "<img src=\"{}\" usemap=\"#{}\" alt=\"{}\"/>\n{}",
+            escape(src),
+            self.name(),
+            escape(alt),
+            self.map.trim_end()
+        )
+    }
+
+    /// Like `html`, with the image embedded as a data URI
+    pub fn inline_html(&self, alt: &str) -> String {
+        self.html(&as_data_uri(&self.png, OutputFormat::Png), alt)
+    }
+}
+
+/// Render the dot-description as a PNG image with the image map of its links. Graphviz runs once for each, so the
+/// neato and fdp layouts need a `seed` to match.
+/// Usage:
+/// ```rust,no_run
+/// use yuml_rs::{parse_yuml, render_image_map, RenderOptions};
+///
+/// let dot = parse_yuml("// {type:class}\n[Customer{href:customer.html}]->[Order{href:order.html}]").unwrap();
+/// let image_map = render_image_map(&dot.to_string(), &RenderOptions::default()).unwrap();
+/// let html = image_map.inline_html("Customers and their orders");
+/// ```
+pub fn render_image_map(dot: &str, opts: &RenderOptions) -> YumlResult<ImageMap> {
+    let mut png = Vec::new();
+    render_svg_from_dot_to(dot, &mut png, &opts.clone().format(OutputFormat::Png))?;
+    let mut map = Vec::new();
+    render_svg_from_dot_to(dot, &mut map, &opts.clone().format(OutputFormat::Cmapx))?;
+    Ok(ImageMap {
+        png,
+        map: String::from_utf8_lossy(&map).to_string(),
+    })
+}
+
+/// Render SVG using the "dot" binary without blocking the async runtime
+#[cfg(feature = "async")]
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "render", level = "info", skip_all, fields(format = "svg", dot_bytes = dot.len()))
+)]
+pub async fn render_svg_from_dot_async(dot: &str) -> YumlResult<Vec<u8>> {
+    use tokio::io::AsyncWriteExt;
+
+    let mut child = tokio::process::Command::from(RenderOptions::default().command())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    let input = dot.as_bytes().to_vec();
+    let write_input = async move {
+        // a failing write means "dot" exited early; the exit status reports why
+        let _ = stdin.write_all(&input).await;
+    };
+
+    let (_, output) = tokio::join!(write_input, child.wait_with_output());
+    let output = output?;
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(YumlError::Render(message.trim().to_string()));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Prepend `prefix` to the element ids of an SVG document and to the references to these ids
+pub(crate) fn prefix_svg_ids(svg: &str, prefix: &str) -> String {
+    svg.replace(" id=\"", &format!(" id=\"{}", prefix))
+        .replace("url(#", &format!("url(#{}", prefix))
+        .replace("href=\"#", &format!("href=\"#{}", prefix))
+}
+
+/// Replace the value of the `font-family` attributes of an SVG document with `family`
+pub(crate) fn replace_svg_fonts(svg: &str, family: &str) -> String {
+    const ATTRIBUTE: &str = "font-family=\"";
+    let mut parts = svg.split(ATTRIBUTE);
+    let mut result = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        result.push_str(ATTRIBUTE);
+        result.push_str(&escape(family));
+        result.push_str(part.find('"').map_or(part, |end| &part[end..]));
+    }
+    result
+}
+
+/// Apply the id prefix and font family of the options to a rendered SVG document
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub(crate) fn rewrite_svg(svg: &str, opts: &RenderOptions) -> String {
+    let svg = match &opts.id_prefix {
+        Some(prefix) => prefix_svg_ids(svg, prefix),
+        None => svg.to_string(),
+    };
+    match &opts.font_family {
+        Some(family) => replace_svg_fonts(&svg, family),
+        None => svg,
+    }
+}
+
+fn read_chunks(mut stdout: ChildStdout) -> Receiver<std::io::Result<Vec<u8>>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buffer = [0u8; CHUNK_SIZE];
+        loop {
+            match stdout.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n) => {
+                    if tx.send(Ok(buffer[..n].to_vec())).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => {
+                    let _ = tx.send(Err(err));
+                    break;
+                }
+            }
+        }
+    });
+    rx
+}
+
+/// Drain stderr from a separate thread, so "dot" can not block on a full pipe while stdout is read
+fn read_errors(mut stderr: ChildStderr) -> JoinHandle<String> {
+    thread::spawn(move || {
+        let mut message = Vec::new();
+        let _ = stderr.read_to_end(&mut message);
+        String::from_utf8_lossy(&message).into_owned()
+    })
+}
+
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_args() {
+        assert_eq!(RenderOptions::default().args(), vec!["-Tsvg"]);
+        assert_eq!(RenderOptions::default().dpi(144.0).args(), vec!["-Tsvg", "-Gdpi=144"]);
+        assert_eq!(RenderOptions::default().format(OutputFormat::Png).args(), vec!["-Tpng"]);
+        assert_eq!(
+            RenderOptions::default().layout(Layout::Neato).args(),
+            vec!["-Tsvg", "-Kneato"]
+        );
+        assert_eq!(
+            RenderOptions::default().layout(Layout::Fdp).seed(7).args(),
+            vec!["-Tsvg", "-Kfdp", "-Gstart=7"]
+        );
+    }
+
+    #[test]
+    fn test_directives_take_precedence() {
+        // graphviz only applies `-G` and `-K` to graphs without the attribute, so the directives are kept in the graph
+        let yuml = "// {type:activity}\n// {layout:neato}\n// {dpi:300}\n// {seed:3}\n(start)->(end)";
+        let dot = crate::parse_yuml(yuml).unwrap().to_string();
+        assert!(dot.contains("layout = neato"), "{}", dot);
+        assert!(dot.contains("start = 3"), "{}", dot);
+        assert!(dot.contains("dpi=300"), "{}", dot);
+
+        let opts = RenderOptions::default().layout(Layout::Circo).dpi(96.0).seed(7);
+        assert_eq!(opts.args(), vec!["-Tsvg", "-Gdpi=96", "-Kcirco", "-Gstart=7"]);
+    }
+
+    #[test]
+    fn test_find_executable() {
+        let dir = env::temp_dir().join(format!("yuml-locate-{}", std::process::id()));
+        let bin = dir.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(bin.join(DOT_EXECUTABLE), "").unwrap();
+
+        assert_eq!(find_executable(vec![dir.clone()]), None);
+        assert_eq!(
+            find_executable(vec![dir.clone(), bin.clone()]),
+            Some(bin.join(DOT_EXECUTABLE))
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_custom_renderer() {
+        struct Fake;
+
+        impl Renderer for Fake {
+            fn render(&self, df: &DotFile, opts: &RenderOptions) -> YumlResult<Vec<u8>> {
+                Ok(format!("{} nodes as {}", df.nodes().count(), opts.format).into_bytes())
+            }
+        }
+
+        let df = DotFile::try_from("digraph { a -> b }").unwrap();
+        let renderer: &dyn Renderer = &Fake;
+        let image = renderer.render(&df, &RenderOptions::default().format(OutputFormat::Png));
+        assert_eq!(image.unwrap(), b"2 nodes as png");
+    }
+
+    #[test]
+    fn test_prefix_svg_ids() {
+        let svg = r##"<g id="graph0" class="graph"><g id="node1" class="node"><title>d1_A1</title><path fill="url(#l_1)"/><a xlink:href="#node1"/></g></g>"##;
+        assert_eq!(
+            prefix_svg_ids(svg, "d1_"),
+            r##"<g id="d1_graph0" class="graph"><g id="d1_node1" class="node"><title>d1_A1</title><path fill="url(#d1_l_1)"/><a xlink:href="#d1_node1"/></g></g>"##
+        );
+    }
+
+    #[test]
+    fn test_replace_svg_fonts() {
+        let svg = r#"<text x="27" font-family="Times,serif" font-size="14.00">A</text><text font-family="Helvetica,sans-Serif">B</text>"#;
+        assert_eq!(
+            replace_svg_fonts(svg, "Inter, \"Noto Sans\""),
+            r#"<text x="27" font-family="Inter, &quot;Noto Sans&quot;" font-size="14.00">A</text><text font-family="Inter, &quot;Noto Sans&quot;">B</text>"#
+        );
+        assert_eq!(replace_svg_fonts("<svg/>", "Inter"), "<svg/>");
+
+        let opts = RenderOptions::default().id_prefix("d1_").font_family("Inter");
+        assert!(opts.rewrites_svg());
+        assert_eq!(
+            rewrite_svg(r#"<g id="node1"><text font-family="Times,serif">A</text></g>"#, &opts),
+            r#"<g id="d1_node1"><text font-family="Inter">A</text></g>"#
+        );
+        assert!(!opts.format(OutputFormat::Png).rewrites_svg());
+    }
+
+    #[test]
+    fn test_render_svg() {
+        if graphviz_version(&RenderOptions::default()).is_err() {
+            // graphviz is not installed
+            return;
+        }
+
+        let mut svg = Vec::new();
+        let opts = RenderOptions::default().timeout(Duration::from_secs(30));
+        render_svg_from_dot_to("digraph G { A -> B }", &mut svg, &opts).expect("can not render");
+        assert!(String::from_utf8_lossy(&svg).contains("<svg"));
+
+        let opts = RenderOptions::default().max_output_size(10);
+        let err = render_svg_from_dot_to("digraph G { A -> B }", Vec::new(), &opts).unwrap_err();
+        assert_eq!(err.to_string(), "Rendered output exceeds the limit of 10 bytes");
+    }
+
+    #[test]
+    fn test_data_uri() {
+        assert_eq!(
+            as_data_uri(b"<svg/>", OutputFormat::Svg),
+            "data:image/svg+xml;base64,PHN2Zy8+"
+        );
+        assert_eq!(
+            as_data_uri(&[0x89, b'P'], OutputFormat::Png),
+            "data:image/png;base64,iVA="
+        );
+
+        if graphviz_version(&RenderOptions::default()).is_err() {
+            // graphviz is not installed
+            return;
+        }
+        let uri = render_svg_data_uri("digraph G { A -> B }").expect("can not render");
+        assert!(uri.starts_with("data:image/svg+xml;base64,"));
+    }
+
+    #[test]
+    fn test_image_map() {
+        let image_map = ImageMap {
+            png: b"png".to_vec(),
+            map: "<map id=\"G\" name=\"G\">\n<area shape=\"poly\" href=\"customer.html\" alt=\"\"/>\n</map>\n"
+                .to_string(),
+        };
+        assert_eq!(image_map.name(), "G");
+        assert_eq!(
+            image_map.html("img/a&b.png", "Customers"),
+            "<img src=\"img/a&amp;b.png\" usemap=\"#G\" alt=\"Customers\"/>\n<map id=\"G\" name=\"G\">\n<area shape=\"poly\" href=\"customer.html\" alt=\"\"/>\n</map>"
+        );
+        assert!(image_map
+            .inline_html("")
+            .starts_with("<img src=\"data:image/png;base64,cG5n\" usemap=\"#G\""));
+
+        if graphviz_version(&RenderOptions::default()).is_err() {
+            // graphviz is not installed
+            return;
+        }
+        let image_map = render_image_map("digraph G { A [href=\"a.html\"]; A -> B }", &RenderOptions::default())
+            .expect("can not render");
+        assert!(!image_map.png.is_empty() && !image_map.map.is_empty());
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_render_svg_async() {
+        if graphviz_version(&RenderOptions::default()).is_err() {
+            // graphviz is not installed
+            return;
+        }
+
+        let svg = render_svg_from_dot_async("digraph G { A -> B }")
+            .await
+            .expect("can not render");
+        assert!(String::from_utf8_lossy(&svg).contains("<svg"));
+    }
+}
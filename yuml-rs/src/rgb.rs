@@ -0,0 +1,303 @@
+//! Named colors of graphviz, to validate the colors of attributes like `{bg:orange}`
+
+/// The X11 colors of graphviz, which also cover the names of CSS; variants like `orange3` or `gray50` are derived from
+/// these names
+pub const COLOR_TABLE: &[(&str, (u8, u8, u8))] = &[
+    ("aliceblue", (240, 248, 255)),
+    ("antiquewhite", (250, 235, 215)),
+    ("aqua", (0, 255, 255)),
+    ("aquamarine", (127, 255, 212)),
+    ("azure", (240, 255, 255)),
+    ("beige", (245, 245, 220)),
+    ("bisque", (255, 228, 196)),
+    ("black", (0, 0, 0)),
+    ("blanchedalmond", (255, 235, 205)),
+    ("blue", (0, 0, 255)),
+    ("blueviolet", (138, 43, 226)),
+    ("brown", (165, 42, 42)),
+    ("burlywood", (222, 184, 135)),
+    ("cadetblue", (95, 158, 160)),
+    ("chartreuse", (127, 255, 0)),
+    ("chocolate", (210, 105, 30)),
+    ("coral", (255, 127, 80)),
+    ("cornflowerblue", (100, 149, 237)),
+    ("cornsilk", (255, 248, 220)),
+    ("crimson", (220, 20, 60)),
+    ("cyan", (0, 255, 255)),
+    ("darkblue", (0, 0, 139)),
+    ("darkcyan", (0, 139, 139)),
+    ("darkgoldenrod", (184, 134, 11)),
+    ("darkgray", (169, 169, 169)),
+    ("darkgreen", (0, 100, 0)),
+    ("darkgrey", (169, 169, 169)),
+    ("darkkhaki", (189, 183, 107)),
+    ("darkmagenta", (139, 0, 139)),
+    ("darkolivegreen", (85, 107, 47)),
+    ("darkorange", (255, 140, 0)),
+    ("darkorchid", (153, 50, 204)),
+    ("darkred", (139, 0, 0)),
+    ("darksalmon", (233, 150, 122)),
+    ("darkseagreen", (143, 188, 143)),
+    ("darkslateblue", (72, 61, 139)),
+    ("darkslategray", (47, 79, 79)),
+    ("darkslategrey", (47, 79, 79)),
+    ("darkturquoise", (0, 206, 209)),
+    ("darkviolet", (148, 0, 211)),
+    ("deeppink", (255, 20, 147)),
+    ("deepskyblue", (0, 191, 255)),
+    ("dimgray", (105, 105, 105)),
+    ("dimgrey", (105, 105, 105)),
+    ("dodgerblue", (30, 144, 255)),
+    ("firebrick", (178, 34, 34)),
+    ("floralwhite", (255, 250, 240)),
+    ("forestgreen", (34, 139, 34)),
+    ("fuchsia", (255, 0, 255)),
+    ("gainsboro", (220, 220, 220)),
+    ("ghostwhite", (248, 248, 255)),
+    ("gold", (255, 215, 0)),
+    ("goldenrod", (218, 165, 32)),
+    ("gray", (190, 190, 190)),
+    ("green", (0, 255, 0)),
+    ("greenyellow", (173, 255, 47)),
+    ("grey", (190, 190, 190)),
+    ("honeydew", (240, 255, 240)),
+    ("hotpink", (255, 105, 180)),
+    ("indianred", (205, 92, 92)),
+    ("indigo", (75, 0, 130)),
+    ("ivory", (255, 255, 240)),
+    ("khaki", (240, 230, 140)),
+    ("lavender", (230, 230, 250)),
+    ("lavenderblush", (255, 240, 245)),
+    ("lawngreen", (124, 252, 0)),
+    ("lemonchiffon", (255, 250, 205)),
+    ("lightblue", (173, 216, 230)),
+    ("lightcoral", (240, 128, 128)),
+    ("lightcyan", (224, 255, 255)),
+    ("lightgoldenrod", (238, 221, 130)),
+    ("lightgoldenrodyellow", (250, 250, 210)),
+    ("lightgray", (211, 211, 211)),
+    ("lightgreen", (144, 238, 144)),
+    ("lightgrey", (211, 211, 211)),
+    ("lightpink", (255, 182, 193)),
+    ("lightsalmon", (255, 160, 122)),
+    ("lightseagreen", (32, 178, 170)),
+    ("lightskyblue", (135, 206, 250)),
+    ("lightslateblue", (132, 112, 255)),
+    ("lightslategray", (119, 136, 153)),
+    ("lightslategrey", (119, 136, 153)),
+    ("lightsteelblue", (176, 196, 222)),
+    ("lightyellow", (255, 255, 224)),
+    ("lime", (0, 255, 0)),
+    ("limegreen", (50, 205, 50)),
+    ("linen", (250, 240, 230)),
+    ("magenta", (255, 0, 255)),
+    ("maroon", (176, 48, 96)),
+    ("mediumaquamarine", (102, 205, 170)),
+    ("mediumblue", (0, 0, 205)),
+    ("mediumorchid", (186, 85, 211)),
+    ("mediumpurple", (147, 112, 219)),
+    ("mediumseagreen", (60, 179, 113)),
+    ("mediumslateblue", (123, 104, 238)),
+    ("mediumspringgreen", (0, 250, 154)),
+    ("mediumturquoise", (72, 209, 204)),
+    ("mediumvioletred", (199, 21, 133)),
+    ("midnightblue", (25, 25, 112)),
+    ("mintcream", (245, 255, 250)),
+    ("mistyrose", (255, 228, 225)),
+    ("moccasin", (255, 228, 181)),
+    ("navajowhite", (255, 222, 173)),
+    ("navy", (0, 0, 128)),
+    ("navyblue", (0, 0, 128)),
+    ("oldlace", (253, 245, 230)),
+    ("olive", (128, 128, 0)),
+    ("olivedrab", (107, 142, 35)),
+    ("orange", (255, 165, 0)),
+    ("orangered", (255, 69, 0)),
+    ("orchid", (218, 112, 214)),
+    ("palegoldenrod", (238, 232, 170)),
+    ("palegreen", (152, 251, 152)),
+    ("paleturquoise", (175, 238, 238)),
+    ("palevioletred", (219, 112, 147)),
+    ("papayawhip", (255, 239, 213)),
+    ("peachpuff", (255, 218, 185)),
+    ("peru", (205, 133, 63)),
+    ("pink", (255, 192, 203)),
+    ("plum", (221, 160, 221)),
+    ("powderblue", (176, 224, 230)),
+    ("purple", (160, 32, 240)),
+    ("rebeccapurple", (102, 51, 153)),
+    ("red", (255, 0, 0)),
+    ("rosybrown", (188, 143, 143)),
+    ("royalblue", (65, 105, 225)),
+    ("saddlebrown", (139, 69, 19)),
+    ("salmon", (250, 128, 114)),
+    ("sandybrown", (244, 164, 96)),
+    ("seagreen", (46, 139, 87)),
+    ("seashell", (255, 245, 238)),
+    ("sienna", (160, 82, 45)),
+    ("silver", (192, 192, 192)),
+    ("skyblue", (135, 206, 235)),
+    ("slateblue", (106, 90, 205)),
+    ("slategray", (112, 128, 144)),
+    ("slategrey", (112, 128, 144)),
+    ("snow", (255, 250, 250)),
+    ("springgreen", (0, 255, 127)),
+    ("steelblue", (70, 130, 180)),
+    ("tan", (210, 180, 140)),
+    ("teal", (0, 128, 128)),
+    ("thistle", (216, 191, 216)),
+    ("tomato", (255, 99, 71)),
+    ("turquoise", (64, 224, 208)),
+    ("violet", (238, 130, 238)),
+    ("violetred", (208, 32, 144)),
+    ("webgray", (128, 128, 128)),
+    ("webgreen", (0, 128, 0)),
+    ("webmaroon", (128, 0, 0)),
+    ("webpurple", (128, 0, 128)),
+    ("wheat", (245, 222, 179)),
+    ("white", (255, 255, 255)),
+    ("whitesmoke", (245, 245, 245)),
+    ("yellow", (255, 255, 0)),
+    ("yellowgreen", (154, 205, 50)),
+];
+
+/// Names which are not colors, but are accepted by graphviz where colors are expected
+const SPECIAL_COLORS: &[&str] = &["none", "transparent", "invis"];
+
+fn named(name: &str) -> Option<(u8, u8, u8)> {
+    COLOR_TABLE
+        .binary_search_by(|(color, _)| color.cmp(&name))
+        .ok()
+        .map(|index| COLOR_TABLE[index].1)
+}
+
+/// `#rrggbb`, optionally followed by an alpha channel
+fn hex(color: &str) -> Option<(u8, u8, u8)> {
+    let digits = color.strip_prefix('#')?;
+    if !(digits.len() == 6 || digits.len() == 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// The shades of the X11 colors, like `orange3`, and the levels of gray, like `gray50`
+fn is_variant(name: &str) -> bool {
+    let base = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    match name[base.len()..].parse::<u8>() {
+        Ok(level) if base == "gray" || base == "grey" => level <= 100,
+        Ok(shade) => (1..=4).contains(&shade) && named(base).is_some(),
+        Err(_) => false,
+    }
+}
+
+/// `hue saturation value`, each between 0 and 1, separated by commas or spaces
+fn is_hsv(color: &str) -> bool {
+    let values: Vec<&str> = color.split([',', ' ']).filter(|value| !value.is_empty()).collect();
+    values.len() == 3
+        && values
+            .iter()
+            .all(|value| matches!(value.parse::<f32>(), Ok(v) if (0.0..=1.0).contains(&v)))
+}
+
+/// `true` when graphviz understands the color, a name from the `COLOR_TABLE`, an RGB value like `#ff8000` or an HSV
+/// value like `0.1 0.8 1.0`. Gradients like `red:blue` are valid when all their colors are.
+/// Usage:
+/// ```rust
+/// use yuml_rs::is_valid_color;
+///
+/// assert!(is_valid_color("orange") && is_valid_color("#FF8000") && is_valid_color("gray50"));
+/// assert!(!is_valid_color("oragne"));
+/// ```
+pub fn is_valid_color(name: &str) -> bool {
+    name.split(':').all(|color| {
+        // a gradient stop may carry its weight, like `red;0.3`
+        let color = color.split(';').next().unwrap_or(color).trim().to_lowercase();
+        named(&color).is_some()
+            || hex(&color).is_some()
+            || is_variant(&color)
+            || is_hsv(&color)
+            || SPECIAL_COLORS.contains(&color.as_str())
+            // colors of another scheme, like `/blues9/3`
+            || color.starts_with('/')
+    })
+}
+
+/// The perceived brightness of the color, between 0 (black) and 1 (white), for names from the `COLOR_TABLE` and RGB
+/// values. Usage:
+/// ```rust
+/// use yuml_rs::luma;
+///
+/// assert_eq!(luma("white"), Some(1.0));
+/// assert!(luma("navy").unwrap() < 0.5);
+/// assert_eq!(luma("oragne"), None);
+/// ```
+pub fn luma(color: &str) -> Option<f32> {
+    let color = color.trim().to_lowercase();
+    let (r, g, b) = named(&color).or_else(|| hex(&color))?;
+    Some((0.299 * r as f32 + 0.587 * g as f32 + 0.114 * b as f32) / 255.0)
+}
+
+/// The number of single character edits to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Names of the `COLOR_TABLE` which are a few typos away from `name`, closest first
+pub(crate) fn close_matches(name: &str) -> Vec<&'static str> {
+    let name = name.trim().to_lowercase();
+    let mut matches: Vec<(usize, &str)> = COLOR_TABLE
+        .iter()
+        .map(|(color, _)| (edit_distance(&name, color), *color))
+        .filter(|(distance, _)| *distance <= 2)
+        .collect();
+    matches.sort();
+    matches.into_iter().take(3).map(|(_, color)| color).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color_table_is_sorted() {
+        assert!(COLOR_TABLE.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    }
+
+    #[test]
+    fn test_is_valid_color() {
+        for color in [
+            "Orange",
+            "#ff800080",
+            "orange3",
+            "grey100",
+            "0.1, 0.8 1",
+            "red:blue;0.3",
+            "none",
+            "/blues9/3",
+        ] {
+            assert!(is_valid_color(color), "{}", color);
+        }
+        for color in ["oragne", "#ff80", "orange5", "gray101", "1.5 0 0", "red:bleu", ""] {
+            assert!(!is_valid_color(color), "{}", color);
+        }
+    }
+
+    #[test]
+    fn test_close_matches() {
+        assert_eq!(close_matches("oragne"), vec!["orange"]);
+        assert_eq!(close_matches("gren"), vec!["green", "grey", "gray"]);
+        assert!(close_matches("xyzzy").is_empty());
+    }
+}
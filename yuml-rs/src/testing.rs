@@ -0,0 +1,102 @@
+//! Helpers for the tests of crates building on the parser, like analyzers, which should not depend on its internals
+
+use crate::{
+    error::{YumlError, YumlResult},
+    model::dot::{ChartType, DotElement},
+    parse_yuml,
+};
+
+/// A node or edge of a parsed statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestElement {
+    /// The dot id of the node, or of the node the edge starts at, like `A1`
+    pub uid: String,
+    /// The dot id of the node the edge ends at; `None` for nodes
+    pub target: Option<String>,
+    /// The graphviz attributes as written in the dot file, without the quotes around their values
+    pub attributes: Vec<(String, String)>,
+}
+
+impl TestElement {
+    pub fn is_edge(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// The value of the graphviz attribute, like `rectangle` for `shape`
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl From<&DotElement> for TestElement {
+    fn from(e: &DotElement) -> Self {
+        TestElement {
+            uid: e.uid.clone(),
+            target: e.uid2.clone(),
+            attributes: e.dot.attributes(),
+        }
+    }
+}
+
+/// Parse a single statement of a diagram of the chart type into its nodes, followed by its edges.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{testing::{assert_attribute, parse_statement}, ChartType};
+///
+/// let elements = parse_statement(ChartType::Activity, "(Fill Kettle{bg:red})->(Boil Kettle)").unwrap();
+/// assert_eq!(elements.len(), 3);
+/// assert_attribute(&elements[0], "fillcolor", "red");
+/// assert_eq!(elements[2].target.as_deref(), Some("A2"));
+/// ```
+pub fn parse_statement(chart_type: ChartType, line: &str) -> YumlResult<Vec<TestElement>> {
+    if line.trim_end().contains('\n') {
+        return Err(YumlError::InvalidFile("expected a single statement".to_string()));
+    }
+
+    let parsed = parse_yuml(&format!("// {{type:{}}}\n{}", chart_type, line))?;
+    let df = parsed
+        .dot_file()
+        .ok_or_else(|| YumlError::InvalidFile(format!("'{}' diagrams are not supported", chart_type)))?;
+    Ok(df.nodes().chain(df.edges()).map(TestElement::from).collect())
+}
+
+/// Assert that the element has the graphviz attribute with the expected value, listing its attributes otherwise
+#[track_caller]
+pub fn assert_attribute(element: &TestElement, key: &str, expected: &str) {
+    assert!(
+        element.attribute(key) == Some(expected),
+        "expected {}=\"{}\" on {}, found {:?}",
+        key,
+        expected,
+        element.uid,
+        element.attributes
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_statement() {
+        let elements = parse_statement(ChartType::Class, "[Customer{bg:orange}]-.->[Order]").unwrap();
+        assert_eq!(elements.iter().filter(|e| e.is_edge()).count(), 1);
+        assert_attribute(&elements[0], "label", "Customer");
+        assert_attribute(&elements[0], "fillcolor", "orange");
+        assert_attribute(&elements[2], "style", "dashed");
+        assert_eq!(elements[0].attribute("tooltip"), None);
+
+        assert!(parse_statement(ChartType::Class, "[A]->[B]\n[B]->[C]").is_err());
+        assert!(parse_statement(ChartType::Sequence, "[A]->[B]").is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "expected fillcolor=\"red\" on A1")]
+    fn test_assert_attribute() {
+        let elements = parse_statement(ChartType::Activity, "(a{bg:blue})").unwrap();
+        assert_attribute(&elements[0], "fillcolor", "red");
+    }
+}
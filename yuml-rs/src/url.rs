@@ -0,0 +1,190 @@
+use crate::{
+    error::{YumlError, YumlResult},
+    model::dot::{ChartType, Directions},
+};
+use itertools::Itertools;
+use std::convert::TryFrom;
+
+const YUML_ME_DIAGRAM: &str = "https://yuml.me/diagram/";
+const YUML_ME_STYLE: &str = "scruffy";
+
+/// Encode yUML text into a diagram URL for the hosted [yuml.me](https://yuml.me) service.
+///
+/// Comment lines are dropped, apart from the `direction` directive which is passed as a style option.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{to_yuml_me_url, ChartType};
+///
+/// let url = to_yuml_me_url(ChartType::Class, "[Customer]->[Order]");
+/// assert_eq!(url, "https://yuml.me/diagram/scruffy/class/%5BCustomer%5D-%3E%5BOrder%5D");
+/// ```
+pub fn to_yuml_me_url(chart_type: ChartType, text: &str) -> String {
+    let mut dir = None;
+    let mut statements = vec![];
+
+    for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        if let Some(comment) = line.strip_prefix("//") {
+            if let Some(value) = directive_value(comment, "direction") {
+                dir = Directions::try_from(value).ok();
+            }
+        } else {
+            statements.push(line);
+        }
+    }
+
+    let style = match dir {
+        Some(dir) if dir != Directions::TopDown => format!("{};dir:{}", YUML_ME_STYLE, dir),
+        _ => YUML_ME_STYLE.to_string(),
+    };
+
+    format!(
+        "{}{}/{}/{}",
+        YUML_ME_DIAGRAM,
+        style,
+        chart_type,
+        percent_encode(&statements.join(", "))
+    )
+}
+
+/// Decode a [yuml.me](https://yuml.me) diagram URL into yUML text, including the `type` directive
+/// Usage:
+/// ```rust
+/// use yuml_rs::from_yuml_me_url;
+///
+/// let yuml = from_yuml_me_url("https://yuml.me/diagram/scruffy/class/[Customer]->[Order], [Order]->[Item]").unwrap();
+/// assert_eq!(yuml, "// {type:class}\n[Customer]->[Order]\n[Order]->[Item]\n");
+/// ```
+pub fn from_yuml_me_url(url: &str) -> YumlResult<String> {
+    let invalid = || YumlError::InvalidUrl(url.to_string());
+
+    let (_, path) = url.split_once("/diagram/").ok_or_else(invalid)?;
+    let mut segments = path.splitn(3, '/');
+    let style = segments.next().ok_or_else(invalid)?;
+    let chart_type = segments.next().ok_or_else(invalid)?;
+    let body = segments.next().ok_or_else(invalid)?;
+
+    let chart_type = ChartType::try_from(chart_type)?;
+    let body = percent_decode(body).ok_or_else(invalid)?;
+    // a trailing file extension selects the image format on yuml.me
+    let body = [".svg", ".png", ".jpg", ".pdf", ".json"]
+        .iter()
+        .find_map(|ext| body.strip_suffix(ext))
+        .unwrap_or(&body);
+
+    let mut yuml = format!("// {{type:{}}}\n", chart_type);
+    let dir = style.split(';').find_map(|option| option.strip_prefix("dir:"));
+    match dir {
+        Some("LR") => yuml.push_str("// {direction:leftToRight}\n"),
+        Some("RL") => yuml.push_str("// {direction:rightToLeft}\n"),
+        _ => {}
+    }
+
+    for statement in split_statements(body) {
+        yuml.push_str(statement);
+        yuml.push('\n');
+    }
+
+    Ok(yuml)
+}
+
+fn directive_value<'a>(comment: &'a str, key: &str) -> Option<&'a str> {
+    let directive = comment.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let (k, v) = directive.split_once(':')?;
+    if k.trim() == key {
+        Some(v.trim())
+    } else {
+        None
+    }
+}
+
+/// Split on commas which are not part of a node, so labels may still contain commas
+fn split_statements(body: &str) -> Vec<&str> {
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut statements = vec![];
+
+    for (idx, c) in body.char_indices() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                statements.push(&body[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push(&body[start..]);
+
+    statements
+        .into_iter()
+        .map(str::trim)
+        .filter(|statement| !statement.is_empty())
+        .collect()
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b'(' | b')' | b'*' | b'+' | b',' | b';' | b':' | b'=' | b'@' | b'!' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .join("")
+}
+
+fn percent_decode(text: &str) -> Option<String> {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'%' => {
+                let hex = text.get(idx + 1..idx + 3)?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                idx += 3;
+            }
+            b => {
+                decoded.push(b);
+                idx += 1;
+            }
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let yuml = "// {type:activity}\n// {direction:leftToRight}\n(start)-><a>[kettle empty]->(Fill Kettle)\n<a>[kettle full]->(end)\n";
+        let url = to_yuml_me_url(ChartType::Activity, yuml);
+        assert!(url.starts_with("https://yuml.me/diagram/scruffy;dir:LR/activity/(start)-%3E%3Ca%3E"));
+        assert_eq!(from_yuml_me_url(&url).unwrap(), yuml);
+    }
+
+    #[test]
+    fn test_split_statements() {
+        assert_eq!(
+            split_statements("[Customer]->[Order], [note: one, two]  ,[Order]"),
+            vec!["[Customer]->[Order]", "[note: one, two]", "[Order]"]
+        );
+    }
+
+    #[test]
+    fn test_from_url_with_extension() {
+        let yuml = from_yuml_me_url("https://yuml.me/diagram/plain/class/%5BA%5D-%3E%5BB%5D.svg").unwrap();
+        assert_eq!(yuml, "// {type:class}\n[A]->[B]\n");
+    }
+
+    #[test]
+    fn test_invalid_url() {
+        assert!(from_yuml_me_url("https://yuml.me/").is_err());
+        assert!(from_yuml_me_url("https://yuml.me/diagram/scruffy/unknown/[A]").is_err());
+        assert!(from_yuml_me_url("https://yuml.me/diagram/scruffy/class/%5").is_err());
+    }
+}
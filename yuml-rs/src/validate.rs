@@ -0,0 +1,851 @@
+use crate::{
+    error::{OptionsError, YumlError, YumlResult},
+    limits::ParseOptions,
+    macros::expand_macros,
+    model::{
+        activity,
+        attributes::{is_safe_attribute, parse_attributes, parse_graph_attributes, EDGE_ATTRIBUTES, NODE_ATTRIBUTES},
+        dot::ChartType,
+        shared::{normalize_label, LabeledElement},
+    },
+    parser::{
+        activity::parse_activity_statements, class::parse_class_statements, determine_file_options,
+        er::parse_er_statements, mindmap::parse_mindmap_statements, parse_headers, timeline::parse_timeline_statements,
+        Header, Statement, DIRECTIVES,
+    },
+    rgb::{close_matches, is_valid_color},
+};
+use itertools::Itertools;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fmt::{Display, Formatter},
+};
+
+/// Attributes of which the value is a color
+const COLOR_ATTRIBUTES: &[&str] = &["bg", "color", "fillcolor", "fontcolor"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WarningKind {
+    /// An arrow without a node on one of its ends
+    DanglingArrow,
+    /// The same label is used for different kinds of elements, which are merged into one node
+    DuplicateLabel,
+    /// A parallel bar which is only mentioned once, so it neither forks nor joins flows
+    UnreferencedParallel,
+    UnknownDirective,
+    /// A key in an attribute block which is not applied to the node, like `[Customer{colour:red}]`
+    UnknownAttribute,
+    /// A color which graphviz does not know, like `[Customer{bg:oragne}]`
+    UnknownColor,
+    /// A link which is not applied, as it is neither relative nor an http, https or mailto URL, like
+    /// `[Customer{href:javascript:alert(1)}]`
+    UnsafeLink,
+    /// A node which is defined again with other attributes, like `[Order{bg:red}]` and `[Order{bg:green}]`; only one
+    /// of the definitions is applied
+    ConflictingDefinition,
+    /// A diagram without any elements
+    EmptyDiagram,
+    UnsupportedChartType,
+}
+
+/// How an issue found by the linter is treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    Allow,
+    Warn,
+    /// Fail instead of rendering the diagram
+    Deny,
+}
+
+/// The lint level for each kind of issue; issues are warnings unless configured otherwise
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    levels: HashMap<WarningKind, LintLevel>,
+    strict: bool,
+}
+
+impl LintConfig {
+    /// Deny all issues without an explicit level, like the `// {strict:true}` directive
+    pub fn strict() -> Self {
+        LintConfig {
+            strict: true,
+            ..LintConfig::default()
+        }
+    }
+
+    pub fn level(mut self, kind: WarningKind, level: LintLevel) -> Self {
+        self.levels.insert(kind, level);
+        self
+    }
+
+    pub fn level_of(&self, kind: WarningKind) -> LintLevel {
+        match self.levels.get(&kind) {
+            Some(level) => *level,
+            None if self.strict => LintLevel::Deny,
+            None => LintLevel::Warn,
+        }
+    }
+}
+
+/// A non-fatal issue found in a yUML document, with the 1-based line number it occurs on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub line: usize,
+    pub kind: WarningKind,
+    pub message: String,
+}
+
+/// The reason why a yUML document can not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseFailure {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ValidationReport {
+    pub chart_type: Option<ChartType>,
+    pub warnings: Vec<Warning>,
+    pub error: Option<ParseFailure>,
+}
+
+impl ValidationReport {
+    /// `true` when the document can be parsed; warnings do not affect validity
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none()
+    }
+
+    fn warn(&mut self, line: usize, kind: WarningKind, message: String) {
+        self.warnings.push(Warning { line, kind, message });
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("line {}: {}", self.line, self.message))
+    }
+}
+
+impl Display for ParseFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("line {}: {}", self.line, self.message))
+    }
+}
+
+/// Validate the yUML document, failing on the issues denied by the `config` or, with the `// {strict:true}`
+/// directive, on all issues without an explicit level. Returns the remaining warnings.
+/// Usage:
+/// ```rust
+/// use yuml_rs::{lint_yuml, LintConfig, LintLevel, WarningKind};
+///
+/// let yuml = "// {type:activity}\n(start)->(Boil Kettle)->\n";
+/// assert_eq!(lint_yuml(yuml, &LintConfig::default()).unwrap().len(), 1);
+///
+/// let config = LintConfig::default().level(WarningKind::DanglingArrow, LintLevel::Deny);
+/// assert!(lint_yuml(yuml, &config).is_err());
+/// ```
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub fn lint_yuml(yuml: &str, config: &LintConfig) -> YumlResult<Vec<Warning>> {
+    let report = validate_yuml(yuml);
+    if let Some(error) = report.error {
+        return Err(YumlError::InvalidFile(error.to_string()));
+    }
+
+    let config = LintConfig {
+        strict: config.strict || is_strict(yuml),
+        ..config.clone()
+    };
+
+    let (denied, warnings): (Vec<Warning>, Vec<Warning>) = report
+        .warnings
+        .into_iter()
+        .filter(|w| config.level_of(w.kind) != LintLevel::Allow)
+        .partition(|w| config.level_of(w.kind) == LintLevel::Deny);
+
+    match denied.as_slice() {
+        [] => Ok(warnings),
+        denied => Err(YumlError::Lint(
+            denied.iter().map(|w| w.to_string()).collect::<Vec<_>>().join("; "),
+        )),
+    }
+}
+
+/// Whether the document has the `// {strict:true}` directive
+pub(crate) fn is_strict(yuml: &str) -> bool {
+    parse_headers(yuml).is_ok_and(|(_, headers)| determine_file_options(&headers).strict)
+}
+
+/// Parse the yUML document and report issues, without rendering it. Macros are expanded first, like when parsing.
+/// Usage:
+/// ```rust
+/// use yuml_rs::validate_yuml;
+///
+/// let report = validate_yuml("// {type:activity}\n(start)->(Boil Kettle)->\n");
+/// assert!(report.is_valid());
+/// assert_eq!(report.warnings[0].to_string(), "line 2: arrow after 'Boil Kettle' does not point to anything");
+/// ```
+pub fn validate_yuml(yuml: &str) -> ValidationReport {
+    let mut report = ValidationReport::default();
+
+    // the expansion keeps the statements on their lines
+    let expanded = match expand_macros(yuml, &ParseOptions::default()) {
+        Ok(expanded) => expanded,
+        Err(YumlError::Macro { name, message }) => {
+            report.error = Some(ParseFailure {
+                line: line_of_define(yuml, &name),
+                message: format!("invalid macro '{}': {}", name, message),
+            });
+            return report;
+        }
+        // invalid directives are reported below
+        Err(_) => Cow::Borrowed(yuml),
+    };
+    let yuml = expanded.as_ref();
+
+    let (body, headers) = match parse_headers(yuml) {
+        Ok(parsed) => parsed,
+        Err(err) => {
+            report.error = Some(as_failure(yuml, err));
+            return report;
+        }
+    };
+
+    let body_offset = line_of(yuml, body) - 1;
+    report.warnings.extend(unknown_directives(yuml, &headers));
+    report.warnings.extend(invalid_graph_attributes(yuml, &headers));
+    if let Some(conflict) = conflicting_directive(yuml, &headers) {
+        report.error = Some(conflict);
+        return report;
+    }
+
+    let options = determine_file_options(&headers);
+    report.chart_type = options.chart_type;
+
+    match options.chart_type {
+        Some(ChartType::Activity) => match parse_activity_statements(body, &options) {
+            Ok((_, statements)) => {
+                validate_statements(&statements, body_offset, &mut report);
+                validate_parallel_bars(&statements, body_offset, &mut report);
+                validate_not_empty(&statements, body_offset, &mut report);
+            }
+            Err(err) => report.error = Some(as_failure(yuml, err)),
+        },
+        Some(ChartType::Class) => match parse_class_statements(body, &options) {
+            Ok((_, statements)) => {
+                validate_statements(&statements, body_offset, &mut report);
+                validate_not_empty(&statements, body_offset, &mut report);
+            }
+            Err(err) => report.error = Some(as_failure(yuml, err)),
+        },
+        Some(ChartType::Timeline) => match parse_timeline_statements(body, &options) {
+            Ok((_, statements)) => {
+                validate_statements(&statements, body_offset, &mut report);
+                validate_not_empty(&statements, body_offset, &mut report);
+            }
+            Err(err) => report.error = Some(as_failure(yuml, err)),
+        },
+        Some(ChartType::Er) => match parse_er_statements(body, &options) {
+            Ok((_, statements)) => {
+                validate_statements(&statements, body_offset, &mut report);
+                validate_not_empty(&statements, body_offset, &mut report);
+            }
+            Err(err) => report.error = Some(as_failure(yuml, err)),
+        },
+        Some(ChartType::Mindmap) => match parse_mindmap_statements(body, &options) {
+            Ok((_, statements)) => {
+                validate_statements(&statements, body_offset, &mut report);
+                validate_not_empty(&statements, body_offset, &mut report);
+            }
+            Err(err) => report.error = Some(as_failure(yuml, err)),
+        },
+        Some(chart_type) => report.warn(
+            1,
+            WarningKind::UnsupportedChartType,
+            format!("'{}' diagrams are not supported", chart_type),
+        ),
+        None => report.warn(
+            1,
+            WarningKind::UnsupportedChartType,
+            "missing or invalid 'type' directive".to_string(),
+        ),
+    }
+
+    report
+}
+
+fn validate_statements<T: LabeledElement>(statements: &[Statement<T>], offset: usize, report: &mut ValidationReport) {
+    let mut kinds: HashMap<String, (&str, usize)> = HashMap::new();
+
+    for statement in statements {
+        let line = offset + statement.line + 1;
+        let elements = &statement.elements;
+
+        for (pos, e) in elements.iter().enumerate() {
+            if e.is_connection() {
+                report.warnings.extend(dangling_arrow(elements, pos, line));
+            }
+
+            for (key, value) in e.attributes().map(parse_attributes).unwrap_or_default() {
+                let known = match e.is_connection() {
+                    true => EDGE_ATTRIBUTES.contains(&key),
+                    false => key == "bg" || key == "rank" || NODE_ATTRIBUTES.contains(&key),
+                };
+                if !known {
+                    report.warn(
+                        line,
+                        WarningKind::UnknownAttribute,
+                        format!("unknown attribute '{}' on {}", key, describe(e)),
+                    );
+                } else if !is_safe_attribute(key, value) {
+                    report.warn(
+                        line,
+                        WarningKind::UnsafeLink,
+                        format!("link '{}' on {} is not applied", value, describe(e)),
+                    );
+                }
+            }
+
+            report.warnings.extend(unknown_colors(e, line));
+            if e.is_connection() {
+                continue;
+            }
+
+            match kinds.get(&normalize_label(e.label())) {
+                Some((kind, first_line)) if *kind != e.kind() => report.warn(
+                    line,
+                    WarningKind::DuplicateLabel,
+                    format!(
+                        "{} '{}' has the same label as the {} on line {}",
+                        e.kind(),
+                        e.label(),
+                        kind,
+                        first_line
+                    ),
+                ),
+                Some(_) => {}
+                None => {
+                    kinds.insert(normalize_label(e.label()), (e.kind(), line));
+                }
+            }
+        }
+    }
+
+    report.warnings.extend(conflicting_definitions(statements, offset));
+}
+
+/// The kind of the element with its label, like `class 'Customer'`; connections are often unlabeled
+fn describe<T: LabeledElement>(e: &T) -> String {
+    match e.label() {
+        "" => e.kind().to_string(),
+        label => format!("{} '{}'", e.kind(), label),
+    }
+}
+
+/// Warn about the colors in the attributes of the element which graphviz does not know, suggesting the names they may
+/// be a typo of
+fn unknown_colors<T: LabeledElement>(e: &T, line: usize) -> Vec<Warning> {
+    e.attributes()
+        .map(parse_attributes)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(key, value)| COLOR_ATTRIBUTES.contains(key) && !is_valid_color(value))
+        .map(|(_, value)| {
+            let suggestions = close_matches(value);
+            let message = match suggestions.as_slice() {
+                [] => format!("unknown color '{}' on {}", value, describe(e)),
+                _ => format!(
+                    "unknown color '{}' on {}, did you mean {}?",
+                    value,
+                    describe(e),
+                    suggestions.iter().map(|color| format!("'{}'", color)).join(" or ")
+                ),
+            };
+            Warning {
+                line,
+                kind: WarningKind::UnknownColor,
+                message,
+            }
+        })
+        .collect()
+}
+
+/// Warnings for the unknown colors of the elements of the statements; graphviz draws these elements in black
+pub(crate) fn unknown_element_colors<T: LabeledElement>(statements: &[Statement<T>], offset: usize) -> Vec<Warning> {
+    statements
+        .iter()
+        .flat_map(|statement| {
+            statement
+                .elements
+                .iter()
+                .flat_map(move |e| unknown_colors(e, offset + statement.line + 1))
+        })
+        .collect()
+}
+
+/// Whether both attribute blocks are given and set different attributes, regardless of their order
+fn conflicting_attributes(first: Option<&str>, second: Option<&str>) -> bool {
+    match (first, second) {
+        (Some(first), Some(second)) => {
+            parse_attributes(first).into_iter().sorted().collect_vec()
+                != parse_attributes(second).into_iter().sorted().collect_vec()
+        }
+        _ => false,
+    }
+}
+
+/// Warnings for the nodes which are defined again with other attributes. The nodes are keyed by their label, so the
+/// first definition is used, unless a later one has an explicit id.
+pub(crate) fn conflicting_definitions<T: LabeledElement>(statements: &[Statement<T>], offset: usize) -> Vec<Warning> {
+    let mut definitions: HashMap<String, (usize, &T)> = HashMap::new();
+    let mut warnings = vec![];
+
+    for statement in statements {
+        let line = offset + statement.line + 1;
+        for e in statement.elements.iter().filter(|e| !e.is_connection()) {
+            let key = normalize_label(e.label());
+            let (first_line, definition) = match definitions.get(&key) {
+                Some(definition) => *definition,
+                None => {
+                    definitions.insert(key, (line, e));
+                    continue;
+                }
+            };
+
+            let redefines = e.has_id() && !definition.has_id();
+            if conflicting_attributes(definition.attributes(), e.attributes()) {
+                warnings.push(Warning {
+                    line,
+                    kind: WarningKind::ConflictingDefinition,
+                    message: format!(
+                        "{} is defined with {{{}}} here and with {{{}}} on line {}, {}",
+                        describe(e),
+                        e.attributes().unwrap_or_default(),
+                        definition.attributes().unwrap_or_default(),
+                        first_line,
+                        match redefines {
+                            true => "this definition is used as it has an id",
+                            false => "the first definition is used",
+                        }
+                    ),
+                });
+            }
+            if redefines {
+                definitions.insert(key, (line, e));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Warn about the directives which are not understood, and therefore ignored, with the `headers` of the `yuml`
+pub(crate) fn unknown_directives(yuml: &str, headers: &[Header]) -> Vec<Warning> {
+    headers
+        .iter()
+        .filter(|header| !DIRECTIVES.contains(&header.key))
+        .map(|header| Warning {
+            line: line_of_header(yuml, header),
+            kind: WarningKind::UnknownDirective,
+            message: format!("unknown directive '{}'", header.key),
+        })
+        .collect()
+}
+
+/// Warnings for the attributes of `graphviz` directives which are not applied, as they are not supported or their
+/// value could break out of the graph attributes
+pub(crate) fn invalid_graph_attributes(yuml: &str, headers: &[Header]) -> Vec<Warning> {
+    headers
+        .iter()
+        .filter(|header| header.key == "graphviz")
+        .flat_map(|header| {
+            parse_graph_attributes(header.value)
+                .into_iter()
+                .filter_map(Result::err)
+                .map(|message| Warning {
+                    line: line_of_header(yuml, header),
+                    kind: WarningKind::UnknownAttribute,
+                    message,
+                })
+        })
+        .collect()
+}
+
+/// Directives which may be given more than once, each adding to the diagram
+const REPEATABLE_DIRECTIVES: &[&str] = &["samerank", "include", "define", "graphviz"];
+
+/// The first directive that sets an option to another value than a directive before it, like a second `direction`
+pub(crate) fn conflicting_directive(yuml: &str, headers: &[Header]) -> Option<ParseFailure> {
+    headers.iter().enumerate().find_map(|(idx, header)| {
+        let previous = headers[..idx]
+            .iter()
+            .find(|previous| previous.key == header.key && previous.value != header.value)
+            .filter(|_| !REPEATABLE_DIRECTIVES.contains(&header.key))?;
+        Some(ParseFailure {
+            line: line_of_header(yuml, header),
+            message: format!(
+                "'{}' is set to '{}' on line {} and to '{}' here",
+                header.key,
+                previous.value,
+                line_of_header(yuml, previous),
+                header.value
+            ),
+        })
+    })
+}
+
+/// Fail when directives set an option to different values
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all))]
+pub(crate) fn check_directives(yuml: &str) -> YumlResult<()> {
+    let (_, headers) = parse_headers(yuml).map_err(|err| YumlError::InvalidFile(err.to_string()))?;
+    match conflicting_directive(yuml, &headers) {
+        Some(conflict) => Err(OptionsError::new(&conflict.to_string()).into()),
+        None => Ok(()),
+    }
+}
+
+/// The 1-based line of a directive parsed from the `yuml`
+fn line_of_header(yuml: &str, header: &Header) -> usize {
+    let offset = (header.key.as_ptr() as usize).saturating_sub(yuml.as_ptr() as usize);
+    yuml.get(..offset).map_or(1, |before| before.matches('\n').count() + 1)
+}
+
+/// Warn about an arrow at `pos` which is missing an element on one of its ends; these arrows are left out of the graph
+fn dangling_arrow<T: LabeledElement>(elements: &[T], pos: usize, line: usize) -> Option<Warning> {
+    let e = &elements[pos];
+    let previous = pos.checked_sub(1).and_then(|p| elements.get(p));
+    let next = elements.get(pos + 1);
+    let message = match (previous, next) {
+        (Some(previous), Some(next)) if !previous.is_connection() && !next.is_connection() => return None,
+        (Some(previous), _) if !previous.is_connection() => {
+            format!("{} after '{}' does not point to anything", e.kind(), previous.label())
+        }
+        (_, Some(next)) if !next.is_connection() => {
+            format!("{} before '{}' does not start anywhere", e.kind(), next.label())
+        }
+        _ => format!("{} is not connected to anything", e.kind()),
+    };
+
+    Some(Warning {
+        line,
+        kind: WarningKind::DanglingArrow,
+        message,
+    })
+}
+
+/// Warnings for the arrows of the statements that are not drawn, as they are missing an element on one of their ends
+pub(crate) fn dangling_arrows<T: LabeledElement>(statements: &[Statement<T>], offset: usize) -> Vec<Warning> {
+    statements
+        .iter()
+        .flat_map(|statement| {
+            let elements = &statement.elements;
+            (0..elements.len())
+                .filter(|pos| elements[*pos].is_connection())
+                .filter_map(move |pos| dangling_arrow(elements, pos, offset + statement.line + 1))
+        })
+        .collect()
+}
+
+fn validate_not_empty<T: LabeledElement>(statements: &[Statement<T>], offset: usize, report: &mut ValidationReport) {
+    if statements.iter().all(|s| s.elements.is_empty()) {
+        report.warn(
+            offset + 1,
+            WarningKind::EmptyDiagram,
+            "the diagram is empty".to_string(),
+        );
+    }
+}
+
+fn validate_parallel_bars(statements: &[Statement<activity::Element>], offset: usize, report: &mut ValidationReport) {
+    let mut references: HashMap<String, (usize, usize)> = HashMap::new();
+
+    for statement in statements {
+        for e in statement
+            .elements
+            .iter()
+            .filter(|e| matches!(e, activity::Element::Parallel(_)))
+        {
+            let entry = references
+                .entry(normalize_label(e.label()))
+                .or_insert((offset + statement.line + 1, 0));
+            entry.1 += 1;
+        }
+    }
+
+    let mut unreferenced: Vec<(String, usize)> = references
+        .into_iter()
+        .filter(|(_, (_, count))| *count == 1)
+        .map(|(label, (line, _))| (label, line))
+        .collect();
+    unreferenced.sort_by_key(|(_, line)| *line);
+
+    for (label, line) in unreferenced {
+        report.warn(
+            line,
+            WarningKind::UnreferencedParallel,
+            format!("parallel bar '{}' is only used once", label),
+        );
+    }
+}
+
+/// The line of the `define` directive of the macro, or the first line when it is not found
+fn line_of_define(yuml: &str, name: &str) -> usize {
+    yuml.lines()
+        .position(|line| {
+            let definition = line
+                .split_once("{define:")
+                .map(|(_, definition)| definition.trim_start());
+            definition.is_some_and(|definition| definition.starts_with(name))
+        })
+        .map_or(1, |idx| idx + 1)
+}
+
+pub(crate) fn line_of(yuml: &str, remaining: &str) -> usize {
+    let consumed = yuml.len().saturating_sub(remaining.len());
+    yuml[..consumed].matches('\n').count() + 1
+}
+
+fn as_failure(yuml: &str, err: nom::Err<nom::error::Error<&str>>) -> ParseFailure {
+    match err {
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            let statement = e.input.lines().next().unwrap_or_default();
+            ParseFailure {
+                line: line_of(yuml, e.input),
+                message: format!("invalid statement '{}'", statement),
+            }
+        }
+        nom::Err::Incomplete(_) => ParseFailure {
+            line: line_of(yuml, ""),
+            message: "unexpected end of file".to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(report: &ValidationReport) -> Vec<(usize, WarningKind)> {
+        report.warnings.iter().map(|w| (w.line, w.kind)).collect()
+    }
+
+    #[test]
+    fn test_valid_files() {
+        for yuml in [
+            include_str!("../test/activity.yuml"),
+            include_str!("../test/activity_2.yuml"),
+            include_str!("../test/class.yuml"),
+            include_str!("../test/activity_crlf.yuml"),
+            include_str!("../test/class_crlf.yuml"),
+        ] {
+            let report = validate_yuml(yuml);
+            assert!(report.is_valid());
+            assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+        }
+    }
+
+    #[test]
+    fn test_dangling_arrows() {
+        let report = validate_yuml("// {type:activity}\n(a)->\n->(b)\n(c)->->(d)\n");
+        assert_eq!(
+            kinds(&report),
+            vec![
+                (2, WarningKind::DanglingArrow),
+                (3, WarningKind::DanglingArrow),
+                (4, WarningKind::DanglingArrow),
+                (4, WarningKind::DanglingArrow)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_labels() {
+        let report = validate_yuml("// {type:activity}\n(a)->(b)\n<a>->(b)\n");
+        assert_eq!(kinds(&report), vec![(3, WarningKind::DuplicateLabel)]);
+        assert_eq!(
+            report.warnings[0].message,
+            "decision 'a' has the same label as the activity on line 2"
+        );
+    }
+
+    #[test]
+    fn test_comma_separated_statements() {
+        let report = validate_yuml("// {type:class}\n[a]->[b], [b]->\n[c]\n");
+        assert_eq!(kinds(&report), vec![(2, WarningKind::DanglingArrow)]);
+    }
+
+    #[test]
+    fn test_unreferenced_parallel() {
+        let report = validate_yuml("// {type:activity}\n(a)->|b|->(c)\n(c)->|d|\n|d|->(e)\n");
+        assert_eq!(kinds(&report), vec![(2, WarningKind::UnreferencedParallel)]);
+    }
+
+    #[test]
+    fn test_unknown_directive() {
+        let report = validate_yuml("\n// {type:class}\n// {colour:red}\n[a]\n");
+        assert_eq!(kinds(&report), vec![(3, WarningKind::UnknownDirective)]);
+        assert_eq!(report.chart_type, Some(ChartType::Class));
+    }
+
+    #[test]
+    fn test_invalid_graph_attributes() {
+        let yuml = "// {type:class}\n// {graphviz: nodesep=0.3; overlap=false}\n// {graphviz: imagepath=/etc; pad=\"]; x [}\n[a]";
+        let report = validate_yuml(yuml);
+        assert_eq!(
+            report.warnings.iter().map(|w| w.to_string()).collect::<Vec<_>>(),
+            vec![
+                "line 3: graphviz attribute 'imagepath' is not supported",
+                "line 3: invalid value '\"]' for graphviz attribute 'pad'",
+                "line 3: graphviz attribute 'x [' has no value",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directives_in_body() {
+        let report =
+            validate_yuml("// {type:class}\n[a]->[b]\n// {colour:red}\n// {direction:leftToRight}\n[b]->[c]\n");
+        assert!(report.is_valid());
+        assert_eq!(kinds(&report), vec![(3, WarningKind::UnknownDirective)]);
+
+        let report = validate_yuml("// {type:class}\n// {direction:topDown}\n[a]->[b]\n// {direction:leftToRight}\n");
+        assert_eq!(
+            report.error.map(|error| error.to_string()),
+            Some("line 4: 'direction' is set to 'topDown' on line 2 and to 'leftToRight' here".to_string())
+        );
+        assert!(validate_yuml("// {type:class}\n// {samerank:[a],[b]}\n[a]->[b]\n// {samerank:[b],[c]}\n").is_valid());
+    }
+
+    #[test]
+    fn test_unsupported_chart() {
+        let report = validate_yuml("// {type:sequence}\n[a]\n");
+        assert_eq!(kinds(&report), vec![(1, WarningKind::UnsupportedChartType)]);
+    }
+
+    #[test]
+    fn test_unknown_attribute() {
+        let report = validate_yuml("// {type:class}\n[a{bg:red,colour:blue}]->{shape:box}[note: b{shape:box}]\n");
+        assert_eq!(
+            kinds(&report),
+            vec![(2, WarningKind::UnknownAttribute), (2, WarningKind::UnknownAttribute)]
+        );
+        assert_eq!(report.warnings[0].message, "unknown attribute 'colour' on class 'a'");
+        assert_eq!(report.warnings[1].message, "unknown attribute 'shape' on connection");
+    }
+
+    #[test]
+    fn test_macros() {
+        let report = validate_yuml("// {type:class}\n// {define:C=[Clerk]}\nC->[Order]\n[Order]->\n");
+        assert_eq!(kinds(&report), vec![(4, WarningKind::DanglingArrow)]);
+
+        let report = validate_yuml("// {type:class}\n// {define:A=[B]}\n// {define:C=A->C}\nC\n");
+        assert_eq!(
+            report.error,
+            Some(ParseFailure {
+                line: 3,
+                message: "invalid macro 'C': refers to itself".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_unsafe_link() {
+        let report = validate_yuml("// {type:class}\n[a{href:javascript:alert(1)}]->{href:/docs/b.html}[b]\n");
+        assert_eq!(kinds(&report), vec![(2, WarningKind::UnsafeLink)]);
+        assert_eq!(
+            report.warnings[0].message,
+            "link 'javascript:alert(1)' on class 'a' is not applied"
+        );
+    }
+
+    #[test]
+    fn test_unknown_color() {
+        let yuml = "// {type:class}\n[a{bg:oragne}]->[b{bg:#ff8000,fontcolor:xyzzy}]\n";
+        let report = validate_yuml(yuml);
+        assert_eq!(
+            kinds(&report),
+            vec![(2, WarningKind::UnknownColor), (2, WarningKind::UnknownColor)]
+        );
+        assert_eq!(
+            report.warnings[0].message,
+            "unknown color 'oragne' on class 'a', did you mean 'orange'?"
+        );
+        assert_eq!(report.warnings[1].message, "unknown color 'xyzzy' on class 'b'");
+
+        // the warnings are also collected while parsing
+        assert_eq!(crate::parse_yuml(yuml).unwrap().warnings().len(), 2);
+    }
+
+    #[test]
+    fn test_conflicting_definitions() {
+        let yuml = "// {type:class}\n[Order{bg:red}]->[Item]\n[Customer]->[Order{bg:green}]\n[Order]\n";
+        let report = validate_yuml(yuml);
+        assert_eq!(kinds(&report), vec![(3, WarningKind::ConflictingDefinition)]);
+        assert_eq!(
+            report.warnings[0].message,
+            "class 'Order' is defined with {bg:green} here and with {bg:red} on line 2, the first definition is used"
+        );
+        assert_eq!(crate::parse_yuml(yuml).unwrap().warnings().len(), 1);
+
+        // the same attributes in another order do not conflict
+        let report =
+            validate_yuml("// {type:activity}\n(a{bg:red,fontcolor:white})->(b)\n(b)->( a{fontcolor:white, bg:red})\n");
+        assert!(report.warnings.is_empty(), "{:?}", report.warnings);
+
+        let report = validate_yuml("// {type:activity}\n(a{bg:red})->(b)\n(id=a|Apply{bg:green})->(b)\n(a{bg:blue})\n");
+        assert_eq!(
+            report.warnings.iter().map(|w| w.message.as_str()).collect_vec(),
+            vec![
+                "activity 'a' is defined with {bg:green} here and with {bg:red} on line 2, this definition is used as \
+                 it has an id",
+                "activity 'a' is defined with {bg:blue} here and with {bg:green} on line 3, the first definition is used"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_diagram() {
+        let report = validate_yuml("// {type:activity}\n\n");
+        assert_eq!(kinds(&report), vec![(2, WarningKind::EmptyDiagram)]);
+    }
+
+    #[test]
+    fn test_lint_levels() {
+        let yuml = "// {type:activity}\n(a)->(b)\n<a>->(b{colour:red})\n";
+        let warnings = lint_yuml(yuml, &LintConfig::default()).unwrap();
+        assert_eq!(warnings.len(), 2);
+
+        let config = LintConfig::default().level(WarningKind::UnknownAttribute, LintLevel::Allow);
+        let warnings = lint_yuml(yuml, &config).unwrap();
+        assert_eq!(
+            warnings.iter().map(|w| w.kind).collect::<Vec<_>>(),
+            vec![WarningKind::DuplicateLabel]
+        );
+
+        let config = config.level(WarningKind::DuplicateLabel, LintLevel::Deny);
+        assert_eq!(
+            lint_yuml(yuml, &config).err().map(|e| e.to_string()),
+            Some("Diagram has issues: line 3: decision 'a' has the same label as the activity on line 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_strict() {
+        let yuml = "// {type:sequence}\n[a]\n";
+        assert!(lint_yuml(yuml, &LintConfig::strict()).is_err());
+        let config = LintConfig::strict().level(WarningKind::UnsupportedChartType, LintLevel::Allow);
+        assert_eq!(lint_yuml(yuml, &config).unwrap(), vec![]);
+
+        let yuml = "// {type:class}\n// {strict:true}\n[a]->\n";
+        assert!(lint_yuml(yuml, &LintConfig::default()).is_err());
+        let config = LintConfig::default().level(WarningKind::DanglingArrow, LintLevel::Warn);
+        assert_eq!(lint_yuml(yuml, &config).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_invalid_statement() {
+        let report = validate_yuml("// {type:activity}\n(a)->(b)\n(c\n");
+        assert!(!report.is_valid());
+        assert_eq!(report.error.map(|e| e.line), Some(3));
+    }
+}
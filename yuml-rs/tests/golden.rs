@@ -0,0 +1,89 @@
+//! Render the diagrams of `test/` with graphviz and compare the SVG with the snapshots in `test/snapshots/`.
+//!
+//! Missing snapshots are recorded on the first run; set `YUML_UPDATE_SNAPSHOTS=1` to overwrite the snapshots after an
+//! intended change of the output. The test is skipped when graphviz is not installed.
+
+#![cfg(feature = "render")]
+
+use std::{
+    env,
+    fs::{self, read_to_string},
+    path::{Path, PathBuf},
+};
+use yuml_rs::{graphviz_version, parse_yuml, render_svg_from_dot_to, RenderOptions};
+
+const UPDATE_VAR: &str = "YUML_UPDATE_SNAPSHOTS";
+
+/// `true` when the "dot" binary is graphviz, rather than a fake
+fn has_graphviz() -> bool {
+    graphviz_version(&RenderOptions::default())
+        .map(|version| version.contains("graphviz"))
+        .unwrap_or(false)
+}
+
+/// Drop the comments with the version of graphviz and trailing whitespace, which differ between installations
+fn normalize(svg: &str) -> String {
+    svg.lines()
+        .map(str::trim_end)
+        .filter(|line| !(line.starts_with("<!--") && line.ends_with("-->")))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render(yuml: &str) -> Option<String> {
+    let dot = parse_yuml(yuml).expect("invalid yUML").to_string();
+    if dot.is_empty() {
+        // the chart type is not supported
+        return None;
+    }
+
+    let mut svg = Vec::new();
+    render_svg_from_dot_to(&dot, &mut svg, &RenderOptions::default()).expect("can not render");
+    Some(normalize(&String::from_utf8_lossy(&svg)))
+}
+
+fn diagrams(dir: &Path) -> Vec<PathBuf> {
+    let mut diagrams: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("can not read the test directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "yuml"))
+        .collect();
+    diagrams.sort();
+    diagrams
+}
+
+#[test]
+fn test_svg_snapshots() {
+    if !has_graphviz() {
+        eprintln!("graphviz is not installed, skipping the SVG snapshots");
+        return;
+    }
+
+    let test_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test");
+    let snapshot_dir = test_dir.join("snapshots");
+    fs::create_dir_all(&snapshot_dir).expect("can not create the snapshot directory");
+    let update = env::var_os(UPDATE_VAR).is_some_and(|value| value == "1");
+
+    let mut mismatches = vec![];
+    for diagram in diagrams(&test_dir) {
+        let yuml = read_to_string(&diagram).expect("can not read diagram");
+        let Some(svg) = render(&yuml) else { continue };
+
+        let snapshot = snapshot_dir.join(diagram.with_extension("svg").file_name().unwrap_or_default());
+        match read_to_string(&snapshot) {
+            Ok(expected) if !update => {
+                if normalize(&expected) != svg {
+                    mismatches.push(snapshot.display().to_string());
+                }
+            }
+            _ => fs::write(&snapshot, &svg).expect("can not write snapshot"),
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "the SVG differs from the snapshots {}; run with {}=1 to accept the changes",
+        mismatches.join(", "),
+        UPDATE_VAR
+    );
+}